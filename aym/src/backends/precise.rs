@@ -580,3 +580,73 @@ impl AymBackend for AymPrecise {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENVELOPE_SHAPE_CONTINUE: usize = 0x08;
+    const ENVELOPE_SHAPE_ATTACK: usize = 0x04;
+    const ENVELOPE_SHAPE_ALTERNATE: usize = 0x02;
+    const ENVELOPE_SHAPE_HOLD: usize = 0x01;
+
+    /// Returns 16-step AY envelope level at `step` according to the shape bits
+    /// as described in the AY-3-8912 datasheet
+    fn expected_envelope_level(shape: usize, step: usize) -> usize {
+        let attack = shape & ENVELOPE_SHAPE_ATTACK != 0;
+        let cycle = step / 16;
+        let ramp = |rising: bool| if rising { step % 16 } else { 15 - step % 16 };
+        if cycle == 0 {
+            return ramp(attack);
+        }
+        if shape & ENVELOPE_SHAPE_CONTINUE == 0 {
+            return 0;
+        }
+        let alternate = shape & ENVELOPE_SHAPE_ALTERNATE != 0;
+        if shape & ENVELOPE_SHAPE_HOLD != 0 {
+            return if attack ^ alternate { 15 } else { 0 };
+        }
+        ramp(attack ^ (alternate && cycle % 2 == 1))
+    }
+
+    #[test]
+    fn envelope_shapes() {
+        for shape in 0..16u8 {
+            let mut ay = AymPrecise::new(false, 1_773_400.0, 44100);
+            ay.write_register(11, 1);
+            ay.write_register(12, 0);
+            ay.write_register(13, shape);
+            // Internal envelope has 32 steps, AY uses only the upper 4 bits
+            let mut envelope = ay.envelope;
+            for step in 0..64 {
+                assert_eq!(
+                    envelope >> 1,
+                    expected_envelope_level(shape as usize, step),
+                    "shape {:#04X}, step {}",
+                    shape,
+                    step
+                );
+                ay.update_envelope();
+                envelope = ay.update_envelope();
+            }
+        }
+    }
+
+    #[test]
+    fn noise_lfsr_period() {
+        let mut ay = AymPrecise::new(false, 1_773_400.0, 44100);
+        ay.write_register(6, 1);
+        // 17-bit LFSR with taps at bits 0 and 3 has maximal 2^17 - 1 period
+        let mut period = 0;
+        loop {
+            ay.update_noise();
+            ay.update_noise();
+            period += 1;
+            assert_ne!(ay.noise, 0);
+            if ay.noise == 1 {
+                break;
+            }
+        }
+        assert_eq!(period, (1 << 17) - 1);
+    }
+}
@@ -1,6 +1,11 @@
 // emulator
-use crate::{emulator::Emulator, host::Host, zx::tape::TapeImpl, Result};
-use rustzx_z80::{RegName16, Z80Bus, FLAG_CARRY, FLAG_ZERO};
+use crate::{
+    emulator::Emulator,
+    host::Host,
+    zx::{tape::TapeImpl, video::colors::ZXColor},
+    Result,
+};
+use rustzx_z80::{RegName16, RegName8, Z80Bus, FLAG_CARRY, FLAG_ZERO};
 
 pub fn fast_load_tap<H: Host>(emulator: &mut Emulator<H>) -> Result<()> {
     // So, at current moment we at 0x056C in 48K Rom.
@@ -21,6 +26,13 @@ pub fn fast_load_tap<H: Host>(emulator: &mut Emulator<H>) -> Result<()> {
     if !emulator.controller.tape.next_block()? {
         return Ok(());
     }
+    // ROM loader alternates border between colors of C and its complement on
+    // each edge, C is XORed with 3 after the sync pulses. Border writes of the
+    // skipped data loading are recorded as made by the loader
+    let c = emulator.cpu.regs.get_reg_8(RegName8::C) ^ 0x03;
+    emulator
+        .controller
+        .record_loader_border([ZXColor::from_bits(c & 0x07), ZXColor::from_bits(!c & 0x07)]);
 
     'loader: loop {
         // if we still on block
@@ -5,37 +5,71 @@ mod screenshot;
 mod snapshot;
 
 use crate::{
-    error::RomLoadError,
+    error::{CpuStateError, DiskLoadError, RomLoadError, StateLoadError},
     host::{
-        DataRecorder, Host, LoadableAsset, RomFormat, RomSet, Screen, ScreenAsset, Snapshot,
-        SnapshotAsset, SnapshotRecorder, Stopwatch, Tape,
+        DataRecorder, Disk, DiskAsset, FrameBuffer, FrameView, Host, LoadableAsset, RomFormat,
+        RomSet, Screen, ScreenAsset, SeekFrom, Snapshot, SnapshotAsset, SnapshotRecorder,
+        Stopwatch, Tape,
+    },
+    settings::{BorderSize, RustzxSettings, DETERMINISTIC_SEED},
+    utils::{
+        fingerprint::FingerprintHasher,
+        rewind::RewindBuffer,
+        state::{StateReader, StateWriter},
+        EmulationMode,
     },
-    settings::RustzxSettings,
-    utils::EmulationMode,
     zx::{
+        constants::{CANVAS_HEIGHT, SCREEN_MEMORY_SIZE},
         controller::ZXController,
+        disk::{BetaDisk, DiskImage, Fdc, TrdImage},
         events::EmulationEvents,
+        input::{InputButton, InputProfile, InputTarget},
         joy::{
-            kempston::KempstonKey,
+            cursor::CursorKey,
+            fuller::FullerKey,
+            kempston::{KempstonJoyNum, KempstonKey},
             sinclair::{SinclairJoyNum, SinclairKey},
         },
-        keys::{CompoundKey, ZXKey},
+        keys::{CompoundKey, ZXKey, ZX_KEYBOARD_ROWS},
+        machine::ZXMachine,
+        memory::{Page, MEM_BLOCKS, PAGE_SIZE},
         mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
-        tape::{Tap, TapeImpl},
+        printer::ZXPrinter,
+        tape::{Tap, TapeAsset, TapeBlockInfo, TapeImpl, TapePhase, TapePosition, TapeRecorder},
         video::colors::ZXColor,
     },
     Result,
 };
-use core::time::Duration;
-use rustzx_z80::Z80;
+use alloc::vec::Vec;
+use core::{hash::Hasher, time::Duration};
+use rustzx_z80::{
+    disassemble_with, instruction_length, Instruction, MAX_INSTRUCTION_LENGTH, Z80, Z80_STATE_SIZE,
+};
 
+#[cfg(any(feature = "autoload", feature = "compression"))]
+use crate::host::BufferCursor;
+#[cfg(feature = "compression")]
+use crate::utils::{gzip, zip};
+#[cfg(feature = "heatmap")]
+use crate::zx::heatmap::MemStats;
+#[cfg(feature = "next")]
+use crate::zx::memory::MMU_SLOTS;
+#[cfg(all(feature = "sound", feature = "ay"))]
+use crate::zx::sound::ay::{AyLog, AyWrite};
 #[cfg(feature = "sound")]
 use crate::zx::sound::sample::SoundSample;
-#[cfg(feature = "autoload")]
-use crate::{host::BufferCursor, zx::machine::ZXMachine};
+#[cfg(feature = "timex")]
+use crate::zx::video::TimexVideoMode;
+
+/// Emulator state blob signature and format version
+const STATE_MAGIC: &[u8; 4] = b"RZXS";
+const STATE_VERSION: u8 = 1;
+/// Max lag of [Emulator::run_realtime] in frames, the rest is dropped
+const MAX_REALTIME_LAG_FRAMES: u64 = 5;
+const NANOS_PER_SEC: u128 = 1_000_000_000;
 
 /// Represents emulator stop reason
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EmulationStopReason {
     /// Requested frames count have been emulated successfully
     Completed,
@@ -43,6 +77,8 @@ pub enum EmulationStopReason {
     Timeout,
     /// Emulator has reached breakpoint address
     Breakpoint,
+    /// Emulation is paused, nothing was emulated
+    Paused,
 }
 
 /// Represents emulator emulation result
@@ -53,13 +89,41 @@ pub struct EmulationInfo {
     pub stop_reason: EmulationStopReason,
 }
 
+/// Result of [Emulator::profile_until]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileInfo {
+    /// Cpu clocks consumed before the stop. These are not scaled by the cpu
+    /// speed, see [Emulator::set_cpu_speed]
+    pub clocks: u64,
+    /// [EmulationStopReason::Completed] if the target address was reached
+    pub stop_reason: EmulationStopReason,
+}
+
 /// Represents main Emulator structure
 pub struct Emulator<H: Host> {
     settings: RustzxSettings,
     cpu: Z80,
     controller: ZXController<H>,
     mode: EmulationMode,
-    fast_load: bool,
+    // count of frames emulated by `emulate_turbo_frames`
+    turbo: u32,
+    // events which were not yet taken by the frontend
+    events: EmulationEvents,
+    input_profile: InputProfile,
+    // host buttons, currently pressed via `send_input`
+    pressed_inputs: u8,
+    rewind: Option<RewindBuffer>,
+    // cpu clocks which are due to be emulated by `run_realtime`, negative
+    // when the last instruction has run past the target
+    realtime_clocks: i64,
+    // fraction of the clock, accumulated by `run_realtime`, in 1 / 10^9 units
+    realtime_clocks_fraction: u64,
+    // host clock of `run_realtime_elapsed` with its last reading
+    realtime_clock: Option<(H::EmulationStopwatch, Duration)>,
+    // set by `pause`, emulation functions do nothing while paused
+    paused: bool,
+    // frames left to report loading stripes after the instant tape load
+    fastload_border_frames: u32,
     #[cfg(feature = "sound")]
     sound_enabled: bool,
 }
@@ -70,34 +134,358 @@ impl<H: Host> Emulator<H> {
     /// `settings` - emulator settings
     pub fn new(settings: RustzxSettings, context: H::Context) -> Result<Self> {
         let mode = settings.emulation_mode;
-        let fast_load = settings.tape_fastload_enabled;
+        let rewind = (settings.rewind_snapshots != 0)
+            .then(|| RewindBuffer::new(settings.rewind_interval_frames, settings.rewind_snapshots));
         #[cfg(feature = "sound")]
         let sound_enabled = settings.sound_enabled;
 
+        #[cfg(feature = "embedded-roms")]
+        if settings.load_default_rom && !settings.machine.capabilities().has_embedded_rom {
+            return Err(RomLoadError::NoEmbeddedRom.into());
+        }
+
         let cpu = Z80::default();
-        let controller = ZXController::<H>::new(&settings, context);
+        let mut controller = ZXController::<H>::new(&settings, context);
+        if let Some(spec) = &settings.custom_specs {
+            spec.validate()?;
+            controller = controller.with_custom_specs(spec);
+        }
 
-        let this = Self {
+        let mut this = Self {
             settings,
             cpu,
             controller,
             mode,
-            fast_load,
+            turbo: 1,
+            events: EmulationEvents::empty(),
+            input_profile: InputProfile::default(),
+            pressed_inputs: 0,
+            rewind,
+            realtime_clocks: 0,
+            realtime_clocks_fraction: 0,
+            realtime_clock: None,
+            paused: false,
+            fastload_border_frames: 0,
             #[cfg(feature = "sound")]
             sound_enabled,
         };
+        if this.settings.deterministic {
+            this.power_on(DETERMINISTIC_SEED);
+        }
 
         Ok(this)
     }
 
+    /// Performs power-on reset: CPU registers are reset to the initial state
+    /// and RAM is filled with a pseudo-random pattern derived from `seed`.
+    /// Same seed always produces same memory contents, which makes emulation
+    /// of programs depending on power-on memory reproducible. Border and
+    /// screen are then set up as [RustzxSettings::initial_border_color] and
+    /// [RustzxSettings::initial_screen_attribute] require
+    pub fn power_on(&mut self, seed: u64) {
+        self.cpu = Z80::default();
+        self.controller.power_on();
+        self.events = EmulationEvents::empty();
+
+        // splitmix64 generator
+        let mut state = seed;
+        let banks = self.settings.machine.capabilities().ram_size / PAGE_SIZE;
+        for bank in 0..banks {
+            let data = self
+                .controller
+                .memory
+                .ram_bank_mut(bank)
+                .expect("Bank exists on the current machine");
+            for chunk in data.chunks_mut(8) {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut value = state;
+                value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                value ^= value >> 31;
+                chunk.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        self.controller.refresh_memory_dependent_devices();
+        self.controller.apply_boot_screen(
+            self.settings.initial_border_color,
+            self.settings.initial_screen_attribute,
+        );
+    }
+
+    /// Emulates RESET button: cpu restarts from 0x0000 with interrupts
+    /// disabled in IM 0, ROM 0 is paged in and 128K paging is unlocked.
+    /// Unlike [Emulator::power_on], RAM contents are preserved
+    pub fn reset_cpu_only(&mut self) {
+        self.cpu.reset();
+        self.controller.reset();
+    }
+
+    /// Returns hash of the whole emulator state, serialized by
+    /// [Emulator::serialize_state]. Cheap enough to be calculated each frame
+    /// to detect emulation desync, e.g. in netplay. Value is
+    /// platform-independent
+    pub fn state_fingerprint(&self) -> u64 {
+        let mut hasher = FingerprintHasher::default();
+        hasher.write(&self.serialize_state());
+        hasher.finish()
+    }
+
+    /// Serializes the whole emulator state (CPU, all RAM and ROM, memory
+    /// paging, border, sound chips, tape position, disk controllers, pending
+    /// NMI, keyboard, frame and interrupt timings, cpu speed)
+    /// into a binary blob, which could be restored with
+    /// [Emulator::deserialize_state]. State could be saved at any point of
+    /// the frame. Inserted tape and disk contents, settings and host frame
+    /// buffers are not the part of the state
+    pub fn serialize_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::default();
+        writer.write_bytes(STATE_MAGIC);
+        writer.write_u8(STATE_VERSION);
+        writer.write_u8(match self.settings.machine {
+            ZXMachine::Sinclair48K => 0,
+            ZXMachine::Sinclair128K => 1,
+            ZXMachine::SinclairPlus3 => 2,
+            ZXMachine::Sinclair16K => 3,
+        });
+        writer.write_bytes(&self.cpu.save_state());
+        writer.write_u16(self.events.bits());
+        self.controller.save_state(&mut writer);
+        writer.finish()
+    }
+
+    /// Restores emulator state, produced by [Emulator::serialize_state] on
+    /// the emulator with the same machine. Tape playback position is restored
+    /// only if the same tape is inserted. Already emulated part of the
+    /// current frame is redrawn from the restored screen memory. Emulator is
+    /// left intact if state is invalid
+    pub fn deserialize_state(&mut self, state: &[u8]) -> Result<()> {
+        let backup = self.serialize_state();
+        if let Err(e) = self.load_state(state) {
+            self.load_state(&backup)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<()> {
+        let mut reader = StateReader::new(state);
+        if reader.read_bytes(STATE_MAGIC.len())? != STATE_MAGIC {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        if reader.read_u8()? != STATE_VERSION {
+            return Err(StateLoadError::UnsupportedVersion.into());
+        }
+        let machine = match reader.read_u8()? {
+            0 => ZXMachine::Sinclair48K,
+            1 => ZXMachine::Sinclair128K,
+            2 => ZXMachine::SinclairPlus3,
+            3 => ZXMachine::Sinclair16K,
+            _ => return Err(StateLoadError::InvalidState.into()),
+        };
+        if machine != self.settings.machine {
+            return Err(StateLoadError::MachineMismatch.into());
+        }
+        let mut cpu_state = [0u8; Z80_STATE_SIZE];
+        reader.read_into(&mut cpu_state)?;
+        if !self.cpu.load_state(&cpu_state) {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        self.events = EmulationEvents::from_bits_truncate(reader.read_u16()?);
+        self.controller.load_state(&mut reader)?;
+        reader.finish()
+    }
+
+    /// Changes rewind buffer parameters, see [RustzxSettings::rewind_snapshots].
+    /// Already captured states are dropped
+    pub fn set_rewind_buffer(&mut self, snapshots: usize, interval_frames: usize) {
+        self.rewind = (snapshots != 0).then(|| RewindBuffer::new(interval_frames, snapshots));
+    }
+
+    /// Restores the newest rewind buffer state, captured at least `frames`
+    /// frames ago, or the oldest available state. Returns count of frames
+    /// actually rewound, which is `0` when rewind buffer is empty or disabled
+    pub fn rewind(&mut self, frames: usize) -> Result<usize> {
+        match self.rewind.as_mut().and_then(|rewind| rewind.pop(frames)) {
+            Some((rewound, state)) => {
+                self.deserialize_state(&state)?;
+                Ok(rewound)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Returns count of frames, which could be rewound with [Emulator::rewind]
+    pub fn rewind_available(&self) -> usize {
+        self.rewind
+            .as_ref()
+            .map_or(0, RewindBuffer::available_frames)
+    }
+
+    /// Captures rewind buffer state if required
+    fn process_rewind_frame(&mut self) {
+        if self
+            .rewind
+            .as_mut()
+            .is_some_and(RewindBuffer::frame_completed)
+        {
+            let state = self.serialize_state();
+            if let Some(rewind) = &mut self.rewind {
+                rewind.push(state);
+            }
+        }
+    }
+
     /// changes emulation speed
     pub fn set_speed(&mut self, new_speed: EmulationMode) {
         self.mode = new_speed;
     }
 
+    /// Pauses whole emulation, unlike [Emulator::tape_pause]. While paused,
+    /// frame and step functions do nothing and return
+    /// [EmulationStopReason::Paused]. Buffered audio is faded out to silence,
+    /// so host audio does not click when samples stop coming
+    pub fn pause(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.paused = true;
+        #[cfg(feature = "sound")]
+        self.controller.mixer.fade_out();
+    }
+
+    /// Resumes emulation paused by [Emulator::pause] from the same cpu
+    /// state and frame position. Host time passed during the pause is not
+    /// caught up by [Emulator::run_realtime_elapsed]
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.realtime_clock = None;
+    }
+
+    /// Returns true if emulation is paused, see [Emulator::pause]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Sets count of frames emulated by [Emulator::emulate_turbo_frames] for
+    /// fast-forwarding, e.g. through long tape loads. Unlike cpu speed
+    /// multiplier, display runs faster too. `1` disables fast-forward
+    pub fn set_turbo(&mut self, multiplier: u32) {
+        self.turbo = multiplier.max(1);
+    }
+
+    /// Returns fast-forward multiplier, see [Emulator::set_turbo]
+    pub fn turbo(&self) -> u32 {
+        self.turbo
+    }
+
+    /// Changes cpu speed multiplier, see [RustzxSettings::cpu_speed_multiplier].
+    /// Non-finite values are ignored
+    pub fn set_cpu_speed(&mut self, multiplier: f64) {
+        self.controller.set_cpu_speed(multiplier);
+    }
+
+    /// Changes length of the frame interrupt signal in cpu clocks. Defaults to
+    /// the machine specs value (36 clocks on 128K, 32 clocks on other machines)
+    pub fn set_interrupt_length(&mut self, clocks: u32) {
+        self.controller.set_interrupt_length(clocks as usize);
+    }
+
+    /// Sets byte placed on the data bus during interrupt acknowledge, which
+    /// is used as the low byte of IM 2 vector table address. `None` leaves
+    /// the floating bus value (0xFF). Value provided by the
+    /// [IoExtender](crate::host::IoExtender) takes precedence
+    pub fn set_im2_bus_value(&mut self, value: Option<u8>) {
+        self.controller.set_im2_bus_value(value);
+    }
+
+    /// Returns count of clocks between the start of the frame interrupt signal
+    /// and its acceptance by the cpu for the last accepted interrupt, or
+    /// `None` if no interrupt was accepted yet
+    pub fn last_interrupt_latency(&self) -> Option<u32> {
+        self.controller
+            .last_interrupt_clocks()
+            .map(|clocks| clocks as u32)
+    }
+
+    pub fn interrupt_length(&self) -> u32 {
+        self.controller.interrupt_length() as u32
+    }
+
+    /// Triggers non-maskable interrupt: NMI line stays active until the cpu
+    /// accepts it before the next instruction and jumps to 0x0066
+    pub fn trigger_nmi(&mut self) {
+        self.controller.trigger_nmi();
+    }
+
+    /// Returns true if NMI was triggered, but not accepted by the cpu yet
+    pub fn nmi_pending(&self) -> bool {
+        self.controller.nmi_pending()
+    }
+
+    /// Returns current beam position as `(line, col)` where `line` is the
+    /// scanline counted from the frame interrupt (including vsync and top
+    /// border lines) and `col` is the cpu clock within the line
+    pub fn raster_position(&self) -> (u16, u16) {
+        self.controller.raster_position()
+    }
+
+    /// Returns real time duration of the frame, defined by the frame length
+    /// and cpu frequency of the machine
+    pub fn frame_duration(&self) -> Duration {
+        let nanos = self.controller.clocks_frame() as u128 * NANOS_PER_SEC
+            / self.controller.freq_cpu() as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Enables or disables instant loading of the tape with the standard ROM
+    /// loader. When disabled, tape is played in real time with authentic
+    /// sound and border and [EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED]
+    /// is not reported. Fast loading stays disabled in
+    /// [RustzxSettings::deterministic] mode
+    pub fn set_fast_tape_load(&mut self, value: bool) {
+        self.controller
+            .set_fast_load(value && !self.settings.deterministic);
+    }
+
     /// changes fast loading flag
+    #[deprecated(note = "use `Emulator::set_fast_tape_load` instead")]
     pub fn set_fast_load(&mut self, value: bool) {
-        self.fast_load = value;
+        self.set_fast_tape_load(value)
+    }
+
+    /// Returns true if tapes are loaded instantly, see
+    /// [Emulator::set_fast_tape_load]
+    pub fn fast_tape_load(&self) -> bool {
+        self.controller.fast_load()
+    }
+
+    /// Changes address of the ROM loader edge detection loop (`LD-BREAK`,
+    /// 0x056B in the standard ROM), which triggers fast tape loading and
+    /// missing tape signal detection. Custom ROMs with relocated loader
+    /// should set the address of the same point, CPU state at the trap is
+    /// expected to match the standard ROM. `None` disables the trap, tapes
+    /// are always loaded in real time
+    pub fn set_tape_trap_address(&mut self, addr: Option<u16>) {
+        self.controller.set_tape_trap_address(addr);
+    }
+
+    /// Returns address of the ROM loader trap, `None` if the trap is disabled,
+    /// see [Emulator::set_tape_trap_address]
+    pub fn tape_trap_address(&self) -> Option<u16> {
+        self.controller.tape_trap_address()
+    }
+
+    /// Changes count of frames between flashing attributes swaps, `None`
+    /// disables flashing, see [RustzxSettings::flash_rate_frames]
+    pub fn set_flash_rate(&mut self, frames: Option<u32>) {
+        self.controller
+            .screen
+            .set_flash_rate(frames.map(|frames| frames as usize));
+    }
+
+    /// changes frames blending (gigascreen) flag
+    pub fn set_blend_frames(&mut self, value: bool) {
+        self.controller.screen.set_blend_frames(value);
     }
 
     /// changes sound playback flag
@@ -106,6 +494,51 @@ impl<H: Host> Emulator<H> {
         self.sound_enabled = value;
     }
 
+    /// changes audio latency target: how many milliseconds of sound could be
+    /// buffered before the host drains them. Defaults to one frame (20 ms)
+    #[cfg(feature = "sound")]
+    pub fn set_audio_latency_ms(&mut self, ms: usize) {
+        self.controller.mixer.set_target_latency_ms(ms);
+    }
+
+    /// Changes beeper volume relative to other sound sources. Value is
+    /// clamped to `0.0..=2.0`, master volume is applied on top of it. Non-finite
+    /// values are ignored
+    #[cfg(feature = "sound")]
+    pub fn set_beeper_volume(&mut self, volume: f64) {
+        self.controller.mixer.set_beeper_volume(volume);
+    }
+
+    /// Changes AY volume relative to other sound sources. Value is clamped
+    /// to `0.0..=2.0`, master volume is applied on top of it. Non-finite
+    /// values are ignored
+    #[cfg(feature = "ay")]
+    pub fn set_ay_volume(&mut self, volume: f64) {
+        self.controller.mixer.set_ay_volume(volume);
+    }
+
+    /// Sums stereo sound output to mono
+    #[cfg(feature = "sound")]
+    pub fn set_mono_output(&mut self, value: bool) {
+        self.controller.mixer.set_mono_output(value);
+    }
+
+    /// Returns count of audio samples waiting to be drained by the host
+    #[cfg(feature = "sound")]
+    pub fn buffered_audio_samples(&self) -> usize {
+        self.controller.mixer.buffered_samples()
+    }
+
+    /// Returns count of audio samples produced ahead (positive) or behind
+    /// (negative) of the ideal `sample_rate / 50` per emulated frame. Samples
+    /// dropped because of the latency target overflow are counted as lag, so
+    /// hosts could use this value to compensate. Tracking restarts on the
+    /// emulator state load
+    #[cfg(feature = "sound")]
+    pub fn audio_video_drift(&self) -> i64 {
+        self.controller.mixer.drift()
+    }
+
     /// function for sound generation request check
     #[cfg(feature = "sound")]
     pub fn have_sound(&self) -> bool {
@@ -117,8 +550,24 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    /// Loads snapshot. With `compression` feature enabled, gzip-compressed
+    /// snapshots are unpacked transparently, zip archives are searched for
+    /// the first `.sna` file
     pub fn load_snapshot(&mut self, snapshot: Snapshot<impl SnapshotAsset>) -> Result<()> {
         match snapshot {
+            #[cfg(feature = "compression")]
+            Snapshot::Sna(mut asset) => {
+                if gzip::is_gzip(&mut asset)? {
+                    let unpacked = BufferCursor::new(gzip::unpack(asset)?);
+                    return snapshot::sna::load(self, unpacked);
+                }
+                if zip::is_zip(&mut asset)? {
+                    let unpacked = BufferCursor::new(zip::unpack(asset, &["sna"])?);
+                    return snapshot::sna::load(self, unpacked);
+                }
+                snapshot::sna::load(self, asset)
+            }
+            #[cfg(not(feature = "compression"))]
             Snapshot::Sna(asset) => snapshot::sna::load(self, asset),
         }
     }
@@ -132,26 +581,75 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    /// Inserts tape. With `compression` feature enabled, gzip-compressed
+    /// tapes are unpacked transparently, zip archives are searched for the
+    /// first `.tap` file. With `autoload` enabled machine is
+    /// reset to the tape loading state, see [Emulator::insert_tape] to swap
+    /// tapes without affecting the running program
     pub fn load_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
-        match tape {
-            Tape::Tap(asset) => {
-                self.controller.tape = Tap::from_asset(asset)?.into();
-            }
-        }
+        self.insert_tape(tape)?;
 
         #[cfg(feature = "autoload")]
         if self.settings.autoload_enabled {
             let snapshot = match self.settings.machine {
-                ZXMachine::Sinclair48K => &snapshot::autoload::tape::SNAPSHOT_SNA_48K,
-                ZXMachine::Sinclair128K => &snapshot::autoload::tape::SNAPSHOT_SNA_128K,
+                ZXMachine::Sinclair48K => Some(&snapshot::autoload::tape::SNAPSHOT_SNA_48K),
+                ZXMachine::Sinclair128K => Some(&snapshot::autoload::tape::SNAPSHOT_SNA_128K),
+                // Autoload snapshots are made for the 128K ROM and can't be used on +3,
+                // 48K snapshot needs RAM above 0x7FFF
+                ZXMachine::SinclairPlus3 | ZXMachine::Sinclair16K => None,
             };
 
-            self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
+            if let Some(snapshot) = snapshot {
+                self.load_snapshot(Snapshot::Sna(BufferCursor::new(snapshot)))?;
+            }
         }
 
         Ok(())
     }
 
+    /// Swaps inserted tape while emulation is running, e.g. for multi-tape
+    /// games. CPU and memory state is preserved, new tape is rewound to the
+    /// first block and stopped
+    pub fn insert_tape(&mut self, tape: Tape<H::TapeAsset>) -> Result<()> {
+        let tape = match tape {
+            Tape::Tap(asset) => Tap::from_asset(TapeAsset::new(asset)?)?.into(),
+        };
+        self.controller.insert_tape(tape)
+    }
+
+    /// Removes inserted tape
+    pub fn eject_tape(&mut self) {
+        self.controller.eject_tape();
+    }
+
+    /// Drives EAR input with the given level until [Emulator::clear_ear_input]
+    /// call. External EAR input takes priority over the inserted tape
+    pub fn feed_ear_sample(&mut self, level: bool) {
+        self.controller.ear_input.set_level(Some(level));
+    }
+
+    /// Queues external audio (e.g. digitized cassette recording), which
+    /// drives EAR input during emulation. Samples are played back on the cpu
+    /// clocks timeline, so real-time loaders keep their timings. While queued
+    /// audio is played, it takes priority over the inserted tape and the
+    /// level set via [Emulator::feed_ear_sample], fast tape loading is not
+    /// triggered. `sample_rate` applies to all queued samples
+    pub fn feed_ear_audio(&mut self, samples: &[f32], sample_rate: usize) {
+        self.controller.feed_ear_audio(samples, sample_rate);
+    }
+
+    /// Returns count of queued external audio samples, which were not played
+    /// yet
+    pub fn queued_ear_samples(&self) -> usize {
+        self.controller.ear_input.queued_samples()
+    }
+
+    /// Drops queued external audio and releases EAR input level, EAR input
+    /// is driven by the tape again
+    pub fn clear_ear_input(&mut self) {
+        self.controller.ear_input.clear();
+    }
+
     fn load_rom_binary_16k_pages(&mut self, mut rom: impl RomSet) -> Result<()> {
         let page_count = self.settings.machine.specs().rom_pages;
 
@@ -170,6 +668,30 @@ impl<H: Host> Emulator<H> {
         }
     }
 
+    /// Loads ROM from user-provided images, e.g. patched, diagnostic or localized
+    /// ROMs. Images are concatenated in the given order and should exactly fill
+    /// all 16K ROM pages of the machine (one page on 48K, two on 128K and four
+    /// on +3), so both a single image and a list of page images are accepted.
+    /// ROM is left intact when the size does not match
+    pub fn load_rom_data(&mut self, machine_roms: &[&[u8]]) -> Result<()> {
+        let page_count = self.settings.machine.specs().rom_pages;
+        let size: usize = machine_roms.iter().map(|rom| rom.len()).sum();
+        if size != page_count as usize * PAGE_SIZE {
+            return Err(RomLoadError::InvalidRomSize.into());
+        }
+
+        let mut pages = machine_roms.iter().flat_map(|rom| rom.iter());
+        for page_index in 0..page_count {
+            let page_buffer = self.controller.memory.rom_page_data_mut(page_index);
+            page_buffer
+                .iter_mut()
+                .zip(pages.by_ref())
+                .for_each(|(dst, src)| *dst = *src);
+        }
+
+        Ok(())
+    }
+
     pub fn load_screen(&mut self, screen: Screen<impl ScreenAsset>) -> Result<()> {
         match screen {
             Screen::Scr(asset) => screenshot::scr::load(self, asset)?,
@@ -178,6 +700,70 @@ impl<H: Host> Emulator<H> {
         Ok(())
     }
 
+    /// Inserts read-only disk image into the +3 internal drive (`.dsk`) or
+    /// into the drive A: of the Beta Disk interface (`.trd` and `.scl`)
+    pub fn load_disk(&mut self, disk: Disk<impl DiskAsset>) -> Result<()> {
+        match disk {
+            Disk::Dsk(asset) => {
+                if self.controller.fdc.is_none() {
+                    return Err(DiskLoadError::MachineNotSupported.into());
+                }
+                let image = DiskImage::from_dsk(&read_whole_asset(asset)?)?;
+                if let Some(fdc) = &mut self.controller.fdc {
+                    fdc.insert_disk(image);
+                }
+            }
+            Disk::Trd(_) | Disk::Scl(_) if self.controller.beta_disk.is_none() => {
+                return Err(DiskLoadError::MachineNotSupported.into());
+            }
+            Disk::Trd(asset) => {
+                let image = TrdImage::from_trd(&read_whole_asset(asset)?)?;
+                if let Some(beta_disk) = &mut self.controller.beta_disk {
+                    beta_disk.insert_disk(image);
+                }
+            }
+            Disk::Scl(asset) => {
+                let image = TrdImage::from_scl(&read_whole_asset(asset)?)?;
+                if let Some(beta_disk) = &mut self.controller.beta_disk {
+                    beta_disk.insert_disk(image);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if disk is inserted into the +3 or Beta Disk drive
+    pub fn has_disk(&self) -> bool {
+        self.controller.fdc.as_ref().is_some_and(Fdc::has_disk)
+            || self
+                .controller
+                .beta_disk
+                .as_ref()
+                .is_some_and(BetaDisk::has_disk)
+    }
+
+    /// Removes disk from the +3 or Beta Disk drive
+    pub fn eject_disk(&mut self) {
+        if let Some(fdc) = &mut self.controller.fdc {
+            fdc.eject_disk();
+        }
+        if let Some(beta_disk) = &mut self.controller.beta_disk {
+            beta_disk.eject_disk();
+        }
+    }
+
+    /// Loads 16K TR-DOS ROM of the Beta Disk interface
+    pub fn load_trdos_rom(&mut self, mut rom: impl LoadableAsset) -> Result<()> {
+        let page = self
+            .controller
+            .beta_disk
+            .as_ref()
+            .map(BetaDisk::rom_page)
+            .ok_or(RomLoadError::BetaDiskNotEnabled)?;
+        rom.read_exact(self.controller.memory.rom_page_data_mut(page))?;
+        Ok(())
+    }
+
     pub fn play_tape(&mut self) {
         self.controller.tape.play();
     }
@@ -186,6 +772,72 @@ impl<H: Host> Emulator<H> {
         self.controller.tape.stop();
     }
 
+    /// Pauses tape playback mid-pulse. Unlike `stop_tape`, tape stays in
+    /// the middle of the current block and keeps its `ear` level, so ROM
+    /// loaders continue without desync after `tape_resume`
+    pub fn tape_pause(&mut self) {
+        self.controller.tape.pause();
+    }
+
+    /// Resumes tape playback paused with `tape_pause`
+    pub fn tape_resume(&mut self) {
+        self.controller.tape.resume();
+    }
+
+    /// Enables automatic tape pause at the end of each block, as on the real
+    /// tape decks with multi-load tapes.
+    /// [EmulationEvents::TAPE_BLOCK_FINISHED] is reported on each stop,
+    /// playback is continued with `tape_resume`
+    pub fn set_tape_autostop(&mut self, value: bool) {
+        self.controller.set_tape_autostop(value);
+    }
+
+    pub fn is_tape_paused(&self) -> bool {
+        self.controller.tape.is_paused()
+    }
+
+    /// Starts recording of the MIC output. Standard ROM-format blocks are
+    /// decoded from the output pulses, previous recording is discarded
+    pub fn start_tape_record(&mut self) {
+        self.controller.tape_recorder = Some(TapeRecorder::default());
+    }
+
+    /// Stops MIC output recording and returns recorded blocks in TAP format.
+    /// Returns empty buffer if recording was not started
+    pub fn stop_tape_record(&mut self) -> Vec<u8> {
+        self.controller
+            .tape_recorder
+            .take()
+            .map(TapeRecorder::finish)
+            .unwrap_or_default()
+    }
+
+    /// Returns current tape block index, total blocks count and current block progress
+    pub fn tape_position(&self) -> TapePosition {
+        self.controller.tape.position()
+    }
+
+    /// Returns phase of the signal, played by the tape (pilot tone, sync
+    /// pulses, data or gap between blocks), e.g. to draw loading stripes
+    pub fn tape_phase(&self) -> TapePhase {
+        self.controller.tape.phase()
+    }
+
+    /// Returns description of the tape block with given `index`: its size,
+    /// flag byte and parsed standard header (file type, name, length and
+    /// start address), e.g. to build tape browser for multi-load games.
+    /// Tape position is not affected
+    pub fn tape_block_info(&self, index: usize) -> Result<TapeBlockInfo> {
+        self.controller.tape.block_info(index)
+    }
+
+    /// Moves tape to the beginning of the block with given `index`, e.g. to
+    /// skip to the specific level of the multi-load game. Tape play/stop
+    /// state is preserved
+    pub fn tape_seek_block(&mut self, index: usize) -> Result<()> {
+        self.controller.tape.seek_block(index)
+    }
+
     /// Rewinds tape. May return error if underlying tape asset failed to
     /// perform seek operation to go back to the the beginning of the tape
     pub fn rewind_tape(&mut self) -> Result<()> {
@@ -201,6 +853,62 @@ impl<H: Host> Emulator<H> {
         self.controller.border.frame_buffer()
     }
 
+    /// Returns width of the screen frame buffer, see
+    /// [RustzxSettings::canvas_buffer_width]
+    pub fn canvas_buffer_width(&self) -> usize {
+        self.controller.screen.buffer_width()
+    }
+
+    /// Borrows pixel data of the last completed screen frame without copying.
+    /// Returns `None` if host frame buffer does not expose its data, see
+    /// [FrameBuffer::as_bytes]
+    pub fn screen_frame(&self) -> Option<FrameView<'_>> {
+        Some(FrameView {
+            data: self.screen_buffer().as_bytes()?,
+            width: self.canvas_buffer_width(),
+            height: CANVAS_HEIGHT,
+        })
+    }
+
+    /// Borrows pixel data of the last completed border frame, see
+    /// [Emulator::screen_frame]
+    #[cfg(feature = "precise-border")]
+    pub fn border_frame(&self) -> Option<FrameView<'_>> {
+        Some(FrameView {
+            data: self.border_buffer().as_bytes()?,
+            width: self.settings.border_size.screen_width(),
+            height: self.settings.border_size.screen_height(),
+        })
+    }
+
+    /// Returns border size, selected in settings. Host should use it to get
+    /// dimensions of the rendered frame and the canvas position inside it
+    pub fn border_size(&self) -> BorderSize {
+        self.settings.border_size
+    }
+
+    /// Returns `(col, row)` attribute cells which were changed in the last
+    /// completed frame, so frontends could upload only changed screen regions
+    #[cfg(feature = "dirty-cells")]
+    pub fn dirty_cells(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.controller.screen.dirty_cells()
+    }
+
+    /// Returns memory access counters of the cpu address space, one entry
+    /// per [HEATMAP_PAGE_SIZE](crate::zx::heatmap::HEATMAP_PAGE_SIZE) bytes.
+    /// Counters are accumulated since the emulator construction or the last
+    /// [Emulator::reset_memory_heatmap] call
+    #[cfg(feature = "heatmap")]
+    pub fn memory_heatmap(&self) -> &[MemStats] {
+        self.controller.heatmap.pages()
+    }
+
+    /// Resets memory access counters, see [Emulator::memory_heatmap]
+    #[cfg(feature = "heatmap")]
+    pub fn reset_memory_heatmap(&mut self) {
+        self.controller.heatmap.reset();
+    }
+
     pub fn set_io_extender(&mut self, extender: H::IoExtender) {
         self.controller.io_extender = Some(extender);
     }
@@ -219,25 +927,278 @@ impl<H: Host> Emulator<H> {
         self.controller.debug_interface.as_mut()
     }
 
+    /// Returns emulated Z80 CPU, e.g. to inspect registers in debuggers
+    pub fn cpu(&self) -> &Z80 {
+        &self.cpu
+    }
+
+    /// Returns mutable reference to the emulated Z80 CPU
+    pub fn cpu_mut(&mut self) -> &mut Z80 {
+        &mut self.cpu
+    }
+
+    /// Returns `(iff1, iff2, im)` interrupt state of the cpu, where `im` is
+    /// interrupt mode number in 0..=2
+    pub fn interrupt_state(&self) -> (bool, bool, u8) {
+        let regs = &self.cpu.regs;
+        (regs.get_iff1(), regs.get_iff2(), self.cpu.get_im().into())
+    }
+
+    /// Changes interrupt flip-flops and interrupt mode of the cpu, e.g. when
+    /// restoring snapshot or toggling interrupts from the debugger. Returns
+    /// error and leaves the cpu unchanged if `im` is greater than 2
+    pub fn set_interrupt_state(&mut self, iff1: bool, iff2: bool, im: u8) -> Result<()> {
+        if im > 2 {
+            return Err(CpuStateError::InvalidInterruptMode.into());
+        }
+        self.cpu.regs.set_iff1(iff1);
+        self.cpu.regs.set_iff2(iff2);
+        self.cpu.set_im(im);
+        Ok(())
+    }
+
+    /// Returns count of the unattached port reads during the last completed
+    /// frame. Large values point to the code, which polls the floating bus
+    /// to synchronize with the beam
+    pub fn last_frame_floating_bus_reads(&self) -> u32 {
+        self.controller.last_frame_floating_bus_reads()
+    }
+
+    /// Returns floating bus value, which unattached port would read at the
+    /// given T-state of the frame (counted from the frame interrupt) with the
+    /// current memory contents. Values above the frame length wrap to the
+    /// next frame. Always 0xFF on +2A/+3. Emulation state is not affected
+    pub fn floating_bus_at(&self, clocks: u32) -> u8 {
+        self.controller.floating_bus_at(clocks as usize)
+    }
+
     /// Reads byte from memory
     pub fn peek(&self, addr: u16) -> u8 {
         self.controller.memory.read(addr)
     }
 
+    /// Returns 16K slice of the physical ram bank, even if it is not paged in
+    /// at the moment. On 16K machine only bank 0 exists, on 48K banks 0-2
+    pub fn ram_bank(&self, bank: usize) -> Result<&[u8]> {
+        self.controller.memory.ram_bank(bank)
+    }
+
+    /// Returns mutable 16K slice of the physical ram bank, even if it is not
+    /// paged in at the moment. Rendered screen is not updated from the
+    /// modified bytes until the program writes them again, use
+    /// [Emulator::with_ram_bank_mut] to modify video memory
+    pub fn ram_bank_mut(&mut self, bank: usize) -> Result<&mut [u8]> {
+        self.controller.memory.ram_bank_mut(bank)
+    }
+
+    /// Calls `f` with mutable 16K slice of the physical ram bank, then
+    /// updates the screen from the bank if it holds video memory
+    pub fn with_ram_bank_mut<R>(
+        &mut self,
+        bank: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R> {
+        let result = f(self.controller.memory.ram_bank_mut(bank)?);
+        let data = self.controller.memory.ram_bank(bank)?;
+        self.controller.screen.update_range(0, data, bank);
+        Ok(result)
+    }
+
+    /// Returns 8K page of the ZX Spectrum Next MMU slot (0-7), `None` for
+    /// invalid slot index. When MMU is not active, value is derived from the
+    /// 16K memory map
+    #[cfg(feature = "next")]
+    pub fn next_mmu_slot(&self, slot: usize) -> Option<u8> {
+        (slot < MMU_SLOTS).then(|| self.controller.memory.mmu_slot(slot))
+    }
+
+    /// Returns true if memory is paged by the ZX Spectrum Next MMU
+    #[cfg(feature = "next")]
+    pub fn is_next_mmu_active(&self) -> bool {
+        self.controller.memory.is_mmu_active()
+    }
+
+    /// Returns true if 128K paging is locked by bit 5 of 0x7FFD port until
+    /// the next reset
+    pub fn is_paging_locked(&self) -> bool {
+        self.controller.is_paging_locked()
+    }
+
+    /// Returns active ROM page (TR-DOS ROM page when it is paged in)
+    pub fn current_rom_bank(&self) -> u8 {
+        self.controller.current_rom_page()
+    }
+
+    /// Returns page mapped to the given 16K memory block (`0..=3`), `None`
+    /// for invalid block index
+    pub fn current_ram_bank_at(&self, block: usize) -> Option<Page> {
+        (block < MEM_BLOCKS).then(|| self.controller.memory.get_bank_type(block))
+    }
+
+    /// Returns ram bank which is currently displayed (0 on 48K, 5 or 7 on 128K)
+    pub fn current_screen_bank(&self) -> usize {
+        self.controller.screen_bank()
+    }
+
+    /// Returns raw display file and attributes bytes of the active screen
+    pub fn screen_bytes(&self) -> &[u8] {
+        let bank = self
+            .controller
+            .memory
+            .ram_bank(self.controller.screen_bank())
+            .expect("Screen bank is always present");
+        &bank[..SCREEN_MEMORY_SIZE]
+    }
+
+    /// Returns standard 6912-byte `*.scr` dump of the active screen
+    pub fn export_scr(&self) -> Vec<u8> {
+        screenshot::scr::export(self)
+    }
+
+    /// Encodes the last completed frame (with border, if it is rendered) to
+    /// PNG image at native resolution. Returns `None` if host frame buffer
+    /// does not expose its pixels, see [FrameBuffer::pixel_format]
+    #[cfg(feature = "png")]
+    pub fn export_png(&self) -> Option<Vec<u8>> {
+        screenshot::png::export(self)
+    }
+
+    /// Returns raw bytes of the instruction at current PC and its decoded
+    /// length. Bytes past instruction length are filled with the following
+    /// memory content. Memory is read without any side effects
+    pub fn peek_instruction(&self) -> ([u8; MAX_INSTRUCTION_LENGTH], u8) {
+        let pc = self.cpu.regs.get_pc();
+        let mut bytes = [0u8; MAX_INSTRUCTION_LENGTH];
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.peek(pc.wrapping_add(offset as u16));
+        }
+        (bytes, instruction_length(&bytes))
+    }
+
+    /// Disassembles `count` instructions starting from `addr` using currently
+    /// paged memory. Memory is read without any side effects
+    pub fn disassemble(&self, addr: u16, count: usize) -> Vec<Instruction> {
+        disassemble_with(addr, count, |addr| self.peek(addr))
+    }
+
+    /// Returns current Timex video mode, selected via port 0xFF
+    #[cfg(feature = "timex")]
+    pub fn timex_video_mode(&self) -> TimexVideoMode {
+        self.controller.screen.timex_video_mode()
+    }
+
     pub fn border_color(&self) -> ZXColor {
         self.controller.border_color
     }
 
+    /// Returns pair of border colors, alternated by the tape loader on each
+    /// edge of the tape signal, as recorded from its last border change.
+    /// `None` if tape signal is not played. After the instant tape load
+    /// colors, which ROM loader would have written during data loading, are
+    /// reported for [RustzxSettings::tape_fastload_border_frames] frames, so
+    /// frontend could keep loading stripes while skipping the wait
+    pub fn loading_border_pattern(&self) -> Option<[ZXColor; 2]> {
+        if self.fastload_border_frames != 0 {
+            return self.controller.loader_border();
+        }
+        match self.tape_phase() {
+            TapePhase::Pilot | TapePhase::Sync | TapePhase::Data => self.controller.loader_border(),
+            TapePhase::Stopped | TapePhase::Paused | TapePhase::Gap => None,
+        }
+    }
+
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
         self.controller.send_key(key, pressed);
     }
 
+    /// Ignores key releases, sent via [Emulator::send_key] within `frames`
+    /// after the press of the same key, to smooth out host keyboard
+    /// auto-repeat. Deferred release is applied when the time passes, unless
+    /// the key was pressed again. `0` disables debouncing
+    pub fn set_key_debounce(&mut self, frames: u32) {
+        self.controller.set_key_debounce(frames);
+    }
+
+    /// Remaps `from` key to `to` key for all following [Emulator::send_key] calls
+    pub fn set_key_mapping(&mut self, from: ZXKey, to: ZXKey) {
+        self.controller.set_key_mapping(from, to);
+    }
+
+    /// Removes all key remappings set via [Emulator::set_key_mapping]
+    pub fn reset_key_mapping(&mut self) {
+        self.controller.reset_key_mapping();
+    }
+
+    /// Presses or releases key at the raw keyboard matrix position. `row` is the
+    /// half-port index (0 for 0xFEFE ... 7 for 0x7FFE) and `bit` is the key bit
+    /// in range `0..5`. Positions out of these ranges are ignored
+    pub fn press_matrix(&mut self, row: usize, bit: u8, pressed: bool) {
+        self.controller.press_matrix(row, bit, pressed);
+    }
+
     pub fn send_compound_key(&mut self, key: CompoundKey, pressed: bool) {
         self.controller.send_compound_key(key, pressed);
     }
 
+    /// Returns state of the keyboard half-rows, as read from the ULA port
+    /// (row 0 for 0xFEFE ... 7 for 0x7FFE). Reset bit means pressed key
+    pub fn keyboard_matrix(&self) -> [u8; ZX_KEYBOARD_ROWS] {
+        self.controller.keyboard_matrix()
+    }
+
+    /// Releases all keyboard keys at once, e.g. when host window loses
+    /// focus and key release events are not delivered
+    pub fn clear_keyboard(&mut self) {
+        self.controller.clear_keyboard();
+    }
+
+    /// Presses or releases BREAK (CAPS SHIFT + SPACE) key combination.
+    /// [EmulationEvents::BREAK_KEY] is reported when BASIC program
+    /// execution is interrupted by ROM
+    pub fn send_break(&mut self, pressed: bool) {
+        self.controller
+            .send_compound_key(CompoundKey::Break, pressed);
+    }
+
+    /// Returns true if CPU is halted and waits for an interrupt
+    pub fn is_halted(&self) -> bool {
+        self.cpu.is_halted()
+    }
+
+    /// Returns all events which were reported by the emulator since the
+    /// last call of this method
+    pub fn take_events(&mut self) -> EmulationEvents {
+        self.events.take()
+    }
+
+    /// Returns mask of the memory blocks (bit N for the block at N * 0x4000),
+    /// which were remapped since the last call, see
+    /// [EmulationEvents::MEMORY_PAGED]
+    pub fn take_paged_blocks(&mut self) -> u8 {
+        self.controller.take_paged_blocks()
+    }
+
     pub fn send_kempston_key(&mut self, key: KempstonKey, pressed: bool) {
-        if let Some(joy) = &mut self.controller.kempston {
+        self.send_joystick(KempstonJoyNum::First, key, pressed);
+    }
+
+    /// Changes address decoding of the first Kempston joystick (and lightgun
+    /// trigger), port is matched when `port & mask == match_value`. Defaults
+    /// to the loose decode with A5-A7 reset, see
+    /// [KEMPSTON_PORT_MASK](crate::zx::joy::kempston::KEMPSTON_PORT_MASK).
+    /// `0x00FF` and `0x001F` select the exact port 0x1F
+    pub fn set_kempston_port_mask(&mut self, mask: u16, match_value: u16) {
+        self.controller.set_kempston_port_mask(mask, match_value);
+    }
+
+    /// Sends key state of the given Kempston joystick. Ignored if the
+    /// joystick is not enabled in [RustzxSettings]
+    pub fn send_joystick(&mut self, num: KempstonJoyNum, key: KempstonKey, pressed: bool) {
+        let joy = match num {
+            KempstonJoyNum::First => &mut self.controller.kempston,
+            KempstonJoyNum::Second => &mut self.controller.second_kempston,
+        };
+        if let Some(joy) = joy {
             joy.key(key, pressed);
         }
     }
@@ -246,6 +1207,14 @@ impl<H: Host> Emulator<H> {
         self.controller.send_sinclair_key(num, key, pressed);
     }
 
+    pub fn send_cursor_key(&mut self, key: CursorKey, pressed: bool) {
+        self.controller.send_cursor_key(key, pressed);
+    }
+
+    pub fn send_fuller_key(&mut self, key: FullerKey, pressed: bool) {
+        self.controller.send_fuller_key(key, pressed);
+    }
+
     pub fn send_mouse_button(&mut self, button: KempstonMouseButton, pressed: bool) {
         self.controller.send_mouse_button(button, pressed);
     }
@@ -258,54 +1227,479 @@ impl<H: Host> Emulator<H> {
         self.controller.send_mouse_pos_diff(x, y);
     }
 
+    /// Sets bindings of the host buttons to the emulated inputs for all
+    /// following [Emulator::send_input] calls. Emulated inputs, held by the
+    /// previous profile, are released
+    pub fn configure_input(&mut self, profile: InputProfile) {
+        let pressed = core::mem::take(&mut self.pressed_inputs);
+        let previous = core::mem::replace(&mut self.input_profile, profile);
+        for target in previous.bound_targets(pressed) {
+            self.send_input_target(target, false);
+        }
+    }
+
+    /// Returns current input profile, see [Emulator::configure_input]
+    pub fn input_profile(&self) -> &InputProfile {
+        &self.input_profile
+    }
+
+    /// Presses or releases host button, which drives emulated input bound
+    /// in the current [InputProfile]
+    pub fn send_input(&mut self, button: InputButton, pressed: bool) {
+        let mask = 1 << button as u8;
+        if pressed {
+            self.pressed_inputs |= mask;
+        } else {
+            self.pressed_inputs &= !mask;
+        }
+        if let Some(target) = self.input_profile.binding(button) {
+            self.send_input_target(target, pressed);
+        }
+    }
+
+    /// Converts analog stick position to the direction buttons presses. Both
+    /// axes are in range `-1.0..=1.0`, positive `x` is right and positive `y`
+    /// is down. Deflection below [InputProfile::dead_zone] is ignored
+    pub fn send_input_axes(&mut self, x: f32, y: f32) {
+        let dead_zone = self.input_profile.dead_zone;
+        let directions = [
+            (InputButton::Left, x < -dead_zone),
+            (InputButton::Right, x > dead_zone),
+            (InputButton::Up, y < -dead_zone),
+            (InputButton::Down, y > dead_zone),
+        ];
+        for (button, pressed) in directions {
+            let was_pressed = self.pressed_inputs & (1 << button as u8) != 0;
+            if pressed != was_pressed {
+                self.send_input(button, pressed);
+            }
+        }
+    }
+
+    fn send_input_target(&mut self, target: InputTarget, pressed: bool) {
+        match target {
+            InputTarget::Key(key) => self.send_key(key, pressed),
+            InputTarget::Kempston(num, key) => self.send_joystick(num, key, pressed),
+            InputTarget::Sinclair(num, key) => self.send_sinclair_key(num, key, pressed),
+            InputTarget::Cursor(key) => self.send_cursor_key(key, pressed),
+            InputTarget::Fuller(key) => self.send_fuller_key(key, pressed),
+            InputTarget::MouseButton(button) => self.send_mouse_button(button, pressed),
+        }
+    }
+
+    /// Returns rows printed by ZX Printer since the last call. Each row is
+    /// [PRINTER_ROW_SIZE](crate::zx::PRINTER_ROW_SIZE) bytes long with one
+    /// bit per dot (set for the burned dot), the leftmost dot is the most
+    /// significant bit. Empty if printer is not enabled in [RustzxSettings]
+    pub fn take_printer_output(&mut self) -> Vec<u8> {
+        self.controller
+            .printer
+            .as_mut()
+            .map_or_else(Vec::new, ZXPrinter::take_output)
+    }
+
+    /// Sets lightgun target point in canvas (paper area) coordinates, where
+    /// `x` is in `0..256` and `y` is in `0..192`, and trigger state
+    pub fn set_lightgun(&mut self, x: u16, y: u16, trigger: bool) {
+        self.controller.set_lightgun(x, y, trigger);
+    }
+
+    /// Starts logging of the AY register writes with their frame-relative
+    /// timestamps, e.g. for the music ripping. Up to `capacity` writes are
+    /// kept until taken with [Emulator::take_ay_log], frames are counted
+    /// from this call
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn start_ay_log(&mut self, capacity: usize) {
+        self.controller.ay_log = Some(AyLog::new(capacity));
+    }
+
+    /// Stops AY register writes logging, returns writes which were not taken
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn stop_ay_log(&mut self) -> Vec<AyWrite> {
+        self.controller
+            .ay_log
+            .take()
+            .map(|mut log| log.take())
+            .unwrap_or_default()
+    }
+
+    /// Returns AY register writes, logged since the last call. Empty if
+    /// logging was not started with [Emulator::start_ay_log]
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub fn take_ay_log(&mut self) -> Vec<AyWrite> {
+        self.controller
+            .ay_log
+            .as_mut()
+            .map_or_else(Vec::new, AyLog::take)
+    }
+
     #[cfg(feature = "sound")]
     pub fn next_audio_sample(&mut self) -> Option<SoundSample<f32>> {
         self.controller.mixer.pop()
     }
 
+    /// Returns count of audio samples, which will be produced during the
+    /// current frame: `sample_rate / 50`, rounded up or down so the output
+    /// does not drift from the sample rate. Every frame produces exactly this
+    /// count of samples unless buffer exceeds the latency target
+    #[cfg(feature = "sound")]
+    pub fn samples_per_frame(&self) -> usize {
+        self.controller.mixer.samples_per_frame()
+    }
+
+    /// Fills host buffer with buffered audio as interleaved left/right `f32`
+    /// values, without emulating new frames. If buffered audio is not enough,
+    /// the rest is filled with the last produced sample. Returns count of
+    /// stereo samples taken from the buffer
+    #[cfg(feature = "sound")]
+    pub fn take_audio(&mut self, out: &mut [f32]) -> usize {
+        self.controller.mixer.pop_interleaved(out)
+    }
+
+    /// Fills `samples` with exactly `samples.len()` audio samples, emulating
+    /// additional frames when buffered audio is not enough. Useful for hosts
+    /// with pull-style audio callbacks of arbitrary size. If emulation stops on
+    /// breakpoint, remaining samples repeat the last produced one. Emulation
+    /// is not performed while paused
+    #[cfg(feature = "sound")]
+    pub fn fill_audio_samples(&mut self, samples: &mut [SoundSample<f32>]) -> Result<()> {
+        let mut filled = self.controller.mixer.pop_into(samples);
+        while filled < samples.len() && !self.paused {
+            if self.emulate_single_frame()? == EmulationStopReason::Breakpoint {
+                break;
+            }
+            let popped = self.controller.mixer.pop_into(&mut samples[filled..]);
+            if popped == 0 {
+                break;
+            }
+            filled += popped;
+        }
+        self.controller.mixer.pop_exact(&mut samples[filled..]);
+        Ok(())
+    }
+
+    /// Emulates exactly one frame regardless of current emulation mode
+    fn emulate_single_frame(&mut self) -> Result<EmulationStopReason> {
+        let mode = core::mem::replace(&mut self.mode, EmulationMode::FrameCount(1));
+        let result = self.emulate_frames(Duration::MAX);
+        self.mode = mode;
+        Ok(result?.stop_reason)
+    }
+
+    /// Emulates exactly one frame regardless of current emulation mode and host
+    /// time, then returns rendered screen buffer. Breakpoints do not interrupt the
+    /// frame. Intended for headless drivers, bots and screenshot tests
+    pub fn emulate_frame(&mut self) -> Result<&H::FrameBuffer> {
+        self.emulate_frame_count(1)
+    }
+
+    /// Emulates [Emulator::turbo] frames regardless of current emulation mode
+    /// and host time. Audio of all frames except the last one is dropped, so
+    /// host audio buffer is filled at the normal rate. Stops on breakpoint,
+    /// returns count of completed frames
+    pub fn emulate_turbo_frames(&mut self) -> Result<usize> {
+        #[cfg(feature = "sound")]
+        let buffered = self.controller.mixer.buffered_samples();
+        for frame in 0..self.turbo as usize {
+            #[cfg(feature = "sound")]
+            self.controller.mixer.truncate(buffered);
+            if self.emulate_single_frame()? != EmulationStopReason::Completed {
+                return Ok(frame);
+            }
+        }
+        Ok(self.turbo as usize)
+    }
+
+    /// Emulates exactly `count` frames, see [Emulator::emulate_frame]. Does
+    /// nothing while paused
+    pub fn emulate_frame_count(&mut self, count: usize) -> Result<&H::FrameBuffer> {
+        if self.paused {
+            return Ok(self.screen_buffer());
+        }
+        for _ in 0..count {
+            while self.emulate_single_frame()? != EmulationStopReason::Completed {}
+        }
+        Ok(self.screen_buffer())
+    }
+
     fn process_fast_load_event(&mut self) -> Result<()> {
-        if self.controller.tape.can_fast_load() && self.fast_load {
+        if self.controller.tape.can_fast_load() {
             fastload::tap::fast_load_tap(self)?;
+            self.fastload_border_frames = self.settings.tape_fastload_border_frames;
         }
         Ok(())
     }
 
-    /// Execute `poke::Poke` action on the emulator
-    pub fn execute_poke(&mut self, poke: impl poke::Poke) {
+    /// Writes `value` to `addr` of the current memory map, including ROM
+    pub fn apply_poke(&mut self, addr: u16, value: u8) {
+        self.controller.memory.force_write(addr, value);
+        if let (Page::Ram(bank), offset) = self.controller.memory.paged_address(addr) {
+            self.controller
+                .screen
+                .update(offset as u16, bank as usize, value);
+        }
+    }
+
+    /// Writes list of `(addr, value)` pairs, see [Emulator::apply_poke]
+    pub fn apply_pokes(&mut self, pokes: &[(u16, u8)]) {
+        for (addr, value) in pokes.iter().copied() {
+            self.apply_poke(addr, value);
+        }
+    }
+
+    /// Execute `poke::Poke` action on the emulator. Returns error if poke
+    /// targets ram bank which does not exist on the current machine
+    pub fn execute_poke(&mut self, poke: impl poke::Poke) -> Result<()> {
         for action in poke.actions().iter().copied() {
             match action {
                 poke::PokeAction::Mem { addr, value } => {
-                    self.controller.memory.force_write(addr, value);
+                    self.apply_poke(addr, value);
+                }
+                poke::PokeAction::RamBank { bank, addr, value } => {
+                    if !self.controller.machine.capabilities().has_128k_paging {
+                        self.apply_poke(addr, value);
+                    } else {
+                        let data = self.controller.memory.ram_bank_mut(bank as usize)?;
+                        let offset = addr as usize % PAGE_SIZE;
+                        data[offset] = value;
+                        self.controller
+                            .screen
+                            .update(offset as u16, bank as usize, value);
+                    }
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Executes single cpu step and processes emulation events. Returns `true`
+    /// if breakpoint was hit
+    fn emulate_cpu_step(&mut self) -> Result<bool> {
+        let frames_count = self.controller.frames_count();
+        // Emulation step. if instant event happened then accept in and execute
+        self.cpu.emulate(&mut self.controller);
+        if let Some(e) = self.controller.take_last_emulation_error() {
+            return Err(e);
+        }
+        if self.controller.frames_count() != frames_count {
+            self.process_rewind_frame();
+            self.fastload_border_frames = self.fastload_border_frames.saturating_sub(1);
+        }
+
+        let events = self.controller.take_events();
+        if events.is_empty() {
+            return Ok(false);
+        }
+        self.events |= events;
+        if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
+            self.process_fast_load_event()?;
+        }
+        Ok(events.contains(EmulationEvents::PC_BREAKPOINT))
+    }
+
+    /// Executes single cpu step, see [Emulator::emulate_cpu_step]. Returns
+    /// breakpoint flag with count of clocks, passed during the step
+    fn emulate_timed_cpu_step(&mut self) -> Result<(bool, u64)> {
+        let frames_count = self.controller.frames_count();
+        let frame_clocks = self.controller.frame_clocks();
+        let breakpoint = self.emulate_cpu_step()?;
+        let passed_frames = self.controller.frames_count() - frames_count;
+        let executed = self.controller.frame_clocks()
+            + passed_frames * self.controller.clocks_frame()
+            - frame_clocks;
+        Ok((breakpoint, executed as u64))
+    }
+
+    /// Executes `count` cpu instructions regardless of current emulation mode
+    /// and host time. Stops earlier if breakpoint was hit
+    pub fn step_instructions(&mut self, count: u64) -> Result<EmulationStopReason> {
+        if self.paused {
+            return Ok(EmulationStopReason::Paused);
+        }
+        let target = self.cpu.instructions_executed() + count;
+        while self.cpu.instructions_executed() < target {
+            if self.emulate_cpu_step()? {
+                return Ok(EmulationStopReason::Breakpoint);
+            }
+        }
+        Ok(EmulationStopReason::Completed)
+    }
+
+    /// Executes exactly one cpu instruction, see [Emulator::step_instructions]
+    pub fn step(&mut self) -> Result<EmulationStopReason> {
+        self.step_instructions(1)
+    }
+
+    /// Executes cpu instructions until `target` clock is reached, regardless
+    /// of current emulation mode and host time. Target is counted from the
+    /// start of the current frame, values beyond the frame length are reached
+    /// in the following frames. Emulation stops on the first instruction
+    /// boundary at or after the target, nothing is executed if the target
+    /// has already passed. Returns count of executed instructions, stops
+    /// earlier if breakpoint was hit
+    pub fn run_until_clocks(&mut self, target: u32) -> Result<u64> {
+        if self.paused {
+            return Ok(0);
+        }
+        let start = self.cpu.instructions_executed();
+        let clocks_frame = self.controller.clocks_frame();
+        let mut frames_left = target as usize / clocks_frame;
+        let target = target as usize % clocks_frame;
+        while frames_left > 0 || self.controller.frame_clocks() < target {
+            let frames_count = self.controller.frames_count();
+            if self.emulate_cpu_step()? {
+                break;
+            }
+            if self.controller.frames_count() != frames_count {
+                frames_left = frames_left.saturating_sub(1);
+            }
+        }
+        Ok(self.cpu.instructions_executed() - start)
+    }
+
+    /// Executes one cpu instruction. Subroutine calls (`CALL` and `RST`) are
+    /// executed until return to the following instruction. Stops earlier if
+    /// breakpoint was hit or `timeout` of host time has elapsed
+    pub fn step_over(&mut self, timeout: Duration) -> Result<EmulationStopReason> {
+        let (bytes, length) = self.peek_instruction();
+        // CALL nn, CALL cc, nn and RST p
+        let is_call = bytes[0] == 0xCD || bytes[0] & 0xC7 == 0xC4 || bytes[0] & 0xC7 == 0xC7;
+        if !is_call || self.paused {
+            return self.step();
+        }
+        let return_addr = self.cpu.regs.get_pc().wrapping_add(length as u16);
+        let sp = self.cpu.regs.get_sp();
+        let stopwatch = H::EmulationStopwatch::new();
+        let mut frames_count = self.controller.frames_count();
+        loop {
+            if self.emulate_cpu_step()? {
+                return Ok(EmulationStopReason::Breakpoint);
+            }
+            // Recursive calls return to the same address with lower stack pointer
+            if self.cpu.regs.get_pc() == return_addr && self.cpu.regs.get_sp() >= sp {
+                return Ok(EmulationStopReason::Completed);
+            }
+            // Host time is checked once per frame
+            if self.controller.frames_count() != frames_count {
+                frames_count = self.controller.frames_count();
+                if stopwatch.measure() > timeout {
+                    return Ok(EmulationStopReason::Timeout);
+                }
+            }
+        }
+    }
+
+    /// Executes cpu instructions until PC reaches `addr` and returns count of
+    /// consumed clocks, including contention and interrupts accepted on the
+    /// way. At least one instruction is executed, so a loop iteration could
+    /// be measured by passing its own address. Stops earlier if breakpoint
+    /// was hit or `timeout` of host time has elapsed
+    pub fn profile_until(&mut self, addr: u16, timeout: Duration) -> Result<ProfileInfo> {
+        if self.paused {
+            return Ok(ProfileInfo {
+                clocks: 0,
+                stop_reason: EmulationStopReason::Paused,
+            });
+        }
+        let stopwatch = H::EmulationStopwatch::new();
+        let mut frames_count = self.controller.frames_count();
+        let start_clocks = self.controller.cpu_clocks();
+        let stop_reason = loop {
+            if self.emulate_cpu_step()? {
+                break EmulationStopReason::Breakpoint;
+            }
+            if self.cpu.regs.get_pc() == addr {
+                break EmulationStopReason::Completed;
+            }
+            // Host time is checked once per frame
+            if self.controller.frames_count() != frames_count {
+                frames_count = self.controller.frames_count();
+                if stopwatch.measure() > timeout {
+                    break EmulationStopReason::Timeout;
+                }
+            }
+        };
+        Ok(ProfileInfo {
+            clocks: self.controller.cpu_clocks() - start_clocks,
+            stop_reason,
+        })
+    }
+
+    /// Returns count of cpu instructions executed since the emulator
+    /// construction or the last [Emulator::reset_instruction_counter] call
+    pub fn instructions_executed(&self) -> u64 {
+        self.cpu.instructions_executed()
+    }
+
+    /// Resets counter of executed instructions
+    pub fn reset_instruction_counter(&mut self) {
+        self.cpu.reset_instruction_counter();
+    }
+
+    /// Advances emulation by cpu clocks, which take `dt` of the real time
+    /// (cpu frequency of the machine). Clocks of the instruction
+    /// which has run past the target are subtracted on the next call, so
+    /// pacing does not drift. When host falls behind by more than 5 frames,
+    /// the rest of the lag is dropped and emulation slows down instead of
+    /// catching up. Stops on breakpoint, pending clocks are dropped then
+    pub fn run_realtime(&mut self, dt: Duration) -> Result<EmulationStopReason> {
+        if self.paused {
+            return Ok(EmulationStopReason::Paused);
+        }
+        let clocks_frame = self.controller.clocks_frame();
+        let clocks = dt.as_nanos() * self.controller.freq_cpu() as u128
+            + self.realtime_clocks_fraction as u128;
+        self.realtime_clocks_fraction = (clocks % NANOS_PER_SEC) as u64;
+        let max_lag = clocks_frame as u64 * MAX_REALTIME_LAG_FRAMES;
+        let clocks = (clocks / NANOS_PER_SEC).min(max_lag as u128) as i64;
+        self.realtime_clocks = (self.realtime_clocks + clocks).min(max_lag as i64);
+
+        while self.realtime_clocks > 0 {
+            let (breakpoint, executed) = self.emulate_timed_cpu_step()?;
+            self.realtime_clocks -= executed as i64;
+            if breakpoint {
+                self.realtime_clocks = 0;
+                return Ok(EmulationStopReason::Breakpoint);
+            }
+        }
+        Ok(EmulationStopReason::Completed)
+    }
+
+    /// Advances emulation by the host time elapsed since the previous call,
+    /// measured with [Host::EmulationStopwatch]. The first call only starts
+    /// the clock. See [Emulator::run_realtime]
+    pub fn run_realtime_elapsed(&mut self) -> Result<EmulationStopReason> {
+        let Some((stopwatch, last)) = &mut self.realtime_clock else {
+            let stopwatch = H::EmulationStopwatch::new();
+            let started = stopwatch.measure();
+            self.realtime_clock = Some((stopwatch, started));
+            return Ok(EmulationStopReason::Completed);
+        };
+        let now = stopwatch.measure();
+        let dt = now.saturating_sub(*last);
+        *last = now;
+        self.run_realtime(dt)
     }
 
     /// Perform emulatio up to `emulation_limit` duration, returns actual elapsed duration
     pub fn emulate_frames(&mut self, emulation_limit: Duration) -> Result<EmulationInfo> {
+        if self.paused {
+            return Ok(EmulationInfo {
+                duration: Duration::ZERO,
+                stop_reason: EmulationStopReason::Paused,
+            });
+        }
         let stopwatch = H::EmulationStopwatch::new();
         // frame loop
         loop {
             // reset controller internal frame counter
             self.controller.reset_frame_counter();
             'cpu: loop {
-                // Emulation step. if instant event happened then accept in and execute
-                self.cpu.emulate(&mut self.controller);
-                if let Some(e) = self.controller.take_last_emulation_error() {
-                    return Err(e);
-                }
-
-                let events = self.controller.take_events();
-                if !events.is_empty() {
-                    if events.contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED) {
-                        self.process_fast_load_event()?;
-                    }
-                    if events.contains(EmulationEvents::PC_BREAKPOINT) {
-                        return Ok(EmulationInfo {
-                            duration: stopwatch.measure(),
-                            stop_reason: EmulationStopReason::Breakpoint,
-                        });
-                    }
+                if self.emulate_cpu_step()? {
+                    return Ok(EmulationInfo {
+                        duration: stopwatch.measure(),
+                        stop_reason: EmulationStopReason::Breakpoint,
+                    });
                 }
 
                 match self.mode {
@@ -334,3 +1728,12 @@ impl<H: Host> Emulator<H> {
         }
     }
 }
+
+/// Reads whole seekable asset into memory
+fn read_whole_asset(mut asset: impl DiskAsset) -> Result<Vec<u8>> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    let mut data = alloc::vec![0u8; size];
+    asset.read_exact(&mut data)?;
+    Ok(data)
+}
@@ -1,9 +1,21 @@
 //! Pokes are used to modify internal emulator state such as memory, registers, etc.
+use crate::{error::PokeParseError, Result};
+use alloc::{string::String, vec::Vec};
 
 /// Action to perform on emulator state
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PokeAction {
-    Mem { addr: u16, value: u8 },
+    Mem {
+        addr: u16,
+        value: u8,
+    },
+    /// Writes to the given 128K ram bank, even if it is not paged in. `addr` is
+    /// taken modulo bank size. On machines without paging acts as `Mem`
+    RamBank {
+        bank: u8,
+        addr: u16,
+        value: u8,
+    },
 }
 
 impl PokeAction {
@@ -11,6 +23,11 @@ impl PokeAction {
     pub const fn mem(addr: u16, value: u8) -> Self {
         Self::Mem { addr, value }
     }
+
+    /// Creates new ram bank poke action
+    pub const fn ram_bank(bank: u8, addr: u16, value: u8) -> Self {
+        Self::RamBank { bank, addr, value }
+    }
 }
 
 pub trait Poke {
@@ -18,6 +35,12 @@ pub trait Poke {
     fn actions(&self) -> &[PokeAction];
 }
 
+impl<T: Poke> Poke for &T {
+    fn actions(&self) -> &[PokeAction] {
+        (*self).actions()
+    }
+}
+
 /// Poke which disables message and enter key prompt in 48K ROM when scrolling screen in BASIC mode
 pub struct DisableScrollMessageRom48;
 impl Poke for DisableScrollMessageRom48 {
@@ -33,3 +56,149 @@ impl Poke for DisableScrollMessageRom48 {
         ACTIONS
     }
 }
+
+/// Value in `.pok` file which means that poke value should be asked from user
+const POK_USER_VALUE: u16 = 256;
+/// Bank bit in `.pok` file which means that poke is not bound to 128K bank
+const POK_NO_BANK: u8 = 0x08;
+
+/// Named set of pokes (e.g. "Infinite lives") from the `.pok` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PokTrainer {
+    pub name: String,
+    actions: Vec<PokeAction>,
+    // Indices of actions which require user-provided value
+    user_value_actions: Vec<usize>,
+}
+
+impl PokTrainer {
+    /// Returns true if trainer expects value provided by user (e.g. lives count),
+    /// which should be set via [PokTrainer::set_value]
+    pub fn requires_value(&self) -> bool {
+        !self.user_value_actions.is_empty()
+    }
+
+    /// Sets user-provided value for the pokes which require it
+    pub fn set_value(&mut self, value: u8) {
+        for idx in self.user_value_actions.iter().copied() {
+            match &mut self.actions[idx] {
+                PokeAction::Mem { value: v, .. } | PokeAction::RamBank { value: v, .. } => {
+                    *v = value
+                }
+            }
+        }
+    }
+}
+
+impl Poke for PokTrainer {
+    fn actions(&self) -> &[PokeAction] {
+        &self.actions
+    }
+}
+
+/// Parses trainers from the `.pok` file content. Each trainer starts with
+/// `N<name>` line, followed by `M`/`Z` poke lines (`Z` marks the last poke of
+/// the trainer) in `<bank> <addr> <value> <original>` format. File ends with `Y`
+pub fn parse_pok(data: &str) -> Result<Vec<PokTrainer>> {
+    let mut trainers = Vec::new();
+    let mut current: Option<PokTrainer> = None;
+    for line in data.lines() {
+        let line = line.trim_end();
+        let mut chars = line.chars();
+        match chars.next() {
+            Some('N') => {
+                if current.is_some() {
+                    return Err(PokeParseError::InvalidPokFile.into());
+                }
+                current = Some(PokTrainer {
+                    name: chars.as_str().trim().into(),
+                    actions: Vec::new(),
+                    user_value_actions: Vec::new(),
+                });
+            }
+            Some(kind @ ('M' | 'Z')) => {
+                let trainer = current.as_mut().ok_or(PokeParseError::InvalidPokFile)?;
+                let (bank, addr, value) = parse_pok_line(chars.as_str())?;
+                let value = if value == POK_USER_VALUE {
+                    trainer.user_value_actions.push(trainer.actions.len());
+                    0
+                } else {
+                    u8::try_from(value).map_err(|_| PokeParseError::InvalidPokFile)?
+                };
+                let action = if bank & POK_NO_BANK != 0 {
+                    PokeAction::mem(addr, value)
+                } else {
+                    PokeAction::ram_bank(bank, addr, value)
+                };
+                trainer.actions.push(action);
+                if kind == 'Z' {
+                    trainers.extend(current.take());
+                }
+            }
+            Some('Y') => break,
+            None => {}
+            Some(_) => return Err(PokeParseError::InvalidPokFile.into()),
+        }
+    }
+    if current.is_some() {
+        return Err(PokeParseError::InvalidPokFile.into());
+    }
+    Ok(trainers)
+}
+
+/// Returns bank, address and value of the poke line
+fn parse_pok_line(line: &str) -> Result<(u8, u16, u16)> {
+    let mut fields = line.split_whitespace().map(|f| f.parse::<u16>().ok());
+    let mut next = || {
+        fields
+            .next()
+            .flatten()
+            .ok_or(PokeParseError::InvalidPokFile)
+    };
+    let bank = u8::try_from(next()?).map_err(|_| PokeParseError::InvalidPokFile)?;
+    let addr = next()?;
+    let value = next()?;
+    // Original value is not used
+    next()?;
+    Ok((bank, addr, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POK: &str = "\
+NInfinite lives
+M  8 43562   0  61
+Z  8 43563 201  62
+NStart level
+Z  3 49152 256   1
+Y
+";
+
+    #[test]
+    fn pok_parsing() {
+        let mut trainers = parse_pok(POK).unwrap();
+        assert_eq!(trainers.len(), 2);
+
+        assert_eq!(trainers[0].name, "Infinite lives");
+        assert!(!trainers[0].requires_value());
+        assert_eq!(
+            trainers[0].actions(),
+            &[PokeAction::mem(43562, 0), PokeAction::mem(43563, 201)]
+        );
+
+        assert_eq!(trainers[1].name, "Start level");
+        assert!(trainers[1].requires_value());
+        trainers[1].set_value(5);
+        assert_eq!(trainers[1].actions(), &[PokeAction::ram_bank(3, 49152, 5)]);
+    }
+
+    #[test]
+    fn pok_parsing_invalid() {
+        assert!(parse_pok("M  8 43562   0  61\nY\n").is_err());
+        assert!(parse_pok("NLives\nZ  8 43562\nY\n").is_err());
+        assert!(parse_pok("NLives\nM  8 43562   0  61\nY\n").is_err());
+        assert!(parse_pok("NLives\nZ  8 43562 300  61\nY\n").is_err());
+    }
+}
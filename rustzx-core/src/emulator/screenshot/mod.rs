@@ -1 +1,3 @@
+#[cfg(feature = "png")]
+pub mod png;
 pub mod scr;
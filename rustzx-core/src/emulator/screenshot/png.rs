@@ -0,0 +1,119 @@
+//! Minimal PNG encoder for the screen snapshots
+use crate::{
+    emulator::Emulator,
+    host::{FrameBuffer, FrameView, Host, PixelFormat},
+    utils::crc32::crc32,
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
+        video::colors::{ZXBrightness, ZXColor},
+    },
+    Palette,
+};
+use alloc::vec::Vec;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGB: u8 = 2;
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Encodes the last completed frame to RGB PNG image. Border frame (with
+/// `precise-border` feature) is placed around the screen, Timex hi-res screen
+/// is twice wider, so border pixels are doubled horizontally. Returns `None`
+/// if host frame buffers do not expose their pixels, see
+/// [FrameBuffer::pixel_format]
+pub fn export<H: Host>(emulator: &Emulator<H>) -> Option<Vec<u8>> {
+    let screen = emulator.screen_frame()?;
+    let screen_format = emulator.screen_buffer().pixel_format()?;
+    #[cfg(feature = "precise-border")]
+    let border = emulator
+        .border_frame()
+        .zip(emulator.border_buffer().pixel_format());
+    #[cfg(not(feature = "precise-border"))]
+    let border: Option<(FrameView, PixelFormat)> = None;
+
+    let border_size = emulator.settings.border_size;
+    let (border_width, border_height) = match border {
+        Some(_) => (border_size.border_width(), border_size.border_height()),
+        None => (0, 0),
+    };
+    let scale = screen.width / CANVAS_WIDTH;
+    let width = screen.width + border_width * 2 * scale;
+    let height = CANVAS_HEIGHT + border_height * 2;
+    let palette = emulator.settings.palette;
+
+    // Each row starts with filter type byte
+    let mut pixels = Vec::with_capacity((width * 3 + 1) * height);
+    for y in 0..height {
+        pixels.push(0);
+        for x in 0..width {
+            let canvas_x = x.wrapping_sub(border_width * scale);
+            let canvas_y = y.wrapping_sub(border_height);
+            let rgb = match &border {
+                Some((border, format)) if canvas_x >= screen.width || canvas_y >= CANVAS_HEIGHT => {
+                    pixel_rgb(border, *format, palette, x / scale, y)
+                }
+                _ => pixel_rgb(&screen, screen_format, palette, canvas_x, canvas_y),
+            };
+            pixels.extend_from_slice(&rgb);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGB, 0, 0, 0]);
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(
+        &mut out,
+        b"IDAT",
+        &compress_to_vec_zlib(&pixels, COMPRESSION_LEVEL),
+    );
+    write_chunk(&mut out, b"IEND", &[]);
+    Some(out)
+}
+
+/// Returns RGB color of the frame pixel. Indexed colors are looked up in the
+/// `palette`
+fn pixel_rgb(
+    frame: &FrameView,
+    format: PixelFormat,
+    palette: Palette,
+    x: usize,
+    y: usize,
+) -> [u8; 3] {
+    let row = &frame.data[y * format.row_size(frame.width)..];
+    match format {
+        PixelFormat::Rgba8888 => [row[x * 4], row[x * 4 + 1], row[x * 4 + 2]],
+        PixelFormat::Rgb565 => {
+            let value = u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]);
+            let (r, g, b) = (
+                (value >> 11) as u8,
+                (value >> 5) as u8 & 0x3F,
+                value as u8 & 0x1F,
+            );
+            [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+        }
+        PixelFormat::Indexed4 => {
+            let index = row[x / 2] >> (if x.is_multiple_of(2) { 4 } else { 0 }) & 0x0F;
+            let brightness = if index < 8 {
+                ZXBrightness::Normal
+            } else {
+                ZXBrightness::Bright
+            };
+            let [r, g, b, _] = palette.rgba(ZXColor::from_bits(index & 0x07), brightness);
+            [r, g, b]
+        }
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[crc_start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
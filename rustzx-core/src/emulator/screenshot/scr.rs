@@ -5,6 +5,7 @@ use crate::{
     zx::memory::Page,
     Result,
 };
+use alloc::vec::Vec;
 use rustzx_z80::CodeGenerator;
 
 const PRIMARY_SCREEN_MEMORY_SIZE: usize = 6912;
@@ -28,7 +29,7 @@ where
 
     let bank = match emulator.controller.memory.get_page(SCREEN_ADDR) {
         Page::Ram(page) => page,
-        Page::Rom(_) => {
+        Page::Rom(_) | Page::Unmapped => {
             // Machine with such memory map is not implemented yet
             return Err(ScreenLoadError::MachineNotSupported.into());
         }
@@ -49,3 +50,8 @@ where
 
     Ok(())
 }
+
+/// Returns `*.scr` dump (bitmap and attributes) of the active screen
+pub fn export<H: Host>(emulator: &Emulator<H>) -> Vec<u8> {
+    emulator.screen_bytes()[..PRIMARY_SCREEN_MEMORY_SIZE].to_vec()
+}
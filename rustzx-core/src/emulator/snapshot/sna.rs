@@ -2,9 +2,10 @@ use crate::{
     emulator::Emulator,
     error::IoError,
     host::{DataRecorder, Host, LoadableAsset, SeekFrom, SeekableAsset},
-    zx::{machine::ZXMachine, video::colors::ZXColor},
+    zx::{machine::ZXMachine, memory::PAGE_SIZE, video::colors::ZXColor},
     Result,
 };
+use alloc::vec;
 
 const SNA_HEADER_SIZE: usize = 27;
 const SNA_128K_SECONDARY_HEADER_SIZE: usize = 4;
@@ -18,7 +19,7 @@ const SNA_INTERRUPT_MODE_MASK: u8 = 0x03;
 const SNA_BORDER_COLOR_MASK: u8 = 0x07;
 const SNA_128K_TAIL_BANKS: &[u8] = &[0, 1, 3, 4, 6, 7];
 const SNA_PAGINATED_PAGED_BANK_ADDRESS: u16 = 0xFFFF;
-const SNA_48K_RAM_PAGES_COUNT: u8 = 3;
+const SNA_48K_RAM_PAGES_COUNT: usize = 3;
 
 /// SNA snapshot loading function
 pub fn load<H, A>(emulator: &mut Emulator<H>, mut asset: A) -> Result<()>
@@ -126,7 +127,7 @@ where
             .get_page(SNA_PAGINATED_PAGED_BANK_ADDRESS)
         {
             crate::zx::memory::Page::Ram(bank) => bank,
-            crate::zx::memory::Page::Rom(_) => 0,
+            crate::zx::memory::Page::Rom(_) | crate::zx::memory::Page::Unmapped => 0,
         };
 
         // write 3 head banks
@@ -150,8 +151,13 @@ where
             asset.read_exact(page)?;
         }
     } else {
+        let mut unmapped = vec![0u8; PAGE_SIZE];
         for page_index in 0..SNA_48K_RAM_PAGES_COUNT {
-            let page = emulator.controller.memory.ram_page_data_mut(page_index);
+            // 16K machine has no RAM above 0x7FFF, these pages are skipped
+            let page = match emulator.controller.memory.ram_bank_mut(page_index) {
+                Ok(page) => page,
+                Err(_) => &mut unmapped,
+            };
             asset.read_exact(page)?;
         }
 
@@ -161,6 +167,13 @@ where
     // Refresh screen and other memory-dependent peripheral
     emulator.controller.refresh_memory_dependent_devices();
 
+    log_debug!(
+        "SNA snapshot loaded: {}, PC = {:#06X}, SP = {:#06X}",
+        if is_128k { "128K" } else { "48K" },
+        emulator.cpu.regs.get_pc(),
+        emulator.cpu.regs.get_sp()
+    );
+
     Ok(())
 }
 
@@ -173,7 +186,10 @@ struct ScopedSnapshotState<'a, H: Host> {
 
 impl<'a, H: Host> ScopedSnapshotState<'a, H> {
     fn enter(emulator: &'a mut Emulator<H>) -> Self {
-        let is_48k = emulator.settings.machine == ZXMachine::Sinclair48K;
+        let is_48k = matches!(
+            emulator.settings.machine,
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K
+        );
         if is_48k {
             emulator.cpu.push_pc_to_stack(&mut emulator.controller);
         }
@@ -246,8 +262,13 @@ where
     recorder.write_all(&header)?;
 
     if *is_48k {
+        let unmapped = vec![0xFFu8; PAGE_SIZE];
         for page_index in 0..SNA_48K_RAM_PAGES_COUNT {
-            let page = emulator.controller.memory.ram_page_data(page_index);
+            let page = emulator
+                .controller
+                .memory
+                .ram_bank(page_index)
+                .unwrap_or(&unmapped);
             recorder.write_all(page)?;
         }
     } else {
@@ -257,7 +278,7 @@ where
             .get_page(SNA_PAGINATED_PAGED_BANK_ADDRESS)
         {
             crate::zx::memory::Page::Ram(bank) => bank,
-            crate::zx::memory::Page::Rom(_) => 0,
+            crate::zx::memory::Page::Rom(_) | crate::zx::memory::Page::Unmapped => 0,
         };
         let head_banks = &[
             SNA_128K_PERSISTENT_BANK_0,
@@ -11,6 +11,18 @@ pub enum Error {
     TapeLoad(TapeLoadError),
     /// Failed to load screen
     ScreenLoad(ScreenLoadError),
+    /// Failed to load disk image
+    DiskLoad(DiskLoadError),
+    /// Failed to access memory
+    MemoryAccess(MemoryAccessError),
+    /// Failed to parse pokes
+    PokeParse(PokeParseError),
+    /// Failed to load emulator state
+    StateLoad(StateLoadError),
+    /// Invalid custom machine specs
+    MachineSpec(MachineSpecError),
+    /// Invalid cpu state
+    CpuState(CpuStateError),
 }
 
 #[derive(Debug, Display)]
@@ -23,18 +35,30 @@ pub enum IoError {
     SeekBeforeStart,
     /// Host-provided asset implementation failed
     HostAssetImplFailed,
+    /// Compressed asset data is corrupted
+    InvalidCompressedAsset,
+    /// Archive does not contain file of the supported format
+    NoSupportedArchivedFile,
 }
 
 #[derive(Debug, Display)]
 pub enum RomLoadError {
     /// More assets required to load rom
     MoreAssetsRequired,
+    /// Beta Disk interface is not enabled
+    BetaDiskNotEnabled,
+    /// Provided ROM data size does not match ROM size of the machine
+    InvalidRomSize,
+    /// Machine has no built-in ROM, it should be loaded with `Emulator::load_rom`
+    NoEmbeddedRom,
 }
 
 #[derive(Debug, Display)]
 pub enum TapeLoadError {
     /// Provided tap file is invalid
     InvalidTapFile,
+    /// Requested tape block does not exist
+    BlockOutOfRange,
 }
 
 #[derive(Debug, Display)]
@@ -44,3 +68,55 @@ pub enum ScreenLoadError {
     /// Selected machine can't be used to load given screen file
     MachineNotSupported,
 }
+
+#[derive(Debug, Display)]
+pub enum DiskLoadError {
+    /// Provided dsk file is invalid
+    InvalidDskFile,
+    /// Provided trd file is invalid
+    InvalidTrdFile,
+    /// Provided scl file is invalid
+    InvalidSclFile,
+    /// Emulator has no disk drive compatible with provided image
+    MachineNotSupported,
+}
+
+#[derive(Debug, Display)]
+pub enum PokeParseError {
+    /// Provided pok file is invalid
+    InvalidPokFile,
+}
+
+#[derive(Debug, Display)]
+pub enum MemoryAccessError {
+    /// Requested ram bank does not exist on the current machine
+    RamBankOutOfRange,
+}
+
+#[derive(Debug, Display)]
+pub enum StateLoadError {
+    /// Provided state data is invalid
+    InvalidState,
+    /// State was saved by incompatible emulator version
+    UnsupportedVersion,
+    /// State was saved for the different machine or machine timings
+    MachineMismatch,
+}
+
+#[derive(Debug, Display)]
+pub enum MachineSpecError {
+    /// Line is too short to fit the screen row and the borders
+    LineTooShort,
+    /// Frame is not a whole number of lines or can't fit screen and borders
+    InvalidFrameLength,
+    /// First pixel position leaves no room for the top or bottom border
+    FirstPixelOutOfFrame,
+    /// Interrupt length should be in range `1..clocks_line`
+    InvalidInterruptLength,
+}
+
+#[derive(Debug, Display)]
+pub enum CpuStateError {
+    /// Interrupt mode should be in range `0..=2`
+    InvalidInterruptMode,
+}
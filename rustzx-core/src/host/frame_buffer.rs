@@ -1,4 +1,11 @@
-use crate::zx::video::colors::{ZXBrightness, ZXColor};
+use crate::{
+    zx::video::colors::{ZXBrightness, ZXColor},
+    Palette,
+};
+use alloc::{vec, vec::Vec};
+
+/// Count of colors: normal colors followed by bright
+const PALETTE_SIZE: usize = 16;
 
 pub enum FrameBufferSource {
     Screen,
@@ -11,4 +18,231 @@ pub trait FrameBuffer {
     fn new(width: usize, height: usize, source: FrameBufferSource, context: Self::Context) -> Self;
     /// Set `color` with `brightness` for pixel on canvas at (`x`, `y`)
     fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness);
+    /// Set pixel on canvas at (`x`, `y`) to the mix of the current and previous
+    /// frame colors. Called instead of `set_color` when frames blending
+    /// (gigascreen) is enabled. Default implementation just uses current color
+    fn set_blended_color(
+        &mut self,
+        x: usize,
+        y: usize,
+        current: (ZXColor, ZXBrightness),
+        _previous: (ZXColor, ZXBrightness),
+    ) {
+        self.set_color(x, y, current.0, current.1);
+    }
+    /// Returns raw pixel data of the buffer if host stores it contiguously
+    /// in memory (e.g. RGBA bytes), which allows frontends to upload the
+    /// completed frame to the texture without copying. Default implementation
+    /// returns `None`
+    fn as_bytes(&self) -> Option<&[u8]> {
+        None
+    }
+    /// Returns format of the pixel data, returned by [FrameBuffer::as_bytes],
+    /// if buffer rows are stored in one of the [PixelFormat]s. Used for the
+    /// frame export, default implementation returns `None`
+    fn pixel_format(&self) -> Option<PixelFormat> {
+        None
+    }
+}
+
+/// Borrowed pixel data of the completed frame with its dimensions in pixels
+pub struct FrameView<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Pixel format of the [PixelFrameBuffer]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel: red, green, blue and alpha
+    #[default]
+    Rgba8888,
+    /// 2 bytes per pixel: little-endian `RRRRRGGGGGGBBBBB` word
+    Rgb565,
+    /// 4 bits per pixel: color index, where bright colors start from 8. Left
+    /// pixel of the pair is stored in the high nibble
+    Indexed4,
+}
+
+impl PixelFormat {
+    /// Returns size of the `width` pixels row in bytes
+    pub fn row_size(self, width: usize) -> usize {
+        match self {
+            PixelFormat::Rgba8888 => width * 4,
+            PixelFormat::Rgb565 => width * 2,
+            PixelFormat::Indexed4 => width.div_ceil(2),
+        }
+    }
+}
+
+/// Context of the [PixelFrameBuffer], selects its pixel format and palette
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelFrameBufferContext {
+    pub format: PixelFormat,
+    /// Colors of the RGB formats, not used by [PixelFormat::Indexed4]
+    pub palette: Palette,
+}
+
+/// Ready to use [FrameBuffer], which stores pixels contiguously in the
+/// format, selected on construction. RGBA suits desktop frontends, while
+/// embedded ones could save memory and bandwidth with RGB565 or indexed
+/// pixels for the palette-based displays
+pub struct PixelFrameBuffer {
+    buffer: Vec<u8>,
+    format: PixelFormat,
+    colors: [[u8; 4]; PALETTE_SIZE],
+    row_size: usize,
+}
+
+impl PixelFrameBuffer {
+    /// Returns pixel format of the buffer
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    fn write_rgba(&mut self, x: usize, y: usize, [r, g, b, a]: [u8; 4]) {
+        let pos = y * self.row_size;
+        match self.format {
+            PixelFormat::Rgba8888 => {
+                let pos = pos + x * 4;
+                self.buffer[pos..pos + 4].copy_from_slice(&[r, g, b, a]);
+            }
+            PixelFormat::Rgb565 => {
+                let pos = pos + x * 2;
+                let value = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+                self.buffer[pos..pos + 2].copy_from_slice(&value.to_le_bytes());
+            }
+            PixelFormat::Indexed4 => unreachable!("Indexed pixels have no RGB value"),
+        }
+    }
+
+    fn write_index(&mut self, x: usize, y: usize, index: u8) {
+        let byte = &mut self.buffer[y * self.row_size + x / 2];
+        *byte = if x.is_multiple_of(2) {
+            (*byte & 0x0F) | (index << 4)
+        } else {
+            (*byte & 0xF0) | index
+        };
+    }
+}
+
+fn color_index(color: ZXColor, brightness: ZXBrightness) -> u8 {
+    u8::from(color) + brightness as u8 * 8
+}
+
+impl FrameBuffer for PixelFrameBuffer {
+    type Context = PixelFrameBufferContext;
+
+    fn new(
+        width: usize,
+        height: usize,
+        _source: FrameBufferSource,
+        context: Self::Context,
+    ) -> Self {
+        let row_size = context.format.row_size(width);
+        let colors = core::array::from_fn(|index| {
+            let brightness = if index < 8 {
+                ZXBrightness::Normal
+            } else {
+                ZXBrightness::Bright
+            };
+            context
+                .palette
+                .rgba(ZXColor::from_bits(index as u8 & 0x07), brightness)
+        });
+        Self {
+            buffer: vec![0; row_size * height],
+            format: context.format,
+            colors,
+            row_size,
+        }
+    }
+
+    fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+        let index = color_index(color, brightness);
+        match self.format {
+            PixelFormat::Indexed4 => self.write_index(x, y, index),
+            _ => self.write_rgba(x, y, self.colors[index as usize]),
+        }
+    }
+
+    fn set_blended_color(
+        &mut self,
+        x: usize,
+        y: usize,
+        current: (ZXColor, ZXBrightness),
+        previous: (ZXColor, ZXBrightness),
+    ) {
+        let current = color_index(current.0, current.1);
+        // Mixed colors could not be represented by the color index
+        if self.format == PixelFormat::Indexed4 {
+            self.write_index(x, y, current);
+            return;
+        }
+        let current = self.colors[current as usize];
+        let previous = self.colors[color_index(previous.0, previous.1) as usize];
+        let blended =
+            core::array::from_fn(|n| ((current[n] as u16 + previous[n] as u16) / 2) as u8);
+        self.write_rgba(x, y, blended);
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(&self.buffer)
+    }
+
+    fn pixel_format(&self) -> Option<PixelFormat> {
+        Some(self.format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_buffer(format: PixelFormat) -> PixelFrameBuffer {
+        let context = PixelFrameBufferContext {
+            format,
+            palette: Palette::Modern,
+        };
+        PixelFrameBuffer::new(3, 2, FrameBufferSource::Screen, context)
+    }
+
+    #[test]
+    fn rgba8888() {
+        let mut fb = frame_buffer(PixelFormat::Rgba8888);
+        fb.set_color(1, 1, ZXColor::Red, ZXBrightness::Normal);
+        let data = fb.as_bytes().unwrap();
+        assert_eq!(data.len(), 24);
+        assert_eq!(&data[16..20], &[0xCD, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn rgb565() {
+        let mut fb = frame_buffer(PixelFormat::Rgb565);
+        fb.set_color(1, 1, ZXColor::Red, ZXBrightness::Bright);
+        fb.set_color(2, 1, ZXColor::Cyan, ZXBrightness::Bright);
+        fb.set_blended_color(
+            0,
+            0,
+            (ZXColor::White, ZXBrightness::Bright),
+            (ZXColor::Black, ZXBrightness::Normal),
+        );
+        let data = fb.as_bytes().unwrap();
+        assert_eq!(data.len(), 12);
+        assert_eq!(&data[8..12], &[0x00, 0xF8, 0xFF, 0x07]);
+        // 0x7F on each channel
+        assert_eq!(&data[0..2], &0x7BEFu16.to_le_bytes());
+    }
+
+    #[test]
+    fn indexed4() {
+        let mut fb = frame_buffer(PixelFormat::Indexed4);
+        fb.set_color(0, 1, ZXColor::Yellow, ZXBrightness::Bright);
+        fb.set_color(1, 1, ZXColor::Blue, ZXBrightness::Normal);
+        fb.set_color(2, 1, ZXColor::White, ZXBrightness::Normal);
+        fb.set_color(0, 1, ZXColor::Green, ZXBrightness::Normal);
+        // Two bytes per row, the last one is half-used
+        assert_eq!(fb.as_bytes().unwrap(), &[0x00, 0x00, 0x41, 0x70]);
+    }
 }
@@ -1,8 +1,12 @@
 mod frame_buffer;
 mod io;
 
+use crate::zx::video::colors::ZXColor;
 pub use core::time::Duration;
-pub use frame_buffer::{FrameBuffer, FrameBufferSource};
+pub use frame_buffer::{
+    FrameBuffer, FrameBufferSource, FrameView, PixelFormat, PixelFrameBuffer,
+    PixelFrameBufferContext,
+};
 pub use io::{BufferCursor, DataRecorder, LoadableAsset, SeekFrom, SeekableAsset};
 
 pub trait Stopwatch {
@@ -28,6 +32,12 @@ pub enum Screen<LoadableAssetImpl: LoadableAsset> {
     Scr(LoadableAssetImpl),
 }
 
+pub enum Disk<LoadableAssetImpl: LoadableAsset> {
+    Dsk(LoadableAssetImpl),
+    Trd(LoadableAssetImpl),
+    Scl(LoadableAssetImpl),
+}
+
 pub enum RomFormat {
     Binary16KPages,
 }
@@ -49,6 +59,9 @@ impl<T> ScreenAsset for T where T: LoadableAsset + SeekableAsset {}
 pub trait SnapshotAsset: LoadableAsset + SeekableAsset {}
 impl<T> SnapshotAsset for T where T: LoadableAsset + SeekableAsset {}
 
+pub trait DiskAsset: LoadableAsset + SeekableAsset {}
+impl<T> DiskAsset for T where T: LoadableAsset + SeekableAsset {}
+
 /// Allows to extend base rustzx-core functionality by providing
 /// interface for user-defined IO ports handling
 pub trait IoExtender {
@@ -60,6 +73,12 @@ pub trait IoExtender {
     /// incoming read/write operation for a
     /// given port
     fn extends_port(&self, port: u16) -> bool;
+    /// Returns byte which interface places on the data bus during interrupt
+    /// acknowledge cycle (e.g. IM 2 vector), or `None` to leave the bus
+    /// untouched
+    fn interrupt_bus_value(&mut self) -> Option<u8> {
+        None
+    }
 }
 
 /// IO externder which does nothing
@@ -81,6 +100,11 @@ impl IoExtender for StubIoExtender {
 pub trait DebugInterface {
     /// Returns true if breakpoint at given address is set and emulation should be stopped
     fn check_pc_breakpoint(&mut self, addr: u16) -> bool;
+
+    /// Called when the beam enters the scanline `line`, counted from the
+    /// frame interrupt, with the border color at that moment. Allows hosts
+    /// to implement raster effects without `precise-border` feature
+    fn on_scanline(&mut self, _line: u16, _border: ZXColor) {}
 }
 
 /// Debug interface which does nothing
@@ -1,5 +1,8 @@
 #![no_std]
 
+#[macro_use]
+mod macros;
+
 pub(crate) mod emulator;
 pub(crate) mod settings;
 pub(crate) mod utils;
@@ -8,8 +11,10 @@ pub mod error;
 pub mod host;
 pub mod zx;
 
-pub use emulator::{poke, EmulationInfo, EmulationStopReason, Emulator};
-pub use settings::RustzxSettings;
+pub use emulator::{poke, EmulationInfo, EmulationStopReason, Emulator, ProfileInfo};
+pub use settings::{BorderSize, Palette, RustzxSettings, DETERMINISTIC_SEED};
+#[cfg(feature = "compression")]
+pub use utils::zip::archived_format as zip_archived_format;
 pub use utils::EmulationMode;
 
 #[cfg(feature = "strum")]
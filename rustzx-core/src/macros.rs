@@ -0,0 +1,24 @@
+//! Internal diagnostic logging macros. With `log` feature enabled messages are
+//! forwarded to the `log` crate, otherwise they are compiled out completely
+
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "log")]
+        log::warn!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
@@ -1,14 +1,81 @@
-use crate::{utils::EmulationMode, zx::machine::ZXMachine};
+use crate::{
+    utils::EmulationMode,
+    zx::{
+        constants::{CANVAS_BUFFER_WIDTH, CANVAS_HEIGHT, CANVAS_WIDTH},
+        keys::KeyboardIssue,
+        machine::{MachineSpec, ZXMachine},
+        video::colors::{ZXBrightness, ZXColor},
+    },
+};
 
+#[cfg(feature = "timex")]
+use crate::zx::constants::TIMEX_CANVAS_BUFFER_WIDTH;
 #[cfg(all(feature = "sound", feature = "ay"))]
 use crate::zx::sound::ay::ZXAYMode;
 
+/// Seed of the power-on memory pattern in [RustzxSettings::deterministic] mode
+pub const DETERMINISTIC_SEED: u64 = 0;
+
 pub struct RustzxSettings {
     pub machine: ZXMachine,
+    /// Overrides frame timings of the `machine` to emulate Spectrum clones,
+    /// `None` uses timings of the real machine. See [MachineSpec]
+    pub custom_specs: Option<MachineSpec>,
     pub emulation_mode: EmulationMode,
+    /// Count of cpu clocks executed per single frame clock, `1.0` for the real
+    /// machine speed. Display and sound timings are not affected, so values above
+    /// `1.0` accelerate programs and values below `1.0` slow them down. Clamped
+    /// to `0.1..=16.0` range, non-finite values are replaced with `1.0`
+    pub cpu_speed_multiplier: f64,
+    /// Deterministic emulation for reproducible runs: on construction RAM is
+    /// filled with the pattern of [DETERMINISTIC_SEED] instead of zeroes (see
+    /// `Emulator::power_on`) and tape fast loading is disabled, because its
+    /// ROM trap skips loader instructions without R register refresh. This
+    /// way R register increments of every instruction are accounted
+    pub deterministic: bool,
     pub tape_fastload_enabled: bool,
+    /// Count of frames, during which `Emulator::loading_border_pattern`
+    /// reports data loading stripes after the instant tape load, `0` disables
+    /// the indicator
+    pub tape_fastload_border_frames: u32,
     pub kempston_enabled: bool,
+    /// Enables second Kempston joystick for two-player games, decoded when
+    /// address bit A5 is set (port 0x3F)
+    pub second_kempston_enabled: bool,
+    /// Enables Fuller Box: joystick on port 0x7F and AY chip on ports 0x3F/0x5F
+    pub fuller_enabled: bool,
     pub mouse_enabled: bool,
+    /// Enables Magnum Light Phaser lightgun, see `Emulator::set_lightgun`
+    pub lightgun_enabled: bool,
+    /// Enables ZX Printer on port 0xFB, see `Emulator::take_printer_output`
+    pub printer_enabled: bool,
+    /// Enables Beta Disk 128 interface. TR-DOS ROM is not embedded and should
+    /// be loaded with `Emulator::load_trdos_rom`
+    pub beta_disk_enabled: bool,
+    /// Size of the border, rendered around the canvas, see [BorderSize]
+    pub border_size: BorderSize,
+    /// Border color on power-on, before the program changes it
+    pub initial_border_color: ZXColor,
+    /// Attribute of the screen on power-on. When set, screen bitmap is
+    /// cleared and attributes are filled with this value, `None` leaves
+    /// screen memory as is
+    pub initial_screen_attribute: Option<u8>,
+    /// Colors used for the PNG screenshots and by the frontends to convert
+    /// [ZXColor] to RGBA, see [Palette]
+    pub palette: Palette,
+    /// Selects EAR input behavior of the keyboard port
+    pub keyboard_issue: KeyboardIssue,
+    /// Blend each frame with the previous one to emulate gigascreen
+    /// flicker colors on modern displays
+    pub blend_frames: bool,
+    /// Count of frames between flashing attributes ink/paper swaps, `16` on
+    /// the real machine. `None` disables flashing
+    pub flash_rate_frames: Option<u32>,
+    /// Max count of states kept in the rewind buffer, `0` disables rewind.
+    /// See `Emulator::rewind`
+    pub rewind_snapshots: usize,
+    /// Count of frames between rewind buffer states
+    pub rewind_interval_frames: usize,
     #[cfg(all(feature = "sound", feature = "ay"))]
     pub ay_mode: ZXAYMode,
     #[cfg(all(feature = "sound", feature = "ay"))]
@@ -17,12 +84,180 @@ pub struct RustzxSettings {
     pub beeper_enabled: bool,
     #[cfg(feature = "sound")]
     pub sound_enabled: bool,
+    /// Sound volume in range `0..=100`, mapped to the logarithmic volume curve
     #[cfg(feature = "sound")]
     pub sound_volume: u8,
+    /// Cutoff frequency in Hz of the beeper low-pass filter, which emulates
+    /// RC filter of the real hardware. `None` disables filtering
+    #[cfg(feature = "sound")]
+    pub beeper_lowpass_cutoff: Option<usize>,
+    /// Sum stereo sound to mono for hosts with a single output channel
+    #[cfg(feature = "sound")]
+    pub mono_output: bool,
     #[cfg(feature = "sound")]
     pub sound_sample_rate: usize,
+    /// Count of internal samples per output sample in range `1..=4`. Beeper
+    /// and AY are rendered at the multiplied rate and averaged down to the
+    /// output rate, which reduces aliasing of the high-frequency tones at the
+    /// cost of CPU time. `1` disables oversampling
+    #[cfg(feature = "sound")]
+    pub sound_oversampling: usize,
+    /// Loads built-in ROM of the machine on construction. Emulator creation
+    /// fails when the machine has no built-in ROM (+2A/+3), see
+    /// [crate::zx::machine::MachineCapabilities::has_embedded_rom]
     #[cfg(feature = "embedded-roms")]
     pub load_default_rom: bool,
     #[cfg(feature = "autoload")]
     pub autoload_enabled: bool,
+    /// Enables Timex SCLD port 0xFF with hi-colour and hi-res video modes and
+    /// doubles the screen frame buffer width, see
+    /// [RustzxSettings::canvas_buffer_width]. When disabled, port 0xFF reads
+    /// the floating bus as on Sinclair machines. Has effect only with `timex`
+    /// feature enabled
+    pub timex_enabled: bool,
+    /// Enables ZX Spectrum Next register ports 0x243B/0x253B with MMU paging.
+    /// When disabled, the ports are not decoded as on Sinclair machines. Has
+    /// effect only with `next` feature enabled
+    pub next_enabled: bool,
+}
+
+impl RustzxSettings {
+    /// Returns width of the screen frame buffer, created by the emulator
+    pub fn canvas_buffer_width(&self) -> usize {
+        #[cfg(feature = "timex")]
+        if self.timex_enabled {
+            return TIMEX_CANVAS_BUFFER_WIDTH;
+        }
+        CANVAS_BUFFER_WIDTH
+    }
+}
+
+/// Size of the border, rendered to the border frame buffer around the
+/// 256x192 canvas
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderSize {
+    /// Border is not rendered, frame is pixel-exact 256x192 canvas
+    None,
+    /// 32 pixels on the sides and 24 lines on the top and the bottom (320x240)
+    #[default]
+    Minimal,
+    /// Full overscan, visible on the real TV: 48 pixels on the sides and 52
+    /// lines on the top and the bottom (352x296)
+    Full,
+}
+
+impl BorderSize {
+    /// Returns border width in pixels on each side of the canvas
+    pub fn border_width(self) -> usize {
+        match self {
+            BorderSize::None => 0,
+            BorderSize::Minimal => 32,
+            BorderSize::Full => 48,
+        }
+    }
+
+    /// Returns border height in lines above and below the canvas
+    pub fn border_height(self) -> usize {
+        match self {
+            BorderSize::None => 0,
+            BorderSize::Minimal => 24,
+            BorderSize::Full => 52,
+        }
+    }
+
+    /// Returns width of the rendered frame including border
+    pub fn screen_width(self) -> usize {
+        CANVAS_WIDTH + self.border_width() * 2
+    }
+
+    /// Returns height of the rendered frame including border
+    pub fn screen_height(self) -> usize {
+        CANVAS_HEIGHT + self.border_height() * 2
+    }
+}
+
+/// Palette for the conversion of the Spectrum colors to RGB. Bright and flash
+/// attributes are applied before the palette lookup
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    /// Saturated colors with 0xCD normal and 0xFF bright levels
+    #[default]
+    Modern,
+    /// Colors of the real machine, connected to the PAL TV
+    PalTv,
+    /// Green phosphor monochrome CRT
+    MonochromeGreen,
+    /// Amber phosphor monochrome CRT
+    MonochromeAmber,
+    /// Black and white TV
+    MonochromeWhite,
+}
+
+/// Normal colors followed by bright
+const MODERN_COLORS: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0xCD],
+    [0xCD, 0x00, 0x00],
+    [0xCD, 0x00, 0xCD],
+    [0x00, 0xCD, 0x00],
+    [0x00, 0xCD, 0xCD],
+    [0xCD, 0xCD, 0x00],
+    [0xCD, 0xCD, 0xCD],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0x00],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0x00],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// Measured output of the PAL encoder: colors are not pure primaries and
+/// bright black is slightly lighter than black
+const PAL_TV_COLORS: [[u8; 3]; 16] = [
+    [0x06, 0x08, 0x00],
+    [0x0D, 0x13, 0xA7],
+    [0xBD, 0x07, 0x07],
+    [0xC3, 0x12, 0xAF],
+    [0x07, 0xBA, 0x0C],
+    [0x0D, 0xC6, 0xB4],
+    [0xBC, 0xB9, 0x16],
+    [0xC2, 0xC2, 0xC2],
+    [0x06, 0x08, 0x00],
+    [0x16, 0x1C, 0xB0],
+    [0xCE, 0x18, 0x18],
+    [0xDC, 0x2C, 0xC8],
+    [0x28, 0xDC, 0x2D],
+    [0x36, 0xEF, 0xDE],
+    [0xEE, 0xEB, 0x46],
+    [0xFD, 0xFF, 0xF7],
+];
+
+const GREEN_PHOSPHOR: [u8; 3] = [0x33, 0xFF, 0x33];
+const AMBER_PHOSPHOR: [u8; 3] = [0xFF, 0xB0, 0x00];
+const WHITE_PHOSPHOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+impl Palette {
+    /// Returns RGBA value of the color, alpha is always 0xFF
+    pub fn rgba(self, color: ZXColor, brightness: ZXBrightness) -> [u8; 4] {
+        let index = u8::from(color) as usize + brightness as usize * 8;
+        let [r, g, b] = match self {
+            Palette::Modern => MODERN_COLORS[index],
+            Palette::PalTv => PAL_TV_COLORS[index],
+            Palette::MonochromeGreen => monochrome(MODERN_COLORS[index], GREEN_PHOSPHOR),
+            Palette::MonochromeAmber => monochrome(MODERN_COLORS[index], AMBER_PHOSPHOR),
+            Palette::MonochromeWhite => monochrome(MODERN_COLORS[index], WHITE_PHOSPHOR),
+        };
+        [r, g, b, 0xFF]
+    }
+}
+
+/// Converts color to the luma (ITU-R BT.601) and scales the phosphor color
+/// with it
+fn monochrome([r, g, b]: [u8; 3], phosphor: [u8; 3]) -> [u8; 3] {
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    phosphor.map(|c| (c as u32 * luma / 255) as u8)
 }
@@ -0,0 +1,25 @@
+//! CRC-32 checksum shared by archives unpacking and PNG export
+
+/// CRC-32 (ISO-HDLC) checksum, used by gzip and zip archives and PNG chunks
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b"IEND"), 0xAE426082);
+    }
+}
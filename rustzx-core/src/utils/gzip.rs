@@ -0,0 +1,121 @@
+//! Transparent gzip (RFC 1952) assets unpacking
+use crate::{
+    error::IoError,
+    host::{LoadableAsset, SeekFrom, SeekableAsset},
+    utils::{crc32::crc32, inflate::inflate},
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const GZIP_HEADER_SIZE: usize = 10;
+const GZIP_TRAILER_SIZE: usize = 8;
+const GZIP_METHOD_DEFLATE: u8 = 8;
+const GZIP_FLAG_HCRC: u8 = 0x02;
+const GZIP_FLAG_EXTRA: u8 = 0x04;
+const GZIP_FLAG_NAME: u8 = 0x08;
+const GZIP_FLAG_COMMENT: u8 = 0x10;
+
+/// Checks asset for the gzip magic header. Asset position is reset to
+/// the beginning of the asset after the check
+pub fn is_gzip(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<bool> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    if size < GZIP_MAGIC.len() {
+        return Ok(false);
+    }
+
+    let mut magic = [0u8; 2];
+    asset.read_exact(&mut magic)?;
+    asset.seek(SeekFrom::Start(0))?;
+
+    Ok(magic == GZIP_MAGIC)
+}
+
+/// Reads whole gzip asset and returns its unpacked content
+pub fn unpack(mut asset: impl LoadableAsset + SeekableAsset) -> Result<Vec<u8>> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    let mut packed = vec![0u8; size];
+    asset.read_exact(&mut packed)?;
+
+    let deflate_start = deflate_stream_offset(&packed).ok_or(IoError::InvalidCompressedAsset)?;
+    if packed.len() < deflate_start + GZIP_TRAILER_SIZE {
+        return Err(IoError::InvalidCompressedAsset.into());
+    }
+
+    // Trailer holds CRC-32 of the unpacked data followed by its size modulo 2^32
+    let trailer = &packed[packed.len() - GZIP_TRAILER_SIZE..];
+    let expected_crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let expected_size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    let deflate_end = packed.len() - GZIP_TRAILER_SIZE;
+    let unpacked = inflate(&packed[deflate_start..deflate_end], expected_size as usize)
+        .map_err(|_| IoError::InvalidCompressedAsset)?;
+    if unpacked.len() as u32 != expected_size || crc32(&unpacked) != expected_crc {
+        return Err(IoError::InvalidCompressedAsset.into());
+    }
+
+    Ok(unpacked)
+}
+
+/// Parses gzip header and returns offset of the deflate stream
+fn deflate_stream_offset(data: &[u8]) -> Option<usize> {
+    let header = data.get(..GZIP_HEADER_SIZE)?;
+    if header[0..2] != GZIP_MAGIC || header[2] != GZIP_METHOD_DEFLATE {
+        return None;
+    }
+    let flags = header[3];
+    let mut offset = GZIP_HEADER_SIZE;
+
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let len = data.get(offset..offset + 2)?;
+        offset += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    if flags & GZIP_FLAG_NAME != 0 {
+        offset += data.get(offset..)?.iter().position(|&b| b == 0)? + 1;
+    }
+    if flags & GZIP_FLAG_COMMENT != 0 {
+        offset += data.get(offset..)?.iter().position(|&b| b == 0)? + 1;
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        offset += 2;
+    }
+
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::BufferCursor;
+    use miniz_oxide::deflate::compress_to_vec;
+
+    fn pack(data: &[u8]) -> Vec<u8> {
+        let mut packed = vec![0x1F, 0x8B, GZIP_METHOD_DEFLATE, 0, 0, 0, 0, 0, 0, 0xFF];
+        packed.extend_from_slice(&compress_to_vec(data, 6));
+        packed.extend_from_slice(&crc32(data).to_le_bytes());
+        packed.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packed
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let data = b"ZX Spectrum".repeat(16);
+        let mut packed = pack(&data);
+        assert_eq!(unpack(BufferCursor::new(packed.clone())).unwrap(), data);
+
+        let crc_offset = packed.len() - GZIP_TRAILER_SIZE;
+        packed[crc_offset] ^= 0x01;
+        assert!(unpack(BufferCursor::new(packed)).is_err());
+    }
+
+    #[test]
+    fn size_limit() {
+        let data = vec![0xAAu8; 0x1000];
+        let mut packed = pack(&data);
+        let size_offset = packed.len() - 4;
+        packed[size_offset..].copy_from_slice(&0x800u32.to_le_bytes());
+        assert!(unpack(BufferCursor::new(packed)).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! Bounded raw deflate stream unpacking for the compressed assets
+use alloc::{boxed::Box, vec, vec::Vec};
+use miniz_oxide::inflate::{
+    core::{
+        decompress, inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF, DecompressorOxide,
+    },
+    TINFLStatus,
+};
+
+/// Inflates raw deflate stream, which should unpack to at most `max_size`
+/// bytes. Output buffer grows on demand, so bogus size from the archive
+/// header does not result in huge allocation up front
+pub(crate) fn inflate(data: &[u8], max_size: usize) -> Result<Vec<u8>, TINFLStatus> {
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let mut unpacked = vec![0u8; data.len().saturating_mul(2).min(max_size)];
+    let mut data_pos = 0;
+    let mut unpacked_pos = 0;
+    loop {
+        let (status, data_consumed, unpacked_written) = decompress(
+            &mut decompressor,
+            &data[data_pos..],
+            &mut unpacked,
+            unpacked_pos,
+            TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+        );
+        data_pos += data_consumed;
+        unpacked_pos += unpacked_written;
+
+        match status {
+            TINFLStatus::Done => {
+                unpacked.truncate(unpacked_pos);
+                return Ok(unpacked);
+            }
+            TINFLStatus::HasMoreOutput if unpacked.len() < max_size => {
+                let new_len = unpacked.len().saturating_mul(2).max(1).min(max_size);
+                unpacked.resize(new_len, 0);
+            }
+            status => return Err(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec;
+
+    #[test]
+    fn inflate_limit() {
+        // Highly compressible data grows output buffer several times
+        let data = vec![0xAAu8; 0x10000];
+        let packed = compress_to_vec(&data, 6);
+        assert_eq!(inflate(&packed, data.len()), Ok(data.clone()));
+        assert_eq!(
+            inflate(&packed, data.len() - 1),
+            Err(TINFLStatus::HasMoreOutput)
+        );
+    }
+}
@@ -1,6 +1,17 @@
 //! Some emulator-related utils
 
+#[cfg(any(feature = "compression", feature = "png"))]
+pub(crate) mod crc32;
+pub(crate) mod fingerprint;
+#[cfg(feature = "compression")]
+pub mod gzip;
+#[cfg(feature = "compression")]
+pub(crate) mod inflate;
+pub(crate) mod rewind;
 pub mod screen;
+pub(crate) mod state;
+#[cfg(feature = "compression")]
+pub mod zip;
 
 #[derive(Copy, Clone)]
 pub enum EmulationMode {
@@ -0,0 +1,203 @@
+//! Rewind ring buffer of the emulator states, see [crate::Emulator::rewind].
+//!
+//! Only the newest state is stored as is, each older state is stored as a
+//! delta to the next newer one. Consecutive states differ only in a small
+//! part of RAM, so deltas take much less memory than full states
+use alloc::{collections::VecDeque, vec::Vec};
+
+const DELTA_FULL: u8 = 0;
+const DELTA_RUNS: u8 = 1;
+
+struct RewindSnapshot {
+    frame: u64,
+    data: Vec<u8>,
+}
+
+pub(crate) struct RewindBuffer {
+    interval: u64,
+    capacity: usize,
+    // count of frames emulated since the buffer creation
+    frame: u64,
+    last_capture: Option<u64>,
+    snapshots: VecDeque<RewindSnapshot>,
+}
+
+impl RewindBuffer {
+    /// Constructs buffer which keeps up to `capacity` states, captured
+    /// every `interval` frames
+    pub fn new(interval: usize, capacity: usize) -> Self {
+        Self {
+            interval: interval.max(1) as u64,
+            capacity,
+            frame: 0,
+            last_capture: None,
+            snapshots: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Counts completed frame, returns true if state should be captured
+    pub fn frame_completed(&mut self) -> bool {
+        self.frame += 1;
+        self.last_capture
+            .is_none_or(|last| self.frame - last >= self.interval)
+    }
+
+    /// Stores state of the current frame, the oldest state is dropped when
+    /// the buffer is full
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(newest) = self.snapshots.back_mut() {
+            newest.data = encode_delta(&state, &newest.data);
+        }
+        self.snapshots.push_back(RewindSnapshot {
+            frame: self.frame,
+            data: state,
+        });
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.last_capture = Some(self.frame);
+    }
+
+    /// Removes and returns the newest state, captured at least `frames`
+    /// frames ago, or the oldest one if there is no such state. Newer
+    /// states are dropped. Returns count of frames between the current
+    /// frame and the returned state
+    pub fn pop(&mut self, frames: usize) -> Option<(usize, Vec<u8>)> {
+        let target = self.frame.saturating_sub(frames as u64);
+        loop {
+            let newest = self.snapshots.pop_back()?;
+            if let Some(next) = self.snapshots.back_mut() {
+                next.data = decode_delta(&newest.data, &next.data);
+                if newest.frame > target {
+                    continue;
+                }
+            }
+            let rewound = (self.frame - newest.frame) as usize;
+            self.frame = newest.frame;
+            self.last_capture = Some(newest.frame);
+            return Some((rewound, newest.data));
+        }
+    }
+
+    /// Returns count of frames, which could be rewound
+    pub fn available_frames(&self) -> usize {
+        self.snapshots
+            .front()
+            .map_or(0, |oldest| (self.frame - oldest.frame) as usize)
+    }
+}
+
+/// Encodes `target` as a sequence of `(unchanged, changed)` runs relative to
+/// `base`, each followed by bytes of the changed run
+fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if base.len() != target.len() {
+        out.push(DELTA_FULL);
+        out.extend_from_slice(target);
+        return out;
+    }
+    out.push(DELTA_RUNS);
+    let mut pos = 0;
+    while pos < target.len() {
+        let unchanged = base[pos..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        pos += unchanged;
+        let changed = base[pos..]
+            .iter()
+            .zip(&target[pos..])
+            .take_while(|(a, b)| a != b)
+            .count();
+        write_varint(&mut out, unchanged);
+        write_varint(&mut out, changed);
+        out.extend_from_slice(&target[pos..pos + changed]);
+        pos += changed;
+    }
+    out
+}
+
+/// Restores data, encoded with [encode_delta]
+fn decode_delta(base: &[u8], delta: &[u8]) -> Vec<u8> {
+    let (tag, mut delta) = delta.split_first().expect("Delta is never empty");
+    if *tag == DELTA_FULL {
+        return delta.to_vec();
+    }
+    let mut out = Vec::with_capacity(base.len());
+    while !delta.is_empty() {
+        let unchanged = read_varint(&mut delta);
+        let changed = read_varint(&mut delta);
+        let pos = out.len();
+        out.extend_from_slice(&base[pos..pos + unchanged]);
+        let (bytes, rest) = delta.split_at(changed);
+        out.extend_from_slice(bytes);
+        delta = rest;
+    }
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let (byte, rest) = data.split_first().expect("Varint is not truncated");
+        *data = rest;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn delta_roundtrip() {
+        let base = vec![0u8; 1000];
+        let mut target = base.clone();
+        target[0] = 1;
+        target[500..700].fill(2);
+        target[999] = 3;
+        let delta = encode_delta(&base, &target);
+        assert!(delta.len() < 250);
+        assert_eq!(decode_delta(&base, &delta), target);
+
+        let other_size = vec![4u8; 10];
+        assert_eq!(
+            decode_delta(&base, &encode_delta(&base, &other_size)),
+            other_size
+        );
+    }
+
+    #[test]
+    fn pop_newest_states() {
+        let mut buffer = RewindBuffer::new(2, 3);
+        for frame in 1..=10u8 {
+            if buffer.frame_completed() {
+                buffer.push(vec![frame; 16]);
+            }
+        }
+        // States of frames 5, 7 and 9 are kept
+        assert_eq!(buffer.available_frames(), 5);
+        assert_eq!(buffer.pop(3), Some((3, vec![7; 16])));
+        assert_eq!(buffer.available_frames(), 2);
+        assert_eq!(buffer.pop(0), Some((2, vec![5; 16])));
+        assert_eq!(buffer.pop(1), None);
+    }
+}
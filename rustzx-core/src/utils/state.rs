@@ -0,0 +1,165 @@
+//! Binary emulator state serialization primitives, used by
+//! [crate::Emulator::serialize_state]. All multi-byte values are stored in
+//! little-endian byte order, so state blobs could be moved between hosts
+use crate::{error::StateLoadError, Result};
+use alloc::vec::Vec;
+
+/// Sequential writer of the emulator state blob
+#[derive(Default)]
+pub(crate) struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Writes length-prefixed byte sequence
+    pub fn write_blob(&mut self, bytes: &[u8]) {
+        self.write_usize(bytes.len());
+        self.write_bytes(bytes);
+    }
+
+    /// Writes length-prefixed section, filled by `f`. Sections of optional
+    /// devices could be skipped by the reader
+    pub fn write_section(&mut self, f: impl FnOnce(&mut StateWriter)) {
+        let mut section = StateWriter::default();
+        f(&mut section);
+        self.write_blob(&section.data);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Sequential reader of the emulator state blob. All methods fail with
+/// [StateLoadError::InvalidState] when data ends unexpectedly
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        if count > self.data.len() {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Reads exactly `out.len()` bytes
+    pub fn read_into(&mut self, out: &mut [u8]) -> Result<()> {
+        out.copy_from_slice(self.read_bytes(out.len())?);
+        Ok(())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        let mut bytes = [0u8; 2];
+        self.read_into(&mut bytes)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.read_into(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.read_into(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize> {
+        usize::try_from(self.read_u64()?).map_err(|_| StateLoadError::InvalidState.into())
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64> {
+        Ok(f64::from_bits(self.read_u64()?))
+    }
+
+    /// Reads length-prefixed byte sequence
+    pub fn read_blob(&mut self) -> Result<&'a [u8]> {
+        let count = self.read_usize()?;
+        self.read_bytes(count)
+    }
+
+    /// Checks that whole state has been consumed
+    pub fn finish(self) -> Result<()> {
+        if !self.data.is_empty() {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_roundtrip() {
+        let mut writer = StateWriter::default();
+        writer.write_u8(0x12);
+        writer.write_bool(true);
+        writer.write_u32(0x789A_BCDE);
+        writer.write_usize(42);
+        writer.write_f64(0.25);
+        writer.write_blob(&[1, 2, 3]);
+        let data = writer.finish();
+
+        let mut reader = StateReader::new(&data);
+        assert_eq!(reader.read_u8().unwrap(), 0x12);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_u32().unwrap(), 0x789A_BCDE);
+        assert_eq!(reader.read_usize().unwrap(), 42);
+        assert_eq!(reader.read_f64().unwrap(), 0.25);
+        assert_eq!(reader.read_blob().unwrap(), [1, 2, 3]);
+        assert!(reader.read_u8().is_err());
+        reader.finish().unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! Transparent zip archive assets unpacking
+use crate::{
+    error::IoError,
+    host::{LoadableAsset, SeekFrom, SeekableAsset},
+    utils::{crc32::crc32, inflate::inflate},
+    Result,
+};
+use alloc::{vec, vec::Vec};
+
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+const ZIP_CENTRAL_HEADER_MAGIC: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
+const ZIP_END_OF_CENTRAL_DIR_MAGIC: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
+const ZIP_LOCAL_HEADER_SIZE: usize = 30;
+const ZIP_CENTRAL_HEADER_SIZE: usize = 46;
+const ZIP_END_OF_CENTRAL_DIR_SIZE: usize = 22;
+const ZIP_MAX_COMMENT_SIZE: usize = 0xFFFF;
+const ZIP_FLAG_ENCRYPTED: u16 = 0x0001;
+const ZIP_METHOD_STORED: u16 = 0;
+const ZIP_METHOD_DEFLATE: u16 = 8;
+
+/// Archived file description from the zip central directory
+struct Entry {
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+    format_index: usize,
+}
+
+/// Checks asset for the zip magic header. Asset position is reset to
+/// the beginning of the asset after the check
+pub fn is_zip(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<bool> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    if size < ZIP_MAGIC.len() {
+        return Ok(false);
+    }
+
+    let mut magic = [0u8; 4];
+    asset.read_exact(&mut magic)?;
+    asset.seek(SeekFrom::Start(0))?;
+
+    Ok(magic == ZIP_MAGIC)
+}
+
+/// Returns format (one of `formats` file extensions) of the first archived
+/// file which would be picked by [unpack]. Asset position is reset to the
+/// beginning of the asset after the check
+pub fn archived_format<'a>(
+    asset: &mut (impl LoadableAsset + SeekableAsset),
+    formats: &[&'a str],
+) -> Result<Option<&'a str>> {
+    let archive = read_all(asset)?;
+    asset.seek(SeekFrom::Start(0))?;
+    Ok(find_entry(&archive, formats)?.map(|entry| formats[entry.format_index]))
+}
+
+/// Reads whole zip asset and returns unpacked content of the first archived
+/// file with extension from `formats` list (case-insensitive)
+pub fn unpack(mut asset: impl LoadableAsset + SeekableAsset, formats: &[&str]) -> Result<Vec<u8>> {
+    let archive = read_all(&mut asset)?;
+    let entry = find_entry(&archive, formats)?.ok_or(IoError::NoSupportedArchivedFile)?;
+
+    let local_header = slice(&archive, entry.local_header_offset, ZIP_LOCAL_HEADER_SIZE)?;
+    if local_header[0..4] != ZIP_MAGIC {
+        return Err(IoError::InvalidCompressedAsset.into());
+    }
+    let data_start = checked_sum(&[
+        entry.local_header_offset,
+        ZIP_LOCAL_HEADER_SIZE,
+        read_u16(local_header, 26) as usize,
+        read_u16(local_header, 28) as usize,
+    ])?;
+    let data = slice(&archive, data_start, entry.compressed_size)?;
+
+    let unpacked = match entry.method {
+        ZIP_METHOD_STORED => data.to_vec(),
+        ZIP_METHOD_DEFLATE => {
+            inflate(data, entry.uncompressed_size).map_err(|_| IoError::InvalidCompressedAsset)?
+        }
+        _ => return Err(IoError::InvalidCompressedAsset.into()),
+    };
+    if unpacked.len() != entry.uncompressed_size || crc32(&unpacked) != entry.crc {
+        return Err(IoError::InvalidCompressedAsset.into());
+    }
+
+    Ok(unpacked)
+}
+
+fn read_all(asset: &mut (impl LoadableAsset + SeekableAsset)) -> Result<Vec<u8>> {
+    let size = asset.seek(SeekFrom::End(0))?;
+    asset.seek(SeekFrom::Start(0))?;
+    let mut data = vec![0u8; size];
+    asset.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Returns `len` bytes of `data` starting at `start`, failing on out of
+/// bounds or overflowing ranges
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    start
+        .checked_add(len)
+        .and_then(|end| data.get(start..end))
+        .ok_or_else(|| IoError::InvalidCompressedAsset.into())
+}
+
+fn checked_sum(values: &[usize]) -> Result<usize> {
+    values
+        .iter()
+        .try_fold(0usize, |sum, value| sum.checked_add(*value))
+        .ok_or_else(|| IoError::InvalidCompressedAsset.into())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Walks zip central directory and returns first supported unencrypted file
+fn find_entry(archive: &[u8], formats: &[&str]) -> Result<Option<Entry>> {
+    let eocd = end_of_central_dir_offset(archive).ok_or(IoError::InvalidCompressedAsset)?;
+    let entries_count = read_u16(archive, eocd + 10) as usize;
+    let mut offset = read_u32(archive, eocd + 16) as usize;
+
+    for _ in 0..entries_count {
+        let header = slice(archive, offset, ZIP_CENTRAL_HEADER_SIZE)?;
+        if header[0..4] != ZIP_CENTRAL_HEADER_MAGIC {
+            return Err(IoError::InvalidCompressedAsset.into());
+        }
+        let name_len = read_u16(header, 28) as usize;
+        let extra_len = read_u16(header, 30) as usize;
+        let comment_len = read_u16(header, 32) as usize;
+        let name_start = checked_sum(&[offset, ZIP_CENTRAL_HEADER_SIZE])?;
+        let name = slice(archive, name_start, name_len)?;
+
+        let encrypted = read_u16(header, 8) & ZIP_FLAG_ENCRYPTED != 0;
+        let format_index = formats
+            .iter()
+            .position(|format| extension_matches(name, format));
+        if let (false, Some(format_index)) = (encrypted, format_index) {
+            return Ok(Some(Entry {
+                method: read_u16(header, 10),
+                crc: read_u32(header, 16),
+                compressed_size: read_u32(header, 20) as usize,
+                uncompressed_size: read_u32(header, 24) as usize,
+                local_header_offset: read_u32(header, 42) as usize,
+                format_index,
+            }));
+        }
+
+        offset = checked_sum(&[name_start, name_len, extra_len, comment_len])?;
+    }
+
+    Ok(None)
+}
+
+/// Searches for the end of central directory record, which is located at
+/// the end of the archive, followed by optional archive comment
+fn end_of_central_dir_offset(archive: &[u8]) -> Option<usize> {
+    let last = archive.len().checked_sub(ZIP_END_OF_CENTRAL_DIR_SIZE)?;
+    let first = last.saturating_sub(ZIP_MAX_COMMENT_SIZE);
+    (first..=last)
+        .rev()
+        .find(|&offset| archive[offset..offset + 4] == ZIP_END_OF_CENTRAL_DIR_MAGIC)
+}
+
+fn extension_matches(name: &[u8], format: &str) -> bool {
+    let format = format.as_bytes();
+    name.len() > format.len()
+        && name[name.len() - format.len() - 1] == b'.'
+        && name[name.len() - format.len()..].eq_ignore_ascii_case(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::BufferCursor;
+
+    /// Builds single file archive with stored (not compressed) `data`
+    fn stored_archive(name: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut entry = Vec::new();
+        // Version, flags and method
+        entry.extend_from_slice(&[20, 0, 0, 0, 0, 0]);
+        // Modification time and date
+        entry.extend_from_slice(&[0; 4]);
+        entry.extend_from_slice(&crc32(data).to_le_bytes());
+        entry.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        // Extra field length
+        entry.extend_from_slice(&[0; 2]);
+
+        let mut archive = ZIP_MAGIC.to_vec();
+        archive.extend_from_slice(&entry);
+        archive.extend_from_slice(name);
+        archive.extend_from_slice(data);
+
+        let central_dir_offset = archive.len() as u32;
+        archive.extend_from_slice(&ZIP_CENTRAL_HEADER_MAGIC);
+        // Version made by
+        archive.extend_from_slice(&[20, 0]);
+        archive.extend_from_slice(&entry);
+        // Comment length, disk, attributes and local header offset
+        archive.extend_from_slice(&[0; 14]);
+        archive.extend_from_slice(name);
+        let central_dir_size = archive.len() as u32 - central_dir_offset;
+
+        archive.extend_from_slice(&ZIP_END_OF_CENTRAL_DIR_MAGIC);
+        archive.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        archive.extend_from_slice(&central_dir_size.to_le_bytes());
+        archive.extend_from_slice(&central_dir_offset.to_le_bytes());
+        archive.extend_from_slice(&[0; 2]);
+        archive
+    }
+
+    #[test]
+    fn crc_mismatch() {
+        let data = b"ZX Spectrum".repeat(16);
+        let mut archive = stored_archive(b"GAME.TAP", &data);
+        assert_eq!(
+            unpack(BufferCursor::new(archive.clone()), &["tap"]).unwrap(),
+            data
+        );
+
+        let data_offset = ZIP_LOCAL_HEADER_SIZE + b"GAME.TAP".len();
+        archive[data_offset] ^= 0x01;
+        assert!(unpack(BufferCursor::new(archive), &["tap"]).is_err());
+    }
+
+    #[test]
+    fn overflowing_offsets() {
+        assert!(slice(&[0u8; 4], usize::MAX, 2).is_err());
+        assert!(slice(&[0u8; 4], 2, 3).is_err());
+        assert_eq!(slice(&[1u8, 2, 3, 4], 1, 2).unwrap(), &[2, 3]);
+        assert!(checked_sum(&[usize::MAX - 1, 1, 1]).is_err());
+        assert_eq!(checked_sum(&[1, 2, 3]).unwrap(), 6);
+    }
+}
@@ -5,9 +5,29 @@ pub const CANVAS_WIDTH: usize = 256;
 pub const CANVAS_HEIGHT: usize = 192;
 pub const CANVAS_X: usize = 32;
 pub const CANVAS_Y: usize = 24;
-/// canvas (emulated screen) constants
+/// Width of the screen (canvas) frame buffer
+pub const CANVAS_BUFFER_WIDTH: usize = CANVAS_WIDTH;
+/// Width of the screen (canvas) frame buffer with enabled Timex video modes.
+/// Timex hi-res mode doubles horizontal resolution, therefore all other video
+/// modes are rendered with horizontally doubled pixels
+#[cfg(feature = "timex")]
+pub const TIMEX_CANVAS_BUFFER_WIDTH: usize = CANVAS_WIDTH * 2;
+/// canvas (emulated screen) constants for the default
+/// [BorderSize::Minimal](crate::BorderSize::Minimal) border
 pub const SCREEN_WIDTH: usize = CANVAS_WIDTH + BORDER_COLS * 8 * 2;
 pub const SCREEN_HEIGHT: usize = CANVAS_HEIGHT + BORDER_ROWS * 8 * 2;
+/// Fuller Box ports (low byte)
+pub(crate) const FULLER_JOY_PORT: u8 = 0x7F;
+pub(crate) const FULLER_AY_SELECT_PORT: u8 = 0x3F;
+pub(crate) const FULLER_AY_DATA_PORT: u8 = 0x5F;
+/// ZX Spectrum Next register select and data ports
+#[cfg(feature = "next")]
+pub(crate) const NEXT_REG_SELECT_PORT: u16 = 0x243B;
+#[cfg(feature = "next")]
+pub(crate) const NEXT_REG_DATA_PORT: u16 = 0x253B;
+/// First of the eight Next MMU slot registers
+#[cfg(feature = "next")]
+pub(crate) const NEXT_REG_MMU0: u8 = 0x50;
 /// Frames per second
 pub const FPS: usize = 50;
 
@@ -15,6 +35,19 @@ pub const FPS: usize = 50;
 pub(crate) const BITMAP_MAX_REL: u16 = 0x17FF;
 pub(crate) const ATTR_BASE_REL: u16 = 0x1800;
 pub(crate) const ATTR_MAX_REL: u16 = 0x1AFF;
+/// Size of the display file including attributes
+pub const SCREEN_MEMORY_SIZE: usize = ATTR_MAX_REL as usize + 1;
+/// Timex second screen is located 0x2000 bytes after the main one
+#[cfg(feature = "timex")]
+pub(crate) const TIMEX_SCREEN_OFFSET_REL: u16 = 0x2000;
+#[cfg(feature = "timex")]
+pub(crate) const TIMEX_BITMAP_BASE_REL: u16 = TIMEX_SCREEN_OFFSET_REL;
+#[cfg(feature = "timex")]
+pub(crate) const TIMEX_BITMAP_MAX_REL: u16 = TIMEX_SCREEN_OFFSET_REL + BITMAP_MAX_REL;
+#[cfg(feature = "timex")]
+pub(crate) const TIMEX_ATTR_BASE_REL: u16 = TIMEX_SCREEN_OFFSET_REL + ATTR_BASE_REL;
+#[cfg(feature = "timex")]
+pub(crate) const TIMEX_ATTR_MAX_REL: u16 = TIMEX_SCREEN_OFFSET_REL + ATTR_MAX_REL;
 /// on all spectrums these values are fixed
 pub(crate) const CLOCKS_PER_COL: usize = 4;
 #[cfg(feature = "precise-border")]
@@ -26,3 +59,13 @@ pub(crate) const BORDER_COLS: usize = 4;
 pub(crate) const BORDER_ROWS: usize = 3;
 /// Tape loading trap at LD-BREAK routine in ROM
 pub(crate) const ADDR_LD_BREAK: u16 = 0x056B;
+/// LD-LOOK-H routine in 48K BASIC ROM, which looks for the tape header of
+/// `LOAD`, `VERIFY` and `MERGE` commands
+pub(crate) const ADDR_LD_LOOK_H: u16 = 0x0767;
+/// "Tape loading error" report (RST 8 in LD-BLOCK) in 48K BASIC ROM
+pub(crate) const ADDR_REPORT_TAPE_LOADING: u16 = 0x0806;
+/// Frames count of ROM loader waiting for the signal without playing tape,
+/// after which tape loading error is reported
+pub(crate) const TAPE_NO_SIGNAL_FRAMES: usize = 50;
+/// "BREAK into program" error report (REPORT-L) in 48K BASIC ROM
+pub(crate) const ADDR_REPORT_BREAK: u16 = 0x1B7B;
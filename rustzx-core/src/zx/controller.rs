@@ -1,62 +1,184 @@
 //! Contains ZX Spectrum System controller (like ula or so) of emulator
 use crate::{
-    error::Error,
+    error::{Error, StateLoadError},
     host::{DebugInterface, Host, HostContext, IoExtender},
     settings::RustzxSettings,
-    utils::screen::bitmap_line_addr,
+    utils::{
+        screen::bitmap_line_addr,
+        state::{StateReader, StateWriter},
+    },
     zx::{
-        constants::{ADDR_LD_BREAK, CANVAS_HEIGHT, CLOCKS_PER_COL},
+        constants::{
+            ADDR_LD_BREAK, ADDR_LD_LOOK_H, ADDR_REPORT_BREAK, ADDR_REPORT_TAPE_LOADING,
+            ATTR_BASE_REL, CANVAS_HEIGHT, CLOCKS_PER_COL, FULLER_AY_DATA_PORT,
+            FULLER_AY_SELECT_PORT, FULLER_JOY_PORT, SCREEN_MEMORY_SIZE, TAPE_NO_SIGNAL_FRAMES,
+        },
+        disk::{BetaDisk, Fdc, TRDOS_ENTRY_END, TRDOS_ENTRY_START},
+        ear_input::ExternalEarInput,
         events::EmulationEvents,
         joy::{
-            kempston::KempstonJoy,
+            cursor::{self, CursorKey},
+            fuller::{FullerJoy, FullerKey},
+            kempston::{KempstonJoy, KEMPSTON_PORT_MASK, KEMPSTON_PORT_VALUE},
             sinclair::{self, SinclairJoyNum, SinclairKey},
         },
-        keys::{CompoundKey, ZXKey},
-        machine::ZXMachine,
-        memory::{Page, RamType, RomType, ZXMemory, PAGE_SIZE},
+        keys::{
+            CompoundKey, KeyboardIssue, ZXKey, ZX_KEYBOARD_ROWS, ZX_KEYBOARD_ROW_KEYS,
+            ZX_KEYS_COUNT,
+        },
+        lightgun::Lightgun,
+        machine::{ContentionModel, MachineSpec, ZXMachine, ZXSpecs},
+        memory::{Page, RamType, RomType, ZXMemory},
         mouse::kempston::{KempstonMouse, KempstonMouseButton, KempstonMouseWheelDirection},
-        tape::{TapeImpl, ZXTape},
+        printer::ZXPrinter,
+        tape::{TapeAsset, TapeImpl, TapeRecorder, ZXTape},
         video::{colors::ZXColor, screen::ZXScreen},
     },
+    Result,
 };
-use rustzx_z80::Z80Bus;
+use rustzx_z80::{Opcode, Prefix, Z80Bus};
 
+#[cfg(feature = "next")]
+use crate::zx::constants::{NEXT_REG_DATA_PORT, NEXT_REG_MMU0, NEXT_REG_SELECT_PORT};
+#[cfg(feature = "heatmap")]
+use crate::zx::heatmap::MemoryHeatmap;
+#[cfg(feature = "next")]
+use crate::zx::memory::MEM_BLOCKS;
 #[cfg(feature = "embedded-roms")]
 use crate::zx::roms;
+#[cfg(all(feature = "sound", feature = "ay"))]
+use crate::zx::sound::ay::AyLog;
 #[cfg(feature = "sound")]
-use crate::zx::sound::mixer::ZXMixer;
+use crate::zx::sound::mixer::{volume_curve, ZXMixer};
 #[cfg(feature = "precise-border")]
 use crate::zx::video::border::ZXBorder;
 
+/// Bounds of the cpu speed multiplier
+const MIN_CPU_SPEED: f64 = 0.1;
+const MAX_CPU_SPEED: f64 = 16.0;
+
+/// Returns cpu speed multiplier clamped to the supported range, `None` for
+/// non-finite values
+fn clamp_cpu_speed(value: f64) -> Option<f64> {
+    value
+        .is_finite()
+        .then(|| value.clamp(MIN_CPU_SPEED, MAX_CPU_SPEED))
+}
+
+/// Ram banks of the +2A/+3 special paging configurations, selected with
+/// bits 1-2 of the port 0x1FFD
+const PLUS3_SPECIAL_PAGING: [[u8; 4]; 4] = [[0, 1, 2, 3], [4, 5, 6, 7], [4, 5, 6, 3], [4, 7, 6, 3]];
+
 /// ZX System controller
 pub(crate) struct ZXController<H: Host> {
     // parts of ZX Spectrum.
     pub machine: ZXMachine,
+    // frame timings, preset of the `machine` or custom clone timings
+    specs: ZXSpecs,
     pub memory: ZXMemory,
     pub screen: ZXScreen<H::FrameBuffer>,
-    pub tape: ZXTape<H::TapeAsset>,
+    pub tape: ZXTape<TapeAsset<H::TapeAsset>>,
+    // active only while MIC output recording is in progress
+    pub tape_recorder: Option<TapeRecorder>,
     #[cfg(feature = "precise-border")]
     pub border: ZXBorder<H::FrameBuffer>,
     pub kempston: Option<KempstonJoy>,
+    pub second_kempston: Option<KempstonJoy>,
+    pub fuller: Option<FullerJoy>,
     pub mouse: Option<KempstonMouse>,
+    pub lightgun: Option<Lightgun>,
+    // Host-provided EAR signal, takes priority over the tape
+    pub ear_input: ExternalEarInput,
+    pub printer: Option<ZXPrinter>,
+    // +3 floppy disk controller
+    pub fdc: Option<Fdc>,
+    // Beta Disk 128 interface with TR-DOS ROM
+    pub beta_disk: Option<BetaDisk>,
     pub io_extender: Option<H::IoExtender>,
     pub debug_interface: Option<H::DebugInterface>,
     #[cfg(feature = "sound")]
     pub mixer: ZXMixer,
-    pub keyboard: [u8; 8],
-    pub keyboard_extended: [u8; 8],
-    pub keyboard_sinclair: [u8; 8],
+    // active only while AY register writes logging is in progress
+    #[cfg(all(feature = "sound", feature = "ay"))]
+    pub ay_log: Option<AyLog>,
+    #[cfg(feature = "heatmap")]
+    pub heatmap: MemoryHeatmap,
+    pub keyboard: [u8; ZX_KEYBOARD_ROWS],
+    pub keyboard_extended: [u8; ZX_KEYBOARD_ROWS],
+    pub keyboard_sinclair: [u8; ZX_KEYBOARD_ROWS],
+    pub keyboard_cursor: [u8; ZX_KEYBOARD_ROWS],
+    // user-defined key remapping, indexed by `ZXKey::index`
+    key_mapping: [Option<ZXKey>; ZX_KEYS_COUNT],
+    // key releases within `key_debounce_frames` after the press are deferred:
+    // frames left until the release is allowed and the deferred release
+    // itself, indexed by `ZXKey::index` of the mapped key
+    key_debounce_frames: u32,
+    key_hold_frames: [u32; ZX_KEYS_COUNT],
+    key_pending_release: [Option<ZXKey>; ZX_KEYS_COUNT],
+    // first Kempston joystick responds when `port & mask == value`
+    kempston_port_mask: u16,
+    kempston_port_value: u16,
     pub caps_shift_modifier_mask: u32,
     // current border color
     pub border_color: ZXColor,
+    // last border color change, made while the tape signal is played, as
+    // (previous, new) colors pair
+    loader_border: Option<[ZXColor; 2]>,
+    keyboard_issue: KeyboardIssue,
+    // last value written to the ULA port
+    ula_out: u8,
     // clocls count from frame start
     frame_clocks: usize,
+    // cpu clocks passed since construction, not scaled by the cpu speed
+    cpu_clocks: u64,
+    // cpu clocks passed since the frame start, INT signal is timed by them
+    frame_cpu_clocks: usize,
+    // length of the INT signal in cpu clocks from the frame start
+    interrupt_length: usize,
+    // standard ROM loader trap is enabled
+    fast_load: bool,
+    // address of the ROM loader edge detection loop (LD-BREAK), where fast
+    // loading is triggered and missing tape signal is detected
+    tape_trap_address: Option<u16>,
+    // pause tape at the end of each block
+    tape_autostop: bool,
+    // ROM loader was waiting for the signal without playing tape during the
+    // current frame, and count of such consecutive frames
+    loader_no_signal: bool,
+    loader_no_signal_frames: usize,
+    // reads of the unattached ports during the current and the last frame
+    floating_bus_reads: u32,
+    last_frame_floating_bus_reads: u32,
+    // frame clocks of the next scanline start, reported to the debug interface
+    next_scanline_clocks: usize,
+    // frame clocks at the moment of the last accepted interrupt
+    last_interrupt_clocks: Option<usize>,
+    // Timex SCLD decodes port 0xFF
+    #[cfg(feature = "timex")]
+    timex_enabled: bool,
+    nmi_pending: bool,
+    // value placed on the data bus during interrupt acknowledge, floating bus
+    // (0xFF) when not set
+    im2_bus_value: Option<u8>,
+    // Ratio of cpu clocks to the frame clocks and not yet accounted fraction
+    // of the frame clock
+    cpu_speed: f64,
+    frame_clocks_fraction: f64,
     // frames count, which passed during emulation invocation
     passed_frames: usize,
     events: EmulationEvents,
+    // Memory blocks, remapped since the last `take_paged_blocks` call
+    paged_blocks: u8,
     paging_enabled: bool,
     screen_bank: u8,
     current_port_7ffd: u8,
+    current_port_1ffd: u8,
+    // Next register ports are decoded
+    #[cfg(feature = "next")]
+    next_enabled: bool,
+    // Selected ZX Spectrum Next register
+    #[cfg(feature = "next")]
+    next_register: u8,
     // Z80 module expected controller implementation without errors,
     // so we need to store the internal errors manually. For sake of simplicity,
     // Only last error is saved
@@ -67,8 +189,13 @@ impl<H: Host> ZXController<H> {
     /// Returns new ZXController from settings
     #[allow(clippy::let_and_return)]
     pub fn new(settings: &RustzxSettings, host_context: H::Context) -> Self {
-        let (memory, paging, screen_bank);
+        let (mut memory, paging, screen_bank);
         match settings.machine {
+            ZXMachine::Sinclair16K => {
+                memory = ZXMemory::new(RomType::K16, RamType::K16);
+                paging = false;
+                screen_bank = 0;
+            }
             ZXMachine::Sinclair48K => {
                 memory = ZXMemory::new(RomType::K16, RamType::K48);
                 paging = false;
@@ -79,6 +206,11 @@ impl<H: Host> ZXController<H> {
                 paging = true;
                 screen_bank = 5;
             }
+            ZXMachine::SinclairPlus3 => {
+                memory = ZXMemory::new(RomType::K64, RamType::K128);
+                paging = true;
+                screen_bank = 5;
+            }
         };
 
         let kempston = if settings.kempston_enabled {
@@ -87,58 +219,169 @@ impl<H: Host> ZXController<H> {
             None
         };
 
+        let second_kempston = if settings.second_kempston_enabled {
+            Some(KempstonJoy::default())
+        } else {
+            None
+        };
+
+        let fuller = if settings.fuller_enabled {
+            Some(FullerJoy::default())
+        } else {
+            None
+        };
+
         let mouse = if settings.mouse_enabled {
             Some(KempstonMouse::default())
         } else {
             None
         };
 
-        let screen = ZXScreen::new(settings.machine, host_context.frame_buffer_context());
+        let lightgun = if settings.lightgun_enabled {
+            Some(Lightgun::default())
+        } else {
+            None
+        };
+
+        let fdc = if settings.machine == ZXMachine::SinclairPlus3 {
+            Some(Fdc::default())
+        } else {
+            None
+        };
+
+        let printer = if settings.printer_enabled {
+            Some(ZXPrinter::default())
+        } else {
+            None
+        };
+
+        let beta_disk = if settings.beta_disk_enabled {
+            Some(BetaDisk::new(memory.add_rom_page()))
+        } else {
+            None
+        };
+
+        let specs = settings.machine.specs().clone();
+        let mut screen = ZXScreen::new(
+            settings.machine,
+            specs.clone(),
+            settings.canvas_buffer_width(),
+            host_context.frame_buffer_context(),
+        );
+        screen.set_blend_frames(settings.blend_frames);
+        screen.set_flash_rate(settings.flash_rate_frames.map(|frames| frames as usize));
         #[cfg(feature = "precise-border")]
-        let border = ZXBorder::new(settings.machine, host_context.frame_buffer_context());
+        let border = ZXBorder::new(
+            specs.clone(),
+            settings.border_size,
+            host_context.frame_buffer_context(),
+        );
 
         #[cfg(feature = "sound")]
-        let mixer = Self::create_mixer(settings);
+        let mixer = Self::create_mixer(settings, &specs);
 
-        let out = ZXController {
+        let mut out = ZXController {
             machine: settings.machine,
+            interrupt_length: specs.interrupt_length,
+            specs,
             memory,
             screen,
             #[cfg(feature = "precise-border")]
             border,
             kempston,
+            second_kempston,
+            fuller,
             mouse,
+            lightgun,
+            ear_input: ExternalEarInput::default(),
+            printer,
+            fdc,
+            beta_disk,
             io_extender: None,
             debug_interface: None,
             #[cfg(feature = "sound")]
             mixer,
-            keyboard: [0xFF; 8],
-            keyboard_extended: [0xFF; 8],
-            keyboard_sinclair: [0xFF; 8],
+            #[cfg(all(feature = "sound", feature = "ay"))]
+            ay_log: None,
+            #[cfg(feature = "heatmap")]
+            heatmap: MemoryHeatmap::default(),
+            keyboard: [0xFF; ZX_KEYBOARD_ROWS],
+            keyboard_extended: [0xFF; ZX_KEYBOARD_ROWS],
+            keyboard_sinclair: [0xFF; ZX_KEYBOARD_ROWS],
+            keyboard_cursor: [0xFF; ZX_KEYBOARD_ROWS],
+            key_mapping: [None; ZX_KEYS_COUNT],
+            key_debounce_frames: 0,
+            key_hold_frames: [0; ZX_KEYS_COUNT],
+            key_pending_release: [None; ZX_KEYS_COUNT],
+            kempston_port_mask: KEMPSTON_PORT_MASK,
+            kempston_port_value: KEMPSTON_PORT_VALUE,
             caps_shift_modifier_mask: 0,
-            border_color: ZXColor::Black,
+            border_color: settings.initial_border_color,
+            loader_border: None,
+            keyboard_issue: settings.keyboard_issue,
+            ula_out: 0,
             frame_clocks: 0,
+            cpu_clocks: 0,
+            frame_cpu_clocks: 0,
+            im2_bus_value: None,
+            last_interrupt_clocks: None,
+            nmi_pending: false,
+            fast_load: settings.tape_fastload_enabled && !settings.deterministic,
+            tape_trap_address: Some(ADDR_LD_BREAK),
+            tape_autostop: false,
+            #[cfg(feature = "timex")]
+            timex_enabled: settings.timex_enabled,
+            loader_no_signal: false,
+            loader_no_signal_frames: 0,
+            floating_bus_reads: 0,
+            last_frame_floating_bus_reads: 0,
+            cpu_speed: clamp_cpu_speed(settings.cpu_speed_multiplier).unwrap_or(1.0),
+            frame_clocks_fraction: 0.0,
+            next_scanline_clocks: 0,
             passed_frames: 0,
             tape: Default::default(),
+            tape_recorder: None,
             events: Default::default(),
+            paged_blocks: 0,
             paging_enabled: paging,
             screen_bank,
             current_port_7ffd: 0,
+            current_port_1ffd: 0,
+            #[cfg(feature = "next")]
+            next_enabled: settings.next_enabled,
+            #[cfg(feature = "next")]
+            next_register: 0,
             last_emulation_error: None,
         };
+        out.apply_boot_screen(
+            settings.initial_border_color,
+            settings.initial_screen_attribute,
+        );
 
         #[cfg(feature = "embedded-roms")]
         if settings.load_default_rom {
-            let mut out = out;
             out.load_default_rom();
-            return out;
         }
 
         out
     }
 
+    /// Replaces frame timings of the machine with custom clone timings.
+    /// Spec should be validated with [MachineSpec::validate]
+    pub fn with_custom_specs(mut self, spec: &MachineSpec) -> Self {
+        self.specs = spec.build(self.machine);
+        self.interrupt_length = self.specs.interrupt_length;
+        self.screen.set_specs(self.specs.clone());
+        #[cfg(feature = "precise-border")]
+        self.border.set_specs(self.specs.clone());
+        #[cfg(feature = "sound")]
+        self.mixer
+            .set_frame_timings(self.specs.clocks_frame, self.specs.freq_cpu);
+        self
+    }
+
     #[cfg(feature = "sound")]
-    fn create_mixer(settings: &RustzxSettings) -> ZXMixer {
+    fn create_mixer(settings: &RustzxSettings, specs: &ZXSpecs) -> ZXMixer {
         let mut mixer = ZXMixer::new(
             settings.beeper_enabled,
             #[cfg(feature = "ay")]
@@ -146,15 +389,20 @@ impl<H: Host> ZXController<H> {
             #[cfg(feature = "ay")]
             settings.ay_mode,
             settings.sound_sample_rate,
+            settings.sound_oversampling,
+            specs.clocks_frame,
+            specs.freq_cpu,
         );
-        mixer.volume(settings.sound_volume as f64 / 200.0);
+        mixer.volume(volume_curve(settings.sound_volume) / 2.0);
+        mixer.set_beeper_lowpass(settings.beeper_lowpass_cutoff);
+        mixer.set_mono_output(settings.mono_output);
         mixer
     }
 
     /// returns current frame emulation pos in percents
     #[cfg(feature = "sound")]
     fn frame_pos(&self) -> f64 {
-        let val = self.frame_clocks as f64 / self.machine.specs().clocks_frame as f64;
+        let val = self.frame_clocks as f64 / self.specs.clocks_frame as f64;
         if val > 1.0 {
             1.0
         } else {
@@ -166,7 +414,7 @@ impl<H: Host> ZXController<H> {
     #[cfg(feature = "embedded-roms")]
     fn load_default_rom(&mut self) {
         match self.machine {
-            ZXMachine::Sinclair48K => {
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K => {
                 let page = self.memory.rom_page_data_mut(0);
                 page.copy_from_slice(roms::ROM_48K);
             }
@@ -176,18 +424,107 @@ impl<H: Host> ZXController<H> {
                 let page = self.memory.rom_page_data_mut(1);
                 page.copy_from_slice(roms::ROM_128K_1);
             }
+            // +3 ROMs are not embedded, `Emulator::new` rejects such settings
+            ZXMachine::SinclairPlus3 => {}
         }
     }
 
     /// Changes key state in controller
     pub fn send_key(&mut self, key: ZXKey, pressed: bool) {
+        let key = self.key_mapping[key.index()].unwrap_or(key);
         if pressed {
             self.keyboard[key.row_id()] &= !key.mask();
+            self.key_hold_frames[key.index()] = self.key_debounce_frames;
+            self.key_pending_release[key.index()] = None;
+            return;
+        }
+        if self.key_hold_frames[key.index()] != 0 {
+            self.key_pending_release[key.index()] = Some(key);
             return;
         }
         self.keyboard[key.row_id()] |= key.mask();
     }
 
+    /// Defers key releases, sent within `frames` after the press of the same
+    /// key. Key is released when the time passes, unless it was pressed again
+    pub fn set_key_debounce(&mut self, frames: u32) {
+        self.key_debounce_frames = frames;
+        if frames == 0 {
+            self.release_debounced_keys(u32::MAX);
+        }
+    }
+
+    /// Advances key debounce timers by `frames` and applies expired releases
+    fn release_debounced_keys(&mut self, frames: u32) {
+        for index in 0..ZX_KEYS_COUNT {
+            let hold = &mut self.key_hold_frames[index];
+            *hold = hold.saturating_sub(frames);
+            if *hold != 0 {
+                continue;
+            }
+            if let Some(key) = self.key_pending_release[index].take() {
+                self.keyboard[key.row_id()] |= key.mask();
+            }
+        }
+    }
+
+    /// Makes `send_key` to press `to` key instead of `from`
+    pub fn set_key_mapping(&mut self, from: ZXKey, to: ZXKey) {
+        self.key_mapping[from.index()] = Some(to);
+    }
+
+    /// Restores default one-to-one key mapping
+    pub fn reset_key_mapping(&mut self) {
+        self.key_mapping = [None; ZX_KEYS_COUNT];
+    }
+
+    pub fn set_kempston_port_mask(&mut self, mask: u16, value: u16) {
+        self.kempston_port_mask = mask;
+        self.kempston_port_value = value;
+    }
+
+    /// Changes state of the raw keyboard matrix position, positions out of
+    /// keyboard matrix bounds are ignored
+    pub fn press_matrix(&mut self, row: usize, bit: u8, pressed: bool) {
+        if row >= ZX_KEYBOARD_ROWS || bit >= ZX_KEYBOARD_ROW_KEYS {
+            return;
+        }
+        let mask = 1 << bit;
+        if pressed {
+            self.keyboard[row] &= !mask;
+            return;
+        }
+        self.keyboard[row] |= mask;
+    }
+
+    /// Returns state of the keyboard half-rows as seen by the ULA port, keys
+    /// pressed via keyboard, compound keys and keyboard-mapped joysticks are
+    /// combined. Reset bit means pressed key
+    pub fn keyboard_matrix(&self) -> [u8; ZX_KEYBOARD_ROWS] {
+        core::array::from_fn(|n| {
+            self.keyboard[n]
+                & self.keyboard_extended[n]
+                & self.keyboard_sinclair[n]
+                & self.keyboard_cursor[n]
+        })
+    }
+
+    /// Releases all keyboard keys, including compound keys and
+    /// keyboard-mapped joysticks
+    pub fn clear_keyboard(&mut self) {
+        for keyboard in [
+            &mut self.keyboard,
+            &mut self.keyboard_extended,
+            &mut self.keyboard_sinclair,
+            &mut self.keyboard_cursor,
+        ] {
+            *keyboard = [0xFF; ZX_KEYBOARD_ROWS];
+        }
+        self.caps_shift_modifier_mask = 0;
+        self.key_hold_frames = [0; ZX_KEYS_COUNT];
+        self.key_pending_release = [None; ZX_KEYS_COUNT];
+    }
+
     pub fn send_sinclair_key(&mut self, num: SinclairJoyNum, key: SinclairKey, pressed: bool) {
         let key = sinclair::sinclair_event_to_zx_key(key, num);
         if pressed {
@@ -197,6 +534,41 @@ impl<H: Host> ZXController<H> {
         self.keyboard_sinclair[key.row_id()] |= key.mask();
     }
 
+    pub fn send_cursor_key(&mut self, key: CursorKey, pressed: bool) {
+        let key = cursor::cursor_event_to_zx_key(key);
+        if pressed {
+            self.keyboard_cursor[key.row_id()] &= !key.mask();
+            return;
+        }
+        self.keyboard_cursor[key.row_id()] |= key.mask();
+    }
+
+    pub fn send_fuller_key(&mut self, key: FullerKey, pressed: bool) {
+        if let Some(joy) = &mut self.fuller {
+            joy.key(key, pressed);
+        }
+    }
+
+    /// Queues external EAR input audio with given sample rate
+    pub fn feed_ear_audio(&mut self, samples: &[f32], sample_rate: usize) {
+        self.ear_input
+            .feed_audio(samples, self.specs.freq_cpu as f64 / sample_rate as f64);
+    }
+
+    pub fn set_lightgun(&mut self, x: u16, y: u16, trigger: bool) {
+        if let Some(gun) = &mut self.lightgun {
+            gun.set(x, y, trigger);
+        }
+    }
+
+    /// Checks if lightgun sensor currently sees the lit pixel under the beam
+    fn lightgun_light_detected(&self) -> bool {
+        match (&self.lightgun, self.memory.ram_bank(self.screen_bank())) {
+            (Some(gun), Ok(screen)) => gun.light_detected(self.frame_clocks, &self.specs, screen),
+            _ => false,
+        }
+    }
+
     pub fn send_compound_key(&mut self, key: CompoundKey, pressed: bool) {
         let mut dummy_modifier_mask = 0;
         let modifier_mask = match key.modifier_key() {
@@ -239,8 +611,20 @@ impl<H: Host> ZXController<H> {
 
     /// Returns current bus floating value
     fn floating_bus_value(&self) -> u8 {
-        let specs = self.machine.specs();
-        let clocks = self.frame_clocks;
+        self.floating_bus_at(self.frame_clocks)
+    }
+
+    /// Returns value, which is left on the data bus by the ULA screen fetch
+    /// at the given frame `clocks`, without any side effects. Memory is read
+    /// with the current memory map
+    pub fn floating_bus_at(&self, clocks: usize) -> u8 {
+        // Screen data fetched by the +2A/+3 gate array does not leak to the
+        // unattached ports
+        if self.specs.contention_model != ContentionModel::Ula {
+            return 0xFF;
+        }
+        let specs = &self.specs;
+        let clocks = clocks % specs.clocks_frame;
         if clocks < specs.clocks_first_pixel + 2 {
             return 0xFF;
         }
@@ -264,13 +648,13 @@ impl<H: Host> ZXController<H> {
 
     /// make contention
     fn do_contention(&mut self) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.specs.contention_clocks(self.frame_clocks);
         self.wait_internal(contention);
     }
 
     /// make contention + wait some clocks
     fn do_contention_and_wait(&mut self, wait_time: usize) {
-        let contention = self.machine.contention_clocks(self.frame_clocks);
+        let contention = self.specs.contention_clocks(self.frame_clocks);
         self.wait_internal(contention + wait_time);
     }
 
@@ -284,8 +668,24 @@ impl<H: Host> ZXController<H> {
     }
 
     /// Returns early IO contention clocks
+    ///
+    /// I/O cycle of the Sinclair ULA machines takes 4 clocks, contention
+    /// depends on the high byte of the port (is it in the contended memory
+    /// range) and on the ULA port bit 0:
+    /// - uncontended high byte, bit 0 set: `N:4`
+    /// - uncontended high byte, bit 0 reset: `N:1, C:3`
+    /// - contended high byte, bit 0 set: `C:1, C:1, C:1, C:1`
+    /// - contended high byte, bit 0 reset: `C:1, C:3`
+    ///
+    /// Cycle is split between [Self::io_contention_first] (first clock),
+    /// [Self::io_contention_last] (next 2 clocks) and the last clock, added
+    /// by the caller after the port access. Memory accesses of the same
+    /// instruction (opcode fetch, `IR:1` and `(HL)` of the block I/O
+    /// instructions) are contended separately in [Z80Bus::wait_mreq] and
+    /// [Z80Bus::wait_no_mreq] before or after the whole I/O cycle, so delays
+    /// are never merged or counted twice
     fn io_contention_first(&mut self, port: u16) {
-        if self.addr_is_contended(port) {
+        if self.specs.contention_model == ContentionModel::Ula && self.addr_is_contended(port) {
             self.do_contention();
         };
         self.wait_internal(1);
@@ -293,7 +693,10 @@ impl<H: Host> ZXController<H> {
 
     /// Returns late IO contention clocks
     fn io_contention_last(&mut self, port: u16) {
-        if self.machine.port_is_contended(port) {
+        if self.specs.contention_model != ContentionModel::Ula {
+            // IO is not contended on machines without Sinclair ULA
+            self.wait_internal(2);
+        } else if self.specs.port_is_contended(port) {
             self.do_contention_and_wait(2);
         } else if self.addr_is_contended(port) {
             self.do_contention_and_wait(1);
@@ -304,14 +707,156 @@ impl<H: Host> ZXController<H> {
         }
     }
 
+    /// Changes ratio of the cpu clocks to the frame clocks, e.g. with value of
+    /// 2.0 cpu executes twice more instructions during the single frame
+    pub fn set_cpu_speed(&mut self, value: f64) {
+        if let Some(speed) = clamp_cpu_speed(value) {
+            self.cpu_speed = speed;
+            self.frame_clocks_fraction = 0.0;
+        }
+    }
+
+    /// Changes length of the frame interrupt signal in cpu clocks
+    pub fn set_interrupt_length(&mut self, clocks: usize) {
+        self.interrupt_length = clocks;
+    }
+
+    /// Enables or disables fast tape loading trap
+    pub fn set_fast_load(&mut self, value: bool) {
+        self.fast_load = value;
+    }
+
+    pub fn fast_load(&self) -> bool {
+        self.fast_load
+    }
+
+    pub fn set_tape_trap_address(&mut self, addr: Option<u16>) {
+        self.tape_trap_address = addr;
+    }
+
+    pub fn tape_trap_address(&self) -> Option<u16> {
+        self.tape_trap_address
+    }
+
+    /// Replaces inserted tape, the rest of the machine state is preserved.
+    /// New tape is rewound to the first block and stopped, so it could be
+    /// either fast loaded or played
+    pub fn insert_tape(&mut self, mut tape: ZXTape<TapeAsset<H::TapeAsset>>) -> Result<()> {
+        tape.rewind()?;
+        tape.stop();
+        tape.resume();
+        tape.take_block_finished();
+        self.tape = tape;
+        Ok(())
+    }
+
+    /// Removes inserted tape, EAR input becomes silent
+    pub fn eject_tape(&mut self) {
+        self.tape = ZXTape::default();
+    }
+
+    /// Enables or disables tape pause at the end of each block
+    pub fn set_tape_autostop(&mut self, value: bool) {
+        self.tape_autostop = value;
+    }
+
+    /// Changes value placed on the data bus during interrupt acknowledge
+    pub fn set_im2_bus_value(&mut self, value: Option<u8>) {
+        self.im2_bus_value = value;
+    }
+
+    /// Returns frame clocks at which the last maskable interrupt was accepted
+    pub fn last_interrupt_clocks(&self) -> Option<usize> {
+        self.last_interrupt_clocks
+    }
+
+    pub fn interrupt_length(&self) -> usize {
+        self.interrupt_length
+    }
+
+    /// Activates NMI line until the cpu accepts the interrupt
+    pub fn trigger_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    pub fn nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    /// Returns clocks passed since the start of the current frame
+    pub fn frame_clocks(&self) -> usize {
+        self.frame_clocks
+    }
+
+    /// Returns cpu clocks passed since construction. Unlike frame clocks,
+    /// these are not affected by the cpu speed
+    pub fn cpu_clocks(&self) -> u64 {
+        self.cpu_clocks
+    }
+
+    /// Returns length of the frame in clocks
+    pub fn clocks_frame(&self) -> usize {
+        self.specs.clocks_frame
+    }
+
+    /// Returns cpu frequency of the machine in Hz
+    pub fn freq_cpu(&self) -> usize {
+        self.specs.freq_cpu
+    }
+
+    /// Returns current beam position as line and clock within the line, both
+    /// counted from the frame interrupt
+    pub fn raster_position(&self) -> (u16, u16) {
+        let specs = &self.specs;
+        let clocks = self.frame_clocks % specs.clocks_frame;
+        (
+            (clocks / specs.clocks_line) as u16,
+            (clocks % specs.clocks_line) as u16,
+        )
+    }
+
+    /// Converts passed cpu clocks to the frame (video) clocks according to the
+    /// current cpu speed
+    fn cpu_to_frame_clocks(&mut self, clk: usize) -> usize {
+        if self.cpu_speed == 1.0 {
+            return clk;
+        }
+        self.frame_clocks_fraction += clk as f64 / self.cpu_speed;
+        let whole = self.frame_clocks_fraction as usize;
+        self.frame_clocks_fraction -= whole as f64;
+        whole
+    }
+
     /// Starts a new frame
     fn new_frame(&mut self) {
-        self.frame_clocks -= self.machine.specs().clocks_frame;
+        self.frame_clocks -= self.specs.clocks_frame;
+        // Clocks which overrun the frame belong to the new one
+        self.frame_cpu_clocks = (self.frame_clocks as f64 * self.cpu_speed) as usize;
+        self.next_scanline_clocks = 0;
         self.screen.new_frame();
         #[cfg(feature = "precise-border")]
         self.border.new_frame();
         #[cfg(feature = "sound")]
         self.mixer.new_frame();
+        #[cfg(all(feature = "sound", feature = "ay"))]
+        if let Some(log) = &mut self.ay_log {
+            log.new_frame();
+        }
+        if core::mem::take(&mut self.loader_no_signal) {
+            self.loader_no_signal_frames += 1;
+            if self.loader_no_signal_frames == TAPE_NO_SIGNAL_FRAMES {
+                self.events |= EmulationEvents::TAPE_LOAD_ERROR;
+            }
+        } else {
+            self.loader_no_signal_frames = 0;
+        }
+        self.release_debounced_keys(1);
+        self.last_frame_floating_bus_reads = core::mem::take(&mut self.floating_bus_reads);
+    }
+
+    /// Returns count of the unattached port reads during the last frame
+    pub fn last_frame_floating_bus_reads(&self) -> u32 {
+        self.last_frame_floating_bus_reads
     }
 
     /// Collects all events from the last emulation step
@@ -319,6 +864,12 @@ impl<H: Host> ZXController<H> {
         self.events.take()
     }
 
+    /// Returns mask of the memory blocks (bit N for the block at N * 0x4000),
+    /// which were remapped since the last call
+    pub fn take_paged_blocks(&mut self) -> u8 {
+        core::mem::take(&mut self.paged_blocks)
+    }
+
     /// Returns true if all frame clocks has been passed
     pub fn frames_count(&self) -> usize {
         self.passed_frames
@@ -328,30 +879,414 @@ impl<H: Host> ZXController<H> {
         self.passed_frames = 0;
     }
 
+    /// Restores power-on state of the machine: memory paging and frame
+    /// timings. Memory contents and peripherals state are not affected
+    pub fn power_on(&mut self) {
+        self.frame_clocks = 0;
+        self.frame_cpu_clocks = 0;
+        self.frame_clocks_fraction = 0.0;
+        self.next_scanline_clocks = 0;
+        self.passed_frames = 0;
+        self.floating_bus_reads = 0;
+        self.last_frame_floating_bus_reads = 0;
+        self.reset();
+    }
+
+    /// Applies RESET signal to the machine: ROM 0 is paged in and 128K
+    /// paging is unlocked. Frame timings and memory contents are not affected
+    pub fn reset(&mut self) {
+        self.nmi_pending = false;
+        self.events.remove(EmulationEvents::CPU_HALTED);
+        self.paging_enabled = self.machine.capabilities().has_128k_paging;
+        self.current_port_1ffd = 0;
+        #[cfg(feature = "next")]
+        {
+            self.next_register = 0;
+            if self.memory.is_mmu_active() {
+                // 16K memory map is used again for all blocks
+                self.memory.reset_mmu();
+                self.paged_blocks |= (1 << MEM_BLOCKS) - 1;
+                self.events |= EmulationEvents::MEMORY_PAGED;
+            }
+        }
+        if let Some(beta_disk) = &mut self.beta_disk {
+            beta_disk.set_rom_active(false);
+            self.remap_block(0, Page::Rom(0));
+        }
+        if self.paging_enabled {
+            self.write_7ffd(0);
+        }
+    }
+
+    /// Returns machine timings, which saved state depends on
+    fn specs_timings(&self) -> [usize; 5] {
+        let specs = &self.specs;
+        [
+            specs.freq_cpu,
+            specs.clocks_line,
+            specs.clocks_frame,
+            specs.clocks_first_pixel,
+            specs.contention_model as usize,
+        ]
+    }
+
+    /// Writes machine state (memory, paging, frame timings, keyboard, sound
+    /// devices, tape position and peripherals) to the emulator state
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        for value in self.specs_timings() {
+            writer.write_usize(value);
+        }
+        self.memory.save_state(writer);
+        writer.write_bool(self.paging_enabled);
+        writer.write_u8(self.screen_bank);
+        writer.write_u8(self.current_port_7ffd);
+        writer.write_u8(self.current_port_1ffd);
+        writer.write_bool(self.beta_disk.as_ref().is_some_and(BetaDisk::rom_active));
+        writer.write_usize(self.frame_clocks);
+        writer.write_usize(self.frame_cpu_clocks);
+        writer.write_usize(self.interrupt_length);
+        writer.write_f64(self.cpu_speed);
+        writer.write_bool(self.im2_bus_value.is_some());
+        writer.write_u8(self.im2_bus_value.unwrap_or(0xFF));
+        writer.write_f64(self.frame_clocks_fraction);
+        writer.write_usize(self.passed_frames);
+        writer.write_u16(self.events.bits());
+        writer.write_u8(self.border_color.into());
+        writer.write_u8(self.ula_out);
+        writer.write_bool(self.nmi_pending);
+        for keyboard in [
+            &self.keyboard,
+            &self.keyboard_extended,
+            &self.keyboard_sinclair,
+            &self.keyboard_cursor,
+        ] {
+            writer.write_bytes(keyboard);
+        }
+        writer.write_u32(self.caps_shift_modifier_mask);
+        writer.write_u8(self.kempston.as_ref().map_or(0, KempstonJoy::read));
+        writer.write_u8(self.second_kempston.as_ref().map_or(0, KempstonJoy::read));
+        writer.write_u8(self.fuller.as_ref().map_or(0xFF, FullerJoy::read));
+        let mouse = self.mouse.as_ref();
+        writer.write_u8(mouse.map_or(0xFF, |m| m.buttons_port));
+        writer.write_u8(mouse.map_or(0xFF, |m| m.x_pos_port));
+        writer.write_u8(mouse.map_or(0xFF, |m| m.y_pos_port));
+        self.screen.save_state(writer);
+
+        // Optional devices are saved as separate sections, so state could be
+        // loaded by the emulator built without them
+        writer.write_section(|_section| {
+            #[cfg(feature = "precise-border")]
+            self.border.save_state(_section);
+        });
+        writer.write_section(|_section| {
+            #[cfg(feature = "sound")]
+            self.mixer.save_state(_section);
+        });
+        writer.write_section(|section| {
+            if let ZXTape::Tap(tap) = &self.tape {
+                tap.save_state(section);
+            }
+        });
+        writer.write_section(|section| {
+            if let Some(printer) = &self.printer {
+                printer.save_state(section);
+            }
+        });
+        writer.write_section(|_section| {
+            #[cfg(feature = "next")]
+            {
+                _section.write_u8(self.next_register);
+                _section.write_bool(self.memory.is_mmu_active());
+                self.memory.save_mmu_state(_section);
+            }
+        });
+        writer.write_section(|section| {
+            if let Some(fdc) = &self.fdc {
+                fdc.save_state(section);
+            }
+        });
+        writer.write_section(|section| {
+            if let Some(beta_disk) = &self.beta_disk {
+                beta_disk.save_state(section);
+            }
+        });
+    }
+
+    /// Restores state, saved with [ZXController::save_state]. Tape state is
+    /// restored only when the same tape is inserted
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        // State of the machine with custom timings is not compatible with
+        // the built-in machine and vice versa
+        for value in self.specs_timings() {
+            if reader.read_usize()? != value {
+                return Err(StateLoadError::MachineMismatch.into());
+            }
+        }
+        self.memory.load_state(reader)?;
+        self.paging_enabled = reader.read_bool()?;
+        self.screen_bank = reader.read_u8()?;
+        self.current_port_7ffd = reader.read_u8()?;
+        self.current_port_1ffd = reader.read_u8()?;
+        let trdos_rom_active = reader.read_bool()?;
+        if let Some(beta_disk) = &mut self.beta_disk {
+            beta_disk.set_rom_active(trdos_rom_active);
+        }
+        self.frame_clocks = reader.read_usize()?;
+        self.frame_cpu_clocks = reader.read_usize()?;
+        self.interrupt_length = reader.read_usize()?;
+        let cpu_speed = reader.read_f64()?;
+        self.cpu_speed = clamp_cpu_speed(cpu_speed)
+            .filter(|&speed| speed == cpu_speed)
+            .ok_or(StateLoadError::InvalidState)?;
+        let has_im2_bus_value = reader.read_bool()?;
+        let im2_bus_value = reader.read_u8()?;
+        self.im2_bus_value = has_im2_bus_value.then_some(im2_bus_value);
+        self.frame_clocks_fraction = reader.read_f64()?;
+        let clocks_line = self.specs.clocks_line;
+        self.next_scanline_clocks = self.frame_clocks.div_ceil(clocks_line) * clocks_line;
+        self.passed_frames = reader.read_usize()?;
+        // Floating bus reads statistics are not saved, counting starts over
+        self.floating_bus_reads = 0;
+        self.last_frame_floating_bus_reads = 0;
+        self.events = EmulationEvents::from_bits_truncate(reader.read_u16()?);
+        self.border_color = ZXColor::from_bits(reader.read_u8()?);
+        self.ula_out = reader.read_u8()?;
+        self.nmi_pending = reader.read_bool()?;
+        for keyboard in [
+            &mut self.keyboard,
+            &mut self.keyboard_extended,
+            &mut self.keyboard_sinclair,
+            &mut self.keyboard_cursor,
+        ] {
+            reader.read_into(keyboard)?;
+        }
+        self.caps_shift_modifier_mask = reader.read_u32()?;
+        let kempston = reader.read_u8()?;
+        if let Some(joy) = &mut self.kempston {
+            *joy = KempstonJoy::from_state(kempston);
+        }
+        let second_kempston = reader.read_u8()?;
+        if let Some(joy) = &mut self.second_kempston {
+            *joy = KempstonJoy::from_state(second_kempston);
+        }
+        let fuller = reader.read_u8()?;
+        if let Some(joy) = &mut self.fuller {
+            *joy = FullerJoy::from_state(!fuller);
+        }
+        let mouse_ports = [reader.read_u8()?, reader.read_u8()?, reader.read_u8()?];
+        if let Some(mouse) = &mut self.mouse {
+            [mouse.buttons_port, mouse.x_pos_port, mouse.y_pos_port] = mouse_ports;
+        }
+        if self.frame_clocks >= self.specs.clocks_frame {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        self.refresh_memory_dependent_devices();
+        self.screen.load_state(reader)?;
+
+        let _border = reader.read_blob()?;
+        #[cfg(feature = "precise-border")]
+        if !_border.is_empty() {
+            let mut section = StateReader::new(_border);
+            self.border.load_state(&mut section)?;
+            section.finish()?;
+        }
+
+        let _mixer = reader.read_blob()?;
+        #[cfg(feature = "sound")]
+        if !_mixer.is_empty() {
+            let mut section = StateReader::new(_mixer);
+            self.mixer.load_state(&mut section)?;
+            section.finish()?;
+        }
+
+        let tape = reader.read_blob()?;
+        if let ZXTape::Tap(tap) = &mut self.tape {
+            if !tape.is_empty() {
+                let mut section = StateReader::new(tape);
+                if tap.load_state(&mut section)? {
+                    section.finish()?;
+                }
+            }
+        }
+        let printer = reader.read_blob()?;
+        if let Some(printer_device) = &mut self.printer {
+            if !printer.is_empty() {
+                let mut section = StateReader::new(printer);
+                printer_device.load_state(&mut section)?;
+                section.finish()?;
+            }
+        }
+        let _next = reader.read_blob()?;
+        #[cfg(feature = "next")]
+        {
+            self.next_register = 0;
+            self.memory.reset_mmu();
+            if !_next.is_empty() {
+                let mut section = StateReader::new(_next);
+                self.next_register = section.read_u8()?;
+                if section.read_bool()? {
+                    self.memory.load_mmu_state(&mut section)?;
+                }
+                section.finish()?;
+            }
+        }
+        let fdc_state = reader.read_blob()?;
+        if let Some(fdc) = &mut self.fdc {
+            if !fdc_state.is_empty() {
+                let mut section = StateReader::new(fdc_state);
+                fdc.load_state(&mut section)?;
+                section.finish()?;
+            }
+        }
+        let beta_disk_state = reader.read_blob()?;
+        if let Some(beta_disk) = &mut self.beta_disk {
+            if !beta_disk_state.is_empty() {
+                let mut section = StateReader::new(beta_disk_state);
+                beta_disk.load_state(&mut section)?;
+                section.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns ram bank which is currently displayed by ULA
+    pub fn screen_bank(&self) -> usize {
+        self.screen_bank as usize
+    }
+
+    /// Returns true if further 0x7FFD/0x1FFD writes are ignored until reset.
+    /// Always false on machines without paging
+    pub fn is_paging_locked(&self) -> bool {
+        self.machine.capabilities().has_128k_paging && !self.paging_enabled
+    }
+
+    /// Returns ROM page mapped to the first memory block, or the page
+    /// selected by the paging ports when all-RAM configuration is active
+    pub fn current_rom_page(&self) -> u8 {
+        match self.memory.get_bank_type(0) {
+            Page::Rom(page) => page,
+            Page::Ram(_) | Page::Unmapped => self.selected_rom_page(),
+        }
+    }
+
+    /// Returns ROM page selected by 0x7FFD and 0x1FFD ports
+    fn selected_rom_page(&self) -> u8 {
+        let mut rom = (self.current_port_7ffd >> 4) & 0x01;
+        if self.machine == ZXMachine::SinclairPlus3 {
+            rom |= (self.current_port_1ffd >> 1) & 0x02;
+        }
+        rom
+    }
+
     pub fn write_7ffd(&mut self, val: u8) {
         if !self.paging_enabled {
             return;
         }
         self.current_port_7ffd = val;
-        // remap top 16K of the ram
-        self.memory.remap(3, Page::Ram(val & 0x07));
-        // third block is not pageable
         // second block is screen buffer, not pageable. but we need to change active buffer
         let new_screen_bank = if val & 0x08 == 0 { 5 } else { 7 };
+        if new_screen_bank != self.screen_bank {
+            self.events |= EmulationEvents::SHADOW_SCREEN_SWITCHED;
+        }
         self.screen.switch_bank(new_screen_bank as usize);
         self.screen_bank = new_screen_bank;
-        // remap ROM
-        self.memory.remap(0, Page::Rom((val >> 4) & 0x01));
+        self.remap_paged_memory();
         // check paging allow bit
         if val & 0x20 != 0 {
             self.paging_enabled = false;
         }
+        log_debug!(
+            "Port 0x7FFD write {:#04X}: ram bank {} at 0xC000, screen bank {}, rom {}, locked: {}",
+            val,
+            val & 0x07,
+            new_screen_bank,
+            (val >> 4) & 0x01,
+            !self.paging_enabled
+        );
     }
 
     pub fn read_7ffd(&self) -> u8 {
         self.current_port_7ffd
     }
 
+    /// Writes +2A/+3 memory configuration port, locked together with 0x7FFD
+    pub fn write_1ffd(&mut self, val: u8) {
+        // Disk motor is not affected by the paging lock
+        if let Some(fdc) = &mut self.fdc {
+            fdc.set_motor(val & 0x08 != 0);
+        }
+        if !self.paging_enabled {
+            return;
+        }
+        self.current_port_1ffd = val;
+        self.remap_paged_memory();
+        log_debug!(
+            "Port 0x1FFD write {:#04X}: special paging: {}, config {}",
+            val,
+            val & 0x01 != 0,
+            (val >> 1) & 0x03
+        );
+    }
+
+    /// Builds memory map from the 0x7FFD and 0x1FFD port values
+    fn remap_paged_memory(&mut self) {
+        let is_plus3 = self.machine == ZXMachine::SinclairPlus3;
+        if is_plus3 && self.current_port_1ffd & 0x01 != 0 {
+            // Special all-RAM configuration, ROM and 0x7FFD ram bank are ignored
+            let config = (self.current_port_1ffd >> 1) & 0x03;
+            for (block, bank) in PLUS3_SPECIAL_PAGING[config as usize].iter().enumerate() {
+                self.remap_block(block, Page::Ram(*bank));
+            }
+            return;
+        }
+        // ROM high bit is provided by 0x1FFD on +3
+        let rom = self.selected_rom_page();
+        let trdos_page = self
+            .beta_disk
+            .as_ref()
+            .filter(|beta_disk| beta_disk.rom_active())
+            .map(BetaDisk::rom_page);
+        self.remap_block(0, Page::Rom(trdos_page.unwrap_or(rom)));
+        // second and third blocks are not pageable in normal mode
+        self.remap_block(1, Page::Ram(5));
+        self.remap_block(2, Page::Ram(2));
+        // remap top 16K of the ram
+        self.remap_block(3, Page::Ram(self.current_port_7ffd & 0x07));
+    }
+
+    /// Maps page to the memory block, changes of the memory map are
+    /// reported with [EmulationEvents::MEMORY_PAGED]
+    fn remap_block(&mut self, block: usize, page: Page) {
+        if self.memory.is_block_mapped_to(block, page) {
+            return;
+        }
+        self.memory.remap(block, page);
+        self.paged_blocks |= 1 << block;
+        self.events |= EmulationEvents::MEMORY_PAGED;
+    }
+
+    /// Pages TR-DOS ROM in or out of the first memory block
+    fn set_trdos_rom_active(&mut self, active: bool) {
+        if let Some(beta_disk) = &mut self.beta_disk {
+            beta_disk.set_rom_active(active);
+        }
+        if self.machine.capabilities().has_128k_paging {
+            self.remap_paged_memory();
+        } else {
+            let page = match &self.beta_disk {
+                Some(beta_disk) if active => beta_disk.rom_page(),
+                _ => 0,
+            };
+            self.remap_block(0, Page::Rom(page));
+        }
+    }
+
+    /// Beta Disk ports (0x1F, 0x3F, 0x5F, 0x7F and 0xFF) are accessible
+    /// only while TR-DOS ROM is active
+    fn beta_disk_port_active(&self, port: u16) -> bool {
+        port & 0x1F == 0x1F && self.beta_disk.as_ref().is_some_and(BetaDisk::rom_active)
+    }
+
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn read_ay_port(&mut self) -> u8 {
         self.mixer.ay.read()
@@ -364,6 +1299,10 @@ impl<H: Host> ZXController<H> {
 
     #[cfg(all(feature = "sound", feature = "ay"))]
     fn write_ay_port(&mut self, value: u8) {
+        if let Some(log) = &mut self.ay_log {
+            let (register, _) = self.mixer.ay.registers();
+            log.record(self.frame_clocks, register as u8, value);
+        }
         self.mixer.ay.write(value);
     }
 
@@ -378,35 +1317,143 @@ impl<H: Host> ZXController<H> {
     #[cfg(not(all(feature = "sound", feature = "ay")))]
     fn select_ay_reg(&mut self, _: u8) {}
 
+    /// Returns true if Timex SCLD is enabled and decodes `port`
+    #[cfg(feature = "timex")]
+    fn timex_port_active(&self, port: u16) -> bool {
+        self.timex_enabled && port & 0x00FF == 0x00FF
+    }
+
+    #[cfg(not(feature = "timex"))]
+    fn timex_port_active(&self, _: u16) -> bool {
+        false
+    }
+
+    #[cfg(feature = "timex")]
+    fn read_timex_port(&mut self) -> u8 {
+        self.screen.timex_port()
+    }
+
+    #[cfg(not(feature = "timex"))]
+    fn read_timex_port(&mut self) -> u8 {
+        self.floating_bus_value()
+    }
+
+    #[cfg(feature = "timex")]
+    fn write_timex_port(&mut self, value: u8) {
+        self.screen.set_timex_port(value);
+    }
+
+    #[cfg(not(feature = "timex"))]
+    fn write_timex_port(&mut self, _: u8) {}
+
+    /// Returns true if Next registers are enabled and `port` is one of the
+    /// register select/data ports
+    #[cfg(feature = "next")]
+    fn next_port_active(&self, port: u16) -> bool {
+        self.next_enabled && (port == NEXT_REG_SELECT_PORT || port == NEXT_REG_DATA_PORT)
+    }
+
+    #[cfg(not(feature = "next"))]
+    fn next_port_active(&self, _: u16) -> bool {
+        false
+    }
+
+    #[cfg(feature = "next")]
+    fn read_next_port(&mut self, port: u16) -> u8 {
+        if port == NEXT_REG_SELECT_PORT {
+            return self.next_register;
+        }
+        match self.next_register.wrapping_sub(NEXT_REG_MMU0) {
+            slot @ 0..=7 => self.memory.mmu_slot(slot as usize),
+            _ => 0xFF,
+        }
+    }
+
+    #[cfg(not(feature = "next"))]
+    fn read_next_port(&mut self, _: u16) -> u8 {
+        self.floating_bus_value()
+    }
+
+    #[cfg(feature = "next")]
+    fn write_next_port(&mut self, port: u16, value: u8) {
+        if port == NEXT_REG_SELECT_PORT {
+            self.next_register = value;
+            return;
+        }
+        if let slot @ 0..=7 = self.next_register.wrapping_sub(NEXT_REG_MMU0) {
+            self.set_mmu_slot(slot as usize, value);
+        }
+    }
+
+    /// Maps 8K page to the Next MMU slot, changes of the memory map are
+    /// reported with [EmulationEvents::MEMORY_PAGED]
+    #[cfg(feature = "next")]
+    fn set_mmu_slot(&mut self, slot: usize, page: u8) {
+        let changed = self.memory.mmu_slot(slot) != page;
+        self.memory.set_mmu_slot(slot, page);
+        if changed {
+            self.paged_blocks |= 1 << (slot / 2);
+            self.events |= EmulationEvents::MEMORY_PAGED;
+        }
+    }
+
+    #[cfg(not(feature = "next"))]
+    fn write_next_port(&mut self, _: u16, _: u8) {}
+
     pub(crate) fn set_border_color(
         &mut self,
         #[cfg(feature = "precise-border")] clocks: usize,
         #[cfg(not(feature = "precise-border"))] _clocks: usize,
         color: ZXColor,
     ) {
+        let changed = u8::from(color) != u8::from(self.border_color);
+        if changed && self.tape.is_playing() && !self.tape.is_paused() {
+            self.loader_border = Some([self.border_color, color]);
+        }
         self.border_color = color;
         #[cfg(feature = "precise-border")]
         self.border.set_border(clocks, color);
     }
 
+    /// Returns colors of the last border change, made by the tape loader
+    pub fn loader_border(&self) -> Option<[ZXColor; 2]> {
+        self.loader_border
+    }
+
+    /// Records border colors, which tape loader alternates, e.g. when its
+    /// border writes are skipped by the instant tape load
+    pub(crate) fn record_loader_border(&mut self, colors: [ZXColor; 2]) {
+        self.loader_border = Some(colors);
+    }
+
     pub(crate) fn take_last_emulation_error(&mut self) -> Option<Error> {
         self.last_emulation_error.take()
     }
 
+    /// Sets power-on border color and clears the screen if `attribute` is
+    /// set, see [RustzxSettings::initial_screen_attribute]
+    pub(crate) fn apply_boot_screen(&mut self, border: ZXColor, attribute: Option<u8>) {
+        self.set_border_color(0, border);
+        let Some(attribute) = attribute else {
+            return;
+        };
+        let bank = self
+            .memory
+            .ram_bank_mut(self.screen_bank())
+            .expect("Screen bank is always present");
+        bank[..ATTR_BASE_REL as usize].fill(0);
+        bank[ATTR_BASE_REL as usize..SCREEN_MEMORY_SIZE].fill(attribute);
+        self.refresh_memory_dependent_devices();
+    }
+
     pub(crate) fn refresh_memory_dependent_devices(&mut self) {
         match self.machine {
-            ZXMachine::Sinclair48K => {
-                for (idx, data) in self.memory.ram_page_data(0).iter().enumerate() {
-                    self.screen.update(idx as u16, 0, *data);
-                }
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K => {
+                self.screen.update_range(0, self.memory.ram_page_data(0), 0);
             }
-            ZXMachine::Sinclair128K => {
-                for (idx, data) in self.memory.ram_page_data(5).iter().enumerate() {
-                    self.screen.update(idx as u16, 5, *data);
-                }
-                for (idx, data) in self.memory.ram_page_data(7).iter().enumerate() {
-                    self.screen.update(idx as u16, 7, *data);
-                }
+            ZXMachine::Sinclair128K | ZXMachine::SinclairPlus3 => {
+                self.screen.update_range(0, self.memory.ram_page_data(5), 5);
+                self.screen.update_range(0, self.memory.ram_page_data(7), 7);
             }
         }
     }
@@ -416,20 +1463,53 @@ impl<H: Host> Z80Bus for ZXController<H> {
     /// we need to check different breakpoints like tape
     /// loading detection breakpoint
     fn pc_callback(&mut self, addr: u16) {
+        #[cfg(feature = "heatmap")]
+        self.heatmap.record_execute(addr);
         // check mapped memory page at 0x0000 .. 0x3FFF
-        let check_fast_load = match self.machine {
-            ZXMachine::Sinclair48K if self.memory.get_bank_type(0) == Page::Rom(0) => true,
+        let basic_48k_rom_active = match self.machine {
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K
+                if self.memory.get_bank_type(0) == Page::Rom(0) =>
+            {
+                true
+            }
             ZXMachine::Sinclair128K if self.memory.get_bank_type(0) == Page::Rom(1) => true,
+            ZXMachine::SinclairPlus3 if self.memory.get_bank_type(0) == Page::Rom(3) => true,
             _ => false,
         };
-        if check_fast_load {
-            // Tape LOAD/VERIFY
-            if addr == ADDR_LD_BREAK {
-                // Add event (Fast tape loading request) it must be executed
-                // by emulator immediately
-                self.events |= EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED;
+        if basic_48k_rom_active {
+            match addr {
+                // Tape LOAD/VERIFY
+                _ if Some(addr) == self.tape_trap_address => {
+                    if self.fast_load && !self.ear_input.is_active() {
+                        // Add event (Fast tape loading request) it must be executed
+                        // by emulator immediately
+                        self.events |= EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED;
+                    }
+                    // Loader returns to LD-BREAK each time edge is not found
+                    let tape_silent = !self.tape.is_playing() || self.tape.is_paused();
+                    if tape_silent && !self.ear_input.is_active() {
+                        self.loader_no_signal = true;
+                    }
+                }
+                ADDR_LD_LOOK_H => {
+                    self.events |= EmulationEvents::TAPE_LOAD_REQUESTED;
+                }
+                ADDR_REPORT_TAPE_LOADING => {
+                    self.events |= EmulationEvents::TAPE_LOAD_ERROR;
+                }
+                ADDR_REPORT_BREAK => {
+                    self.events |= EmulationEvents::BREAK_KEY;
+                }
+                TRDOS_ENTRY_START..=TRDOS_ENTRY_END if self.beta_disk.is_some() => {
+                    self.set_trdos_rom_active(true);
+                }
+                _ => {}
             }
         }
+        // TR-DOS ROM is paged out as soon as code is executed from RAM
+        if addr >= 0x4000 && self.beta_disk.as_ref().is_some_and(BetaDisk::rom_active) {
+            self.set_trdos_rom_active(false);
+        }
         if let Some(debug) = &mut self.debug_interface {
             if debug.check_pc_breakpoint(addr) {
                 self.events |= EmulationEvents::PC_BREAKPOINT;
@@ -439,32 +1519,61 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// read data without taking onto account contention
     fn read_internal(&mut self, addr: u16) -> u8 {
+        #[cfg(feature = "heatmap")]
+        self.heatmap.record_read(addr);
         self.memory.read(addr)
     }
 
     /// write data without taking onto account contention
     fn write_internal(&mut self, addr: u16, data: u8) {
+        #[cfg(feature = "heatmap")]
+        self.heatmap.record_write(addr);
         self.memory.write(addr, data);
         // if ram then compare bank to screen bank
-        if let Page::Ram(bank) = self.memory.get_page(addr) {
+        if let (Page::Ram(bank), offset) = self.memory.paged_address(addr) {
             self.screen
-                .update(addr % PAGE_SIZE as u16, bank as usize, data);
+                .update_batched(offset as u16, bank as usize, data);
         }
     }
 
     /// Changes internal state on clocks count change (emulation processing)
     fn wait_internal(&mut self, clk: usize) {
-        self.frame_clocks += clk;
+        // Tape is driven by the cpu clocks, so loaders keep their timings
+        // regardless of the cpu speed
         if let Err(e) = self.tape.process_clocks(clk) {
             self.last_emulation_error = Some(e);
         }
+        if self.tape.take_block_finished() && self.tape_autostop {
+            self.tape.pause();
+            self.events |= EmulationEvents::TAPE_BLOCK_FINISHED;
+        }
+        self.ear_input.process_clocks(clk);
+        if let Some(recorder) = &mut self.tape_recorder {
+            recorder.process_clocks(clk);
+        }
+        if let Some(beta_disk) = &mut self.beta_disk {
+            beta_disk.process_clocks(clk);
+        }
+        self.cpu_clocks += clk as u64;
+        self.frame_cpu_clocks += clk;
+        self.frame_clocks += self.cpu_to_frame_clocks(clk);
+        if let Some(debug) = &mut self.debug_interface {
+            let specs = &self.specs;
+            while self.frame_clocks >= self.next_scanline_clocks
+                && self.next_scanline_clocks < specs.clocks_frame
+            {
+                let line = self.next_scanline_clocks / specs.clocks_line;
+                debug.on_scanline(line as u16, self.border_color);
+                self.next_scanline_clocks += specs.clocks_line;
+            }
+        }
         #[cfg(feature = "sound")]
         {
             let pos = self.frame_pos();
             self.mixer.process(pos);
         }
         self.screen.process_clocks(self.frame_clocks);
-        if self.frame_clocks >= self.machine.specs().clocks_frame {
+        if self.frame_clocks >= self.specs.clocks_frame {
             self.new_frame();
             self.passed_frames += 1;
         }
@@ -473,7 +1582,10 @@ impl<H: Host> Z80Bus for ZXController<H> {
     // wait with memory request pin active
     fn wait_mreq(&mut self, addr: u16, clk: usize) {
         match self.machine {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
+            ZXMachine::Sinclair16K
+            | ZXMachine::Sinclair48K
+            | ZXMachine::Sinclair128K
+            | ZXMachine::SinclairPlus3 => {
                 // contention in low 16k RAM
                 if self.addr_is_contended(addr) {
                     self.do_contention();
@@ -485,8 +1597,12 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// wait without memory request pin active
     fn wait_no_mreq(&mut self, addr: u16, clk: usize) {
-        // only for 48 K!
-        self.wait_mreq(addr, clk);
+        // Gate array contends only MREQ cycles
+        if self.specs.contention_model == ContentionModel::Ula {
+            self.wait_mreq(addr, clk);
+        } else {
+            self.wait_internal(clk);
+        }
     }
 
     /// read io from hardware
@@ -504,26 +1620,50 @@ impl<H: Host> Z80Bus for ZXController<H> {
         let [_, h] = port.to_le_bytes();
         let output = if let Some(value) = io_extender_value {
             value
+        } else if self.next_port_active(port) {
+            self.read_next_port(port)
+        } else if self.beta_disk_port_active(port) {
+            let beta_disk = self.beta_disk.as_mut().unwrap();
+            if port & 0x0080 != 0 {
+                beta_disk.read_system()
+            } else {
+                beta_disk.read((port as u8 >> 5) & 0x03)
+            }
         } else if port & 0x0001 == 0 {
             // ULA port
             let mut tmp: u8 = 0xFF;
-            for n in 0..8 {
+            for (n, keyboard_byte) in self.keyboard_matrix().into_iter().enumerate() {
                 // if bit of row reset
                 if ((h >> n) & 0x01) == 0 {
-                    let keyboard_byte =
-                        self.keyboard[n] & self.keyboard_extended[n] & self.keyboard_sinclair[n];
                     tmp &= keyboard_byte;
                 }
             }
 
-            // Emulate zx spectrum "issue 2" model.
-            // For future "issue 3" implementation condition will be `!self.ear`, but
-            // different zx spectrum "issues" emulation is not planned yet
-            if !self.tape.current_bit() {
+            // EAR input is driven by the external signal or by the tape
+            // while it is playing, otherwise it senses the last written
+            // EAR/MIC outputs
+            let ear = if self.ear_input.is_active() {
+                self.ear_input.current_bit()
+            } else if self.tape.is_playing() {
+                self.tape.current_bit()
+            } else {
+                self.keyboard_issue.ear_sense(self.ula_out)
+            };
+            if !ear {
                 tmp ^= 0x40;
             }
+            // Lightgun sensor is wired to the EAR input
+            if self.lightgun_light_detected() {
+                tmp |= 0x40;
+            }
             // 5 and 7 bits are unused
             tmp
+        } else if self.printer.is_some() && (port & 0x0004 == 0) {
+            self.printer.as_mut().map_or(0xFF, ZXPrinter::read)
+        } else if self.fdc.is_some() && (port & 0xF002 == 0x2000) {
+            self.fdc.as_ref().map_or(0xFF, Fdc::read_status)
+        } else if self.fdc.is_some() && (port & 0xF002 == 0x3000) {
+            self.fdc.as_mut().map_or(0xFF, Fdc::read_data)
         } else if self.mouse.is_some() && (port & 0x0121 == 0x0001) {
             self.mouse.as_ref().unwrap().buttons_port
         } else if self.mouse.is_some() && (port & 0x0521 == 0x0101) {
@@ -532,9 +1672,21 @@ impl<H: Host> Z80Bus for ZXController<H> {
             self.mouse.as_ref().unwrap().y_pos_port
         } else if port & 0xC002 == 0xC000 {
             self.read_ay_port()
-        } else if self.kempston.is_some() && (port & 0x00E0 == 0) {
-            self.kempston.as_ref().unwrap().read()
+        } else if self.fuller.is_some() && port as u8 == FULLER_JOY_PORT {
+            self.fuller.as_ref().map_or(0xFF, FullerJoy::read)
+        } else if self.fuller.is_some() && port as u8 == FULLER_AY_SELECT_PORT {
+            self.read_ay_port()
+        } else if (self.kempston.is_some() || self.lightgun.is_some())
+            && (port & self.kempston_port_mask == self.kempston_port_value)
+        {
+            let joy = self.kempston.as_ref().map_or(0, KempstonJoy::read);
+            joy | self.lightgun.as_ref().map_or(0, Lightgun::trigger_bits)
+        } else if self.second_kempston.is_some() && (port & 0x00E0 == 0x0020) {
+            self.second_kempston.as_ref().map_or(0, KempstonJoy::read)
+        } else if self.timex_port_active(port) {
+            self.read_timex_port()
         } else {
+            self.floating_bus_reads += 1;
             self.floating_bus_value()
         };
         // add one clock after operation
@@ -554,20 +1706,51 @@ impl<H: Host> Z80Bus for ZXController<H> {
             .map_or(false, |e| e.extends_port(port))
         {
             self.io_extender.as_mut().unwrap().write(port, data);
+        } else if self.next_port_active(port) {
+            self.write_next_port(port, data);
+        } else if self.beta_disk_port_active(port) {
+            let beta_disk = self.beta_disk.as_mut().unwrap();
+            if port & 0x0080 != 0 {
+                beta_disk.write_system(data);
+            } else {
+                beta_disk.write((port as u8 >> 5) & 0x03, data);
+            }
+        } else if self.timex_port_active(port) {
+            self.write_timex_port(data);
         } else if port & 0xC002 == 0xC000 {
             self.select_ay_reg(data);
         } else if port & 0xC002 == 0x8000 {
             self.write_ay_port(data);
+        } else if self.fuller.is_some() && port as u8 == FULLER_AY_SELECT_PORT {
+            self.select_ay_reg(data);
+        } else if self.fuller.is_some() && port as u8 == FULLER_AY_DATA_PORT {
+            self.write_ay_port(data);
         } else if port & 0x0001 == 0 {
             self.set_border_color(self.frame_clocks, ZXColor::from_bits(data & 0x07));
+            self.ula_out = data;
+            let mic = data & 0x08 != 0;
+            if let Some(recorder) = &mut self.tape_recorder {
+                recorder.set_mic(mic);
+            }
             #[cfg(feature = "sound")]
             {
-                let mic = data & 0x08 != 0;
                 let ear = data & 0x10 != 0;
                 self.mixer.beeper.change_state(ear, mic);
             }
         } else if (port & 0x8002 == 0) && (self.machine == ZXMachine::Sinclair128K) {
             self.write_7ffd(data);
+        } else if (port & 0xF002 == 0x1000) && (self.machine == ZXMachine::SinclairPlus3) {
+            self.write_1ffd(data);
+        } else if (port & 0xC002 == 0x4000) && (self.machine == ZXMachine::SinclairPlus3) {
+            self.write_7ffd(data);
+        } else if port & 0xF002 == 0x3000 {
+            if let Some(fdc) = &mut self.fdc {
+                fdc.write_data(data);
+            }
+        } else if port & 0x0004 == 0 {
+            if let Some(printer) = &mut self.printer {
+                printer.write(data);
+            }
         }
         // last contention after byte write
         self.io_contention_last(port);
@@ -577,23 +1760,48 @@ impl<H: Host> Z80Bus for ZXController<H> {
 
     /// value, requested during `INT0` interrupt
     fn read_interrupt(&mut self) -> u8 {
-        0xFF
+        self.io_extender
+            .as_mut()
+            .and_then(|extender| extender.interrupt_bus_value())
+            .or(self.im2_bus_value)
+            .unwrap_or(0xFF)
     }
 
-    /// checks system maskable interrupt pin state
+    /// checks system maskable interrupt pin state. INT length is fixed in the
+    /// cpu clocks, so it is not affected by the cpu speed
     fn int_active(&self) -> bool {
-        self.frame_clocks % self.machine.specs().clocks_frame
-            < self.machine.specs().interrupt_length
+        self.frame_cpu_clocks < self.interrupt_length
     }
 
     /// checks non-maskable interrupt pin state
     fn nmi_active(&self) -> bool {
-        false
+        self.nmi_pending
     }
 
     /// CPU calls it when RETI instruction was processed
     fn reti(&mut self) {}
 
     /// CPU calls when was being halted
-    fn halt(&mut self, _: bool) {}
+    fn halt(&mut self, halted: bool) {
+        self.events.set(EmulationEvents::CPU_HALTED, halted);
+    }
+
+    /// CPU calls when maskable interrupt is accepted
+    fn interrupt_accepted(&mut self) {
+        self.last_interrupt_clocks = Some(self.frame_clocks);
+        self.events |= EmulationEvents::INTERRUPT_ACCEPTED;
+    }
+
+    /// CPU calls when non-maskable interrupt is accepted
+    fn nmi_accepted(&mut self) {
+        self.nmi_pending = false;
+    }
+
+    fn process_unknown_opcode(&mut self, prefix: Prefix, opcode: Opcode) {
+        log_warn!(
+            "Unknown opcode {:?} {:#04X} executed as NOP",
+            prefix,
+            opcode.byte
+        );
+    }
 }
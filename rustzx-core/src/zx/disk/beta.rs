@@ -0,0 +1,399 @@
+//! Beta Disk 128 interface with WD1793 floppy disk controller. Only drive A:
+//! is connected; commands are executed instantly and disks are read-only
+use crate::{
+    error::StateLoadError,
+    utils::state::{StateReader, StateWriter},
+    zx::disk::trd::{TrdImage, SECTORS_PER_TRACK},
+    Result,
+};
+use alloc::vec::Vec;
+
+/// TR-DOS ROM is paged in when PC enters this range with 48K BASIC ROM active
+pub const TRDOS_ENTRY_START: u16 = 0x3D00;
+pub const TRDOS_ENTRY_END: u16 = 0x3DFF;
+
+/// Disk rotation period at 300 RPM and length of the index pulse in cpu clocks
+const ROTATION_CLOCKS: usize = 700_000;
+const INDEX_PULSE_CLOCKS: usize = 14_000;
+const MAX_CYLINDER: u8 = 85;
+
+// Status register bits
+const STATUS_NOT_READY: u8 = 0x80;
+const STATUS_WRITE_PROTECT: u8 = 0x40;
+const STATUS_HEAD_LOADED: u8 = 0x20;
+const STATUS_RECORD_NOT_FOUND: u8 = 0x10;
+const STATUS_TRACK_0: u8 = 0x04;
+const STATUS_INDEX: u8 = 0x02;
+const STATUS_DRQ: u8 = 0x02;
+const STATUS_BUSY: u8 = 0x01;
+
+// System register (port 0xFF) bits
+const SYSTEM_INTRQ: u8 = 0x80;
+const SYSTEM_DRQ: u8 = 0x40;
+const SYSTEM_DRIVE_MASK: u8 = 0x03;
+const SYSTEM_RESET: u8 = 0x04;
+const SYSTEM_SIDE_0: u8 = 0x10;
+
+// Command flags
+const FLAG_UPDATE_TRACK: u8 = 0x10;
+const FLAG_HEAD_LOAD: u8 = 0x08;
+const FLAG_MULTIPLE: u8 = 0x10;
+
+pub struct BetaDisk {
+    disk: Option<TrdImage>,
+    /// ROM page index, used for TR-DOS ROM
+    rom_page: u8,
+    rom_active: bool,
+    system: u8,
+    status: u8,
+    /// Type I commands show dynamic drive status instead of the latched one
+    type_one_status: bool,
+    head_loaded: bool,
+    track: u8,
+    sector: u8,
+    data: u8,
+    /// Physical head position
+    cylinder: u8,
+    step_in: bool,
+    intrq: bool,
+    buffer: Vec<u8>,
+    buffer_pos: usize,
+    multiple: bool,
+    read_address_index: u8,
+    rotation_clocks: usize,
+}
+
+impl BetaDisk {
+    pub fn new(rom_page: u8) -> Self {
+        Self {
+            disk: None,
+            rom_page,
+            rom_active: false,
+            system: SYSTEM_RESET,
+            status: 0,
+            type_one_status: true,
+            head_loaded: false,
+            track: 0,
+            sector: 1,
+            data: 0,
+            cylinder: 0,
+            step_in: true,
+            intrq: false,
+            buffer: Vec::new(),
+            buffer_pos: 0,
+            multiple: false,
+            read_address_index: 0,
+            rotation_clocks: 0,
+        }
+    }
+
+    pub fn insert_disk(&mut self, disk: TrdImage) {
+        self.disk = Some(disk);
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.disk = None;
+    }
+
+    pub fn has_disk(&self) -> bool {
+        self.disk.is_some()
+    }
+
+    pub fn rom_page(&self) -> u8 {
+        self.rom_page
+    }
+
+    pub fn rom_active(&self) -> bool {
+        self.rom_active
+    }
+
+    pub fn set_rom_active(&mut self, active: bool) {
+        self.rom_active = active;
+    }
+
+    /// Advances disk rotation, used to generate index pulses
+    pub fn process_clocks(&mut self, clocks: usize) {
+        self.rotation_clocks = (self.rotation_clocks + clocks) % ROTATION_CLOCKS;
+    }
+
+    fn current_disk(&self) -> Option<&TrdImage> {
+        if self.system & SYSTEM_DRIVE_MASK == 0 {
+            self.disk.as_ref()
+        } else {
+            None
+        }
+    }
+
+    fn side(&self) -> u8 {
+        // Side select output is inverted
+        if self.system & SYSTEM_SIDE_0 != 0 {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Reads WD1793 register, `reg` is selected by address bits 5-6
+    pub fn read(&mut self, reg: u8) -> u8 {
+        match reg {
+            0 => {
+                self.intrq = false;
+                self.read_status()
+            }
+            1 => self.track,
+            2 => self.sector,
+            _ => self.read_data(),
+        }
+    }
+
+    /// Writes WD1793 register, `reg` is selected by address bits 5-6
+    pub fn write(&mut self, reg: u8, value: u8) {
+        match reg {
+            0 => self.execute(value),
+            1 => self.track = value,
+            2 => self.sector = value,
+            _ => self.data = value,
+        }
+    }
+
+    /// Reads system register (port 0xFF) with INTRQ and DRQ signals
+    pub fn read_system(&self) -> u8 {
+        let mut value = 0x3F;
+        if self.intrq {
+            value |= SYSTEM_INTRQ;
+        }
+        if self.drq() {
+            value |= SYSTEM_DRQ;
+        }
+        value
+    }
+
+    /// Writes system register (port 0xFF): drive, side and controller reset
+    pub fn write_system(&mut self, value: u8) {
+        self.system = value;
+        if value & SYSTEM_RESET == 0 {
+            self.buffer.clear();
+            self.status = 0;
+            self.type_one_status = true;
+            self.intrq = false;
+            self.track = 0;
+            self.cylinder = 0;
+            self.sector = 1;
+        }
+    }
+
+    fn drq(&self) -> bool {
+        self.buffer_pos < self.buffer.len()
+    }
+
+    fn read_status(&self) -> u8 {
+        if self.current_disk().is_none() {
+            return STATUS_NOT_READY;
+        }
+        if !self.type_one_status {
+            return self.status;
+        }
+        let mut status = STATUS_WRITE_PROTECT;
+        if self.head_loaded {
+            status |= STATUS_HEAD_LOADED;
+        }
+        if self.cylinder == 0 {
+            status |= STATUS_TRACK_0;
+        }
+        if self.rotation_clocks < INDEX_PULSE_CLOCKS {
+            status |= STATUS_INDEX;
+        }
+        status
+    }
+
+    fn read_data(&mut self) -> u8 {
+        if !self.drq() {
+            return self.data;
+        }
+        self.data = self.buffer[self.buffer_pos];
+        self.buffer_pos += 1;
+        if !self.drq() {
+            if self.multiple {
+                self.sector = self.sector.wrapping_add(1);
+                self.read_sector();
+            } else {
+                self.finish(0);
+            }
+        }
+        self.data
+    }
+
+    fn finish(&mut self, status: u8) {
+        self.buffer.clear();
+        self.buffer_pos = 0;
+        self.status = status;
+        self.intrq = true;
+    }
+
+    fn execute(&mut self, command: u8) {
+        // Force interrupt is accepted at any time
+        if command & 0xF0 == 0xD0 {
+            self.buffer.clear();
+            self.buffer_pos = 0;
+            self.type_one_status = true;
+            self.intrq = command & 0x0F != 0;
+            return;
+        }
+        self.intrq = false;
+        if command & 0x80 == 0 {
+            self.execute_type_one(command);
+            return;
+        }
+        self.type_one_status = false;
+        match command & 0xF0 {
+            0x80 | 0x90 => {
+                self.multiple = command & FLAG_MULTIPLE != 0;
+                self.read_sector();
+            }
+            0xC0 => self.read_address(),
+            // Write sector and write track
+            0xA0 | 0xB0 | 0xF0 => {
+                let status = if self.current_disk().is_some() {
+                    STATUS_WRITE_PROTECT
+                } else {
+                    STATUS_NOT_READY
+                };
+                self.finish(status);
+            }
+            // Read track is not supported
+            _ => self.finish(STATUS_RECORD_NOT_FOUND),
+        }
+    }
+
+    fn execute_type_one(&mut self, command: u8) {
+        self.type_one_status = true;
+        self.head_loaded = command & FLAG_HEAD_LOAD != 0;
+        match command & 0xF0 {
+            // Restore
+            0x00 => {
+                self.cylinder = 0;
+                self.track = 0;
+            }
+            // Seek to the track in data register
+            0x10 => {
+                let distance = self.data as i16 - self.track as i16;
+                self.step_in = distance > 0;
+                self.cylinder =
+                    (self.cylinder as i16 + distance).clamp(0, MAX_CYLINDER as i16) as u8;
+                self.track = self.data;
+            }
+            // Step, step in and step out
+            step => {
+                match step & 0xE0 {
+                    0x40 => self.step_in = true,
+                    0x60 => self.step_in = false,
+                    _ => {}
+                }
+                self.cylinder = if self.step_in {
+                    (self.cylinder + 1).min(MAX_CYLINDER)
+                } else {
+                    self.cylinder.saturating_sub(1)
+                };
+                if command & FLAG_UPDATE_TRACK != 0 {
+                    self.track = if self.step_in {
+                        self.track.wrapping_add(1)
+                    } else {
+                        self.track.wrapping_sub(1)
+                    };
+                }
+            }
+        }
+        self.status = 0;
+        self.intrq = true;
+    }
+
+    fn read_sector(&mut self) {
+        let side = self.side();
+        let sector = match self.current_disk() {
+            None => {
+                self.finish(STATUS_NOT_READY);
+                return;
+            }
+            // TR-DOS sector address fields contain physical cylinder number
+            Some(_) if self.track != self.cylinder => None,
+            Some(disk) => disk.sector(self.cylinder, side, self.sector),
+        };
+        match sector {
+            Some(data) => {
+                self.buffer = data.to_vec();
+                self.buffer_pos = 0;
+                self.status = STATUS_BUSY | STATUS_DRQ;
+            }
+            None => self.finish(STATUS_RECORD_NOT_FOUND),
+        }
+    }
+
+    fn read_address(&mut self) {
+        let side = self.side();
+        let formatted = match self.current_disk() {
+            None => {
+                self.finish(STATUS_NOT_READY);
+                return;
+            }
+            Some(disk) => disk.sector(self.cylinder, side, 1).is_some(),
+        };
+        if !formatted {
+            self.finish(STATUS_RECORD_NOT_FOUND);
+            return;
+        }
+        let sector = self.read_address_index % SECTORS_PER_TRACK as u8 + 1;
+        self.read_address_index = self.read_address_index.wrapping_add(1);
+        // Track, side, sector, size code (256 bytes) and CRC
+        self.buffer = alloc::vec![self.cylinder, 0, sector, 1, 0, 0];
+        self.buffer_pos = 0;
+        self.sector = self.cylinder;
+        self.multiple = false;
+        self.status = STATUS_BUSY | STATUS_DRQ;
+    }
+
+    /// Saves WD1793 and system register state. Inserted disk and TR-DOS ROM
+    /// paging are not a part of the state
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.system);
+        writer.write_u8(self.status);
+        writer.write_bool(self.type_one_status);
+        writer.write_bool(self.head_loaded);
+        writer.write_u8(self.track);
+        writer.write_u8(self.sector);
+        writer.write_u8(self.data);
+        writer.write_u8(self.cylinder);
+        writer.write_bool(self.step_in);
+        writer.write_bool(self.intrq);
+        writer.write_blob(&self.buffer);
+        writer.write_usize(self.buffer_pos);
+        writer.write_bool(self.multiple);
+        writer.write_u8(self.read_address_index);
+        writer.write_usize(self.rotation_clocks);
+    }
+
+    /// Restores state, saved with [BetaDisk::save_state]
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        self.system = reader.read_u8()?;
+        self.status = reader.read_u8()?;
+        self.type_one_status = reader.read_bool()?;
+        self.head_loaded = reader.read_bool()?;
+        self.track = reader.read_u8()?;
+        self.sector = reader.read_u8()?;
+        self.data = reader.read_u8()?;
+        self.cylinder = reader.read_u8()?;
+        self.step_in = reader.read_bool()?;
+        self.intrq = reader.read_bool()?;
+        self.buffer = reader.read_blob()?.to_vec();
+        self.buffer_pos = reader.read_usize()?;
+        self.multiple = reader.read_bool()?;
+        self.read_address_index = reader.read_u8()?;
+        self.rotation_clocks = reader.read_usize()?;
+        if self.buffer_pos > self.buffer.len()
+            || self.cylinder > MAX_CYLINDER
+            || self.rotation_clocks >= ROTATION_CLOCKS
+        {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! CPCEMU standard and extended DSK disk images parsing
+use crate::{error::DiskLoadError, Result};
+use alloc::vec::Vec;
+
+const DISK_INFO_SIZE: usize = 0x100;
+const TRACK_INFO_SIZE: usize = 0x100;
+const STANDARD_SIGNATURE: &[u8] = b"MV - CPC";
+const EXTENDED_SIGNATURE: &[u8] = b"EXTENDED CPC DSK File";
+const TRACK_SIGNATURE: &[u8] = b"Track-Info";
+const DISK_TRACKS_OFFSET: usize = 0x30;
+const DISK_SIDES_OFFSET: usize = 0x31;
+const DISK_TRACK_SIZE_OFFSET: usize = 0x32;
+const DISK_TRACK_SIZE_TABLE_OFFSET: usize = 0x34;
+const TRACK_SECTOR_SIZE_OFFSET: usize = 0x14;
+const TRACK_SECTORS_OFFSET: usize = 0x15;
+const SECTOR_INFO_OFFSET: usize = 0x18;
+const SECTOR_INFO_SIZE: usize = 8;
+
+/// Sector address field, as it was written during disk formatting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectorId {
+    pub cylinder: u8,
+    pub head: u8,
+    pub sector: u8,
+    /// Sector size code, size in bytes is `128 << size`
+    pub size: u8,
+}
+
+pub struct Sector {
+    pub id: SectorId,
+    /// FDC status registers 1 and 2, reported on sector read
+    pub st1: u8,
+    pub st2: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Track {
+    pub sectors: Vec<Sector>,
+}
+
+/// Read-only disk image
+pub struct DiskImage {
+    sides: usize,
+    // Tracks are interleaved by side: track 0 side 0, track 0 side 1, ...
+    tracks: Vec<Track>,
+}
+
+impl DiskImage {
+    /// Parses standard or extended DSK image
+    pub fn from_dsk(data: &[u8]) -> Result<Self> {
+        let extended = if data.starts_with(EXTENDED_SIGNATURE) {
+            true
+        } else if data.starts_with(STANDARD_SIGNATURE) {
+            false
+        } else {
+            return Err(DiskLoadError::InvalidDskFile.into());
+        };
+        if data.len() < DISK_INFO_SIZE {
+            return Err(DiskLoadError::InvalidDskFile.into());
+        }
+
+        let sides = data[DISK_SIDES_OFFSET] as usize;
+        let tracks_count = data[DISK_TRACKS_OFFSET] as usize * sides;
+        if sides == 0 || sides > 2 || DISK_TRACK_SIZE_TABLE_OFFSET + tracks_count > DISK_INFO_SIZE {
+            return Err(DiskLoadError::InvalidDskFile.into());
+        }
+        let standard_track_size = u16::from_le_bytes([
+            data[DISK_TRACK_SIZE_OFFSET],
+            data[DISK_TRACK_SIZE_OFFSET + 1],
+        ]) as usize;
+
+        let mut tracks = Vec::with_capacity(tracks_count);
+        let mut offset = DISK_INFO_SIZE;
+        for index in 0..tracks_count {
+            let track_size = if extended {
+                data[DISK_TRACK_SIZE_TABLE_OFFSET + index] as usize * 256
+            } else {
+                standard_track_size
+            };
+            // Unformatted track
+            if track_size == 0 {
+                tracks.push(Track::default());
+                continue;
+            }
+            let track_data = data
+                .get(offset..offset + track_size)
+                .ok_or(DiskLoadError::InvalidDskFile)?;
+            tracks.push(Track::from_dsk(track_data, extended)?);
+            offset += track_size;
+        }
+
+        Ok(Self { sides, tracks })
+    }
+
+    /// Returns track on the given physical cylinder and head
+    pub fn track(&self, cylinder: u8, head: u8) -> Option<&Track> {
+        let head = head as usize;
+        if head >= self.sides {
+            return None;
+        }
+        self.tracks.get(cylinder as usize * self.sides + head)
+    }
+}
+
+impl Track {
+    fn from_dsk(data: &[u8], extended: bool) -> Result<Self> {
+        if data.len() < TRACK_INFO_SIZE || !data.starts_with(TRACK_SIGNATURE) {
+            return Err(DiskLoadError::InvalidDskFile.into());
+        }
+        let sectors_count = data[TRACK_SECTORS_OFFSET] as usize;
+        if SECTOR_INFO_OFFSET + sectors_count * SECTOR_INFO_SIZE > TRACK_INFO_SIZE {
+            return Err(DiskLoadError::InvalidDskFile.into());
+        }
+
+        let mut sectors = Vec::with_capacity(sectors_count);
+        let mut offset = TRACK_INFO_SIZE;
+        for index in 0..sectors_count {
+            let info_offset = SECTOR_INFO_OFFSET + index * SECTOR_INFO_SIZE;
+            let info = &data[info_offset..info_offset + SECTOR_INFO_SIZE];
+            let length = if extended {
+                u16::from_le_bytes([info[6], info[7]]) as usize
+            } else {
+                // Standard images use the same size for all sectors of the track
+                128 << (data[TRACK_SECTOR_SIZE_OFFSET] & 0x07)
+            };
+            let sector_data = data
+                .get(offset..offset + length)
+                .ok_or(DiskLoadError::InvalidDskFile)?;
+            sectors.push(Sector {
+                id: SectorId {
+                    cylinder: info[0],
+                    head: info[1],
+                    sector: info[2],
+                    size: info[3],
+                },
+                st1: info[4],
+                st2: info[5],
+                data: sector_data.to_vec(),
+            });
+            offset += length;
+        }
+
+        Ok(Self { sectors })
+    }
+
+    /// Finds sector with the given address field
+    pub fn find_sector(&self, id: SectorId) -> Option<&Sector> {
+        self.sectors.iter().find(|s| s.id == id)
+    }
+}
@@ -0,0 +1,545 @@
+//! Minimal NEC uPD765 floppy disk controller emulation, as used by the +3.
+//! Only the read-only subset of commands is implemented (read data, read ID,
+//! seek, recalibrate and status commands); writes are reported as
+//! write-protected. Commands are executed instantly.
+use crate::{
+    error::StateLoadError,
+    utils::state::{StateReader, StateWriter},
+    zx::disk::{dsk::SectorId, DiskImage},
+    Result,
+};
+use alloc::vec::Vec;
+
+const MAX_COMMAND_LENGTH: usize = 9;
+
+// Main status register bits
+const MSR_REQUEST_FOR_MASTER: u8 = 0x80;
+const MSR_DATA_TO_CPU: u8 = 0x40;
+const MSR_EXECUTION: u8 = 0x20;
+const MSR_BUSY: u8 = 0x10;
+
+// Status register 0 bits
+const ST0_ABNORMAL: u8 = 0x40;
+const ST0_INVALID: u8 = 0x80;
+const ST0_SEEK_END: u8 = 0x20;
+const ST0_EQUIPMENT_CHECK: u8 = 0x10;
+const ST0_NOT_READY: u8 = 0x08;
+// Status register 1 bits
+const ST1_END_OF_CYLINDER: u8 = 0x80;
+const ST1_DATA_ERROR: u8 = 0x20;
+const ST1_NO_DATA: u8 = 0x04;
+const ST1_NOT_WRITABLE: u8 = 0x02;
+const ST1_MISSING_ADDRESS_MARK: u8 = 0x01;
+// Status register 2 bits
+const ST2_CONTROL_MARK: u8 = 0x40;
+const ST2_DATA_ERROR: u8 = 0x20;
+// Status register 3 bits
+const ST3_WRITE_PROTECTED: u8 = 0x40;
+const ST3_READY: u8 = 0x20;
+const ST3_TRACK_0: u8 = 0x10;
+
+const CMD_READ_TRACK: u8 = 0x02;
+const CMD_SPECIFY: u8 = 0x03;
+const CMD_SENSE_DRIVE_STATUS: u8 = 0x04;
+const CMD_WRITE_DATA: u8 = 0x05;
+const CMD_READ_DATA: u8 = 0x06;
+const CMD_RECALIBRATE: u8 = 0x07;
+const CMD_SENSE_INTERRUPT_STATUS: u8 = 0x08;
+const CMD_WRITE_DELETED_DATA: u8 = 0x09;
+const CMD_READ_ID: u8 = 0x0A;
+const CMD_READ_DELETED_DATA: u8 = 0x0C;
+const CMD_FORMAT_TRACK: u8 = 0x0D;
+const CMD_SEEK: u8 = 0x0F;
+const CMD_SCAN_EQUAL: u8 = 0x11;
+const CMD_SCAN_LOW_OR_EQUAL: u8 = 0x19;
+const CMD_SCAN_HIGH_OR_EQUAL: u8 = 0x1D;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Command,
+    Execution,
+    Result,
+}
+
+/// Returns full command length in bytes or `None` for invalid command
+fn command_length(command: u8) -> Option<usize> {
+    match command & 0x1F {
+        CMD_SENSE_INTERRUPT_STATUS => Some(1),
+        CMD_SENSE_DRIVE_STATUS | CMD_RECALIBRATE | CMD_READ_ID => Some(2),
+        CMD_SPECIFY | CMD_SEEK => Some(3),
+        CMD_FORMAT_TRACK => Some(6),
+        CMD_READ_TRACK
+        | CMD_WRITE_DATA
+        | CMD_READ_DATA
+        | CMD_WRITE_DELETED_DATA
+        | CMD_READ_DELETED_DATA
+        | CMD_SCAN_EQUAL
+        | CMD_SCAN_LOW_OR_EQUAL
+        | CMD_SCAN_HIGH_OR_EQUAL => Some(9),
+        _ => None,
+    }
+}
+
+/// uPD765 controller with the single drive (+3 internal drive A:)
+pub struct Fdc {
+    disk: Option<DiskImage>,
+    motor: bool,
+    phase: Phase,
+    command: [u8; MAX_COMMAND_LENGTH],
+    command_length: usize,
+    command_pos: usize,
+    data: Vec<u8>,
+    data_pos: usize,
+    result: Vec<u8>,
+    result_pos: usize,
+    /// Current head position of the drive
+    cylinder: u8,
+    /// ST0 and cylinder reported by Sense Interrupt Status after seek
+    seek_interrupt: Option<(u8, u8)>,
+    /// Index of the sector under the head, advanced by Read ID
+    read_id_index: usize,
+}
+
+impl Default for Fdc {
+    fn default() -> Self {
+        Self {
+            disk: None,
+            motor: false,
+            phase: Phase::Command,
+            command: [0; MAX_COMMAND_LENGTH],
+            command_length: 0,
+            command_pos: 0,
+            data: Vec::new(),
+            data_pos: 0,
+            result: Vec::new(),
+            result_pos: 0,
+            cylinder: 0,
+            seek_interrupt: None,
+            read_id_index: 0,
+        }
+    }
+}
+
+impl Fdc {
+    pub fn insert_disk(&mut self, disk: DiskImage) {
+        self.disk = Some(disk);
+        self.read_id_index = 0;
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.disk = None;
+    }
+
+    pub fn has_disk(&self) -> bool {
+        self.disk.is_some()
+    }
+
+    /// Changes drive motor state, controlled by bit 3 of port 0x1FFD
+    pub fn set_motor(&mut self, on: bool) {
+        self.motor = on;
+    }
+
+    /// Reads main status register (port 0x2FFD)
+    pub fn read_status(&self) -> u8 {
+        match self.phase {
+            Phase::Command if self.command_pos == 0 => MSR_REQUEST_FOR_MASTER,
+            Phase::Command => MSR_REQUEST_FOR_MASTER | MSR_BUSY,
+            Phase::Execution => MSR_REQUEST_FOR_MASTER | MSR_DATA_TO_CPU | MSR_EXECUTION | MSR_BUSY,
+            Phase::Result => MSR_REQUEST_FOR_MASTER | MSR_DATA_TO_CPU | MSR_BUSY,
+        }
+    }
+
+    /// Reads data register (port 0x3FFD)
+    pub fn read_data(&mut self) -> u8 {
+        match self.phase {
+            Phase::Command => 0xFF,
+            Phase::Execution => {
+                let value = self.data[self.data_pos];
+                self.data_pos += 1;
+                if self.data_pos == self.data.len() {
+                    self.phase = Phase::Result;
+                }
+                value
+            }
+            Phase::Result => {
+                let value = self.result[self.result_pos];
+                self.result_pos += 1;
+                if self.result_pos == self.result.len() {
+                    self.phase = Phase::Command;
+                }
+                value
+            }
+        }
+    }
+
+    /// Writes data register (port 0x3FFD)
+    pub fn write_data(&mut self, value: u8) {
+        if self.phase != Phase::Command {
+            return;
+        }
+        if self.command_pos == 0 {
+            match command_length(value) {
+                Some(length) => self.command_length = length,
+                None => {
+                    self.finish(&[ST0_INVALID]);
+                    return;
+                }
+            }
+        }
+        self.command[self.command_pos] = value;
+        self.command_pos += 1;
+        if self.command_pos == self.command_length {
+            self.command_pos = 0;
+            self.execute();
+        }
+    }
+
+    fn ready(&self) -> bool {
+        self.motor && self.disk.is_some()
+    }
+
+    /// Moves controller to the result phase or back to the command phase if
+    /// command has no result
+    fn finish(&mut self, result: &[u8]) {
+        self.result.clear();
+        self.result.extend_from_slice(result);
+        self.result_pos = 0;
+        self.phase = if self.result.is_empty() {
+            Phase::Command
+        } else {
+            Phase::Result
+        };
+    }
+
+    fn execute(&mut self) {
+        // Head and drive select bits
+        let unit = self.command[1] & 0x07;
+        let head = (unit >> 2) & 0x01;
+        let drive_connected = unit & 0x03 == 0;
+        match self.command[0] & 0x1F {
+            CMD_SPECIFY => self.finish(&[]),
+            CMD_SENSE_DRIVE_STATUS => {
+                let mut st3 = unit;
+                if drive_connected {
+                    st3 |= ST3_WRITE_PROTECTED;
+                    if self.ready() {
+                        st3 |= ST3_READY;
+                    }
+                    if self.cylinder == 0 {
+                        st3 |= ST3_TRACK_0;
+                    }
+                }
+                self.finish(&[st3]);
+            }
+            CMD_SENSE_INTERRUPT_STATUS => match self.seek_interrupt.take() {
+                Some((st0, cylinder)) => self.finish(&[st0, cylinder]),
+                None => self.finish(&[ST0_INVALID]),
+            },
+            CMD_RECALIBRATE | CMD_SEEK => {
+                let unit = unit & 0x03;
+                let target = if self.command[0] & 0x1F == CMD_SEEK {
+                    self.command[2]
+                } else {
+                    0
+                };
+                let st0 = if drive_connected {
+                    self.cylinder = target;
+                    self.read_id_index = 0;
+                    ST0_SEEK_END | unit
+                } else {
+                    ST0_ABNORMAL | ST0_SEEK_END | ST0_EQUIPMENT_CHECK | unit
+                };
+                self.seek_interrupt = Some((st0, self.cylinder));
+                self.finish(&[]);
+            }
+            CMD_READ_ID => self.read_id(unit, head, drive_connected),
+            CMD_READ_DATA | CMD_READ_DELETED_DATA => self.read_data_command(unit, drive_connected),
+            CMD_WRITE_DATA | CMD_WRITE_DELETED_DATA => {
+                let [_, _, c, h, r, n, ..] = self.command;
+                self.finish(&[ST0_ABNORMAL | unit, ST1_NOT_WRITABLE, 0, c, h, r, n]);
+            }
+            CMD_FORMAT_TRACK => {
+                let n = self.command[2];
+                let st0 = ST0_ABNORMAL | unit;
+                self.finish(&[st0, ST1_NOT_WRITABLE, 0, self.cylinder, head, 0, n]);
+            }
+            // Read track and scan commands are not supported
+            _ => {
+                let [_, _, c, h, r, n, ..] = self.command;
+                self.finish(&[ST0_ABNORMAL | unit, ST1_NO_DATA, 0, c, h, r, n]);
+            }
+        }
+    }
+
+    fn read_id(&mut self, unit: u8, head: u8, drive_connected: bool) {
+        if !drive_connected || !self.ready() {
+            self.finish(&[ST0_ABNORMAL | ST0_NOT_READY | unit, 0, 0, 0, 0, 0, 0]);
+            return;
+        }
+        let sector_id = self
+            .disk
+            .as_ref()
+            .and_then(|disk| disk.track(self.cylinder, head))
+            .filter(|track| !track.sectors.is_empty())
+            .map(|track| track.sectors[self.read_id_index % track.sectors.len()].id);
+        match sector_id {
+            Some(id) => {
+                self.read_id_index = self.read_id_index.wrapping_add(1);
+                self.finish(&[unit, 0, 0, id.cylinder, id.head, id.sector, id.size]);
+            }
+            None => {
+                let st0 = ST0_ABNORMAL | unit;
+                self.finish(&[st0, ST1_MISSING_ADDRESS_MARK, 0, 0, 0, 0, 0]);
+            }
+        }
+    }
+
+    fn read_data_command(&mut self, unit: u8, drive_connected: bool) {
+        let [_, _, cylinder, head, mut sector, size, end_of_track, ..] = self.command;
+        let physical_head = (unit >> 2) & 0x01;
+        self.data.clear();
+        self.data_pos = 0;
+
+        let track = match self.disk.as_ref() {
+            Some(disk) if drive_connected && self.motor => disk.track(self.cylinder, physical_head),
+            _ => {
+                let st0 = ST0_ABNORMAL | ST0_NOT_READY | unit;
+                self.finish(&[st0, 0, 0, cylinder, head, sector, size]);
+                return;
+            }
+        };
+
+        let mut st0 = unit;
+        let (mut st1, mut st2) = (0, 0);
+        let mut result_cylinder = cylinder;
+        loop {
+            let id = SectorId {
+                cylinder,
+                head,
+                sector,
+                size,
+            };
+            let Some(found) = track.and_then(|t| t.find_sector(id)) else {
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_NO_DATA;
+                break;
+            };
+            self.data.extend_from_slice(&found.data);
+            // Data errors and deleted data marks are reported from the image
+            st1 |= found.st1 & ST1_DATA_ERROR;
+            st2 |= found.st2 & (ST2_CONTROL_MARK | ST2_DATA_ERROR);
+            if st1 & ST1_DATA_ERROR != 0 {
+                st0 |= ST0_ABNORMAL;
+                break;
+            }
+            if sector == end_of_track {
+                // +3 does not use terminal count signal, so reading always ends
+                // with "end of cylinder" error, which is ignored by +3DOS
+                st0 |= ST0_ABNORMAL;
+                st1 |= ST1_END_OF_CYLINDER;
+                result_cylinder = cylinder.wrapping_add(1);
+                sector = 1;
+                break;
+            }
+            sector = sector.wrapping_add(1);
+        }
+
+        self.finish(&[st0, st1, st2, result_cylinder, head, sector, size]);
+        if !self.data.is_empty() {
+            self.phase = Phase::Execution;
+        }
+    }
+
+    /// Saves controller state. Inserted disk is not a part of the state
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.motor);
+        writer.write_u8(self.phase as u8);
+        writer.write_bytes(&self.command);
+        writer.write_usize(self.command_length);
+        writer.write_usize(self.command_pos);
+        writer.write_blob(&self.data);
+        writer.write_usize(self.data_pos);
+        writer.write_blob(&self.result);
+        writer.write_usize(self.result_pos);
+        writer.write_u8(self.cylinder);
+        writer.write_bool(self.seek_interrupt.is_some());
+        let (st0, cylinder) = self.seek_interrupt.unwrap_or_default();
+        writer.write_u8(st0);
+        writer.write_u8(cylinder);
+        writer.write_usize(self.read_id_index);
+    }
+
+    /// Restores state, saved with [Fdc::save_state]
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        self.motor = reader.read_bool()?;
+        self.phase = match reader.read_u8()? {
+            0 => Phase::Command,
+            1 => Phase::Execution,
+            2 => Phase::Result,
+            _ => return Err(StateLoadError::InvalidState.into()),
+        };
+        reader.read_into(&mut self.command)?;
+        self.command_length = reader.read_usize()?;
+        self.command_pos = reader.read_usize()?;
+        self.data = reader.read_blob()?.to_vec();
+        self.data_pos = reader.read_usize()?;
+        self.result = reader.read_blob()?.to_vec();
+        self.result_pos = reader.read_usize()?;
+        self.cylinder = reader.read_u8()?;
+        let seek_interrupt = reader.read_bool()?;
+        let seek_result = (reader.read_u8()?, reader.read_u8()?);
+        self.seek_interrupt = seek_interrupt.then_some(seek_result);
+        self.read_id_index = reader.read_usize()?;
+        let valid = self.command_length <= MAX_COMMAND_LENGTH
+            && self.command_pos <= self.command_length
+            && self.data_pos <= self.data.len()
+            && self.result_pos <= self.result.len()
+            && match self.phase {
+                Phase::Command => true,
+                Phase::Execution => self.data_pos < self.data.len(),
+                Phase::Result => self.result_pos < self.result.len(),
+            };
+        if !valid {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const SECTORS: u8 = 9;
+
+    /// Builds extended DSK image with 2 single-sided tracks of 9 512-byte sectors,
+    /// sector data is filled with `track * 16 + sector` value
+    fn test_disk() -> DiskImage {
+        let mut dsk = vec![0u8; 0x100];
+        dsk[..34].copy_from_slice(b"EXTENDED CPC DSK File\r\nDisk-Info\r\n");
+        dsk[0x30] = 2;
+        dsk[0x31] = 1;
+        dsk[0x34] = 0x13;
+        dsk[0x35] = 0x13;
+        for track in 0..2u8 {
+            let mut info = vec![0u8; 0x100];
+            info[..12].copy_from_slice(b"Track-Info\r\n");
+            info[0x10] = track;
+            info[0x14] = 2;
+            info[0x15] = SECTORS;
+            for sector in 0..SECTORS {
+                let offset = 0x18 + sector as usize * 8;
+                info[offset..offset + 8].copy_from_slice(&[track, 0, sector + 1, 2, 0, 0, 0, 2]);
+            }
+            dsk.extend_from_slice(&info);
+            for sector in 0..SECTORS {
+                dsk.extend_from_slice(&[track * 16 + sector + 1; 512]);
+            }
+        }
+        DiskImage::from_dsk(&dsk).unwrap()
+    }
+
+    fn command(fdc: &mut Fdc, bytes: &[u8]) {
+        for byte in bytes {
+            assert_eq!(fdc.read_status() & MSR_DATA_TO_CPU, 0);
+            fdc.write_data(*byte);
+        }
+    }
+
+    fn read_all(fdc: &mut Fdc) -> Vec<u8> {
+        let mut out = Vec::new();
+        while fdc.read_status() & MSR_DATA_TO_CPU != 0 {
+            out.push(fdc.read_data());
+        }
+        out
+    }
+
+    #[test]
+    fn dsk_parsing() {
+        let disk = test_disk();
+        let track = disk.track(1, 0).unwrap();
+        assert_eq!(track.sectors.len(), SECTORS as usize);
+        assert_eq!(track.sectors[2].id.sector, 3);
+        assert_eq!(track.sectors[2].data, vec![0x13; 512]);
+        assert!(disk.track(2, 0).is_none());
+        assert!(disk.track(0, 1).is_none());
+        assert!(DiskImage::from_dsk(b"MV - CPC").is_err());
+        assert!(DiskImage::from_dsk(&[0u8; 0x200]).is_err());
+    }
+
+    #[test]
+    fn seek_and_read() {
+        let mut fdc = Fdc::default();
+        fdc.insert_disk(test_disk());
+
+        // Drive is not ready until motor is on
+        command(&mut fdc, &[CMD_SENSE_DRIVE_STATUS, 0x00]);
+        assert_eq!(read_all(&mut fdc), [ST3_WRITE_PROTECTED | ST3_TRACK_0]);
+        fdc.set_motor(true);
+        command(&mut fdc, &[CMD_SENSE_DRIVE_STATUS, 0x00]);
+        let ready = ST3_WRITE_PROTECTED | ST3_READY | ST3_TRACK_0;
+        assert_eq!(read_all(&mut fdc), [ready]);
+
+        command(&mut fdc, &[CMD_SEEK, 0x00, 1]);
+        assert!(read_all(&mut fdc).is_empty());
+        command(&mut fdc, &[CMD_SENSE_INTERRUPT_STATUS]);
+        assert_eq!(read_all(&mut fdc), [ST0_SEEK_END, 1]);
+        command(&mut fdc, &[CMD_SENSE_INTERRUPT_STATUS]);
+        assert_eq!(read_all(&mut fdc), [ST0_INVALID]);
+
+        command(&mut fdc, &[CMD_READ_ID, 0x00]);
+        assert_eq!(read_all(&mut fdc), [0, 0, 0, 1, 0, 1, 2]);
+        command(&mut fdc, &[CMD_READ_ID, 0x00]);
+        assert_eq!(read_all(&mut fdc), [0, 0, 0, 1, 0, 2, 2]);
+
+        // Read sectors 2..=3 of the track 1
+        command(&mut fdc, &[0x46, 0x00, 1, 0, 2, 2, 3, 0x2A, 0xFF]);
+        assert_ne!(fdc.read_status() & MSR_EXECUTION, 0);
+        let data = read_all(&mut fdc);
+        assert_eq!(data.len(), 1024 + 7);
+        assert!(data[..512].iter().all(|b| *b == 0x12));
+        assert!(data[512..1024].iter().all(|b| *b == 0x13));
+        let status = ST0_ABNORMAL;
+        assert_eq!(data[1024..], [status, ST1_END_OF_CYLINDER, 0, 2, 0, 1, 2]);
+
+        // Missing sector
+        command(&mut fdc, &[0x46, 0x00, 1, 0, 10, 2, 10, 0x2A, 0xFF]);
+        assert_eq!(
+            read_all(&mut fdc),
+            [ST0_ABNORMAL, ST1_NO_DATA, 0, 1, 0, 10, 2]
+        );
+
+        command(&mut fdc, &[CMD_RECALIBRATE, 0x00]);
+        command(&mut fdc, &[CMD_SENSE_INTERRUPT_STATUS]);
+        assert_eq!(read_all(&mut fdc), [ST0_SEEK_END, 0]);
+    }
+
+    #[test]
+    fn read_only() {
+        let mut fdc = Fdc::default();
+        fdc.set_motor(true);
+
+        // No disk inserted
+        command(&mut fdc, &[CMD_READ_DATA, 0x00, 0, 0, 1, 2, 1, 0x2A, 0xFF]);
+        let st0 = ST0_ABNORMAL | ST0_NOT_READY;
+        assert_eq!(read_all(&mut fdc), [st0, 0, 0, 0, 0, 1, 2]);
+
+        fdc.insert_disk(test_disk());
+        command(&mut fdc, &[CMD_WRITE_DATA, 0x00, 0, 0, 1, 2, 1, 0x2A, 0xFF]);
+        assert_eq!(
+            read_all(&mut fdc),
+            [ST0_ABNORMAL, ST1_NOT_WRITABLE, 0, 0, 0, 1, 2]
+        );
+
+        // Drive B: is not connected
+        command(&mut fdc, &[CMD_RECALIBRATE, 0x01]);
+        command(&mut fdc, &[CMD_SENSE_INTERRUPT_STATUS]);
+        let st0 = ST0_ABNORMAL | ST0_SEEK_END | ST0_EQUIPMENT_CHECK | 0x01;
+        assert_eq!(read_all(&mut fdc), [st0, 0]);
+
+        // Invalid command
+        command(&mut fdc, &[0x00]);
+        assert_eq!(read_all(&mut fdc), [ST0_INVALID]);
+    }
+}
@@ -0,0 +1,10 @@
+//! +3 and Beta Disk 128 disk drives emulation
+mod beta;
+mod dsk;
+mod fdc;
+mod trd;
+
+pub use beta::{BetaDisk, TRDOS_ENTRY_END, TRDOS_ENTRY_START};
+pub use dsk::DiskImage;
+pub use fdc::Fdc;
+pub use trd::TrdImage;
@@ -0,0 +1,170 @@
+//! TR-DOS disk images parsing (`.trd` raw images and `.scl` archives)
+use crate::{error::DiskLoadError, Result};
+use alloc::vec::Vec;
+
+pub const SECTOR_SIZE: usize = 256;
+pub const SECTORS_PER_TRACK: usize = 16;
+const TRACK_SIZE: usize = SECTOR_SIZE * SECTORS_PER_TRACK;
+const MAX_TRACKS: usize = 80;
+const MAX_IMAGE_SIZE: usize = TRACK_SIZE * MAX_TRACKS * 2;
+
+// System sector (track 0, sector 9) layout
+const SYSTEM_SECTOR_OFFSET: usize = 8 * SECTOR_SIZE;
+const SYSTEM_FIRST_FREE_SECTOR: usize = SYSTEM_SECTOR_OFFSET + 0xE1;
+const SYSTEM_FIRST_FREE_TRACK: usize = SYSTEM_SECTOR_OFFSET + 0xE2;
+const SYSTEM_DISK_TYPE: usize = SYSTEM_SECTOR_OFFSET + 0xE3;
+const SYSTEM_FILES_COUNT: usize = SYSTEM_SECTOR_OFFSET + 0xE4;
+const SYSTEM_FREE_SECTORS: usize = SYSTEM_SECTOR_OFFSET + 0xE5;
+const SYSTEM_TRDOS_ID: usize = SYSTEM_SECTOR_OFFSET + 0xE7;
+const SYSTEM_LABEL: usize = SYSTEM_SECTOR_OFFSET + 0xEA;
+const SYSTEM_LABEL_LENGTH: usize = 17;
+
+const DISK_TYPE_80_TRACKS_DOUBLE_SIDED: u8 = 0x16;
+const DISK_TYPE_40_TRACKS_DOUBLE_SIDED: u8 = 0x17;
+const DISK_TYPE_80_TRACKS_SINGLE_SIDED: u8 = 0x18;
+const DISK_TYPE_40_TRACKS_SINGLE_SIDED: u8 = 0x19;
+const TRDOS_ID: u8 = 0x10;
+
+const SCL_SIGNATURE: &[u8] = b"SINCLAIR";
+const SCL_HEADER_SIZE: usize = 14;
+const CATALOG_ENTRY_SIZE: usize = 16;
+const MAX_FILES: usize = 128;
+
+/// Read-only TR-DOS disk image with 16 256-byte sectors per track
+pub struct TrdImage {
+    data: Vec<u8>,
+    sides: usize,
+    tracks: usize,
+}
+
+impl TrdImage {
+    /// Parses raw TRD image. Images are allowed to be truncated after the last
+    /// used track, missing tracks are read as empty
+    pub fn from_trd(data: &[u8]) -> Result<Self> {
+        if data.len() <= SYSTEM_SECTOR_OFFSET
+            || data.len() > MAX_IMAGE_SIZE
+            || !data.len().is_multiple_of(SECTOR_SIZE)
+        {
+            return Err(DiskLoadError::InvalidTrdFile.into());
+        }
+        let (tracks, sides) = match data[SYSTEM_DISK_TYPE] {
+            DISK_TYPE_40_TRACKS_DOUBLE_SIDED => (40, 2),
+            DISK_TYPE_80_TRACKS_SINGLE_SIDED => (80, 1),
+            DISK_TYPE_40_TRACKS_SINGLE_SIDED => (40, 1),
+            // Unknown disk types are treated as the most common 80-track DS
+            _ => (80, 2),
+        };
+        let mut data = data.to_vec();
+        let size = (tracks * sides * TRACK_SIZE).max(data.len());
+        data.resize(size, 0);
+        Ok(Self {
+            tracks: size / (sides * TRACK_SIZE),
+            sides,
+            data,
+        })
+    }
+
+    /// Builds 80-track double-sided TRD image from the files of SCL archive
+    pub fn from_scl(scl: &[u8]) -> Result<Self> {
+        if !scl.starts_with(SCL_SIGNATURE) || scl.len() <= SCL_SIGNATURE.len() {
+            return Err(DiskLoadError::InvalidSclFile.into());
+        }
+        let files_count = scl[SCL_SIGNATURE.len()] as usize;
+        if files_count > MAX_FILES {
+            return Err(DiskLoadError::InvalidSclFile.into());
+        }
+        let headers_offset = SCL_SIGNATURE.len() + 1;
+        let headers = scl
+            .get(headers_offset..headers_offset + files_count * SCL_HEADER_SIZE)
+            .ok_or(DiskLoadError::InvalidSclFile)?;
+
+        let mut data = alloc::vec![0u8; MAX_IMAGE_SIZE];
+        // Files are placed right after the catalog track
+        let mut sector = SECTORS_PER_TRACK;
+        let mut offset = headers_offset + headers.len();
+        for (index, header) in headers.chunks(SCL_HEADER_SIZE).enumerate() {
+            let length = header[SCL_HEADER_SIZE - 1] as usize * SECTOR_SIZE;
+            let file = scl
+                .get(offset..offset + length)
+                .ok_or(DiskLoadError::InvalidSclFile)?;
+            let image_offset = sector * SECTOR_SIZE;
+            data.get_mut(image_offset..image_offset + length)
+                .ok_or(DiskLoadError::InvalidSclFile)?
+                .copy_from_slice(file);
+
+            let entry = &mut data[index * CATALOG_ENTRY_SIZE..(index + 1) * CATALOG_ENTRY_SIZE];
+            entry[..SCL_HEADER_SIZE].copy_from_slice(header);
+            entry[SCL_HEADER_SIZE] = (sector % SECTORS_PER_TRACK) as u8;
+            entry[SCL_HEADER_SIZE + 1] = (sector / SECTORS_PER_TRACK) as u8;
+
+            sector += header[SCL_HEADER_SIZE - 1] as usize;
+            offset += length;
+        }
+
+        let free_sectors = (MAX_IMAGE_SIZE / SECTOR_SIZE - sector) as u16;
+        data[SYSTEM_FIRST_FREE_SECTOR] = (sector % SECTORS_PER_TRACK) as u8;
+        data[SYSTEM_FIRST_FREE_TRACK] = (sector / SECTORS_PER_TRACK) as u8;
+        data[SYSTEM_DISK_TYPE] = DISK_TYPE_80_TRACKS_DOUBLE_SIDED;
+        data[SYSTEM_FILES_COUNT] = files_count as u8;
+        data[SYSTEM_FREE_SECTORS..SYSTEM_FREE_SECTORS + 2]
+            .copy_from_slice(&free_sectors.to_le_bytes());
+        data[SYSTEM_TRDOS_ID] = TRDOS_ID;
+        data[SYSTEM_LABEL..SYSTEM_LABEL + SYSTEM_LABEL_LENGTH].fill(b' ');
+
+        Ok(Self {
+            data,
+            sides: 2,
+            tracks: MAX_TRACKS,
+        })
+    }
+
+    /// Returns sector data on the given physical cylinder and side. Sectors are
+    /// numbered from 1
+    pub fn sector(&self, cylinder: u8, side: u8, sector: u8) -> Option<&[u8]> {
+        let (cylinder, side, sector) = (cylinder as usize, side as usize, sector as usize);
+        if cylinder >= self.tracks
+            || side >= self.sides
+            || !(1..=SECTORS_PER_TRACK).contains(&sector)
+        {
+            return None;
+        }
+        let offset =
+            ((cylinder * self.sides + side) * SECTORS_PER_TRACK + sector - 1) * SECTOR_SIZE;
+        Some(&self.data[offset..offset + SECTOR_SIZE])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scl_to_trd() {
+        let mut scl = SCL_SIGNATURE.to_vec();
+        scl.push(2);
+        // Two files: 1 and 2 sectors long
+        scl.extend_from_slice(b"first   B\x00\x01\x00\x01\x01");
+        scl.extend_from_slice(b"second  C\x00\x80\x00\x02\x02");
+        scl.extend_from_slice(&[0x11; SECTOR_SIZE]);
+        scl.extend_from_slice(&[0x22; SECTOR_SIZE * 2]);
+        let image = TrdImage::from_scl(&scl).unwrap();
+
+        let catalog = image.sector(0, 0, 1).unwrap();
+        assert_eq!(&catalog[..9], b"first   B");
+        assert_eq!(catalog[14..16], [0, 1]);
+        assert_eq!(&catalog[16..25], b"second  C");
+        assert_eq!(catalog[30..32], [1, 1]);
+
+        let system = image.sector(0, 0, 9).unwrap();
+        assert_eq!(system[0xE1..0xE8], [3, 1, 0x16, 2, 0xED, 0x09, 0x10]);
+
+        // Track 1 is placed on the second side of cylinder 0
+        assert_eq!(image.sector(0, 1, 1).unwrap(), [0x11; SECTOR_SIZE]);
+        assert_eq!(image.sector(0, 1, 3).unwrap(), [0x22; SECTOR_SIZE]);
+        assert!(image.sector(0, 1, 17).is_none());
+        assert!(image.sector(80, 0, 1).is_none());
+
+        assert!(TrdImage::from_scl(b"SINCLAIR\x01").is_err());
+        assert!(TrdImage::from_trd(&[0u8; 100]).is_err());
+    }
+}
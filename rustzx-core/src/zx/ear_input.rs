@@ -0,0 +1,90 @@
+//! External EAR input signal.
+//!
+//! Drives EAR input (bit 6 of port 0xFE) from the host-provided signal
+//! instead of the internal tape, e.g. to load real cassettes digitized to
+//! WAV or captured from the line-in. Audio is played back on the cpu clocks
+//! timeline, same as the internal tape.
+use alloc::collections::VecDeque;
+
+/// Signal level should cross zero by this value to change EAR input state,
+/// which filters out noise of the digitized audio
+const HYSTERESIS: f32 = 0.02;
+
+#[derive(Default)]
+pub(crate) struct ExternalEarInput {
+    samples: VecDeque<f32>,
+    clocks_per_sample: f64,
+    // cpu clocks passed since the start of the current sample
+    sample_clocks: f64,
+    audio_level: bool,
+    level: Option<bool>,
+}
+
+impl ExternalEarInput {
+    /// Sets EAR input level directly, `None` releases it. Queued audio
+    /// takes priority over this level
+    pub fn set_level(&mut self, level: Option<bool>) {
+        self.level = level;
+    }
+
+    /// Queues audio samples, played back at `clocks_per_sample` rate
+    pub fn feed_audio(&mut self, samples: &[f32], clocks_per_sample: f64) {
+        if samples.is_empty() {
+            return;
+        }
+        if self.samples.is_empty() {
+            self.sample_clocks = 0.0;
+            self.update_audio_level(samples[0]);
+        }
+        self.clocks_per_sample = clocks_per_sample;
+        self.samples.extend(samples);
+    }
+
+    /// Drops queued audio and releases EAR input level
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.level = None;
+    }
+
+    /// Returns count of queued audio samples, which were not played yet
+    pub fn queued_samples(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if external signal drives EAR input
+    pub fn is_active(&self) -> bool {
+        !self.samples.is_empty() || self.level.is_some()
+    }
+
+    /// Returns current EAR input level
+    pub fn current_bit(&self) -> bool {
+        if self.samples.is_empty() {
+            self.level.unwrap_or(false)
+        } else {
+            self.audio_level
+        }
+    }
+
+    pub fn process_clocks(&mut self, clocks: usize) {
+        if self.samples.is_empty() {
+            return;
+        }
+        self.sample_clocks += clocks as f64;
+        while self.sample_clocks >= self.clocks_per_sample {
+            self.sample_clocks -= self.clocks_per_sample;
+            self.samples.pop_front();
+            match self.samples.front() {
+                Some(&sample) => self.update_audio_level(sample),
+                None => break,
+            }
+        }
+    }
+
+    fn update_audio_level(&mut self, sample: f32) {
+        if sample > HYSTERESIS {
+            self.audio_level = true;
+        } else if sample < -HYSTERESIS {
+            self.audio_level = false;
+        }
+    }
+}
@@ -3,15 +3,42 @@ use bitflags::bitflags;
 bitflags! {
     /// Emulation events
     #[derive(Default)]
-    pub struct EmulationEvents: u8 {
+    pub struct EmulationEvents: u16 {
         /// Set when tape fast load trigger is detected
-        const TAPE_FAST_LOAD_TRIGGER_DETECTED = 0b00000001;
+        const TAPE_FAST_LOAD_TRIGGER_DETECTED = 0b0000_0001;
         /// Set when PC breakpoint is reached
-        const PC_BREAKPOINT = 0b00000010;
+        const PC_BREAKPOINT = 0b0000_0010;
+        /// Set when 48K BASIC ROM reports "BREAK into program"
+        const BREAK_KEY = 0b0000_0100;
+        /// Set when CPU executes HALT instruction, cleared if CPU leaves halted
+        /// state (on interrupt) before the event was taken
+        const CPU_HALTED = 0b0000_1000;
+        /// Set when tape is paused at the end of the block with autostop
+        /// enabled, see `Emulator::set_tape_autostop`
+        const TAPE_BLOCK_FINISHED = 0b0001_0000;
+        /// Set when CPU accepts maskable interrupt, see
+        /// `Emulator::last_interrupt_latency`
+        const INTERRUPT_ACCEPTED = 0b0010_0000;
+        /// Set when memory map is changed by the paging ports or TR-DOS ROM
+        /// switching, see `Emulator::take_paged_blocks`
+        const MEMORY_PAGED = 0b0100_0000;
+        /// Set when 48K BASIC ROM reports "Tape loading error" or when ROM
+        /// loader waits for the tape signal for about a second while tape is
+        /// not playing
+        const TAPE_LOAD_ERROR = 0b1000_0000;
+        /// Set when 48K BASIC ROM starts looking for the tape header on
+        /// `LOAD`, `VERIFY` or `MERGE`, so frontend could start the tape
+        /// playback. Set again for each skipped block of the other file
+        const TAPE_LOAD_REQUESTED = 0b1_0000_0000;
+        /// Set when 128K screen is switched between normal (bank 5) and
+        /// shadow (bank 7) screen via port 0x7FFD, see
+        /// `Emulator::current_screen_bank`
+        const SHADOW_SCREEN_SWITCHED = 0b10_0000_0000;
     }
 }
 
 impl EmulationEvents {
+    /// Returns current events and clears them
     pub fn take(&mut self) -> Self {
         let events = *self;
         self.bits = 0;
@@ -0,0 +1,61 @@
+//! Memory access statistics.
+//!
+//! Counts reads, writes and executed instructions per 256-byte page of the
+//! cpu address space, so hot code and data regions could be found without an
+//! external profiler. Pages are counted by the cpu address, regardless of the
+//! memory bank paged in at the moment of access.
+use alloc::{boxed::Box, vec};
+
+/// Size of the address space region, counted by a single [MemStats] entry
+pub const HEATMAP_PAGE_SIZE: usize = 256;
+/// Count of [MemStats] entries, covering whole 64K address space
+pub const HEATMAP_PAGES: usize = 0x10000 / HEATMAP_PAGE_SIZE;
+
+/// Access counters of the single address space page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemStats {
+    /// Count of the byte reads, including opcode fetches
+    pub reads: u64,
+    /// Count of the byte writes
+    pub writes: u64,
+    /// Count of the instructions started in this page
+    pub executes: u64,
+}
+
+pub(crate) struct MemoryHeatmap {
+    pages: Box<[MemStats]>,
+}
+
+impl Default for MemoryHeatmap {
+    fn default() -> Self {
+        Self {
+            pages: vec![MemStats::default(); HEATMAP_PAGES].into_boxed_slice(),
+        }
+    }
+}
+
+impl MemoryHeatmap {
+    fn page_mut(&mut self, addr: u16) -> &mut MemStats {
+        &mut self.pages[addr as usize / HEATMAP_PAGE_SIZE]
+    }
+
+    pub fn record_read(&mut self, addr: u16) {
+        self.page_mut(addr).reads += 1;
+    }
+
+    pub fn record_write(&mut self, addr: u16) {
+        self.page_mut(addr).writes += 1;
+    }
+
+    pub fn record_execute(&mut self, addr: u16) {
+        self.page_mut(addr).executes += 1;
+    }
+
+    pub fn pages(&self) -> &[MemStats] {
+        &self.pages
+    }
+
+    pub fn reset(&mut self) {
+        self.pages.fill(MemStats::default());
+    }
+}
@@ -0,0 +1,150 @@
+//! Host input mapping. [InputProfile] binds buttons of the host controller
+//! (gamepad, touch overlay or keyboard) to the emulated keyboard keys,
+//! joysticks and mouse buttons, so frontends can keep per-game input
+//! configuration in a single place. See `Emulator::configure_input`
+use crate::zx::{
+    joy::{
+        cursor::CursorKey,
+        fuller::FullerKey,
+        kempston::{KempstonJoyNum, KempstonKey},
+        sinclair::{SinclairJoyNum, SinclairKey},
+    },
+    keys::ZXKey,
+    mouse::kempston::KempstonMouseButton,
+};
+
+/// Count of the host buttons, see [InputButton]
+pub const INPUT_BUTTONS_COUNT: usize = 8;
+
+/// Button of the host input device
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Fire,
+    AltFire,
+    Start,
+    Select,
+}
+
+/// Emulated input, driven by the host button
+#[derive(Debug, Clone, Copy)]
+pub enum InputTarget {
+    Key(ZXKey),
+    Kempston(KempstonJoyNum, KempstonKey),
+    Sinclair(SinclairJoyNum, SinclairKey),
+    Cursor(CursorKey),
+    Fuller(FullerKey),
+    MouseButton(KempstonMouseButton),
+}
+
+/// Bindings of the host buttons to the emulated inputs
+#[derive(Debug, Clone)]
+pub struct InputProfile {
+    bindings: [Option<InputTarget>; INPUT_BUTTONS_COUNT],
+    /// Analog stick deflection in range `0.0..1.0`, below which direction
+    /// buttons stay released
+    pub dead_zone: f32,
+}
+
+impl Default for InputProfile {
+    fn default() -> Self {
+        Self {
+            bindings: [None; INPUT_BUTTONS_COUNT],
+            dead_zone: 0.5,
+        }
+    }
+}
+
+impl InputProfile {
+    /// Binds directions and fire (up, down, left, right, fire) buttons
+    fn with_directions(targets: [InputTarget; 5]) -> Self {
+        let mut profile = Self::default();
+        let buttons = [
+            InputButton::Up,
+            InputButton::Down,
+            InputButton::Left,
+            InputButton::Right,
+            InputButton::Fire,
+        ];
+        for (button, target) in buttons.into_iter().zip(targets) {
+            profile.set_binding(button, Some(target));
+        }
+        profile
+    }
+
+    /// Returns profile with directions and fire bound to the Kempston joystick
+    pub fn kempston(num: KempstonJoyNum) -> Self {
+        let key = |key| InputTarget::Kempston(num, key);
+        Self::with_directions([
+            key(KempstonKey::Up),
+            key(KempstonKey::Down),
+            key(KempstonKey::Left),
+            key(KempstonKey::Right),
+            key(KempstonKey::Fire),
+        ])
+    }
+
+    /// Returns profile with directions and fire bound to the Sinclair joystick
+    pub fn sinclair(num: SinclairJoyNum) -> Self {
+        let key = |key| InputTarget::Sinclair(num, key);
+        Self::with_directions([
+            key(SinclairKey::Up),
+            key(SinclairKey::Down),
+            key(SinclairKey::Left),
+            key(SinclairKey::Right),
+            key(SinclairKey::Fire),
+        ])
+    }
+
+    /// Returns profile with directions and fire bound to the Cursor joystick
+    pub fn cursor() -> Self {
+        Self::with_directions([
+            InputTarget::Cursor(CursorKey::Up),
+            InputTarget::Cursor(CursorKey::Down),
+            InputTarget::Cursor(CursorKey::Left),
+            InputTarget::Cursor(CursorKey::Right),
+            InputTarget::Cursor(CursorKey::Fire),
+        ])
+    }
+
+    /// Returns profile with directions and fire bound to the Fuller joystick
+    pub fn fuller() -> Self {
+        Self::with_directions([
+            InputTarget::Fuller(FullerKey::Up),
+            InputTarget::Fuller(FullerKey::Down),
+            InputTarget::Fuller(FullerKey::Left),
+            InputTarget::Fuller(FullerKey::Right),
+            InputTarget::Fuller(FullerKey::Fire),
+        ])
+    }
+
+    /// Returns profile with directions and fire bound to the keyboard keys
+    /// in (up, down, left, right, fire) order, e.g. `Q, A, O, P, Space`
+    pub fn keys(keys: [ZXKey; 5]) -> Self {
+        Self::with_directions(keys.map(InputTarget::Key))
+    }
+
+    /// Binds host button to the emulated input, `None` unbinds the button
+    pub fn set_binding(&mut self, button: InputButton, target: Option<InputTarget>) {
+        self.bindings[button as usize] = target;
+    }
+
+    /// Returns emulated input, bound to the host button
+    pub fn binding(&self, button: InputButton) -> Option<InputTarget> {
+        self.bindings[button as usize]
+    }
+
+    /// Returns emulated inputs of the buttons from the mask (bit N is set for
+    /// the button with index N)
+    pub(crate) fn bound_targets(&self, buttons: u8) -> impl Iterator<Item = InputTarget> + '_ {
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| buttons & (1 << index) != 0)
+            .filter_map(|(_, target)| *target)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::zx::keys::ZXKey;
+
+/// Cursor (Protek/AGF) joystick keys. Interface spans two keyboard
+/// half-rows and emulates cursor keys 5, 6, 7, 8 with fire on 0
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy)]
+pub enum CursorKey {
+    Left,
+    Right,
+    Up,
+    Down,
+    Fire,
+}
+
+pub(crate) fn cursor_event_to_zx_key(key: CursorKey) -> ZXKey {
+    match key {
+        CursorKey::Left => ZXKey::N5,
+        CursorKey::Down => ZXKey::N6,
+        CursorKey::Up => ZXKey::N7,
+        CursorKey::Right => ZXKey::N8,
+        CursorKey::Fire => ZXKey::N0,
+    }
+}
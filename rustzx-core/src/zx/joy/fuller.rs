@@ -0,0 +1,37 @@
+/// Fuller joystick key type. Port bit encoded in enum values
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy)]
+pub enum FullerKey {
+    Up = 0x01,
+    Down = 0x02,
+    Left = 0x04,
+    Right = 0x08,
+    Fire = 0x80,
+}
+
+/// Fuller Box joystick, mapped to port 0x7F with active-low bits
+#[derive(Default)]
+pub(crate) struct FullerJoy {
+    state: u8,
+}
+
+impl FullerJoy {
+    /// Simulates key press/release
+    pub fn key(&mut self, key: FullerKey, state: bool) {
+        if state {
+            self.state |= key as u8;
+        } else {
+            self.state &= !(key as u8);
+        }
+    }
+
+    /// Reads joy value
+    pub fn read(&self) -> u8 {
+        !self.state
+    }
+
+    /// Constructs joystick with given pressed keys bits
+    pub fn from_state(state: u8) -> Self {
+        Self { state }
+    }
+}
@@ -1,6 +1,11 @@
+/// Default Kempston port decode mask, address bits A5-A7 are reset
+pub const KEMPSTON_PORT_MASK: u16 = 0x00E0;
+/// Default Kempston port value after applying [KEMPSTON_PORT_MASK]
+pub const KEMPSTON_PORT_VALUE: u16 = 0x0000;
+
 /// Kempston key type. Port bit encoded in enum values
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub enum KempstonKey {
     Right = 0x01,
     Left = 0x02,
@@ -15,6 +20,15 @@ pub enum KempstonKey {
     Ext3 = 0x80,
 }
 
+/// Kempston joystick number. The first joystick is decoded on port 0x1F,
+/// the second one on port 0x3F
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KempstonJoyNum {
+    First,
+    Second,
+}
+
 /// Kempston Joystick
 #[derive(Default)]
 pub(crate) struct KempstonJoy {
@@ -35,4 +49,9 @@ impl KempstonJoy {
     pub fn read(&self) -> u8 {
         self.state
     }
+
+    /// Constructs joystick with given pressed keys bits
+    pub fn from_state(state: u8) -> Self {
+        Self { state }
+    }
 }
@@ -1,2 +1,4 @@
+pub mod cursor;
+pub mod fuller;
 pub mod kempston;
 pub mod sinclair;
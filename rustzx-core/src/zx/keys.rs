@@ -1,5 +1,35 @@
 //! Module with hardware key port\masks
 
+/// Count of keyboard rows (half-ports) in ZX Spectrum keyboard matrix
+pub const ZX_KEYBOARD_ROWS: usize = 8;
+/// Count of keys in a single keyboard matrix row
+pub const ZX_KEYBOARD_ROW_KEYS: u8 = 5;
+/// Total count of ZX Spectrum keys
+pub(crate) const ZX_KEYS_COUNT: usize = 40;
+
+/// Keyboard circuit revision of the 48K machine. Defines how bit 6 (EAR input)
+/// of port 0xFE reflects the last written EAR/MIC outputs when tape is silent.
+/// 128K machines behave as `Issue3`
+#[cfg_attr(feature = "strum", derive(strum::EnumIter))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyboardIssue {
+    /// Bit 6 is set when either EAR (bit 4) or MIC (bit 3) output is set
+    Issue2,
+    /// Bit 6 is set only when EAR (bit 4) output is set
+    #[default]
+    Issue3,
+}
+
+impl KeyboardIssue {
+    /// Returns EAR input level for the last value written to port 0xFE
+    pub(crate) fn ear_sense(self, ula_out: u8) -> bool {
+        match self {
+            KeyboardIssue::Issue2 => ula_out & 0x18 != 0,
+            KeyboardIssue::Issue3 => ula_out & 0x10 != 0,
+        }
+    }
+}
+
 /// Struct, which contains mast and port of key
 #[rustfmt::skip]
 #[cfg_attr(feature = "strum", derive(strum::EnumIter))]
@@ -70,6 +100,11 @@ impl CompoundKey {
 }
 
 impl ZXKey {
+    /// Returns unique key index in range `0..ZX_KEYS_COUNT`
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
     pub(crate) fn row_id(self) -> usize {
         match self.half_port() {
             0xFE => 0,
@@ -0,0 +1,77 @@
+//! Magnum Light Phaser lightgun emulation.
+//!
+//! Light sensor is wired to the EAR input (bit 6 of port 0xFE is set when
+//! light is detected) and the trigger is reported as Kempston fire bit
+//! (bit 4 of port 0x1F).
+//!
+//! Lens of the gun focuses the sensor on a small spot of the screen, which is
+//! modelled as the character cell under the target point. CRT phosphor glows
+//! much longer than a scan line is drawn, so the sensor sees light from the
+//! moment the beam draws the first pixel of the lit cell until the beam
+//! leaves the last line of the cell. Beam position is derived from the
+//! machine timings the same way as for the border rendering.
+use crate::{
+    utils::screen::bitmap_line_addr,
+    zx::{
+        constants::{ATTR_BASE_REL, ATTR_COLS, CANVAS_HEIGHT, CANVAS_WIDTH, CLOCKS_PER_COL},
+        machine::ZXSpecs,
+        video::colors::{ZXAttribute, ZXColor},
+    },
+};
+
+const TRIGGER_BIT: u8 = 0x10;
+/// Size of the character cell, seen by the sensor, in pixels and lines
+const CELL_SIZE: usize = 8;
+
+#[derive(Default)]
+pub(crate) struct Lightgun {
+    x: u16,
+    y: u16,
+    trigger: bool,
+}
+
+impl Lightgun {
+    /// Sets gun target point in canvas (paper area) coordinates and trigger state
+    pub fn set(&mut self, x: u16, y: u16, trigger: bool) {
+        self.x = x;
+        self.y = y;
+        self.trigger = trigger;
+    }
+
+    /// Returns Kempston port bits for the trigger state
+    pub fn trigger_bits(&self) -> u8 {
+        if self.trigger {
+            TRIGGER_BIT
+        } else {
+            0
+        }
+    }
+
+    /// Checks if the sensor currently sees the lit cell under the target
+    /// point. `screen` is the displayed ram bank
+    pub fn light_detected(&self, frame_clocks: usize, specs: &ZXSpecs, screen: &[u8]) -> bool {
+        let (x, y) = (self.x as usize, self.y as usize);
+        if x >= CANVAS_WIDTH || y >= CANVAS_HEIGHT {
+            return false;
+        }
+        let (col, row) = (x / CELL_SIZE, y / CELL_SIZE);
+        // Beam draws the first pixel of the cell at `start`, the last line of
+        // the cell is finished `CELL_SIZE - 1` lines and one column later
+        let start = specs.clocks_first_pixel
+            + specs.clocks_ula_beam_shift
+            + row * CELL_SIZE * specs.clocks_line
+            + col * CLOCKS_PER_COL;
+        let end = start + (CELL_SIZE - 1) * specs.clocks_line + CLOCKS_PER_COL;
+        (start..end).contains(&frame_clocks) && cell_is_lit(screen, col, row)
+    }
+}
+
+/// Checks if any pixel of the character cell is not black
+fn cell_is_lit(screen: &[u8], col: usize, row: usize) -> bool {
+    let attr = ZXAttribute::from_byte(screen[ATTR_BASE_REL as usize + row * ATTR_COLS + col]);
+    let lit = |state| !matches!(attr.active_color(state, false), ZXColor::Black);
+    (row * CELL_SIZE..(row + 1) * CELL_SIZE).any(|line| {
+        let bitmap = screen[(bitmap_line_addr(line) & 0x3FFF) as usize + col];
+        (bitmap != 0x00 && lit(true)) || (bitmap != 0xFF && lit(false))
+    })
+}
@@ -3,9 +3,11 @@
 // Allow outer modules to use ZXSpecs struct, but not construct
 mod specs;
 
+use crate::{error::MachineSpecError, Result};
 use lazy_static::lazy_static;
 use specs::ZXSpecsBuilder;
 
+pub use specs::ContentionModel;
 pub(crate) use specs::ZXSpecs;
 
 lazy_static! {
@@ -19,6 +21,7 @@ lazy_static! {
             .clocks_row(24, 128, 24, 48)
             .lines(48, 192, 48, 24)
             .contention([6, 5, 4, 3, 2, 1, 0, 0], 1)
+            .contention_model(ContentionModel::Ula)
             .interrupt_length(32)
             .rom_pages(1)
             .build()
@@ -36,61 +39,447 @@ lazy_static! {
             .clocks_row(24, 128, 24, 52)
             .lines(48, 192, 48, 23)
             .contention([6, 5, 4, 3, 2, 1, 0, 0], 1)
-            .interrupt_length(32)
+            .contention_model(ContentionModel::Ula)
+            // 128K ULA holds INT active 4 clocks longer than 48K one
+            .interrupt_length(36)
             .rom_pages(2)
             .build()
     };
 }
 
+lazy_static! {
+    /// ZX Spectrum +2A/+3 Specs
+    pub static ref SPECS_PLUS3: ZXSpecs = {
+        ZXSpecsBuilder::new()
+            .freq_cpu(3_546_900)
+            .clocks_first_pixel(14365)
+            .clocks_ula_read_shift(2)
+            .clocks_ula_beam_shift(1)
+            .clocks_row(24, 128, 24, 52)
+            .lines(48, 192, 48, 23)
+            .contention([1, 0, 7, 6, 5, 4, 3, 2], 0)
+            .contention_model(ContentionModel::GateArray)
+            .interrupt_length(32)
+            .rom_pages(4)
+            .build()
+    };
+}
+
 /// Machine type
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ZXMachine {
+    /// ZX Spectrum 16K, upper 32K of the address space is not populated
+    Sinclair16K,
     Sinclair48K,
     Sinclair128K,
+    /// Amstrad ZX Spectrum +2A/+3, with additional paging via port 0x1FFD
+    SinclairPlus3,
+}
+
+/// Hardware features of the machine, useful for frontend machine selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachineCapabilities {
+    /// Machine has built-in AY sound chip
+    pub has_ay: bool,
+    /// Machine supports 128K memory paging via port 0x7FFD
+    pub has_128k_paging: bool,
+    /// RAM size in bytes
+    pub ram_size: usize,
+    /// Count of 16K ROM banks
+    pub rom_banks: usize,
+    /// ROM is built into the emulator (with `embedded-roms` feature) and
+    /// could be loaded with [crate::RustzxSettings::load_default_rom]
+    pub has_embedded_rom: bool,
 }
 
 impl ZXMachine {
+    /// Returns all machines, supported by the emulator
+    pub fn all() -> &'static [ZXMachine] {
+        &[
+            ZXMachine::Sinclair16K,
+            ZXMachine::Sinclair48K,
+            ZXMachine::Sinclair128K,
+            ZXMachine::SinclairPlus3,
+        ]
+    }
+
+    /// Returns hardware capabilities of the machine
+    pub fn capabilities(self) -> MachineCapabilities {
+        match self {
+            ZXMachine::Sinclair16K => MachineCapabilities {
+                has_ay: false,
+                has_128k_paging: false,
+                ram_size: 16 * 1024,
+                rom_banks: self.specs().rom_pages as usize,
+                has_embedded_rom: true,
+            },
+            ZXMachine::Sinclair48K => MachineCapabilities {
+                has_ay: false,
+                has_128k_paging: false,
+                ram_size: 48 * 1024,
+                rom_banks: self.specs().rom_pages as usize,
+                has_embedded_rom: true,
+            },
+            ZXMachine::Sinclair128K | ZXMachine::SinclairPlus3 => MachineCapabilities {
+                has_ay: true,
+                has_128k_paging: true,
+                ram_size: 128 * 1024,
+                rom_banks: self.specs().rom_pages as usize,
+                has_embedded_rom: self == ZXMachine::Sinclair128K,
+            },
+        }
+    }
+
     /// Returns current machine specs as ref to static value
     pub fn specs(self) -> &'static ZXSpecs {
         match self {
-            ZXMachine::Sinclair48K => &SPECS_48K,
+            // 16K machine shares ULA and timings with 48K
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K => &SPECS_48K,
             ZXMachine::Sinclair128K => &SPECS_128K,
+            ZXMachine::SinclairPlus3 => &SPECS_PLUS3,
         }
     }
 
-    /// Returns contention during specified time
+    /// Returns count of wait clocks which should be added to the memory or IO access,
+    /// started at `clocks` T-state relative to the frame start. See
+    /// [ZXSpecs::contention_clocks]
     pub fn contention_clocks(self, clocks: usize) -> usize {
-        let specs = self.specs();
-        if (clocks < (specs.clocks_first_pixel - 1))
-            || (clocks >= (specs.clocks_first_pixel - 1) + specs.lines_screen * specs.clocks_line)
-        {
-            return 0;
-        }
-        let clocks_trough_line = (clocks - (specs.clocks_first_pixel - 1)) % specs.clocks_line;
-        if clocks_trough_line >= specs.clocks_screen_row {
-            return 0;
-        }
-        return self.specs().contention_pattern[clocks_trough_line % 8];
+        self.specs().contention_clocks(clocks)
     }
 
-    /// Checks port contention on machine
+    /// Returns contention model of the machine
+    pub fn contention_model(self) -> ContentionModel {
+        self.specs().contention_model
+    }
+
+    /// Checks port contention on machine: every even port is contended on the
+    /// machines with Sinclair ULA
     pub fn port_is_contended(self, port: u16) -> bool {
-        match self {
-            ZXMachine::Sinclair48K | ZXMachine::Sinclair128K => {
-                // every even port
-                (port & 0x0001) == 0
-            }
-        }
+        self.specs().port_is_contended(port)
     }
 
     /// Returns contention status of bank
     pub fn bank_is_contended(self, page: usize) -> bool {
         match self {
-            ZXMachine::Sinclair48K => page == 0,
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K => page == 0,
             ZXMachine::Sinclair128K => {
                 let contended_pages = [1, 3, 5, 7];
                 contended_pages.iter().any(|&x| x == page)
             }
+            ZXMachine::SinclairPlus3 => page >= 4,
+        }
+    }
+}
+
+// Screen area with the full-size borders: 24 clocks (48 pixels) of left and
+// right borders around 128 clocks screen row, 48 lines of top and bottom
+// borders around 192 screen lines
+const SCREEN_AREA_CLOCKS: usize = 24 + 128 + 24;
+const SCREEN_AREA_LINES: usize = 48 + 192 + 48;
+const TOP_BORDER_LINES: usize = 48;
+const LEFT_BORDER_CLOCKS: usize = 24;
+
+/// Frame timings of the machine. Built-in machines provide presets via
+/// [MachineSpec::preset], custom values allow to emulate Spectrum clones
+/// (Pentagon, Scorpion, Didaktik, etc.), which share memory layout with the
+/// base machine but differ in ULA timings. See `RustzxSettings::custom_specs`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachineSpec {
+    /// Clocks per line, including borders and retrace
+    pub clocks_line: usize,
+    /// Clocks per frame, should be a whole number of lines
+    pub clocks_frame: usize,
+    /// Length of the frame interrupt in clocks
+    pub interrupt_length: usize,
+    /// Memory and IO contention model
+    pub contention_model: ContentionModel,
+    /// Clocks from the frame interrupt to the first pixel of the screen
+    pub clocks_first_pixel: usize,
+}
+
+impl MachineSpec {
+    /// Returns timings of the built-in machine
+    pub fn preset(machine: ZXMachine) -> Self {
+        let specs = machine.specs();
+        Self {
+            clocks_line: specs.clocks_line,
+            clocks_frame: specs.clocks_frame,
+            interrupt_length: specs.interrupt_length,
+            contention_model: specs.contention_model,
+            clocks_first_pixel: specs.clocks_first_pixel,
+        }
+    }
+
+    /// Returns count of lines in the frame
+    pub fn lines(&self) -> usize {
+        self.clocks_frame / self.clocks_line.max(1)
+    }
+
+    /// Checks that timings produce a consistent frame: frame boundary falls
+    /// on the line boundary and the screen with the borders fits the frame
+    pub fn validate(&self) -> Result<()> {
+        if self.clocks_line < SCREEN_AREA_CLOCKS {
+            return Err(MachineSpecError::LineTooShort.into());
+        }
+        if !self.clocks_frame.is_multiple_of(self.clocks_line) || self.lines() < SCREEN_AREA_LINES {
+            return Err(MachineSpecError::InvalidFrameLength.into());
+        }
+        // Top border should fit before the first pixel, bottom border should
+        // start before the last line of the frame
+        let first_pixel_min = TOP_BORDER_LINES * self.clocks_line + LEFT_BORDER_CLOCKS;
+        let first_pixel_line = self.clocks_first_pixel / self.clocks_line;
+        if self.clocks_first_pixel < first_pixel_min
+            || first_pixel_line + SCREEN_AREA_LINES - TOP_BORDER_LINES > self.lines()
+        {
+            return Err(MachineSpecError::FirstPixelOutOfFrame.into());
+        }
+        if self.interrupt_length == 0 || self.interrupt_length >= self.clocks_line {
+            return Err(MachineSpecError::InvalidInterruptLength.into());
+        }
+        Ok(())
+    }
+
+    /// Builds specs of the `base` machine with overridden timings. Contention
+    /// pattern is selected by the contention model. Spec should be validated
+    pub(crate) fn build(&self, base: ZXMachine) -> ZXSpecs {
+        let base_specs = base.specs();
+        let pattern_specs = match self.contention_model {
+            ContentionModel::GateArray => ZXMachine::SinclairPlus3.specs(),
+            _ => ZXMachine::Sinclair48K.specs(),
+        };
+        ZXSpecsBuilder::new()
+            .freq_cpu(base_specs.freq_cpu)
+            .clocks_first_pixel(self.clocks_first_pixel)
+            .clocks_ula_read_shift(base_specs.clocks_ula_read_shift)
+            .clocks_ula_beam_shift(base_specs.clocks_ula_beam_shift)
+            .clocks_row(
+                LEFT_BORDER_CLOCKS,
+                128,
+                SCREEN_AREA_CLOCKS - 128 - LEFT_BORDER_CLOCKS,
+                self.clocks_line - SCREEN_AREA_CLOCKS,
+            )
+            .lines(
+                TOP_BORDER_LINES,
+                192,
+                SCREEN_AREA_LINES - 192 - TOP_BORDER_LINES,
+                self.lines() - SCREEN_AREA_LINES,
+            )
+            .contention(
+                pattern_specs.contention_pattern,
+                pattern_specs.contention_offset,
+            )
+            .contention_model(self.contention_model)
+            .interrupt_length(self.interrupt_length)
+            .rom_pages(base_specs.rom_pages)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference values from "Contended memory" article on worldofspectrum.org
+    const PATTERN: [usize; 8] = [6, 5, 4, 3, 2, 1, 0, 0];
+
+    fn assert_screen_row(machine: ZXMachine, row_start: usize) {
+        for offset in 0..128 {
+            assert_eq!(
+                machine.contention_clocks(row_start + offset),
+                PATTERN[offset % 8],
+                "{:?}: invalid contention at {}",
+                machine,
+                row_start + offset
+            );
+        }
+        // Right border, retrace and left border are not contended
+        let line = machine.specs().clocks_line;
+        for offset in 128..line {
+            assert_eq!(machine.contention_clocks(row_start + offset), 0);
+        }
+    }
+
+    #[test]
+    fn machines_list() {
+        let machines = ZXMachine::all();
+        assert_eq!(
+            machines,
+            &[
+                ZXMachine::Sinclair16K,
+                ZXMachine::Sinclair48K,
+                ZXMachine::Sinclair128K,
+                ZXMachine::SinclairPlus3
+            ]
+        );
+
+        let caps_16k = ZXMachine::Sinclair16K.capabilities();
+        assert!(!caps_16k.has_ay);
+        assert!(!caps_16k.has_128k_paging);
+        assert_eq!(caps_16k.ram_size, 16 * 1024);
+        assert_eq!(caps_16k.rom_banks, 1);
+
+        let caps_48k = ZXMachine::Sinclair48K.capabilities();
+        assert!(!caps_48k.has_ay);
+        assert!(!caps_48k.has_128k_paging);
+        assert_eq!(caps_48k.ram_size, 48 * 1024);
+        assert_eq!(caps_48k.rom_banks, 1);
+
+        let caps_128k = ZXMachine::Sinclair128K.capabilities();
+        assert!(caps_128k.has_ay);
+        assert!(caps_128k.has_128k_paging);
+        assert_eq!(caps_128k.ram_size, 128 * 1024);
+        assert_eq!(caps_128k.rom_banks, 2);
+
+        let caps_plus3 = ZXMachine::SinclairPlus3.capabilities();
+        assert!(caps_plus3.has_ay);
+        assert!(caps_plus3.has_128k_paging);
+        assert_eq!(caps_plus3.ram_size, 128 * 1024);
+        assert_eq!(caps_plus3.rom_banks, 4);
+    }
+
+    #[test]
+    fn contention_48k() {
+        let m = ZXMachine::Sinclair48K;
+        assert_eq!(m.contention_clocks(0), 0);
+        assert_eq!(m.contention_clocks(14334), 0);
+        assert_eq!(m.contention_clocks(14335), 6);
+        assert_eq!(m.contention_clocks(14336), 5);
+        assert_eq!(m.contention_clocks(14341), 0);
+        assert_eq!(m.contention_clocks(14342), 0);
+        assert_eq!(m.contention_clocks(14343), 6);
+        assert_eq!(m.contention_clocks(14462), 0);
+        assert_eq!(m.contention_clocks(14463), 0);
+        assert_eq!(m.contention_clocks(14559), 6);
+        for row in 0..192 {
+            assert_screen_row(m, 14335 + row * 224);
+        }
+        // First clock after the last screen row
+        assert_eq!(m.contention_clocks(14335 + 192 * 224), 0);
+        assert_eq!(m.contention_clocks(69887), 0);
+    }
+
+    #[test]
+    fn contention_128k() {
+        let m = ZXMachine::Sinclair128K;
+        assert_eq!(m.contention_clocks(14360), 0);
+        assert_eq!(m.contention_clocks(14361), 6);
+        assert_eq!(m.contention_clocks(14362), 5);
+        assert_eq!(m.contention_clocks(14367), 0);
+        assert_eq!(m.contention_clocks(14368), 0);
+        assert_eq!(m.contention_clocks(14369), 6);
+        assert_eq!(m.contention_clocks(14589), 6);
+        for row in 0..192 {
+            assert_screen_row(m, 14361 + row * 228);
+        }
+        assert_eq!(m.contention_clocks(14361 + 192 * 228), 0);
+        assert_eq!(m.contention_clocks(70907), 0);
+    }
+
+    #[test]
+    fn contention_models() {
+        assert_eq!(
+            ZXMachine::Sinclair48K.contention_model(),
+            ContentionModel::Ula
+        );
+        assert_eq!(
+            ZXMachine::Sinclair128K.contention_model(),
+            ContentionModel::Ula
+        );
+        assert_eq!(
+            ZXMachine::SinclairPlus3.contention_model(),
+            ContentionModel::GateArray
+        );
+    }
+
+    #[test]
+    fn contention_plus3() {
+        // Reference values from "Contended memory" article for +2A/+3, delay
+        // pattern starts one clock before the first pixel is fetched
+        let m = ZXMachine::SinclairPlus3;
+        assert_eq!(m.contention_clocks(14364), 0);
+        let expected = [1, 0, 7, 6, 5, 4, 3, 2, 1, 0];
+        for (offset, delay) in expected.iter().enumerate() {
+            assert_eq!(m.contention_clocks(14365 + offset), *delay);
+        }
+        assert_eq!(m.contention_clocks(14365 + 127), 2);
+        assert_eq!(m.contention_clocks(14365 + 128), 0);
+        assert_eq!(m.contention_clocks(14365 + 228), 1);
+        assert_eq!(m.contention_clocks(14365 + 191 * 228 + 126), 3);
+        assert_eq!(m.contention_clocks(14365 + 192 * 228), 0);
+        assert_eq!(m.contention_clocks(70907), 0);
+    }
+
+    #[test]
+    fn machine_spec_presets() {
+        for &machine in ZXMachine::all() {
+            let spec = MachineSpec::preset(machine);
+            assert!(spec.validate().is_ok(), "{:?}", machine);
+            let built = spec.build(machine);
+            let specs = machine.specs();
+            assert_eq!(built.clocks_frame, specs.clocks_frame);
+            assert_eq!(built.lines_all + built.lines_vsync, spec.lines());
+            assert_eq!(built.contention_pattern, specs.contention_pattern);
+            for clocks in (0..specs.clocks_frame).step_by(7) {
+                assert_eq!(
+                    built.contention_clocks(clocks),
+                    machine.contention_clocks(clocks)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn machine_spec_validation() {
+        // Pentagon timings
+        let pentagon = MachineSpec {
+            clocks_line: 224,
+            clocks_frame: 71680,
+            interrupt_length: 32,
+            contention_model: ContentionModel::None,
+            clocks_first_pixel: 17988,
+        };
+        assert!(pentagon.validate().is_ok());
+        let invalid = [
+            MachineSpec {
+                clocks_line: 160,
+                ..pentagon
+            },
+            MachineSpec {
+                clocks_frame: 71681,
+                ..pentagon
+            },
+            MachineSpec {
+                clocks_frame: 224 * 280,
+                ..pentagon
+            },
+            MachineSpec {
+                clocks_first_pixel: 224 * 47,
+                ..pentagon
+            },
+            MachineSpec {
+                clocks_first_pixel: 71680 - 224 * 239,
+                ..pentagon
+            },
+            MachineSpec {
+                interrupt_length: 0,
+                ..pentagon
+            },
+        ];
+        for spec in invalid {
+            assert!(spec.validate().is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn contention_none() {
+        let machine = ZXMachine::SinclairPlus3;
+        let specs = MachineSpec {
+            contention_model: ContentionModel::None,
+            ..MachineSpec::preset(machine)
+        }
+        .build(machine);
+        for clocks in 0..specs.clocks_frame {
+            assert_eq!(specs.contention_clocks(clocks), 0);
         }
     }
 }
@@ -1,3 +1,16 @@
+/// Memory and IO contention model of the machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentionModel {
+    /// Sinclair ULA: memory, IO and non-MREQ cycles are contended (48K, 128K, +2)
+    Ula,
+    /// Amstrad gate array: only MREQ cycles are contended, IO is never
+    /// contended (+2A, +3)
+    GateArray,
+    /// No contention at all (Pentagon)
+    None,
+}
+
+#[derive(Clone)]
 pub struct ZXSpecs {
     // frequencies
     pub freq_cpu: usize,
@@ -25,11 +38,44 @@ pub struct ZXSpecs {
     // interrupt
     pub interrupt_length: usize,
     // contention
+    pub contention_model: ContentionModel,
     pub contention_offset: usize,
     pub contention_pattern: [usize; 8],
     pub rom_pages: u8,
 }
 
+impl ZXSpecs {
+    /// Returns count of wait clocks which should be added to the memory or IO access,
+    /// started at `clocks` T-state relative to the frame start. Returns 0 when ULA does
+    /// not fetch screen data at the given time (border, retrace or outside of the
+    /// screen area) or when machine has no contention at all.
+    ///
+    /// Contention window starts `contention_offset` clocks before the first pixel fetch
+    /// and follows machine `contention_pattern` (e.g. `6, 5, 4, 3, 2, 1, 0, 0`) for
+    /// each 8-clocks group of the screen row.
+    pub fn contention_clocks(&self, clocks: usize) -> usize {
+        if self.contention_model == ContentionModel::None {
+            return 0;
+        }
+        let origin = self.clocks_ula_contention_origin;
+        if (clocks < origin) || (clocks >= origin + self.lines_screen * self.clocks_line) {
+            return 0;
+        }
+        let clocks_through_line = (clocks - origin) % self.clocks_line;
+        if clocks_through_line >= self.clocks_screen_row {
+            return 0;
+        }
+        self.contention_pattern[clocks_through_line % 8]
+    }
+
+    /// Checks if IO cycle of the `port` access is contended by ULA: every even
+    /// port on the machines with Sinclair ULA, gate array and contention-free
+    /// clones do not contend IO
+    pub fn port_is_contended(&self, port: u16) -> bool {
+        self.contention_model == ContentionModel::Ula && (port & 0x0001) == 0
+    }
+}
+
 /// Specs builder, used to make static valiables with machines specs
 pub struct ZXSpecsBuilder {
     specs: ZXSpecs,
@@ -65,6 +111,7 @@ impl ZXSpecsBuilder {
                 // interrupt
                 interrupt_length: 0,
                 // contention
+                contention_model: ContentionModel::Ula,
                 contention_offset: 0,
                 contention_pattern: [0; 8],
                 // memory
@@ -141,6 +188,12 @@ impl ZXSpecsBuilder {
         self
     }
 
+    /// Changes contention model
+    pub fn contention_model(mut self, value: ContentionModel) -> Self {
+        self.specs.contention_model = value;
+        self
+    }
+
     /// changes interrupt length
     pub fn interrupt_length(mut self, value: usize) -> Self {
         self.specs.interrupt_length = value;
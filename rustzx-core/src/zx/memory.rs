@@ -1,3 +1,8 @@
+use crate::{
+    error::{MemoryAccessError, StateLoadError},
+    utils::state::{StateReader, StateWriter},
+    Result,
+};
 use alloc::{vec, vec::Vec};
 
 // page size in bytes
@@ -6,31 +11,48 @@ pub const PAGE_SIZE: usize = 16 * 1024;
 pub const SIZE_16K: usize = PAGE_SIZE;
 pub const SIZE_32K: usize = PAGE_SIZE * 2;
 pub const SIZE_48K: usize = PAGE_SIZE * 3;
+pub const SIZE_64K: usize = PAGE_SIZE * 4;
 pub const SIZE_128K: usize = PAGE_SIZE * 8;
 // count of all memory blocks
 pub const MEM_BLOCKS: usize = 4;
+// count of the 8K slots of the ZX Spectrum Next MMU
+#[cfg(feature = "next")]
+pub const MMU_SLOTS: usize = 8;
+#[cfg(feature = "next")]
+const MMU_SLOT_SIZE: usize = 8 * 1024;
+/// Value of the MMU slot 0 or 1 which maps ROM instead of RAM
+#[cfg(feature = "next")]
+pub const MMU_ROM_PAGE: u8 = 0xFF;
 
 /// Rom can be:
-/// - 16K (Sinclair48K)
+/// - 16K (Sinclair16K, Sinclair48K)
 /// - 32K (Sinclair128K, 2+)
+/// - 64K (Amstrad 2A+, Amstrad 3+)
 pub enum RomType {
     K16,
     K32,
+    K64,
 }
 
 /// Ram can be:
+/// - 16K (Sinclair16K)
 /// - 48K (Sinclair48K)
 /// - 128K (Sinclair128K, Amstrad 2+, Amstrad 3+)
 pub enum RamType {
+    K16,
     K48,
     K128,
 }
 
-// Page info and type
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// Physical 16K page, mapped to one of the memory blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Page {
+    /// RAM bank with the given number
     Ram(u8),
+    /// ROM page with the given number
     Rom(u8),
+    /// Not populated block, reads return 0xFF and writes are ignored
+    Unmapped,
 }
 
 // Memory struct
@@ -39,6 +61,9 @@ pub struct ZXMemory {
     ram: Vec<u8>,
     // 4 x 16K blocks  map
     map: [Page; 4],
+    // 8 x 8K pages of the Next MMU, overrides `map` when set
+    #[cfg(feature = "next")]
+    mmu: Option<[u8; MMU_SLOTS]>,
 }
 
 impl ZXMemory {
@@ -48,6 +73,10 @@ impl ZXMemory {
         let mem_map;
         // build memory map.
         match ram_type {
+            RamType::K16 => {
+                ram_size = SIZE_16K;
+                mem_map = [Page::Rom(0), Page::Ram(0), Page::Unmapped, Page::Unmapped];
+            }
             RamType::K48 => {
                 ram_size = SIZE_48K;
                 mem_map = [Page::Rom(0), Page::Ram(0), Page::Ram(1), Page::Ram(2)];
@@ -60,11 +89,14 @@ impl ZXMemory {
         let rom_size = match rom_type {
             RomType::K16 => SIZE_16K,
             RomType::K32 => SIZE_32K,
+            RomType::K64 => SIZE_64K,
         };
         ZXMemory {
             rom: vec![0; rom_size],
             ram: vec![0; ram_size],
             map: mem_map,
+            #[cfg(feature = "next")]
+            mmu: None,
         }
     }
 
@@ -74,6 +106,7 @@ impl ZXMemory {
         match page {
             Page::Rom(page) => self.rom[(page as usize) * PAGE_SIZE + offset],
             Page::Ram(page) => self.ram[(page as usize) * PAGE_SIZE + offset],
+            Page::Unmapped => 0xFF,
         }
     }
 
@@ -91,6 +124,7 @@ impl ZXMemory {
         match page {
             Page::Ram(page) => self.ram[(page as usize) * PAGE_SIZE + offset] = value,
             Page::Rom(page) => self.rom[(page as usize) * PAGE_SIZE + offset] = value,
+            Page::Unmapped => {}
         }
     }
 
@@ -109,18 +143,111 @@ impl ZXMemory {
             _ => {}
         }
         self.map[block] = page;
+        #[cfg(feature = "next")]
+        if let Some(mmu) = &mut self.mmu {
+            let [low, high] = Self::mmu_pages(page);
+            mmu[block * 2] = low;
+            mmu[block * 2 + 1] = high;
+        }
         self
     }
 
-    /// Returns bank type of mapped page
+    /// Returns bank type of mapped page. When Next MMU maps 8K pages of
+    /// different banks to the block, page of its lower half is returned
     pub fn get_bank_type(&self, block: usize) -> Page {
         assert!(block < MEM_BLOCKS);
-        self.map[block]
+        self.get_page((block * PAGE_SIZE) as u16)
+    }
+
+    /// Returns true if the whole block is already mapped to the `page`, so
+    /// [ZXMemory::remap] would not change memory map
+    pub fn is_block_mapped_to(&self, block: usize, page: Page) -> bool {
+        assert!(block < MEM_BLOCKS);
+        #[cfg(feature = "next")]
+        if let Some(mmu) = &self.mmu {
+            if mmu[block * 2..block * 2 + 2] != Self::mmu_pages(page) {
+                return false;
+            }
+        }
+        self.map[block] == page
     }
 
     /// Returns bank type of address
     pub fn get_page(&self, addr: u16) -> Page {
-        self.map[addr as usize / PAGE_SIZE]
+        self.paged_address(addr).0
+    }
+
+    /// Sets 8K page of the Next MMU slot. The first write activates MMU,
+    /// all slots are initialized from the current 16K memory map
+    #[cfg(feature = "next")]
+    pub fn set_mmu_slot(&mut self, slot: usize, page: u8) {
+        assert!(slot < MMU_SLOTS);
+        let map = self.map;
+        let mmu = self.mmu.get_or_insert_with(|| {
+            let mut mmu = [MMU_ROM_PAGE; MMU_SLOTS];
+            for (block, page) in map.into_iter().enumerate() {
+                mmu[block * 2..block * 2 + 2].copy_from_slice(&Self::mmu_pages(page));
+            }
+            mmu
+        });
+        mmu[slot] = page;
+    }
+
+    /// Returns 8K page of the Next MMU slot, derived from the 16K memory
+    /// map when MMU is not active
+    #[cfg(feature = "next")]
+    pub fn mmu_slot(&self, slot: usize) -> u8 {
+        assert!(slot < MMU_SLOTS);
+        match &self.mmu {
+            Some(mmu) => mmu[slot],
+            None => Self::mmu_pages(self.map[slot / 2])[slot % 2],
+        }
+    }
+
+    /// Returns true if memory is paged by the Next MMU
+    #[cfg(feature = "next")]
+    pub fn is_mmu_active(&self) -> bool {
+        self.mmu.is_some()
+    }
+
+    /// Deactivates Next MMU, 16K memory map is used again
+    #[cfg(feature = "next")]
+    pub fn reset_mmu(&mut self) {
+        self.mmu = None;
+    }
+
+    #[cfg(feature = "next")]
+    fn mmu_pages(page: Page) -> [u8; 2] {
+        match page {
+            Page::Ram(bank) => [bank * 2, bank * 2 + 1],
+            Page::Rom(_) | Page::Unmapped => [MMU_ROM_PAGE; 2],
+        }
+    }
+
+    /// Writes Next MMU slots to the emulator state, empty when MMU is not
+    /// active
+    #[cfg(feature = "next")]
+    pub(crate) fn save_mmu_state(&self, writer: &mut StateWriter) {
+        if let Some(mmu) = &self.mmu {
+            writer.write_bytes(mmu);
+        }
+    }
+
+    /// Restores Next MMU slots, saved with [ZXMemory::save_mmu_state]
+    #[cfg(feature = "next")]
+    pub(crate) fn load_mmu_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        let mut mmu = [0; MMU_SLOTS];
+        reader.read_into(&mut mmu)?;
+        self.mmu = Some(mmu);
+        Ok(())
+    }
+
+    /// Appends zero-filled rom page for the external interface ROM, returns
+    /// index of the new page
+    pub fn add_rom_page(&mut self) -> u8 {
+        let page = self.rom.len() / PAGE_SIZE;
+        self.rom.resize(self.rom.len() + PAGE_SIZE, 0);
+        page as u8
     }
 
     /// Returns mutable slice to rom page
@@ -150,8 +277,86 @@ impl ZXMemory {
         &self.ram[shift..shift + PAGE_SIZE]
     }
 
+    /// Returns 16K slice of the physical ram bank regardless of the current
+    /// memory map. Fails if bank does not exist on the current machine
+    pub fn ram_bank(&self, bank: usize) -> Result<&[u8]> {
+        let range = self.ram_bank_range(bank)?;
+        Ok(&self.ram[range])
+    }
+
+    /// Mutable version of [ZXMemory::ram_bank]
+    pub fn ram_bank_mut(&mut self, bank: usize) -> Result<&mut [u8]> {
+        let range = self.ram_bank_range(bank)?;
+        Ok(&mut self.ram[range])
+    }
+
+    fn ram_bank_range(&self, bank: usize) -> Result<core::ops::Range<usize>> {
+        if bank >= self.ram.len() / PAGE_SIZE {
+            return Err(MemoryAccessError::RamBankOutOfRange.into());
+        }
+        let shift = bank * PAGE_SIZE;
+        Ok(shift..shift + PAGE_SIZE)
+    }
+
+    /// Writes rom, ram and memory map to the emulator state
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_blob(&self.rom);
+        writer.write_blob(&self.ram);
+        for page in self.map {
+            let (kind, index) = match page {
+                Page::Rom(index) => (0, index),
+                Page::Ram(index) => (1, index),
+                Page::Unmapped => (2, 0),
+            };
+            writer.write_u8(kind);
+            writer.write_u8(index);
+        }
+    }
+
+    /// Restores memory, saved with [ZXMemory::save_state]
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        let rom = reader.read_blob()?;
+        let ram = reader.read_blob()?;
+        if rom.len() != self.rom.len() || ram.len() != self.ram.len() {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        let mut map = self.map;
+        for page in map.iter_mut() {
+            let kind = reader.read_u8()?;
+            let index = reader.read_u8()?;
+            let (page_type, size) = match kind {
+                0 => (Page::Rom(index), self.rom.len()),
+                1 => (Page::Ram(index), self.ram.len()),
+                2 => (Page::Unmapped, PAGE_SIZE),
+                _ => return Err(StateLoadError::InvalidState.into()),
+            };
+            if (index as usize + 1) * PAGE_SIZE > size {
+                return Err(StateLoadError::InvalidState.into());
+            }
+            *page = page_type;
+        }
+        self.rom.copy_from_slice(rom);
+        self.ram.copy_from_slice(ram);
+        self.map = map;
+        Ok(())
+    }
+
     /// Calculates [Page] and local offset from memory address
-    fn paged_address(&self, addr: u16) -> (Page, usize) {
+    pub fn paged_address(&self, addr: u16) -> (Page, usize) {
+        #[cfg(feature = "next")]
+        if let Some(mmu) = &self.mmu {
+            let slot = addr as usize / MMU_SLOT_SIZE;
+            let page = mmu[slot];
+            if page == MMU_ROM_PAGE && slot < 2 {
+                return (self.map[0], addr as usize % PAGE_SIZE);
+            }
+            let bank = page as usize / 2;
+            if (bank + 1) * PAGE_SIZE > self.ram.len() {
+                return (Page::Unmapped, 0);
+            }
+            let offset = (page as usize % 2) * MMU_SLOT_SIZE + addr as usize % MMU_SLOT_SIZE;
+            return (Page::Ram(bank as u8), offset);
+        }
         let page = self.map[(addr as usize) / PAGE_SIZE];
         let offset = addr as usize % PAGE_SIZE;
         (page, offset)
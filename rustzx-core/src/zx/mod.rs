@@ -1,13 +1,20 @@
 //! Module with ZX Spectrum related things
 //! One of core platform-independent modules
 pub(crate) mod controller;
-pub(crate) mod events;
+pub(crate) mod disk;
+pub(crate) mod ear_input;
+#[cfg(feature = "heatmap")]
+pub mod heatmap;
+pub(crate) mod lightgun;
 pub(crate) mod memory;
+pub(crate) mod printer;
 #[cfg(feature = "embedded-roms")]
 pub(crate) mod roms;
 pub(crate) mod tape;
 
 pub mod constants;
+pub mod events;
+pub mod input;
 pub mod joy;
 pub mod keys;
 pub mod machine;
@@ -16,3 +23,7 @@ pub mod mouse;
 #[cfg(feature = "sound")]
 pub mod sound;
 pub mod video;
+
+pub use memory::Page;
+pub use printer::{PRINTER_ROW_DOTS, PRINTER_ROW_SIZE};
+pub use tape::{TapeBlockInfo, TapeFileKind, TapeHeader, TapePhase, TapePosition};
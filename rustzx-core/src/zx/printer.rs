@@ -0,0 +1,129 @@
+//! ZX Printer emulation.
+//!
+//! Printer is decoded on port 0xFB (address bit A2 reset). On read, bit 0 is
+//! the encoder pulse, bit 6 is reset when printer is connected and bit 7 is
+//! set when stylus is at the start of the line. On write, bit 7 burns the
+//! paper under the stylus and bit 2 stops the motor.
+//!
+//! Paper feed is modeled as a handshake instead of the motor timings: each
+//! encoder pulse, read by the cpu, allows exactly one stylus write, so ROM
+//! `COPY` and `LPRINT` routines produce the same output at any cpu speed.
+use crate::{
+    error::StateLoadError,
+    utils::state::{StateReader, StateWriter},
+    Result,
+};
+use alloc::vec::Vec;
+
+/// Count of dots in the single printed row
+pub const PRINTER_ROW_DOTS: usize = 256;
+/// Size of the single printed row, one bit per dot, the leftmost dot is
+/// the most significant bit of the first byte
+pub const PRINTER_ROW_SIZE: usize = PRINTER_ROW_DOTS / 8;
+
+const ENCODER_BIT: u8 = 0x01;
+const MOTOR_OFF_BIT: u8 = 0x04;
+const LINE_START_BIT: u8 = 0x80;
+const STYLUS_BIT: u8 = 0x80;
+// Bits 1-5 are not driven by the printer, bit 6 (not connected) is reset
+const UNUSED_BITS: u8 = 0x3E;
+
+pub(crate) struct ZXPrinter {
+    motor_on: bool,
+    // Encoder pulse was read and stylus write is expected
+    encoder_pulse: bool,
+    dot: usize,
+    row: [u8; PRINTER_ROW_SIZE],
+    output: Vec<u8>,
+}
+
+impl Default for ZXPrinter {
+    fn default() -> Self {
+        Self {
+            motor_on: false,
+            encoder_pulse: false,
+            dot: 0,
+            row: [0; PRINTER_ROW_SIZE],
+            output: Vec::new(),
+        }
+    }
+}
+
+impl ZXPrinter {
+    pub fn read(&mut self) -> u8 {
+        let mut value = UNUSED_BITS;
+        if self.motor_on {
+            self.encoder_pulse = true;
+            value |= ENCODER_BIT;
+        }
+        if self.dot == 0 {
+            value |= LINE_START_BIT;
+        }
+        value
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.motor_on = data & MOTOR_OFF_BIT == 0;
+        if !(self.motor_on && core::mem::take(&mut self.encoder_pulse)) {
+            return;
+        }
+        if data & STYLUS_BIT != 0 {
+            self.row[self.dot / 8] |= 0x80 >> (self.dot % 8);
+        }
+        self.dot += 1;
+        if self.dot == PRINTER_ROW_DOTS {
+            self.output.extend_from_slice(&self.row);
+            self.row = [0; PRINTER_ROW_SIZE];
+            self.dot = 0;
+        }
+    }
+
+    /// Returns rows, printed since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.output)
+    }
+
+    /// Writes motor and stylus position to the emulator state. Printed
+    /// rows are not the part of the state
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.motor_on);
+        writer.write_bool(self.encoder_pulse);
+        writer.write_usize(self.dot);
+        writer.write_bytes(&self.row);
+    }
+
+    /// Restores state, saved with [ZXPrinter::save_state]
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        self.motor_on = reader.read_bool()?;
+        self.encoder_pulse = reader.read_bool()?;
+        self.dot = reader.read_usize()?;
+        if self.dot >= PRINTER_ROW_DOTS {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        reader.read_into(&mut self.row)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stylus_writes_need_encoder_pulse() {
+        let mut printer = ZXPrinter::default();
+        // Motor is stopped, no encoder pulses
+        assert_eq!(printer.read(), UNUSED_BITS | LINE_START_BIT);
+        // Motor start is not a dot
+        printer.write(0x00);
+        for dot in 0..PRINTER_ROW_DOTS {
+            assert_ne!(printer.read() & ENCODER_BIT, 0);
+            printer.write(if dot % 2 == 0 { STYLUS_BIT } else { 0 });
+            // Writes without encoder pulse are ignored
+            printer.write(STYLUS_BIT);
+        }
+        printer.write(MOTOR_OFF_BIT);
+        assert_eq!(printer.take_output(), [0xAA; PRINTER_ROW_SIZE]);
+        assert!(printer.take_output().is_empty());
+    }
+}
@@ -1,4 +1,9 @@
-use crate::zx::sound::sample::{SampleGenerator, SoundSample};
+use crate::{
+    utils::state::{StateReader, StateWriter},
+    zx::sound::sample::{SampleGenerator, SoundSample},
+    Result,
+};
+use alloc::vec::Vec;
 use aym::{AyMode, AymBackend, AymPrecise, SoundChip};
 
 /// AY chip runs on the same frequency on 128K, 2+, 3+
@@ -13,6 +18,55 @@ pub enum ZXAYMode {
     ACB,
 }
 
+/// AY register write, recorded by `Emulator::start_ay_log`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AyWrite {
+    /// Index of the frame, counted from the start of the logging
+    pub frame: u32,
+    /// Clocks from the start of the frame
+    pub clocks: u32,
+    pub register: u8,
+    pub value: u8,
+}
+
+/// Log of the AY register writes, limited to `capacity` entries. Writes
+/// above the limit are dropped until the log is taken
+pub(crate) struct AyLog {
+    writes: Vec<AyWrite>,
+    capacity: usize,
+    frame: u32,
+}
+
+impl AyLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            writes: Vec::new(),
+            capacity,
+            frame: 0,
+        }
+    }
+
+    pub fn record(&mut self, clocks: usize, register: u8, value: u8) {
+        if self.writes.len() < self.capacity {
+            self.writes.push(AyWrite {
+                frame: self.frame,
+                clocks: clocks as u32,
+                register,
+                value,
+            });
+        }
+    }
+
+    pub fn new_frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Returns writes recorded since the last call
+    pub fn take(&mut self) -> Vec<AyWrite> {
+        core::mem::take(&mut self.writes)
+    }
+}
+
 pub(crate) struct ZXAyChip {
     ay: AymPrecise,
     current_reg: usize,
@@ -48,9 +102,34 @@ impl ZXAyChip {
         self.ay.write_register(reg as u8, data);
     }
 
+    /// Returns selected register and values of all registers
+    pub fn registers(&self) -> (usize, &[u8; 16]) {
+        (self.current_reg, &self.regs)
+    }
+
     pub fn read(&self) -> u8 {
         self.regs[self.current_reg]
     }
+
+    /// Writes registers to the emulator state. Internal generators state
+    /// (tone, noise and envelope counters) is not saved
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.current_reg as u8);
+        writer.write_bytes(&self.regs);
+    }
+
+    /// Restores registers, saved with [ZXAyChip::save_state]
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        let current_reg = reader.read_u8()?;
+        let mut regs = [0u8; 16];
+        reader.read_into(&mut regs)?;
+        for (reg, value) in regs.iter().enumerate() {
+            self.select_reg(reg as u8);
+            self.write(*value);
+        }
+        self.select_reg(current_reg);
+        Ok(())
+    }
 }
 
 impl SampleGenerator<f64> for ZXAyChip {
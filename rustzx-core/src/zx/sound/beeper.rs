@@ -1,10 +1,18 @@
-use crate::zx::sound::sample::{SampleGenerator, SoundSample};
+use crate::{
+    utils::state::{StateReader, StateWriter},
+    zx::sound::sample::{SampleGenerator, SoundSample},
+    Result,
+};
 
 /// Simple beeper implementation
 #[derive(Default)]
 pub(crate) struct ZXBeeper {
     mic: bool,
     ear: bool,
+    // Coefficient of single-pole low-pass filter, `None` when filter is disabled
+    lowpass_alpha: Option<f64>,
+    // Current filtered output level
+    level: f64,
 }
 
 impl ZXBeeper {
@@ -13,6 +21,29 @@ impl ZXBeeper {
         self.ear = ear;
         self.mic = mic;
     }
+
+    /// Enables RC low-pass filter with given `cutoff` frequency in Hz, which softens
+    /// beeper square wave the same way as the real hardware does. `None` disables
+    /// filtering
+    pub fn set_lowpass(&mut self, sample_rate: usize, cutoff: Option<usize>) {
+        self.lowpass_alpha = cutoff.map(|cutoff| {
+            let omega = 2.0 * core::f64::consts::PI * cutoff as f64 / sample_rate as f64;
+            1.0 - libm::exp(-omega)
+        });
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.ear);
+        writer.write_bool(self.mic);
+        writer.write_f64(self.level);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        self.ear = reader.read_bool()?;
+        self.mic = reader.read_bool()?;
+        self.level = reader.read_f64()?;
+        Ok(())
+    }
 }
 
 impl SampleGenerator<f64> for ZXBeeper {
@@ -35,6 +66,11 @@ impl SampleGenerator<f64> for ZXBeeper {
             sample += MIC_SAMPLE_FACTOR;
         }
 
-        SoundSample::new(sample, sample)
+        self.level = match self.lowpass_alpha {
+            Some(alpha) => self.level + alpha * (sample - self.level),
+            None => sample,
+        };
+
+        SoundSample::new(self.level, self.level)
     }
 }
@@ -1,10 +1,12 @@
 //! Module implements zx spectrum audio devices mixer
-use crate::zx::{
-    constants::FPS,
-    sound::{
+use crate::{
+    error::StateLoadError,
+    utils::state::{StateReader, StateWriter},
+    zx::sound::{
         beeper::ZXBeeper,
         sample::{SampleGenerator, SoundSample},
     },
+    Result,
 };
 
 // TODO(#117): Implement DC filtering for sound mixing
@@ -14,6 +16,26 @@ use crate::zx::sound::ay::{ZXAYMode, ZXAyChip};
 
 use alloc::collections::VecDeque;
 
+/// Dynamic range of the volume control in decibels
+const VOLUME_RANGE_DB: f64 = 40.0;
+/// Max gain of the single sound source, allows to boost it by 6 dB
+const MAX_SOURCE_VOLUME: f64 = 2.0;
+/// Max sound oversampling factor
+pub(crate) const MAX_OVERSAMPLING: usize = 4;
+/// Min length of the fade out, in `1 / FADE_OUT_RATE_DIVIDER` of second
+const FADE_OUT_RATE_DIVIDER: usize = 200;
+
+/// Maps volume setting in range `0..=100` to the amplitude factor in range
+/// `0.0..=1.0` using logarithmic curve, so equal setting steps are perceived
+/// as equal loudness steps
+pub(crate) fn volume_curve(volume: u8) -> f64 {
+    if volume == 0 {
+        return 0.0;
+    }
+    let volume = volume.min(100) as f64 / 100.0;
+    libm::pow(10.0, (volume - 1.0) * VOLUME_RANGE_DB / 20.0)
+}
+
 /// Main sound mixer.
 pub(crate) struct ZXMixer {
     /// direct access to beeper device
@@ -23,12 +45,42 @@ pub(crate) struct ZXMixer {
     pub ay: ZXAyChip,
     ring_buffer: VecDeque<SoundSample<f32>>,
     last_pos: usize,
+    /// Count of internal samples per output sample
+    oversampling: usize,
+    /// Internal samples of the next output sample, which were already
+    /// produced, and sum of their values. Sum could hold less samples than
+    /// produced when they were dropped on the buffer overflow
+    pending_subsamples: usize,
+    subsamples_sum: SoundSample<f64>,
+    subsamples_count: usize,
     last_sample: SoundSample<f32>,
     master_volume: f64,
+    beeper_volume: f64,
+    #[cfg(feature = "ay")]
+    ay_volume: f64,
     #[cfg(feature = "ay")]
     use_ay: bool,
     use_beeper: bool,
+    mono_output: bool,
     sample_rate: usize,
+    /// Frame lasts `clocks_frame / freq_cpu` seconds, so fractional samples
+    /// are counted in `1 / freq_cpu` units
+    clocks_frame: u64,
+    freq_cpu: u64,
+    /// Count of samples in the current frame. When frame length is not a
+    /// multiple of the sample length, frames alternate between the whole
+    /// count of samples and the next one, so the output does not drift from
+    /// the requested rate
+    frame_samples: usize,
+    /// Accumulated fractional part of the samples per frame, in `1 / freq_cpu`
+    /// units
+    frame_samples_remainder: u64,
+    /// Max count of samples, which could be buffered before host drains them
+    target_buffer_size: usize,
+    /// Count of samples pushed to the buffer since the drift tracking start
+    produced_samples: u64,
+    /// Ideal count of samples for the passed frames, in `1 / freq_cpu` units
+    ideal_samples: i64,
 }
 
 impl ZXMixer {
@@ -36,25 +88,100 @@ impl ZXMixer {
     /// # Arguments
     /// - `use_beeper` - process beeper or not
     /// - `use_ay` - process ay chip or not
+    /// - `oversampling` - count of internal samples per output sample, in
+    ///   range `1..=4`
+    /// - `clocks_frame`, `freq_cpu` - frame length and cpu frequency of the
+    ///   machine, which define the frame rate
     pub fn new(
         use_beeper: bool,
         #[cfg(feature = "ay")] use_ay: bool,
         #[cfg(feature = "ay")] ay_mode: ZXAYMode,
         sample_rate: usize,
+        oversampling: usize,
+        clocks_frame: usize,
+        freq_cpu: usize,
     ) -> ZXMixer {
-        ZXMixer {
+        let oversampling = oversampling.clamp(1, MAX_OVERSAMPLING);
+        let mut mixer = ZXMixer {
             beeper: ZXBeeper::default(),
             #[cfg(feature = "ay")]
-            ay: ZXAyChip::new(sample_rate, ay_mode),
+            ay: ZXAyChip::new(sample_rate * oversampling, ay_mode),
             ring_buffer: VecDeque::with_capacity(sample_rate),
             last_pos: 0,
+            oversampling,
+            pending_subsamples: 0,
+            subsamples_sum: SoundSample::new(0.0, 0.0),
+            subsamples_count: 0,
             last_sample: SoundSample::new(0.0, 0.0),
             master_volume: 0.5,
+            beeper_volume: 1.0,
+            #[cfg(feature = "ay")]
+            ay_volume: 1.0,
             #[cfg(feature = "ay")]
             use_ay,
             use_beeper,
+            mono_output: false,
             sample_rate,
-        }
+            clocks_frame: 0,
+            freq_cpu: 1,
+            frame_samples: 0,
+            frame_samples_remainder: 0,
+            target_buffer_size: 0,
+            produced_samples: 0,
+            ideal_samples: 0,
+        };
+        mixer.set_frame_timings(clocks_frame, freq_cpu);
+        mixer
+    }
+
+    /// Changes frame length and cpu frequency of the machine. Frame samples
+    /// counting starts over from the current position
+    pub fn set_frame_timings(&mut self, clocks_frame: usize, freq_cpu: usize) {
+        self.clocks_frame = clocks_frame as u64;
+        self.freq_cpu = (freq_cpu as u64).max(1);
+        let (whole, fraction) = self.samples_per_frame_ratio();
+        self.frame_samples = whole;
+        // Fractional part of the first frame is accounted right away, so
+        // after N frames exactly `N * rate * clocks_frame / freq_cpu` samples
+        // are produced
+        self.frame_samples_remainder = fraction;
+        self.target_buffer_size = self.target_buffer_size.max(self.max_samples_per_frame());
+        self.restart_drift_tracking();
+    }
+
+    /// Returns samples count per frame in `1 / freq_cpu` units
+    fn frame_sample_units(&self) -> u64 {
+        self.sample_rate as u64 * self.clocks_frame
+    }
+
+    /// Returns whole count of samples per frame and its fractional part in
+    /// `1 / freq_cpu` units
+    fn samples_per_frame_ratio(&self) -> (usize, u64) {
+        let units = self.frame_sample_units();
+        ((units / self.freq_cpu) as usize, units % self.freq_cpu)
+    }
+
+    /// Returns max count of samples which could be produced during a single frame
+    fn max_samples_per_frame(&self) -> usize {
+        self.frame_sample_units().div_ceil(self.freq_cpu) as usize
+    }
+
+    /// Changes count of samples which mixer buffers before host drains them.
+    /// Value is clamped to the length of one frame (default value), as lesser
+    /// buffer size would lead to dropped samples even with timely draining
+    pub fn set_target_latency_ms(&mut self, ms: usize) {
+        let samples = self.sample_rate * ms / 1000;
+        self.target_buffer_size = samples.max(self.max_samples_per_frame());
+    }
+
+    /// Returns count of samples, buffered at the moment
+    pub fn buffered_samples(&self) -> usize {
+        self.ring_buffer.len()
+    }
+
+    /// Drops samples, buffered after the first `len` ones
+    pub fn truncate(&mut self, len: usize) {
+        self.ring_buffer.truncate(len);
     }
 
     /// changes volume
@@ -64,64 +191,422 @@ impl ZXMixer {
         self.master_volume = volume;
     }
 
+    /// Changes beeper gain, applied before the master volume. Value is
+    /// clamped to `0.0..=2.0`, non-finite values are ignored
+    pub fn set_beeper_volume(&mut self, volume: f64) {
+        if volume.is_finite() {
+            self.beeper_volume = volume.clamp(0.0, MAX_SOURCE_VOLUME);
+        }
+    }
+
+    /// Changes AY gain, applied before the master volume. Value is clamped
+    /// to `0.0..=2.0`, non-finite values are ignored
+    #[cfg(feature = "ay")]
+    pub fn set_ay_volume(&mut self, volume: f64) {
+        if volume.is_finite() {
+            self.ay_volume = volume.clamp(0.0, MAX_SOURCE_VOLUME);
+        }
+    }
+
+    /// Sums stereo output to mono, both channels carry the same signal
+    pub fn set_mono_output(&mut self, value: bool) {
+        self.mono_output = value;
+    }
+
+    /// Enables beeper low-pass filter with given cutoff frequency in Hz
+    pub fn set_beeper_lowpass(&mut self, cutoff: Option<usize>) {
+        self.beeper
+            .set_lowpass(self.sample_rate * self.oversampling, cutoff);
+    }
+
     /// Updates internal buffer of mixer and fills it with new samples
     pub fn process(&mut self, current_time: f64) {
         // buffer overflow
-        if self.ring_buffer.len() >= self.samples_per_frame() {
+        if self.ring_buffer.len() >= self.target_buffer_size {
             return;
         }
-        // so at this moment we need to get new samples from devices
-        let curr_pos = self.sample_count_for_frame_fraction(current_time);
+        // so at this moment we need to get new samples from devices. With
+        // oversampling, positions are counted in internal samples
+        let curr_pos = self.subsample_count_for_frame_fraction(current_time);
+        let last_pos = self.last_pos * self.oversampling + self.pending_subsamples;
         // if we on same pos or frame passed then no new samples
-        if curr_pos <= self.last_pos {
+        if curr_pos <= last_pos {
             return;
         }
-        let sample_count = curr_pos - self.last_pos;
-        self.last_pos = curr_pos;
-        // fill buffer with new samples
-        for _ in 0..sample_count {
-            let sample = self.gen_sample();
-            self.ring_buffer.push_back(sample);
+        let free_space = (self.target_buffer_size - self.ring_buffer.len()) * self.oversampling
+            - self.pending_subsamples;
+        let subsample_count = (curr_pos - last_pos).min(free_space);
+        // fill buffer with new samples, each output sample is an average of
+        // its internal samples
+        for pos in last_pos + 1..=last_pos + subsample_count {
+            let subsample = self.gen_subsample();
+            self.subsamples_sum.mix(&subsample);
+            self.subsamples_count += 1;
+            if pos % self.oversampling == 0 {
+                let sample = self.take_subsamples_average();
+                self.ring_buffer.push_back(sample);
+                self.produced_samples += 1;
+            }
         }
+        self.last_pos = curr_pos / self.oversampling;
+        self.pending_subsamples = curr_pos % self.oversampling;
     }
 
     /// fills buffer to eng on new frame
     pub fn new_frame(&mut self) {
-        if self.ring_buffer.len() < self.samples_per_frame() {
-            for _ in self.ring_buffer.len()..self.samples_per_frame() {
-                self.ring_buffer.push_back(self.last_sample);
-            }
+        // pad samples which were not produced during the frame
+        let missing_samples = self.samples_per_frame().saturating_sub(self.last_pos);
+        let free_space =
+            self.target_buffer_size - self.ring_buffer.len().min(self.target_buffer_size);
+        for _ in 0..missing_samples.min(free_space) {
+            self.ring_buffer.push_back(self.last_sample);
         }
+        self.produced_samples += missing_samples.min(free_space) as u64;
+        self.ideal_samples += self.frame_sample_units() as i64;
         self.last_pos = 0;
+        self.clear_subsamples();
+        // Distribute fractional samples across frames
+        let (whole, fraction) = self.samples_per_frame_ratio();
+        self.frame_samples_remainder += fraction;
+        self.frame_samples = whole + (self.frame_samples_remainder / self.freq_cpu) as usize;
+        self.frame_samples_remainder %= self.freq_cpu;
+    }
+
+    /// Fades buffered samples out to silence, so output does not click when
+    /// sound generation stops. Short buffer is extended with the last sample
+    /// to make the fade at least 5 ms long, these samples are not counted as
+    /// produced. Padding produces silence afterwards
+    pub fn fade_out(&mut self) {
+        let fade_samples = self.sample_rate / FADE_OUT_RATE_DIVIDER;
+        while self.ring_buffer.len() < fade_samples {
+            self.ring_buffer.push_back(self.last_sample);
+        }
+        let len = self.ring_buffer.len() as f32;
+        for (index, sample) in self.ring_buffer.iter_mut().enumerate() {
+            sample.mul_eq(1.0 - (index + 1) as f32 / len);
+        }
+        self.last_sample = SoundSample::new(0.0, 0.0);
     }
 
     pub fn pop(&mut self) -> Option<SoundSample<f32>> {
         self.ring_buffer.pop_front()
     }
 
-    fn gen_sample(&mut self) -> SoundSample<f32> {
-        let mut master_float = if self.use_beeper {
-            self.beeper.gen_sample()
+    /// Moves buffered samples to `out`, returns count of moved samples
+    pub fn pop_into(&mut self, out: &mut [SoundSample<f32>]) -> usize {
+        let count = out.len().min(self.ring_buffer.len());
+        for (dest, sample) in out.iter_mut().zip(self.ring_buffer.drain(..count)) {
+            *dest = sample;
+        }
+        count
+    }
+
+    /// Fills `out` with exactly `out.len()` samples. When buffer runs dry, the
+    /// last produced sample is repeated
+    pub fn pop_exact(&mut self, out: &mut [SoundSample<f32>]) {
+        let count = self.pop_into(out);
+        out[count..].fill(self.last_sample);
+    }
+
+    /// Moves buffered samples to `out` as interleaved left/right values and
+    /// pads the rest with the last produced sample. Returns count of moved
+    /// stereo samples
+    pub fn pop_interleaved(&mut self, out: &mut [f32]) -> usize {
+        let count = (out.len() / 2).min(self.ring_buffer.len());
+        let mut frames = out.chunks_exact_mut(2);
+        // Drain goes first, so zip does not skip an extra output frame
+        for (sample, dest) in self.ring_buffer.drain(..count).zip(frames.by_ref()) {
+            dest.copy_from_slice(&[sample.left, sample.right]);
+        }
+        for dest in frames {
+            dest.copy_from_slice(&[self.last_sample.left, self.last_sample.right]);
+        }
+        // Odd-sized buffer could not hold the whole stereo sample
+        if let [.., last] = out.chunks_exact_mut(2).into_remainder() {
+            *last = self.last_sample.left;
+        }
+        count
+    }
+
+    /// Writes sound devices and current frame samples position to the
+    /// emulator state. Buffered samples are not the part of the state
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        self.beeper.save_state(writer);
+        #[cfg(feature = "ay")]
+        self.ay.save_state(writer);
+        writer.write_usize(self.last_pos);
+        writer.write_usize(self.frame_samples);
+        writer.write_u64(self.frame_samples_remainder);
+    }
+
+    /// Restores state, saved with [ZXMixer::save_state]
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        self.beeper.load_state(reader)?;
+        #[cfg(feature = "ay")]
+        self.ay.load_state(reader)?;
+        self.last_pos = reader.read_usize()?;
+        self.clear_subsamples();
+        self.frame_samples = reader.read_usize()?;
+        self.frame_samples_remainder = reader.read_u64()?;
+        if self.frame_samples_remainder >= self.freq_cpu {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        self.restart_drift_tracking();
+        Ok(())
+    }
+
+    /// Returns difference between count of samples produced since the drift
+    /// tracking start and the ideal count for the passed emulated time.
+    /// Positive value means audio is ahead of video. Samples, which were not
+    /// produced because of the buffer overflow, are counted as lag
+    pub fn drift(&self) -> i64 {
+        let ideal = self.ideal_samples.div_euclid(self.freq_cpu as i64) + self.last_pos as i64;
+        self.produced_samples as i64 - ideal
+    }
+
+    /// Starts drift tracking from the current frame position. Current frame
+    /// samples are counted as produced up to the current position
+    fn restart_drift_tracking(&mut self) {
+        let (whole, fraction) = self.samples_per_frame_ratio();
+        // Fractional samples accumulated before the current frame
+        self.ideal_samples = self.freq_cpu as i64 * (self.frame_samples as i64 - whole as i64)
+            + self.frame_samples_remainder as i64
+            - fraction as i64;
+        self.produced_samples = self.last_pos as u64;
+    }
+
+    fn clear_subsamples(&mut self) {
+        self.pending_subsamples = 0;
+        self.subsamples_sum = SoundSample::new(0.0, 0.0);
+        self.subsamples_count = 0;
+    }
+
+    /// Produces output sample from the accumulated internal samples. Box
+    /// filter is enough to suppress aliasing of the square waves edges
+    fn take_subsamples_average(&mut self) -> SoundSample<f32> {
+        let mut sample = self.subsamples_sum;
+        sample.mul_eq(1.0 / self.subsamples_count.max(1) as f64);
+        self.subsamples_sum = SoundSample::new(0.0, 0.0);
+        self.subsamples_count = 0;
+        if self.mono_output {
+            sample.downmix();
+        }
+        let master = sample.mul_eq(self.master_volume).into_f32();
+        self.last_sample = master;
+        master
+    }
+
+    /// Mixes sound sources at the internal sample rate
+    fn gen_subsample(&mut self) -> SoundSample<f64> {
+        let master_float = if self.use_beeper {
+            *self.beeper.gen_sample().mul_eq(self.beeper_volume)
         } else {
             SoundSample::new(0.0, 0.0)
         };
         #[cfg(feature = "ay")]
+        let mut master_float = master_float;
+        #[cfg(feature = "ay")]
         if self.use_ay {
-            master_float.mix(&self.ay.gen_sample());
+            master_float.mix(self.ay.gen_sample().mul_eq(self.ay_volume));
         }
-        let master = master_float.mul_eq(self.master_volume).into_f32();
-        self.last_sample = master;
-        master
+        master_float
     }
 
-    fn samples_per_frame(&self) -> usize {
-        self.sample_rate / FPS
+    /// Returns count of samples produced during the current frame. Frames
+    /// alternate between the whole count of samples per frame and the next
+    /// one when frame length is not a multiple of the sample length
+    pub fn samples_per_frame(&self) -> usize {
+        self.frame_samples
     }
 
-    fn sample_count_for_frame_fraction(&self, fraction: f64) -> usize {
+    fn subsample_count_for_frame_fraction(&self, fraction: f64) -> usize {
+        let subsamples = self.samples_per_frame() * self.oversampling;
         if fraction >= 1f64 {
-            return self.samples_per_frame();
+            return subsamples;
+        }
+        (subsamples as f64 * fraction) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn volume_curve_range() {
+        assert_eq!(volume_curve(0), 0.0);
+        assert_eq!(volume_curve(100), 1.0);
+        assert_eq!(volume_curve(255), 1.0);
+        // -20 dB at the middle of the range
+        assert!((volume_curve(50) - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn source_volume_non_finite() {
+        let mut m = mixer(44100);
+        m.set_beeper_volume(0.5);
+        m.set_beeper_volume(f64::NAN);
+        m.set_beeper_volume(f64::INFINITY);
+        assert_eq!(m.beeper_volume, 0.5);
+        m.set_beeper_volume(10.0);
+        assert_eq!(m.beeper_volume, MAX_SOURCE_VOLUME);
+        #[cfg(feature = "ay")]
+        {
+            m.set_ay_volume(f64::NEG_INFINITY);
+            assert_eq!(m.ay_volume, 1.0);
+        }
+    }
+
+    fn mixer(sample_rate: usize) -> ZXMixer {
+        oversampled_mixer(sample_rate, 1)
+    }
+
+    /// Frame timings of exactly 50 frames per second
+    const CLOCKS_FRAME: usize = 70000;
+    const FREQ_CPU: usize = 3_500_000;
+
+    fn oversampled_mixer(sample_rate: usize, oversampling: usize) -> ZXMixer {
+        ZXMixer::new(
+            true,
+            #[cfg(feature = "ay")]
+            false,
+            #[cfg(feature = "ay")]
+            ZXAYMode::Mono,
+            sample_rate,
+            oversampling,
+            CLOCKS_FRAME,
+            FREQ_CPU,
+        )
+    }
+
+    #[test]
+    fn no_drift_with_fractional_samples_per_frame() {
+        // 22050 / 50 = 441 samples per frame, 11025 / 50 = 220.5
+        for rate in [22050, 11025, 44123] {
+            let mut mixer = mixer(rate);
+            mixer.set_target_latency_ms(1000);
+            for frame in 1..=200u64 {
+                mixer.process(0.5);
+                assert_eq!(mixer.drift(), 0);
+                mixer.process(1.0);
+                mixer.new_frame();
+                mixer.truncate(0);
+                assert_eq!(mixer.drift(), 0);
+                assert_eq!(mixer.produced_samples, frame * rate as u64 / 50);
+            }
+        }
+    }
+
+    #[test]
+    fn no_drift_with_machine_frame_rate() {
+        // Pentagon frame: 71680 clocks at 3.5 MHz, ~48.8 frames per second
+        let mut mixer = mixer(44100);
+        mixer.set_frame_timings(71680, FREQ_CPU);
+        mixer.set_target_latency_ms(1000);
+        for _ in 0..125 {
+            mixer.process(1.0);
+            mixer.new_frame();
+            mixer.truncate(0);
+            assert_eq!(mixer.drift(), 0);
+        }
+        // 125 frames take 2.56 seconds
+        assert_eq!(mixer.produced_samples, 112896);
+    }
+
+    #[test]
+    fn overflow_counts_as_drift() {
+        let mut mixer = mixer(44100);
+        for _ in 0..3 {
+            mixer.process(1.0);
+            mixer.new_frame();
+        }
+        // Buffer holds a single frame, two frames were dropped
+        assert_eq!(mixer.drift(), -2 * 882);
+    }
+
+    #[test]
+    fn drift_is_restarted_on_state_load() {
+        let mut mixer = mixer(11025);
+        for _ in 0..3 {
+            mixer.process(1.0);
+            mixer.new_frame();
+            mixer.truncate(0);
+        }
+        mixer.process(0.3);
+        let mut writer = StateWriter::default();
+        mixer.save_state(&mut writer);
+
+        let mut restored = self::mixer(11025);
+        let data = writer.finish();
+        restored.load_state(&mut StateReader::new(&data)).unwrap();
+        assert_eq!(restored.drift(), 0);
+        for _ in 0..5 {
+            restored.process(1.0);
+            restored.new_frame();
+            restored.truncate(0);
+            assert_eq!(restored.drift(), 0);
+        }
+    }
+
+    #[test]
+    fn oversampling_keeps_sample_count() {
+        for oversampling in [1, 2, 4] {
+            let mut mixer = oversampled_mixer(11025, oversampling);
+            mixer.set_target_latency_ms(1000);
+            for _ in 0..10 {
+                for step in 1..=97 {
+                    mixer.process(step as f64 / 97.0);
+                }
+                mixer.new_frame();
+            }
+            // 220.5 samples per frame
+            assert_eq!(mixer.buffered_samples(), 2205, "{}", oversampling);
+            assert_eq!(mixer.drift(), 0);
         }
-        (self.samples_per_frame() as f64 * fraction) as usize
+    }
+
+    #[test]
+    fn oversampling_averages_edges() {
+        // Beeper is enabled in the middle of the first output sample, without
+        // oversampling the edge snaps to the sample start
+        let first_sample = |oversampling: usize| {
+            let mut mixer = oversampled_mixer(22050, oversampling);
+            mixer.set_target_latency_ms(1000);
+            let subsamples = (441 * oversampling) as f64;
+            mixer.process((oversampling as f64 / 2.0 + 0.25) / subsamples);
+            mixer.beeper.change_state(true, false);
+            mixer.process((oversampling as f64 + 0.25) / subsamples);
+            mixer.pop().unwrap().left
+        };
+        // Full beeper level is 0.5, master volume is 0.5
+        assert_eq!(first_sample(1), 0.25);
+        assert_eq!(first_sample(2), 0.125);
+        assert_eq!(first_sample(4), 0.125);
+    }
+
+    #[test]
+    fn oversampling_is_clamped() {
+        assert_eq!(oversampled_mixer(22050, 0).oversampling, 1);
+        assert_eq!(oversampled_mixer(22050, 16).oversampling, MAX_OVERSAMPLING);
+    }
+
+    #[test]
+    fn fade_out_ends_with_silence() {
+        let mut mixer = mixer(22050);
+        mixer.beeper.change_state(true, false);
+        mixer.process(0.1);
+        assert_eq!(mixer.buffered_samples(), 44);
+        mixer.fade_out();
+        // Buffer is extended to 5 ms
+        assert_eq!(mixer.buffered_samples(), 110);
+        let samples: Vec<f32> = core::iter::from_fn(|| mixer.pop())
+            .map(|s| s.left)
+            .collect();
+        assert!(samples.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(*samples.last().unwrap(), 0.0);
+        let mut padding = [SoundSample::new(1.0, 1.0); 4];
+        mixer.pop_exact(&mut padding);
+        assert!(padding.iter().all(|s| s.left == 0.0 && s.right == 0.0));
     }
 }
@@ -56,6 +56,14 @@ impl SoundSample<f64> {
         self
     }
 
+    /// Sums both channels to mono, keeping the same value in both channels
+    pub fn downmix(&mut self) -> &mut Self {
+        let mono = (self.left + self.right) / 2.0;
+        self.left = mono;
+        self.right = mono;
+        self
+    }
+
     /// transform into f32
     pub fn into_f32(self) -> SoundSample<f32> {
         SoundSample {
@@ -27,6 +27,18 @@ impl TapeImpl for Empty {
 
     fn play(&mut self) {}
 
+    fn is_playing(&self) -> bool {
+        false
+    }
+
+    fn pause(&mut self) {}
+
+    fn resume(&mut self) {}
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
     fn rewind(&mut self) -> Result<()> {
         Ok(())
     }
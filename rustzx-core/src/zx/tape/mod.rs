@@ -1,13 +1,23 @@
 mod empty;
+mod recorder;
 mod tap;
 
 pub use empty::Empty;
+pub use recorder::TapeRecorder;
 pub use tap::Tap;
 
 use crate::{
-    host::{LoadableAsset, SeekableAsset},
+    error::{IoError, TapeLoadError},
+    host::{LoadableAsset, SeekFrom, SeekableAsset},
     Result,
 };
+#[cfg(feature = "compression")]
+use crate::{
+    host::BufferCursor,
+    utils::{gzip, zip},
+};
+#[cfg(feature = "compression")]
+use alloc::vec::Vec;
 
 use enum_dispatch::enum_dispatch;
 
@@ -24,6 +34,174 @@ impl<A: LoadableAsset + SeekableAsset> Default for ZXTape<A> {
     }
 }
 
+/// Tape asset which is either provided by the host directly or unpacked
+/// into memory when compressed tape file was detected
+pub enum TapeAsset<A: LoadableAsset + SeekableAsset> {
+    Host(A),
+    #[cfg(feature = "compression")]
+    Unpacked(BufferCursor<Vec<u8>>),
+}
+
+impl<A: LoadableAsset + SeekableAsset> TapeAsset<A> {
+    #[cfg(feature = "compression")]
+    pub fn new(mut asset: A) -> Result<Self> {
+        if gzip::is_gzip(&mut asset)? {
+            return Ok(Self::Unpacked(BufferCursor::new(gzip::unpack(asset)?)));
+        }
+        if zip::is_zip(&mut asset)? {
+            let unpacked = zip::unpack(asset, &["tap"])?;
+            return Ok(Self::Unpacked(BufferCursor::new(unpacked)));
+        }
+        Ok(Self::Host(asset))
+    }
+
+    #[cfg(not(feature = "compression"))]
+    pub fn new(asset: A) -> Result<Self> {
+        Ok(Self::Host(asset))
+    }
+}
+
+impl<A: LoadableAsset + SeekableAsset> LoadableAsset for TapeAsset<A> {
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, IoError> {
+        match self {
+            Self::Host(asset) => asset.read(buf),
+            #[cfg(feature = "compression")]
+            Self::Unpacked(asset) => asset.read(buf),
+        }
+    }
+}
+
+impl<A: LoadableAsset + SeekableAsset> SeekableAsset for TapeAsset<A> {
+    fn seek(&mut self, pos: SeekFrom) -> core::result::Result<usize, IoError> {
+        match self {
+            Self::Host(asset) => asset.seek(pos),
+            #[cfg(feature = "compression")]
+            Self::Unpacked(asset) => asset.seek(pos),
+        }
+    }
+}
+
+/// Current position on the tape
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TapePosition {
+    /// Index of the block which is currently played or will be played next
+    pub block_index: usize,
+    /// Total count of blocks on the tape
+    pub blocks_count: usize,
+    /// Progress of the current block in range `0.0..=1.0`
+    pub block_progress: f32,
+}
+
+/// Size of the standard ROM header block, including flag and checksum
+const HEADER_BLOCK_SIZE: usize = 19;
+
+/// Type of the file, described by the standard ROM header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeFileKind {
+    /// BASIC program
+    Program,
+    /// Numeric array
+    NumberArray,
+    /// Character array
+    CharArray,
+    /// Bytes (machine code or screen)
+    Code,
+}
+
+/// Standard ROM header, which describes the following data block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeHeader {
+    pub kind: TapeFileKind,
+    /// File name in the ZX Spectrum charset, padded with spaces
+    pub name: [u8; 10],
+    /// Length of the data in the following block
+    pub length: u16,
+    /// Autostart line for programs (32768 and above if not set), start
+    /// address for code, array name for arrays
+    pub param1: u16,
+    /// Length of the program without variables, unused for other kinds
+    pub param2: u16,
+}
+
+impl TapeHeader {
+    /// Parses header from the tape block data, including flag and checksum
+    /// bytes. Returns `None` if block is not a standard header
+    fn parse(block: &[u8]) -> Option<Self> {
+        if block.len() != HEADER_BLOCK_SIZE || block[0] != 0x00 {
+            return None;
+        }
+        let kind = match block[1] {
+            0 => TapeFileKind::Program,
+            1 => TapeFileKind::NumberArray,
+            2 => TapeFileKind::CharArray,
+            3 => TapeFileKind::Code,
+            _ => return None,
+        };
+        let word = |offset: usize| u16::from_le_bytes([block[offset], block[offset + 1]]);
+        let mut name = [0u8; 10];
+        name.copy_from_slice(&block[2..12]);
+        Some(Self {
+            kind,
+            name,
+            length: word(12),
+            param1: word(14),
+            param2: word(16),
+        })
+    }
+
+    /// Returns start address of the code file
+    pub fn start_address(&self) -> Option<u16> {
+        (self.kind == TapeFileKind::Code).then_some(self.param1)
+    }
+
+    /// Returns autostart line of the program, if it is set
+    pub fn autostart_line(&self) -> Option<u16> {
+        (self.kind == TapeFileKind::Program && self.param1 < 0x8000).then_some(self.param1)
+    }
+}
+
+/// Description of the tape block, e.g. for the tape browser of multi-load
+/// games
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeBlockInfo {
+    /// Size of the block, including flag and checksum bytes
+    pub size: usize,
+    /// First byte of the block: 0x00 for headers, 0xFF for standard data
+    /// blocks. `None` for empty blocks
+    pub flag: Option<u8>,
+    /// Parsed header, `None` for data and non-standard blocks
+    pub header: Option<TapeHeader>,
+}
+
+impl TapeBlockInfo {
+    /// Builds block info from its size and the first bytes of the block
+    fn new(size: usize, head: &[u8]) -> Self {
+        Self {
+            size,
+            flag: head.first().copied(),
+            header: TapeHeader::parse(head),
+        }
+    }
+}
+
+/// Signal, which is currently played by the tape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TapePhase {
+    /// Tape is stopped or not inserted
+    #[default]
+    Stopped,
+    /// Tape playback is frozen, see `Emulator::tape_pause`
+    Paused,
+    /// Pilot tone at the start of the block
+    Pilot,
+    /// Sync pulses between pilot tone and data
+    Sync,
+    /// Data bits of the block
+    Data,
+    /// Silence between blocks
+    Gap,
+}
+
 #[enum_dispatch]
 pub trait TapeImpl {
     fn can_fast_load(&self) -> bool;
@@ -37,6 +215,33 @@ pub trait TapeImpl {
     fn process_clocks(&mut self, clocks: usize) -> Result<()>;
     fn stop(&mut self);
     fn play(&mut self);
+    /// Returns true if tape drives the `ear` input (playing or paused)
+    fn is_playing(&self) -> bool;
+    /// Freezes tape playback, keeping current pulse phase and `ear` level
+    fn pause(&mut self);
+    /// Continues tape playback from the exact point where it was paused
+    fn resume(&mut self);
+    fn is_paused(&self) -> bool;
+    /// Returns true once after the last byte of the block has been played
+    fn take_block_finished(&mut self) -> bool {
+        false
+    }
     /// Rewinds tape content to the beginning
     fn rewind(&mut self) -> Result<()>;
+    /// Returns current phase of the tape signal
+    fn phase(&self) -> TapePhase {
+        TapePhase::Stopped
+    }
+    /// Returns current tape position
+    fn position(&self) -> TapePosition {
+        TapePosition::default()
+    }
+    /// Returns description of the block with given `index`
+    fn block_info(&self, _index: usize) -> Result<TapeBlockInfo> {
+        Err(TapeLoadError::BlockOutOfRange.into())
+    }
+    /// Moves tape to the beginning of the block with given `index`
+    fn seek_block(&mut self, _index: usize) -> Result<()> {
+        Err(TapeLoadError::BlockOutOfRange.into())
+    }
 }
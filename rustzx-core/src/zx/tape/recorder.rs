@@ -0,0 +1,136 @@
+//! Tape recorder, which reconstructs standard ROM-format tape blocks from
+//! MIC output pulses and stores them in TAP format
+use crate::zx::tape::tap::{BIT_ONE_LENGTH, BIT_ZERO_LENGTH, PILOT_LENGTH, SYNC1_LENGTH};
+use alloc::vec::Vec;
+
+/// Allowed deviation of pilot pulse length
+const PILOT_TOLERANCE: usize = PILOT_LENGTH / 5;
+/// Minimal count of pilot pulses to treat tone as a block start
+const PILOT_PULSES_MIN: usize = 256;
+/// Sync pulses are shorter than pilot and longer than half of the sync pulse
+const SYNC_MIN_LENGTH: usize = SYNC1_LENGTH / 2;
+const SYNC_MAX_LENGTH: usize = (SYNC1_LENGTH + PILOT_LENGTH) / 2;
+/// Full bit (two pulses) length threshold between zero and one bits
+const BIT_THRESHOLD: usize = BIT_ZERO_LENGTH + BIT_ONE_LENGTH;
+/// Pulses longer than this are treated as the end of data block
+const DATA_PULSE_MAX_LENGTH: usize = (BIT_ONE_LENGTH + PILOT_LENGTH) / 2;
+
+#[derive(Clone, Copy)]
+enum RecorderState {
+    Idle,
+    Pilot { pulses: usize },
+    Sync,
+    Data { first_half: Option<usize> },
+}
+
+pub struct TapeRecorder {
+    state: RecorderState,
+    mic: bool,
+    pulse_clocks: usize,
+    block: Vec<u8>,
+    current_byte: u8,
+    bits_in_byte: u8,
+    tap: Vec<u8>,
+}
+
+impl Default for TapeRecorder {
+    fn default() -> Self {
+        Self {
+            state: RecorderState::Idle,
+            mic: false,
+            pulse_clocks: 0,
+            block: Vec::new(),
+            current_byte: 0,
+            bits_in_byte: 0,
+            tap: Vec::new(),
+        }
+    }
+}
+
+impl TapeRecorder {
+    /// Perform recorder timing update within `clocks` time
+    pub fn process_clocks(&mut self, clocks: usize) {
+        self.pulse_clocks = self.pulse_clocks.saturating_add(clocks);
+    }
+
+    /// Changes MIC output state. Each state change finishes the current pulse
+    pub fn set_mic(&mut self, mic: bool) {
+        if mic == self.mic {
+            return;
+        }
+        self.mic = mic;
+        let pulse = self.pulse_clocks;
+        self.pulse_clocks = 0;
+        self.process_pulse(pulse);
+    }
+
+    /// Finishes recording and returns recorded blocks in TAP format
+    pub fn finish(mut self) -> Vec<u8> {
+        self.finish_block();
+        self.tap
+    }
+
+    fn process_pulse(&mut self, pulse: usize) {
+        let is_pilot = pulse.abs_diff(PILOT_LENGTH) <= PILOT_TOLERANCE;
+        self.state = match self.state {
+            RecorderState::Idle | RecorderState::Pilot { .. } if is_pilot => {
+                let pulses = match self.state {
+                    RecorderState::Pilot { pulses } => pulses + 1,
+                    _ => 1,
+                };
+                RecorderState::Pilot { pulses }
+            }
+            RecorderState::Pilot { pulses }
+                if pulses >= PILOT_PULSES_MIN
+                    && (SYNC_MIN_LENGTH..SYNC_MAX_LENGTH).contains(&pulse) =>
+            {
+                RecorderState::Sync
+            }
+            RecorderState::Sync if (SYNC_MIN_LENGTH..SYNC_MAX_LENGTH).contains(&pulse) => {
+                RecorderState::Data { first_half: None }
+            }
+            RecorderState::Data { .. } if pulse > DATA_PULSE_MAX_LENGTH => {
+                self.finish_block();
+                // Long pulse could be the beginning of the next block pilot tone
+                if is_pilot {
+                    RecorderState::Pilot { pulses: 1 }
+                } else {
+                    RecorderState::Idle
+                }
+            }
+            RecorderState::Data { first_half: None } => RecorderState::Data {
+                first_half: Some(pulse),
+            },
+            RecorderState::Data {
+                first_half: Some(first_half),
+            } => {
+                self.push_bit(first_half + pulse > BIT_THRESHOLD);
+                RecorderState::Data { first_half: None }
+            }
+            _ => RecorderState::Idle,
+        };
+    }
+
+    fn push_bit(&mut self, bit: bool) {
+        self.current_byte = (self.current_byte << 1) | bit as u8;
+        self.bits_in_byte += 1;
+        if self.bits_in_byte == 8 {
+            self.block.push(self.current_byte);
+            self.current_byte = 0;
+            self.bits_in_byte = 0;
+        }
+    }
+
+    /// Appends collected block to the TAP data. Incomplete trailing
+    /// byte is discarded
+    fn finish_block(&mut self) {
+        if !self.block.is_empty() {
+            let size = self.block.len() as u16;
+            self.tap.extend_from_slice(&size.to_le_bytes());
+            self.tap.append(&mut self.block);
+        }
+        self.current_byte = 0;
+        self.bits_in_byte = 0;
+        self.state = RecorderState::Idle;
+    }
+}
@@ -1,17 +1,19 @@
 use crate::{
-    error::TapeLoadError,
+    error::{StateLoadError, TapeLoadError},
     host::{LoadableAsset, SeekFrom, SeekableAsset},
-    zx::tape::TapeImpl,
+    utils::state::{StateReader, StateWriter},
+    zx::tape::{TapeBlockInfo, TapeImpl, TapePhase, TapePosition, HEADER_BLOCK_SIZE},
     Result,
 };
+use alloc::vec::Vec;
 
-const PILOT_LENGTH: usize = 2168;
+pub(super) const PILOT_LENGTH: usize = 2168;
 const PILOT_PULSES_HEADER: usize = 8063;
 const PILOT_PULSES_DATA: usize = 3223;
-const SYNC1_LENGTH: usize = 667;
+pub(super) const SYNC1_LENGTH: usize = 667;
 const SYNC2_LENGTH: usize = 735;
-const BIT_ONE_LENGTH: usize = 1710;
-const BIT_ZERO_LENGTH: usize = 855;
+pub(super) const BIT_ONE_LENGTH: usize = 1710;
+pub(super) const BIT_ZERO_LENGTH: usize = 855;
 const PAUSE_LENGTH: usize = 3_500_000;
 const BUFFER_SIZE: usize = 128;
 
@@ -27,6 +29,48 @@ enum TapeState {
     Pause,
 }
 
+impl TapeState {
+    fn save_state(&self, writer: &mut StateWriter) {
+        let (tag, value, mask) = match *self {
+            Self::Stop => (0, 0, 0),
+            Self::Play => (1, 0, 0),
+            Self::Pilot { pulses_left } => (2, pulses_left, 0),
+            Self::Sync => (3, 0, 0),
+            Self::NextByte => (4, 0, 0),
+            Self::NextBit { mask } => (5, 0, mask),
+            Self::BitHalf {
+                half_bit_delay,
+                mask,
+            } => (6, half_bit_delay, mask),
+            Self::Pause => (7, 0, 0),
+        };
+        writer.write_u8(tag);
+        writer.write_usize(value);
+        writer.write_u8(mask);
+    }
+
+    fn load_state(reader: &mut StateReader) -> Result<Self> {
+        let tag = reader.read_u8()?;
+        let value = reader.read_usize()?;
+        let mask = reader.read_u8()?;
+        let state = match tag {
+            0 => Self::Stop,
+            1 => Self::Play,
+            2 => Self::Pilot { pulses_left: value },
+            3 => Self::Sync,
+            4 => Self::NextByte,
+            5 => Self::NextBit { mask },
+            6 => Self::BitHalf {
+                half_bit_delay: value,
+                mask,
+            },
+            7 => Self::Pause,
+            _ => return Err(StateLoadError::InvalidState.into()),
+        };
+        Ok(state)
+    }
+}
+
 pub struct Tap<A: LoadableAsset + SeekableAsset> {
     asset: A,
     state: TapeState,
@@ -36,6 +80,15 @@ pub struct Tap<A: LoadableAsset + SeekableAsset> {
     block_bytes_read: usize,
     current_block_size: Option<usize>,
     tape_ended: bool,
+    paused: bool,
+    // Set when data of the block has been played, until taken by the controller
+    block_finished: bool,
+    // Offsets of the blocks length fields in the asset
+    block_offsets: Vec<usize>,
+    block_infos: Vec<TapeBlockInfo>,
+    // Index of the current block, or of the next block if `block_started` is false
+    block_index: usize,
+    block_started: bool,
     // Non-fastload related fields
     curr_bit: bool,
     curr_byte: u8,
@@ -43,7 +96,8 @@ pub struct Tap<A: LoadableAsset + SeekableAsset> {
 }
 
 impl<A: LoadableAsset + SeekableAsset> Tap<A> {
-    pub fn from_asset(asset: A) -> Result<Self> {
+    pub fn from_asset(mut asset: A) -> Result<Self> {
+        let (block_offsets, block_infos) = Self::scan_blocks(&mut asset)?;
         let tap = Self {
             prev_state: TapeState::Stop,
             state: TapeState::Stop,
@@ -56,14 +110,147 @@ impl<A: LoadableAsset + SeekableAsset> Tap<A> {
             delay: 0,
             asset,
             tape_ended: false,
+            paused: false,
+            block_finished: false,
+            block_offsets,
+            block_infos,
+            block_index: 0,
+            block_started: false,
         };
         Ok(tap)
     }
+
+    /// Returns offsets and descriptions of all tape blocks
+    fn scan_blocks(asset: &mut A) -> Result<(Vec<usize>, Vec<TapeBlockInfo>)> {
+        let mut offsets = Vec::new();
+        let mut infos = Vec::new();
+        let mut offset = 0;
+        let mut block_size_buffer = [0u8; 2];
+        let mut head = [0u8; HEADER_BLOCK_SIZE];
+        while asset.seek(SeekFrom::Start(offset)).is_ok()
+            && asset.read_exact(&mut block_size_buffer).is_ok()
+        {
+            let size = u16::from_le_bytes(block_size_buffer) as usize;
+            // Only the header-sized blocks are read, truncated last block
+            // is described without header
+            let head_size = if size == HEADER_BLOCK_SIZE {
+                size
+            } else {
+                size.min(1)
+            };
+            let head = match asset.read_exact(&mut head[..head_size]) {
+                Ok(()) => &head[..head_size],
+                Err(_) => &[],
+            };
+            offsets.push(offset);
+            infos.push(TapeBlockInfo::new(size, head));
+            offset += 2 + size;
+        }
+        asset.seek(SeekFrom::Start(0))?;
+        Ok((offsets, infos))
+    }
+
+    /// Writes playback state and position to the emulator state. Tape
+    /// content is not saved, state could be restored only with the same tape
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_usize(self.block_offsets.len());
+        self.state.save_state(writer);
+        self.prev_state.save_state(writer);
+        writer.write_bool(self.paused);
+        writer.write_bool(self.tape_ended);
+        writer.write_usize(self.block_index);
+        writer.write_bool(self.block_started);
+        writer.write_bool(self.current_block_size.is_some());
+        writer.write_usize(self.current_block_size.unwrap_or(0));
+        writer.write_usize(self.block_bytes_read);
+        writer.write_usize(self.buffer_offset);
+        writer.write_bool(self.curr_bit);
+        writer.write_u8(self.curr_byte);
+        writer.write_usize(self.delay);
+    }
+
+    /// Restores state, saved with [Tap::save_state]. Returns false and leaves
+    /// tape intact if the state was saved with another tape inserted
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<bool> {
+        if reader.read_usize()? != self.block_offsets.len() {
+            return Ok(false);
+        }
+        let state = TapeState::load_state(reader)?;
+        let prev_state = TapeState::load_state(reader)?;
+        let paused = reader.read_bool()?;
+        let tape_ended = reader.read_bool()?;
+        let block_index = reader.read_usize()?;
+        let block_started = reader.read_bool()?;
+        let has_block = reader.read_bool()?;
+        let block_size = reader.read_usize()?;
+        let current_block_size = has_block.then_some(block_size);
+        let block_bytes_read = reader.read_usize()?;
+        let buffer_offset = reader.read_usize()?;
+        let curr_bit = reader.read_bool()?;
+        let curr_byte = reader.read_u8()?;
+        let delay = reader.read_usize()?;
+
+        // Move asset to the position, from which tape reading will continue
+        let block_offset = self.block_offsets.get(block_index).copied();
+        match (block_offset, current_block_size) {
+            _ if tape_ended => {
+                self.asset.seek(SeekFrom::End(0))?;
+            }
+            (Some(offset), Some(size)) if block_started => {
+                if buffer_offset > size {
+                    return Err(StateLoadError::InvalidState.into());
+                }
+                self.asset
+                    .seek(SeekFrom::Start(offset + 2 + buffer_offset))?;
+                let bytes_to_read = (size - buffer_offset).min(BUFFER_SIZE);
+                self.asset.read_exact(&mut self.buffer[0..bytes_to_read])?;
+            }
+            (Some(_), None) if block_started => {
+                let next_offset = self.block_offsets.get(block_index + 1);
+                match next_offset {
+                    Some(offset) => self.asset.seek(SeekFrom::Start(*offset))?,
+                    None => self.asset.seek(SeekFrom::End(0))?,
+                };
+            }
+            (Some(offset), _) => {
+                self.asset.seek(SeekFrom::Start(offset))?;
+            }
+            // Empty tape
+            (None, _) if block_index == 0 && !block_started => {}
+            (None, _) => return Err(StateLoadError::InvalidState.into()),
+        }
+
+        self.state = state;
+        self.prev_state = prev_state;
+        self.paused = paused;
+        self.tape_ended = tape_ended;
+        self.block_index = block_index;
+        self.block_started = block_started;
+        self.current_block_size = current_block_size;
+        self.block_bytes_read = block_bytes_read;
+        self.buffer_offset = buffer_offset;
+        self.curr_bit = curr_bit;
+        self.curr_byte = curr_byte;
+        self.delay = delay;
+        Ok(true)
+    }
+
+    /// Resets block reading state, asset position is not changed
+    fn reset_block_state(&mut self) {
+        self.curr_bit = false;
+        self.curr_byte = 0x00;
+        self.block_bytes_read = 0;
+        self.buffer_offset = 0;
+        self.current_block_size = None;
+        self.delay = 0;
+        self.tape_ended = false;
+        self.block_started = false;
+    }
 }
 
 impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
     fn can_fast_load(&self) -> bool {
-        self.state == TapeState::Stop
+        self.state == TapeState::Stop && !self.paused
     }
 
     fn next_block_byte(&mut self) -> Result<Option<u8>> {
@@ -113,10 +300,16 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
 
         let mut block_size_buffer = [0u8; 2];
         if self.asset.read_exact(&mut block_size_buffer).is_err() {
+            log_debug!("Tape ended");
             self.tape_ended = true;
             return Ok(false);
         }
         let block_size = u16::from_le_bytes(block_size_buffer) as usize;
+        if self.block_started {
+            self.block_index += 1;
+        }
+        self.block_started = true;
+        log_debug!("Tape block started, size: {} bytes", block_size);
         let block_bytes_to_read = block_size.min(BUFFER_SIZE);
         self.asset
             .read_exact(&mut self.buffer[0..block_bytes_to_read])?;
@@ -133,7 +326,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
     }
 
     fn process_clocks(&mut self, clocks: usize) -> Result<()> {
-        if self.state == TapeState::Stop {
+        if self.state == TapeState::Stop || self.paused {
             return Ok(());
         }
 
@@ -198,6 +391,7 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
                         self.curr_byte = byte;
                         TapeState::NextBit { mask: 0x80 }
                     } else {
+                        self.block_finished = true;
                         TapeState::Pause
                     }
                 }
@@ -261,15 +455,186 @@ impl<A: LoadableAsset + SeekableAsset> TapeImpl for Tap<A> {
         }
     }
 
+    fn is_playing(&self) -> bool {
+        self.state != TapeState::Stop
+    }
+
+    fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn take_block_finished(&mut self) -> bool {
+        core::mem::take(&mut self.block_finished)
+    }
+
     fn rewind(&mut self) -> Result<()> {
-        self.curr_bit = false;
-        self.curr_byte = 0x00;
-        self.block_bytes_read = 0;
-        self.buffer_offset = 0;
-        self.current_block_size = None;
-        self.delay = 0;
+        self.reset_block_state();
+        self.block_index = 0;
         self.asset.seek(SeekFrom::Start(0))?;
-        self.tape_ended = false;
         Ok(())
     }
+
+    fn phase(&self) -> TapePhase {
+        match self.state {
+            TapeState::Stop => TapePhase::Stopped,
+            _ if self.paused => TapePhase::Paused,
+            TapeState::Pilot { .. } => TapePhase::Pilot,
+            TapeState::Sync => TapePhase::Sync,
+            TapeState::NextByte | TapeState::NextBit { .. } | TapeState::BitHalf { .. } => {
+                TapePhase::Data
+            }
+            // Silence is played in `Play` state, before the next block
+            TapeState::Play | TapeState::Pause => TapePhase::Gap,
+        }
+    }
+
+    fn position(&self) -> TapePosition {
+        let block_progress = match self.current_block_size {
+            Some(size) if size > 0 => self.block_bytes_read as f32 / size as f32,
+            _ if self.block_started => 1.0,
+            _ => 0.0,
+        };
+        TapePosition {
+            block_index: self.block_index,
+            blocks_count: self.block_offsets.len(),
+            block_progress,
+        }
+    }
+
+    fn block_info(&self, index: usize) -> Result<TapeBlockInfo> {
+        let info = self
+            .block_infos
+            .get(index)
+            .ok_or(TapeLoadError::BlockOutOfRange)?;
+        Ok(*info)
+    }
+
+    fn seek_block(&mut self, index: usize) -> Result<()> {
+        let offset = *self
+            .block_offsets
+            .get(index)
+            .ok_or(TapeLoadError::BlockOutOfRange)?;
+        self.reset_block_state();
+        self.block_index = index;
+        self.asset.seek(SeekFrom::Start(offset))?;
+        // Start new block from the pilot tone
+        if self.state != TapeState::Stop {
+            self.state = TapeState::Play;
+        } else {
+            self.prev_state = TapeState::Stop;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::BufferCursor;
+
+    const CLOCKS_STEP: usize = 16;
+    // Somewhere in the middle of the data bits of the first block
+    const PAUSE_AT: usize = 7_000_000;
+
+    fn test_tap() -> Tap<BufferCursor<Vec<u8>>> {
+        let data = alloc::vec![0x04, 0x00, 0xFF, 0xAA, 0x55, 0x00];
+        Tap::from_asset(BufferCursor::new(data)).unwrap()
+    }
+
+    /// Returns clocks of the first `count` edges after `PAUSE_AT`
+    fn collect_edges(
+        tap: &mut Tap<BufferCursor<Vec<u8>>>,
+        count: usize,
+        pause: bool,
+    ) -> Vec<usize> {
+        let mut edges = Vec::new();
+        let mut clocks = 0;
+        let mut bit = tap.current_bit();
+        while edges.len() < count {
+            if pause && clocks == PAUSE_AT {
+                tap.pause();
+                for _ in 0..10_000 {
+                    tap.process_clocks(CLOCKS_STEP).unwrap();
+                    assert_eq!(tap.current_bit(), bit);
+                }
+                tap.resume();
+            }
+            tap.process_clocks(CLOCKS_STEP).unwrap();
+            clocks += CLOCKS_STEP;
+            if tap.current_bit() != bit {
+                bit = tap.current_bit();
+                if clocks > PAUSE_AT {
+                    edges.push(clocks);
+                }
+            }
+        }
+        edges
+    }
+
+    #[test]
+    fn pause_preserves_edge_timing() {
+        let mut reference = test_tap();
+        reference.play();
+        let expected = collect_edges(&mut reference, 32, false);
+
+        let mut tap = test_tap();
+        tap.play();
+        let actual = collect_edges(&mut tap, 32, true);
+        assert!(!tap.is_paused());
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn block_finished_after_last_byte() {
+        let mut tap = test_tap();
+        tap.play();
+        let mut clocks = 0;
+        while !tap.take_block_finished() {
+            tap.process_clocks(CLOCKS_STEP).unwrap();
+            clocks += CLOCKS_STEP;
+        }
+        assert_eq!(tap.position().block_progress, 1.0);
+        // Pilot of the data block, sync pulses and 4 bytes of data
+        let expected_min = PILOT_LENGTH * 3223 + SYNC1_LENGTH + SYNC2_LENGTH;
+        assert!(clocks > expected_min, "{}", clocks);
+        assert!(!tap.take_block_finished());
+    }
+
+    #[test]
+    fn phases_follow_signal() {
+        let mut tap = test_tap();
+        assert_eq!(tap.phase(), TapePhase::Stopped);
+        tap.play();
+        let mut phases = alloc::vec![tap.phase()];
+        while phases.last() != Some(&TapePhase::Stopped) {
+            tap.process_clocks(CLOCKS_STEP).unwrap();
+            if phases.last() != Some(&tap.phase()) {
+                phases.push(tap.phase());
+            }
+            if phases.len() == 4 {
+                tap.pause();
+                assert_eq!(tap.phase(), TapePhase::Paused);
+                tap.resume();
+            }
+        }
+        assert_eq!(
+            phases,
+            [
+                TapePhase::Gap,
+                TapePhase::Pilot,
+                TapePhase::Sync,
+                TapePhase::Data,
+                TapePhase::Gap,
+                TapePhase::Stopped
+            ]
+        );
+    }
 }
@@ -1,14 +1,16 @@
 //! Contains ZXSpectrum border implementation
 
 use crate::{
+    error::StateLoadError,
     host::{FrameBuffer, FrameBufferSource},
+    settings::BorderSize,
+    utils::state::{StateReader, StateWriter},
     zx::{
-        constants::{
-            BORDER_COLS, BORDER_ROWS, CLOCKS_PER_COL, PIXELS_PER_CLOCK, SCREEN_HEIGHT, SCREEN_WIDTH,
-        },
-        machine::ZXMachine,
+        constants::PIXELS_PER_CLOCK,
+        machine::ZXSpecs,
         video::colors::{ZXBrightness, ZXColor},
     },
+    Result,
 };
 
 /// Internal struct, which contains information about beam position and color
@@ -38,23 +40,31 @@ impl BeamInfo {
 
 /// ZX Spectrum Border Device
 pub struct ZXBorder<FB: FrameBuffer> {
-    machine: ZXMachine,
+    specs: ZXSpecs,
+    size: BorderSize,
+    // rendered frame size, including canvas
+    width: usize,
+    height: usize,
     buffer: FB,
+    // border is drawn to the back buffer and is swapped with the front one
+    // when frame is finished
+    back_buffer: FB,
     beam_last: BeamInfo,
     border_changed: bool,
     beam_block: bool,
 }
 impl<FB: FrameBuffer> ZXBorder<FB> {
-    /// Returns new instance of border device
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    /// Returns new instance of border device, frame buffer size is
+    /// defined by the border `size`
+    pub fn new(specs: ZXSpecs, size: BorderSize, context: FB::Context) -> Self {
+        let (width, height) = (size.screen_width(), size.screen_height());
         ZXBorder {
-            machine,
-            buffer: FB::new(
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-                FrameBufferSource::Border,
-                context,
-            ),
+            specs,
+            size,
+            width,
+            height,
+            buffer: FB::new(width, height, FrameBufferSource::Border, context.clone()),
+            back_buffer: FB::new(width, height, FrameBufferSource::Border, context),
             beam_last: BeamInfo::first_pixel(ZXColor::White),
             border_changed: true,
             beam_block: false,
@@ -65,12 +75,12 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
     /// This function helps to determine pixel, which will be rendered at specific time
     /// and bool value, which signals end of frame
     fn next_border_pixel(&self, clocks: usize) -> (usize, usize, bool) {
-        let specs = self.machine.specs();
+        let specs = &self.specs;
         // beginning of the first line (first pixel timing minus border lines
         // minus left border columns)
         let clocks_origin = specs.clocks_first_pixel
-            - 8 * BORDER_ROWS * specs.clocks_line
-            - BORDER_COLS * CLOCKS_PER_COL
+            - self.size.border_height() * specs.clocks_line
+            - self.size.border_width() / PIXELS_PER_CLOCK
             + specs.clocks_ula_beam_shift;
         // return first pixel pos
         if clocks < clocks_origin {
@@ -83,13 +93,13 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         let mut pixel = ((clocks % specs.clocks_line) + 1) * PIXELS_PER_CLOCK;
         // if beam out of screen on horizontal pos.
         // pixel - 2 because we added 2 on prev line
-        if pixel - PIXELS_PER_CLOCK >= SCREEN_WIDTH {
+        if pixel - PIXELS_PER_CLOCK >= self.width {
             // first pixel of next line
             pixel = 0;
             line += 1;
         }
         // if beam out of screen on vertical pos.
-        if line >= SCREEN_HEIGHT {
+        if line >= self.height {
             (0, 0, true)
         } else {
             (line, pixel, false)
@@ -99,16 +109,21 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
     /// fills pixels from last pos to passed by arguments with
     fn fill_to(&mut self, line: usize, pixel: usize) {
         let last = self.beam_last;
-        for p in (last.line * SCREEN_WIDTH + last.pixel)..(line * SCREEN_WIDTH + pixel) {
-            self.buffer.set_color(
-                p % SCREEN_WIDTH,
-                p / SCREEN_WIDTH,
+        for p in (last.line * self.width + last.pixel)..(line * self.width + pixel) {
+            self.back_buffer.set_color(
+                p % self.width,
+                p / self.width,
                 last.color,
                 ZXBrightness::Normal,
             );
         }
     }
 
+    /// Changes frame timings, used to render the border
+    pub fn set_specs(&mut self, specs: ZXSpecs) {
+        self.specs = specs;
+    }
+
     /// starts new frame
     pub fn new_frame(&mut self) {
         // if border was not changed during prev frame then force change color of whole border
@@ -117,8 +132,11 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         }
         // fill to end of screen if not already filled
         if !self.beam_block {
-            self.fill_to(SCREEN_HEIGHT - 1, SCREEN_WIDTH);
+            self.fill_to(self.height - 1, self.width);
         }
+        // whole back buffer is redrawn during each frame, so it could be
+        // shown right away
+        core::mem::swap(&mut self.buffer, &mut self.back_buffer);
         // move beam to begin and reset flags
         self.beam_last.reset();
         self.border_changed = false;
@@ -133,7 +151,7 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         if !self.beam_block {
             // if not first pixel then update
             if frame_end {
-                self.fill_to(SCREEN_HEIGHT - 1, SCREEN_WIDTH);
+                self.fill_to(self.height - 1, self.width);
                 self.beam_block = true;
             }
             self.fill_to(line, pixel);
@@ -141,8 +159,32 @@ impl<FB: FrameBuffer> ZXBorder<FB> {
         self.beam_last = BeamInfo::new(line, pixel, color);
     }
 
-    /// Returns reference to texture
+    /// Returns reference to the last completed frame
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
+
+    /// Writes beam position and color to the emulator state
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_usize(self.beam_last.line);
+        writer.write_usize(self.beam_last.pixel);
+        writer.write_u8(self.beam_last.color.into());
+        writer.write_bool(self.border_changed);
+        writer.write_bool(self.beam_block);
+    }
+
+    /// Restores state, saved with [ZXBorder::save_state]. Border pixels
+    /// before the beam are not the part of the state and are kept as is
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        let line = reader.read_usize()?;
+        let pixel = reader.read_usize()?;
+        let color = ZXColor::from_bits(reader.read_u8()?);
+        if line >= self.height || pixel > self.width {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        self.beam_last = BeamInfo::new(line, pixel, color);
+        self.border_changed = reader.read_bool()?;
+        self.beam_block = reader.read_bool()?;
+        Ok(())
+    }
 }
@@ -5,3 +5,6 @@ pub(crate) mod border;
 pub(crate) mod screen;
 
 pub mod colors;
+
+#[cfg(feature = "timex")]
+pub use screen::TimexVideoMode;
@@ -1,18 +1,84 @@
 //! Module describes ZX Spectrum screen
 //! *block* - is 8x1 pxels stripe.
+#[cfg(feature = "timex")]
+use crate::zx::constants::{
+    TIMEX_ATTR_BASE_REL, TIMEX_ATTR_MAX_REL, TIMEX_BITMAP_BASE_REL, TIMEX_BITMAP_MAX_REL,
+    TIMEX_SCREEN_OFFSET_REL,
+};
+use crate::{error::StateLoadError, Result};
 use crate::{
     host::{FrameBuffer, FrameBufferSource},
-    utils::screen::{attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_rel},
+    utils::{
+        screen::{attr_col_rel, attr_row_rel, bitmap_col_rel, bitmap_line_rel},
+        state::{StateReader, StateWriter},
+    },
     zx::{
         constants::{
             ATTR_BASE_REL, ATTR_COLS, ATTR_MAX_REL, ATTR_ROWS, BITMAP_MAX_REL, CANVAS_HEIGHT,
             CANVAS_WIDTH, CLOCKS_PER_COL,
         },
-        machine::ZXMachine,
-        video::colors::ZXAttribute,
+        machine::{ZXMachine, ZXSpecs},
+        video::colors::{ZXAttribute, ZXBrightness, ZXColor},
     },
 };
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec};
+
+/// Count of frames between flash swaps on the real machine
+const DEFAULT_FLASH_RATE: usize = 16;
+
+/// Max count of consecutive screen memory writes, collected before they are
+/// applied to the screen banks
+const PENDING_WRITES_CAPACITY: usize = 256;
+
+/// Returns part of the bytes run, starting at `rel_start`, which lies in the
+/// `base..=max` screen memory region, along with its offset from `base`
+fn region_run(rel_start: u16, data: &[u8], base: u16, max: u16) -> Option<(u16, &[u8])> {
+    let start = rel_start as usize;
+    let end = start + data.len();
+    let first = start.max(base as usize);
+    let last = end.min(max as usize + 1);
+    if first >= last {
+        return None;
+    }
+    Some((
+        (first - base as usize) as u16,
+        &data[first - start..last - start],
+    ))
+}
+
+/// Returns first and last 8x1 blocks, which are rendered using the screen
+/// memory byte at `rel_addr`, or `None` if the byte is not a screen memory
+fn affected_blocks(rel_addr: u16) -> Option<(usize, usize)> {
+    let rel_addr = match rel_addr {
+        #[cfg(feature = "timex")]
+        TIMEX_BITMAP_BASE_REL..=TIMEX_ATTR_MAX_REL => rel_addr - TIMEX_SCREEN_OFFSET_REL,
+        _ => rel_addr,
+    };
+    match rel_addr {
+        0..=BITMAP_MAX_REL => {
+            let block = bitmap_line_rel(rel_addr) * ATTR_COLS + bitmap_col_rel(rel_addr);
+            Some((block, block))
+        }
+        ATTR_BASE_REL..=ATTR_MAX_REL => {
+            let first = attr_row_rel(rel_addr) * 8 * ATTR_COLS + attr_col_rel(rel_addr);
+            Some((first, first + 7 * ATTR_COLS))
+        }
+        _ => None,
+    }
+}
+
+fn write_bitmap_run(bitmap: &mut [u8], offset: u16, run: &[u8]) {
+    for (rel_addr, &data) in (offset..).zip(run) {
+        bitmap[bitmap_line_rel(rel_addr) * ATTR_COLS + bitmap_col_rel(rel_addr)] = data;
+    }
+}
+
+fn write_attributes_run(attributes: &mut [ZXAttribute], offset: u16, run: &[u8]) {
+    let cells = &mut attributes[offset as usize..offset as usize + run.len()];
+    for (attr, &data) in cells.iter_mut().zip(run) {
+        *attr = ZXAttribute::from_byte(data);
+    }
+}
 
 /// Represents how much 8x1 have been already **rendered**.
 #[derive(PartialEq, Eq, Debug)]
@@ -27,10 +93,8 @@ impl BlocksCount {
         BlocksCount { lines, columns }
     }
 
-    /// Constructs self from clocks count, taking into account machine type
-    pub fn from_clocks(clocks: usize, machine: ZXMachine) -> BlocksCount {
-        // get reference to specs for less words
-        let specs = machine.specs();
+    /// Constructs self from clocks count, taking into account machine timings
+    pub fn from_clocks(clocks: usize, specs: &ZXSpecs) -> BlocksCount {
         let mut lines;
         let mut columns;
         if clocks < specs.clocks_ula_read_origin {
@@ -78,72 +142,354 @@ impl BlocksCount {
 struct ScreenBank {
     pub attributes: Box<[ZXAttribute; ATTR_COLS * ATTR_ROWS]>,
     pub bitmap: Box<[u8; ATTR_COLS * CANVAS_HEIGHT]>,
+    /// Timex second screen attributes (0x7800 in 48K memory map)
+    #[cfg(feature = "timex")]
+    pub timex_attributes: Box<[ZXAttribute; ATTR_COLS * ATTR_ROWS]>,
+    /// Timex second screen bitmap (0x6000 in 48K memory map). Also used as
+    /// 8x1 attributes in hi-colour mode and as odd columns in hi-res mode
+    #[cfg(feature = "timex")]
+    pub timex_bitmap: Box<[u8; ATTR_COLS * CANVAS_HEIGHT]>,
+}
+
+impl ScreenBank {
+    fn new() -> Self {
+        Self {
+            attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
+            bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
+            #[cfg(feature = "timex")]
+            timex_attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
+            #[cfg(feature = "timex")]
+            timex_bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
+        }
+    }
+
+    /// Writes run of consecutive bytes of the ram bank, starting at
+    /// `rel_start`, and returns changed parts of the screen memory regions
+    fn write_range(&mut self, rel_start: u16, data: &[u8]) -> ChangedRuns {
+        let mut changed = ChangedRuns::default();
+        if let Some((offset, run)) = region_run(rel_start, data, 0, BITMAP_MAX_REL) {
+            write_bitmap_run(&mut self.bitmap[..], offset, run);
+            changed.bitmap[0] = Some((offset, run.len()));
+        }
+        if let Some((offset, run)) = region_run(rel_start, data, ATTR_BASE_REL, ATTR_MAX_REL) {
+            write_attributes_run(&mut self.attributes[..], offset, run);
+            changed.attributes[0] = Some((offset, run.len()));
+        }
+        #[cfg(feature = "timex")]
+        if let Some((offset, run)) =
+            region_run(rel_start, data, TIMEX_BITMAP_BASE_REL, TIMEX_BITMAP_MAX_REL)
+        {
+            write_bitmap_run(&mut self.timex_bitmap[..], offset, run);
+            changed.bitmap[1] = Some((offset, run.len()));
+        }
+        #[cfg(feature = "timex")]
+        if let Some((offset, run)) =
+            region_run(rel_start, data, TIMEX_ATTR_BASE_REL, TIMEX_ATTR_MAX_REL)
+        {
+            write_attributes_run(&mut self.timex_attributes[..], offset, run);
+            changed.attributes[1] = Some((offset, run.len()));
+        }
+        changed
+    }
+}
+
+/// Parts of the standard and Timex screen memory regions, changed by the
+/// bytes run, as `(offset, len)` from the region start
+#[derive(Default)]
+struct ChangedRuns {
+    bitmap: [Option<(u16, usize)>; 2],
+    attributes: [Option<(u16, usize)>; 2],
+}
+
+/// Run of consecutive screen memory writes, which are not applied to the
+/// screen banks yet
+struct PendingWrites {
+    bank: usize,
+    rel_start: u16,
+    len: usize,
+    data: [u8; PENDING_WRITES_CAPACITY],
+    // range of 8x1 blocks which are rendered using the written bytes
+    first_block: usize,
+    last_block: usize,
+}
+
+impl PendingWrites {
+    fn new() -> Self {
+        Self {
+            bank: 0,
+            rel_start: 0,
+            len: 0,
+            data: [0; PENDING_WRITES_CAPACITY],
+            first_block: 0,
+            last_block: 0,
+        }
+    }
+
+    /// Returns true if the byte at `rel_addr` of the `bank` could be
+    /// appended to the run. Empty run continues the last isolated write
+    fn continues_with(&self, rel_addr: u16, bank: usize) -> bool {
+        self.len < PENDING_WRITES_CAPACITY
+            && self.bank == bank
+            && self.rel_start as usize + self.len == rel_addr as usize
+    }
+}
+
+/// Timex SCLD video mode, selected via bits 0..2 of port 0xFF
+#[cfg(feature = "timex")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimexVideoMode {
+    /// Standard screen at 0x4000
+    Standard,
+    /// Standard screen at 0x6000
+    Alternate,
+    /// Bitmap at 0x4000 with 8x1 attributes at 0x6000
+    HiColor,
+    /// 512x192 monochrome, even columns are taken from 0x4000 and odd
+    /// columns from 0x6000 bitmap
+    HiRes,
+}
+
+#[cfg(feature = "timex")]
+impl TimexVideoMode {
+    /// Decodes video mode from port 0xFF value
+    pub fn from_port_value(value: u8) -> Self {
+        match value & 0x07 {
+            0b110 => Self::HiRes,
+            m if m & 0x02 != 0 => Self::HiColor,
+            m if m & 0x01 != 0 => Self::Alternate,
+            _ => Self::Standard,
+        }
+    }
 }
 
 /// Represents ZXSpectrum emulated mid part of screen (canvas)
 pub struct ZXScreen<FB: FrameBuffer> {
     machine: ZXMachine,
+    specs: ZXSpecs,
     last_blocks: BlocksCount,
     flash: bool,
+    // frames between flash swaps, flashing is disabled when `None`
+    flash_rate: Option<usize>,
     frame_counter: usize,
     buffer: FB,
     back_buffer: FB,
+    // width of the frame buffers, multiple of `CANVAS_WIDTH`
+    buffer_width: usize,
     banks: [ScreenBank; 2],
     active_bank: usize,
+    // Screen memory writes, applied before the beam reaches written cells
+    pending: PendingWrites,
+    // Colors of the previous frame pixels, stored only when frames
+    // blending is enabled
+    prev_frame_colors: Option<Box<[u8]>>,
+    // last value written to the Timex SCLD port 0xFF
+    #[cfg(feature = "timex")]
+    timex_port: u8,
+    // Attribute cells changed during the current and the previous frames, one
+    // bit per column. Write during the previous frame could be visible only
+    // in the current one if the beam has already passed the cell
+    #[cfg(feature = "dirty-cells")]
+    dirty: DirtyCells,
+    #[cfg(feature = "dirty-cells")]
+    prev_dirty: DirtyCells,
+    // Cells which differ between the last completed frame and the one before it
+    #[cfg(feature = "dirty-cells")]
+    frame_dirty: DirtyCells,
 }
 
+#[cfg(feature = "dirty-cells")]
+type DirtyCells = [u32; ATTR_ROWS];
+
 impl<FB: FrameBuffer> ZXScreen<FB> {
-    /// Constructs new canvas of `machine`
-    pub fn new(machine: ZXMachine, context: FB::Context) -> Self {
+    /// Constructs new canvas of `machine` with given frame timings. Each ZX
+    /// Spectrum pixel takes `buffer_width / CANVAS_WIDTH` frame buffer pixels
+    pub fn new(
+        machine: ZXMachine,
+        specs: ZXSpecs,
+        buffer_width: usize,
+        context: FB::Context,
+    ) -> Self {
         Self {
             machine,
+            specs,
             last_blocks: BlocksCount::new(0, 0),
             flash: false,
+            flash_rate: Some(DEFAULT_FLASH_RATE),
             frame_counter: 0,
             buffer: FB::new(
-                CANVAS_WIDTH,
+                buffer_width,
                 CANVAS_HEIGHT,
                 FrameBufferSource::Screen,
                 context.clone(),
             ),
             back_buffer: FB::new(
-                CANVAS_WIDTH,
+                buffer_width,
                 CANVAS_HEIGHT,
                 FrameBufferSource::Screen,
                 context,
             ),
-            banks: [
-                ScreenBank {
-                    attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
-                    bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
-                },
-                ScreenBank {
-                    attributes: Box::new([ZXAttribute::from_byte(0); ATTR_COLS * ATTR_ROWS]),
-                    bitmap: Box::new([0; ATTR_COLS * CANVAS_HEIGHT]),
-                },
-            ],
+            buffer_width,
+            banks: [ScreenBank::new(), ScreenBank::new()],
             active_bank: 0,
+            pending: PendingWrites::new(),
+            prev_frame_colors: None,
+            #[cfg(feature = "timex")]
+            timex_port: 0,
+            #[cfg(feature = "dirty-cells")]
+            dirty: [0; ATTR_ROWS],
+            #[cfg(feature = "dirty-cells")]
+            prev_dirty: [0; ATTR_ROWS],
+            #[cfg(feature = "dirty-cells")]
+            frame_dirty: [0; ATTR_ROWS],
+        }
+    }
+
+    /// Marks attribute cell at (`col`, `row`) as changed
+    #[cfg(feature = "dirty-cells")]
+    fn mark_dirty(&mut self, col: usize, row: usize) {
+        self.dirty[row] |= 1 << col;
+    }
+
+    #[cfg(not(feature = "dirty-cells"))]
+    fn mark_dirty(&mut self, _col: usize, _row: usize) {}
+
+    /// Marks whole screen as changed
+    #[cfg(feature = "dirty-cells")]
+    fn mark_all_dirty(&mut self) {
+        self.dirty = [u32::MAX; ATTR_ROWS];
+    }
+
+    #[cfg(not(feature = "dirty-cells"))]
+    fn mark_all_dirty(&mut self) {}
+
+    /// Returns iterator over `(col, row)` attribute cells (8x8 pixels) which
+    /// were changed in the last completed frame compared to the previous one
+    #[cfg(feature = "dirty-cells")]
+    pub fn dirty_cells(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.frame_dirty
+            .iter()
+            .enumerate()
+            .flat_map(|(row, &cols)| {
+                (0..ATTR_COLS)
+                    .filter(move |col| cols & (1 << col) != 0)
+                    .map(move |col| (col as u8, row as u8))
+            })
+    }
+
+    /// Changes Timex video mode via port 0xFF value
+    #[cfg(feature = "timex")]
+    pub fn set_timex_port(&mut self, value: u8) {
+        if value != self.timex_port {
+            self.mark_all_dirty();
+        }
+        self.timex_port = value;
+    }
+
+    /// Returns last value written to Timex port 0xFF
+    #[cfg(feature = "timex")]
+    pub fn timex_port(&self) -> u8 {
+        self.timex_port
+    }
+
+    /// Returns current Timex video mode
+    #[cfg(feature = "timex")]
+    pub fn timex_video_mode(&self) -> TimexVideoMode {
+        TimexVideoMode::from_port_value(self.timex_port)
+    }
+
+    /// Returns width of the screen frame buffers
+    pub fn buffer_width(&self) -> usize {
+        self.buffer_width
+    }
+
+    /// Returns count of frame buffer pixels per single ZX Spectrum pixel in
+    /// horizontal direction
+    fn pixel_width(&self) -> usize {
+        self.buffer_width / CANVAS_WIDTH
+    }
+
+    /// Changes frame timings, used to render the screen
+    pub fn set_specs(&mut self, specs: ZXSpecs) {
+        self.specs = specs;
+    }
+
+    /// Enables or disables blending of the current frame with the previous one
+    pub fn set_blend_frames(&mut self, value: bool) {
+        if value == self.prev_frame_colors.is_some() {
+            return;
+        }
+        self.prev_frame_colors = value.then(|| {
+            let initial_color = Self::pack_color(ZXColor::Black, ZXBrightness::Normal);
+            vec![initial_color; self.buffer_width * CANVAS_HEIGHT].into_boxed_slice()
+        });
+        self.mark_all_dirty();
+    }
+
+    fn pack_color(color: ZXColor, brightness: ZXBrightness) -> u8 {
+        u8::from(color) | ((brightness as u8) << 3)
+    }
+
+    fn unpack_color(packed: u8) -> (ZXColor, ZXBrightness) {
+        let brightness = if packed & 0x08 != 0 {
+            ZXBrightness::Bright
+        } else {
+            ZXBrightness::Normal
+        };
+        (ZXColor::from_bits(packed & 0x07), brightness)
+    }
+
+    /// Sets pixel color in the back buffer, blending it with the previous
+    /// frame pixel color if required
+    fn put_pixel(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+        match self.prev_frame_colors.as_mut() {
+            Some(prev_frame_colors) => {
+                let pixel = &mut prev_frame_colors[y * self.buffer_width + x];
+                let previous = Self::unpack_color(*pixel);
+                *pixel = Self::pack_color(color, brightness);
+                self.back_buffer
+                    .set_blended_color(x, y, (color, brightness), previous);
+            }
+            None => self.back_buffer.set_color(x, y, color, brightness),
+        }
+    }
+
+    /// Changes count of frames between flash swaps, `None` disables flashing
+    pub fn set_flash_rate(&mut self, frames: Option<usize>) {
+        self.flash_rate = frames.filter(|frames| *frames > 0);
+        if self.flash_rate.is_none() && self.flash {
+            self.switch_flash();
         }
     }
 
     /// changes flash switch
     fn switch_flash(&mut self) {
+        self.flush_pending();
         self.flash = !self.flash;
+        #[cfg(feature = "dirty-cells")]
+        for (index, attr) in self.banks[self.active_bank].attributes.iter().enumerate() {
+            if attr.flash {
+                self.dirty[index / ATTR_COLS] |= 1 << (index % ATTR_COLS);
+            }
+        }
     }
 
     /// transforms zx spectrum bank to local index
     fn local_bank(&self, bank: usize) -> Option<usize> {
         match self.machine {
-            ZXMachine::Sinclair48K if bank == 0 => Some(0),
-            ZXMachine::Sinclair128K if bank == 5 => Some(0),
-            ZXMachine::Sinclair128K if bank == 7 => Some(1),
+            ZXMachine::Sinclair16K | ZXMachine::Sinclair48K if bank == 0 => Some(0),
+            ZXMachine::Sinclair128K | ZXMachine::SinclairPlus3 if bank == 5 => Some(0),
+            ZXMachine::Sinclair128K | ZXMachine::SinclairPlus3 if bank == 7 => Some(1),
             _ => None,
         }
     }
 
     /// selects bank of memory
     pub fn switch_bank(&mut self, bank: usize) {
+        self.flush_pending();
         if let Some(bank) = self.local_bank(bank) {
+            if bank != self.active_bank {
+                self.mark_all_dirty();
+            }
             self.active_bank = bank;
         }
     }
@@ -152,39 +498,108 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
     /// `clocks` - current  clocks count form frame start.
     /// if clocks < previous call clocks then discard processing
     pub fn process_clocks(&mut self, clocks: usize) {
-        let blocks = BlocksCount::from_clocks(clocks, self.machine);
+        let blocks = BlocksCount::from_clocks(clocks, &self.specs);
         // so, let's count of 8x1 blocks, which passed.
         let count = blocks.passed_from(&self.last_blocks);
         if count > 0 {
             // fill pixels from prev to current
             let prev_block = self.last_blocks.lines * ATTR_COLS + self.last_blocks.columns;
             let curr_block = blocks.lines * ATTR_COLS + blocks.columns;
+            if self.pending.len != 0
+                && self.pending.first_block < curr_block
+                && self.pending.last_block >= prev_block
+            {
+                self.flush_pending();
+            }
             // so we know that some blocks have been passed
             // block holds current blocks index
             for block in prev_block..curr_block {
-                let bitmap = self.banks[self.active_bank].bitmap[block];
-                // one attr per 8x8 area
-                let attr_row = block / (ATTR_COLS * 8);
-                let attr_col = block % ATTR_COLS;
-                let attr = self.banks[self.active_bank].attributes[attr_row * ATTR_COLS + attr_col];
-                for pixel in 0..8 {
-                    // from most significant bit
-                    let state = ((bitmap << pixel) & 0x80) != 0;
-                    self.back_buffer.set_color(
-                        (block % ATTR_COLS) * 8 + pixel,
-                        block / ATTR_COLS,
-                        attr.active_color(state, self.flash),
-                        attr.brightness,
-                    );
-                }
+                self.render_block(block);
             }
             // change last block to current
             self.last_blocks = blocks;
         }
     }
 
+    /// Renders single 8x1 block of the screen
+    #[cfg(not(feature = "timex"))]
+    fn render_block(&mut self, block: usize) {
+        let bank = &self.banks[self.active_bank];
+        let bitmap = bank.bitmap[block];
+        let attr = bank.attributes[Self::attr_index(block)];
+        self.render_block_with_attr(block, bitmap, attr);
+    }
+
+    /// Renders single 8x1 block of the screen according to the current
+    /// Timex video mode
+    #[cfg(feature = "timex")]
+    fn render_block(&mut self, block: usize) {
+        let bank = &self.banks[self.active_bank];
+        match self.timex_video_mode() {
+            TimexVideoMode::Standard => {
+                let bitmap = bank.bitmap[block];
+                let attr = bank.attributes[Self::attr_index(block)];
+                self.render_block_with_attr(block, bitmap, attr);
+            }
+            TimexVideoMode::Alternate => {
+                let bitmap = bank.timex_bitmap[block];
+                let attr = bank.timex_attributes[Self::attr_index(block)];
+                self.render_block_with_attr(block, bitmap, attr);
+            }
+            TimexVideoMode::HiColor => {
+                let bitmap = bank.bitmap[block];
+                let attr = ZXAttribute::from_byte(bank.timex_bitmap[block]);
+                self.render_block_with_attr(block, bitmap, attr);
+            }
+            TimexVideoMode::HiRes => {
+                // ink is selected via bits 3..5 of the port, paper is its complement
+                let ink_bits = (self.timex_port >> 3) & 0x07;
+                let ink = ZXColor::from_bits(ink_bits);
+                let paper = ZXColor::from_bits(!ink_bits & 0x07);
+                let halves = [bank.bitmap[block], bank.timex_bitmap[block]];
+                let x_base = (block % ATTR_COLS) * 8 * self.pixel_width();
+                let y = block / ATTR_COLS;
+                for (half, bitmap) in halves.iter().enumerate() {
+                    for pixel in 0..8 {
+                        let color = if ((bitmap << pixel) & 0x80) != 0 {
+                            ink
+                        } else {
+                            paper
+                        };
+                        self.put_pixel(x_base + half * 8 + pixel, y, color, ZXBrightness::Normal);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns index of the 8x8 attribute for given 8x1 block
+    fn attr_index(block: usize) -> usize {
+        let attr_row = block / (ATTR_COLS * 8);
+        let attr_col = block % ATTR_COLS;
+        attr_row * ATTR_COLS + attr_col
+    }
+
+    /// Renders 8x1 block bitmap using given attribute
+    fn render_block_with_attr(&mut self, block: usize, bitmap: u8, attr: ZXAttribute) {
+        for pixel in 0..8 {
+            // from most significant bit
+            let state = ((bitmap << pixel) & 0x80) != 0;
+            let x = ((block % ATTR_COLS) * 8 + pixel) * self.pixel_width();
+            for sub_pixel in 0..self.pixel_width() {
+                self.put_pixel(
+                    x + sub_pixel,
+                    block / ATTR_COLS,
+                    attr.active_color(state, self.flash),
+                    attr.brightness,
+                );
+            }
+        }
+    }
+
     /// starts new frame
     pub fn new_frame(&mut self) {
+        self.flush_pending();
         // post finished bitmap to second buffer (all not-rendered part will be updated)
         {
             let Self {
@@ -194,15 +609,30 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
             } = self;
             core::mem::swap(buffer, back_buffer);
         }
+        #[cfg(feature = "dirty-cells")]
+        {
+            for ((frame, current), prev) in self
+                .frame_dirty
+                .iter_mut()
+                .zip(self.dirty.iter())
+                .zip(self.prev_dirty.iter())
+            {
+                *frame = current | prev;
+            }
+            self.prev_dirty = core::mem::take(&mut self.dirty);
+        }
         self.last_blocks = BlocksCount::new(0, 0);
-        if self.frame_counter % 16 == 0 {
-            self.switch_flash();
+        if let Some(rate) = self.flash_rate {
+            if self.frame_counter.is_multiple_of(rate) {
+                self.switch_flash();
+            }
         }
         self.frame_counter += 1;
     }
 
     /// Updates data if screen ram
     pub fn update(&mut self, rel_addr: u16, bank: usize, data: u8) {
+        self.flush_pending();
         if let Some(bank) = self.local_bank(bank) {
             match rel_addr {
                 // change bitmap
@@ -210,6 +640,9 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
                     let line = bitmap_line_rel(rel_addr);
                     let col = bitmap_col_rel(rel_addr);
                     self.banks[bank].bitmap[line * ATTR_COLS + col] = data;
+                    if bank == self.active_bank {
+                        self.mark_dirty(col, line / 8);
+                    }
                 }
                 // change attribute
                 ATTR_BASE_REL..=ATTR_MAX_REL => {
@@ -217,6 +650,30 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
                     let col = attr_col_rel(rel_addr);
                     self.banks[bank].attributes[row * ATTR_COLS + col] =
                         ZXAttribute::from_byte(data);
+                    if bank == self.active_bank {
+                        self.mark_dirty(col, row);
+                    }
+                }
+                #[cfg(feature = "timex")]
+                TIMEX_BITMAP_BASE_REL..=TIMEX_BITMAP_MAX_REL => {
+                    let rel_addr = rel_addr - TIMEX_SCREEN_OFFSET_REL;
+                    let line = bitmap_line_rel(rel_addr);
+                    let col = bitmap_col_rel(rel_addr);
+                    self.banks[bank].timex_bitmap[line * ATTR_COLS + col] = data;
+                    if bank == self.active_bank {
+                        self.mark_dirty(col, line / 8);
+                    }
+                }
+                #[cfg(feature = "timex")]
+                TIMEX_ATTR_BASE_REL..=TIMEX_ATTR_MAX_REL => {
+                    let rel_addr = rel_addr - TIMEX_SCREEN_OFFSET_REL;
+                    let row = attr_row_rel(rel_addr);
+                    let col = attr_col_rel(rel_addr);
+                    self.banks[bank].timex_attributes[row * ATTR_COLS + col] =
+                        ZXAttribute::from_byte(data);
+                    if bank == self.active_bank {
+                        self.mark_dirty(col, row);
+                    }
                 }
                 // no screen changes
                 _ => {}
@@ -224,7 +681,298 @@ impl<FB: FrameBuffer> ZXScreen<FB> {
         }
     }
 
+    /// Updates screen data from the run of consecutive bytes of the ram bank,
+    /// starting at `rel_start`. Bank and screen region are resolved once per
+    /// run and attributes are copied as a whole, so large writes are cheaper
+    /// than per-byte [ZXScreen::update] calls
+    pub fn update_range(&mut self, rel_start: u16, data: &[u8], bank: usize) {
+        self.flush_pending();
+        self.apply_range(rel_start, data, bank);
+    }
+
+    /// Queues screen data update from the ram bank write. Bank and screen
+    /// region of consecutive writes are resolved once per run, which is
+    /// applied before the beam reaches any of the written cells, so the
+    /// rendering stays beam-timed
+    pub fn update_batched(&mut self, rel_addr: u16, bank: usize, data: u8) {
+        if self.local_bank(bank).is_none() {
+            return;
+        }
+        let Some((first_block, last_block)) = affected_blocks(rel_addr) else {
+            return;
+        };
+        if !self.pending.continues_with(rel_addr, bank) {
+            // Isolated write is applied directly, the next consecutive write
+            // starts a new run
+            self.update(rel_addr, bank, data);
+            self.pending.bank = bank;
+            self.pending.rel_start = rel_addr.wrapping_add(1);
+            return;
+        }
+        if self.pending.len == 0 {
+            self.pending.first_block = first_block;
+            self.pending.last_block = last_block;
+        } else {
+            self.pending.first_block = self.pending.first_block.min(first_block);
+            self.pending.last_block = self.pending.last_block.max(last_block);
+        }
+        self.pending.data[self.pending.len] = data;
+        self.pending.len += 1;
+    }
+
+    /// Applies queued screen memory writes to the screen banks
+    fn flush_pending(&mut self) {
+        let len = core::mem::take(&mut self.pending.len);
+        if len == 0 {
+            return;
+        }
+        let Some(bank) = self.local_bank(self.pending.bank) else {
+            return;
+        };
+        let changed =
+            self.banks[bank].write_range(self.pending.rel_start, &self.pending.data[..len]);
+        if bank == self.active_bank {
+            self.mark_runs_dirty(&changed);
+        }
+    }
+
+    fn apply_range(&mut self, rel_start: u16, data: &[u8], bank: usize) {
+        let Some(bank) = self.local_bank(bank) else {
+            return;
+        };
+        let changed = self.banks[bank].write_range(rel_start, data);
+        if bank == self.active_bank {
+            self.mark_runs_dirty(&changed);
+        }
+    }
+
+    /// Marks cells of the changed screen memory runs as changed
+    fn mark_runs_dirty(&mut self, changed: &ChangedRuns) {
+        for &(offset, len) in changed.bitmap.iter().flatten() {
+            self.mark_bitmap_run_dirty(offset, len);
+        }
+        for &(offset, len) in changed.attributes.iter().flatten() {
+            self.mark_attributes_run_dirty(offset, len);
+        }
+    }
+
+    /// Marks cells of the bitmap bytes run as changed, `offset` is relative
+    /// to the bitmap start
+    fn mark_bitmap_run_dirty(&mut self, offset: u16, len: usize) {
+        for rel_addr in (offset..).take(len) {
+            self.mark_dirty(bitmap_col_rel(rel_addr), bitmap_line_rel(rel_addr) / 8);
+        }
+    }
+
+    /// Marks cells of the attributes run as changed, `offset` is relative to
+    /// the attributes start
+    fn mark_attributes_run_dirty(&mut self, offset: u16, len: usize) {
+        for cell in (offset as usize..).take(len) {
+            self.mark_dirty(cell % ATTR_COLS, cell / ATTR_COLS);
+        }
+    }
+
     pub fn frame_buffer(&self) -> &FB {
         &self.buffer
     }
+
+    /// Writes flash phase, active bank and rendering position to the emulator
+    /// state. Screen memory is restored from the ram contents
+    pub(crate) fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.flash);
+        writer.write_usize(self.frame_counter);
+        writer.write_usize(self.active_bank);
+        #[cfg(feature = "timex")]
+        writer.write_u8(self.timex_port);
+        #[cfg(not(feature = "timex"))]
+        writer.write_u8(0);
+        writer.write_usize(self.last_blocks.lines);
+        writer.write_usize(self.last_blocks.columns);
+    }
+
+    /// Restores state, saved with [ZXScreen::save_state]. Screen banks should
+    /// be already updated from the restored ram. Already passed part of the
+    /// current frame is rendered again from the restored screen memory
+    pub(crate) fn load_state(&mut self, reader: &mut StateReader) -> Result<()> {
+        let flash = reader.read_bool()?;
+        let frame_counter = reader.read_usize()?;
+        let active_bank = reader.read_usize()?;
+        let _timex_port = reader.read_u8()?;
+        let last_blocks = BlocksCount::new(reader.read_usize()?, reader.read_usize()?);
+        if active_bank >= self.banks.len()
+            || last_blocks.lines > CANVAS_HEIGHT
+            || last_blocks.columns > ATTR_COLS
+        {
+            return Err(StateLoadError::InvalidState.into());
+        }
+        self.flash = flash;
+        self.frame_counter = frame_counter;
+        self.active_bank = active_bank;
+        #[cfg(feature = "timex")]
+        {
+            self.timex_port = _timex_port;
+        }
+        let passed_blocks = last_blocks.lines * ATTR_COLS + last_blocks.columns;
+        for block in 0..passed_blocks {
+            self.render_block(block);
+        }
+        self.last_blocks = last_blocks;
+        self.mark_all_dirty();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Stores color index with brightness bit of each pixel
+    struct IndexFrameBuffer {
+        width: usize,
+        pixels: Vec<u8>,
+    }
+
+    impl FrameBuffer for IndexFrameBuffer {
+        type Context = ();
+
+        fn new(width: usize, height: usize, _source: FrameBufferSource, _context: ()) -> Self {
+            Self {
+                width,
+                pixels: vec![0; width * height],
+            }
+        }
+
+        fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
+            self.pixels[y * self.width + x] = u8::from(color) | (brightness as u8) << 3;
+        }
+
+        fn as_bytes(&self) -> Option<&[u8]> {
+            Some(&self.pixels)
+        }
+    }
+
+    fn screen(machine: ZXMachine) -> ZXScreen<IndexFrameBuffer> {
+        ZXScreen::new(machine, machine.specs().clone(), CANVAS_WIDTH, ())
+    }
+
+    fn attr_byte(attr: &ZXAttribute) -> u8 {
+        attr.ink as u8
+            | (attr.paper as u8) << 3
+            | (attr.brightness as u8) << 6
+            | (attr.flash as u8) << 7
+    }
+
+    fn assert_same_banks(range: &ZXScreen<IndexFrameBuffer>, bytes: &ZXScreen<IndexFrameBuffer>) {
+        for (range, bytes) in range.banks.iter().zip(bytes.banks.iter()) {
+            assert_eq!(range.bitmap[..], bytes.bitmap[..]);
+            assert!(range
+                .attributes
+                .iter()
+                .map(attr_byte)
+                .eq(bytes.attributes.iter().map(attr_byte)));
+            #[cfg(feature = "timex")]
+            {
+                assert_eq!(range.timex_bitmap[..], bytes.timex_bitmap[..]);
+                assert!(range
+                    .timex_attributes
+                    .iter()
+                    .map(attr_byte)
+                    .eq(bytes.timex_attributes.iter().map(attr_byte)));
+            }
+        }
+        #[cfg(feature = "dirty-cells")]
+        assert_eq!(range.dirty, bytes.dirty);
+    }
+
+    /// Bulk screen memory write should have the same effect as writing the
+    /// same bytes one by one
+    #[test]
+    fn update_range_matches_update() {
+        const RUNS: &[(u16, usize)] = &[
+            // bitmap only, unaligned to the character lines
+            (0x0123, 0x0345),
+            // bitmap to attributes boundary
+            (0x17FD, 7),
+            // attributes end, running into the unused memory
+            (0x1AFB, 0x0100),
+            // whole screen starting at the first byte of the bank
+            (0x0000, 0x1B00),
+            // Timex bitmap to attributes boundary
+            (0x37FE, 5),
+            // from the standard attributes up to the Timex attributes end
+            (0x1A01, 0x2100),
+            // past the Timex screen end
+            (0x3AFF, 0x0200),
+        ];
+
+        for &(machine, bank) in &[
+            (ZXMachine::Sinclair48K, 0),
+            (ZXMachine::Sinclair128K, 5),
+            (ZXMachine::Sinclair128K, 7),
+        ] {
+            for &(start, len) in RUNS {
+                let data: Vec<u8> = (0..len)
+                    .map(|n| (n as u8).wrapping_mul(37) ^ (start as u8))
+                    .collect();
+                let mut range = screen(machine);
+                let mut bytes = screen(machine);
+                range.update_range(start, &data, bank);
+                for (rel_addr, &value) in (start..).zip(data.iter()) {
+                    bytes.update(rel_addr, bank, value);
+                }
+                assert_same_banks(&range, &bytes);
+            }
+        }
+    }
+
+    /// Screen memory writes, queued between the beam-timed rendering steps,
+    /// should produce the same frames as writes applied immediately
+    #[test]
+    fn update_batched_matches_update() {
+        // (start, len, clocks per write) of the LDIR-like write runs
+        const RUNS: &[(u16, usize, usize)] = &[
+            // whole screen, both ahead and behind the beam
+            (0x0000, 0x1B00, 21),
+            // attributes row rewritten while the beam renders it
+            (0x1900, 0x20, 10),
+            (0x1900, 0x20, 10),
+            // bitmap run crossing the character lines
+            (0x07F0, 0x0120, 16),
+            #[cfg(feature = "timex")]
+            (0x3800, 0x0300, 21),
+        ];
+
+        for &(machine, bank) in &[(ZXMachine::Sinclair48K, 0), (ZXMachine::Sinclair128K, 5)] {
+            let clocks_frame = machine.specs().clocks_frame;
+            let mut batched = screen(machine);
+            let mut immediate = screen(machine);
+            let mut clocks = 0;
+            let mut value = 0u8;
+            for frame in 0..3 {
+                for &(start, len, step) in RUNS {
+                    for rel_addr in (start..).take(len) {
+                        value = value.wrapping_mul(13).wrapping_add(frame + 7);
+                        batched.update_batched(rel_addr, bank, value);
+                        immediate.update(rel_addr, bank, value);
+                        clocks = (clocks + step) % clocks_frame;
+                        if clocks < step {
+                            batched.process_clocks(clocks_frame);
+                            immediate.process_clocks(clocks_frame);
+                            batched.new_frame();
+                            immediate.new_frame();
+                            assert_eq!(
+                                batched.frame_buffer().as_bytes(),
+                                immediate.frame_buffer().as_bytes()
+                            );
+                        }
+                        batched.process_clocks(clocks);
+                        immediate.process_clocks(clocks);
+                    }
+                }
+            }
+            batched.flush_pending();
+            assert_same_banks(&batched, &immediate);
+        }
+    }
 }
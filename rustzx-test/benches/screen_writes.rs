@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use rustzx_test::framework::{presets, RustZXTester};
+
+/// Copies a whole screen from 0x8000 with LDIR in the endless loop
+#[rustfmt::skip]
+const SCREEN_LDIR: &[u8] = &[
+    0x21, 0x00, 0x80,       // LD HL, 0x8000
+    0x11, 0x00, 0x40,       // LD DE, 0x4000
+    0x01, 0x00, 0x1B,       // LD BC, 0x1B00
+    0xED, 0xB0,             // LDIR
+    0x18, 0xF3,             // JR -13
+];
+
+/// Writes every second byte of the screen memory, so no consecutive writes
+/// could be batched
+#[rustfmt::skip]
+const SCREEN_SPARSE: &[u8] = &[
+    0x21, 0x00, 0x40,       // LD HL, 0x4000
+    0x77,                   // LD (HL), A
+    0x23,                   // INC HL
+    0x23,                   // INC HL
+    0x3C,                   // INC A
+    0xCB, 0xAC,             // RES 5, H
+    0x18, 0xF8,             // JR -8
+];
+
+/// Same copy loop as `SCREEN_LDIR`, but to the non-screen ram
+#[rustfmt::skip]
+const RAM_LDIR: &[u8] = &[
+    0x21, 0x00, 0x80,       // LD HL, 0x8000
+    0x11, 0x00, 0xC0,       // LD DE, 0xC000
+    0x01, 0x00, 0x1B,       // LD BC, 0x1B00
+    0xED, 0xB0,             // LDIR
+    0x18, 0xF3,             // JR -13
+];
+
+const EMULATED_DURATION: Duration = Duration::from_secs(20);
+const TEST_ITERATIONS: u32 = 5;
+
+fn bench(name: &str, program: &[u8]) {
+    let mut acc = Duration::ZERO;
+    for _ in 0..TEST_ITERATIONS {
+        let mut tester = RustZXTester::new("screen_writes", presets::settings_48k_nosound());
+        tester.load_program(program);
+        let start = Instant::now();
+        tester.emulate_for(EMULATED_DURATION);
+        acc += start.elapsed();
+    }
+    let duration = acc / TEST_ITERATIONS;
+    println!("`{}` bench took {}ms", name, duration.as_millis());
+}
+
+fn main() {
+    bench("screen_ldir", SCREEN_LDIR);
+    bench("screen_sparse", SCREEN_SPARSE);
+    bench("ram_ldir", RAM_LDIR);
+}
@@ -2,16 +2,17 @@ use expect_test::Expect;
 use rustzx_core::{
     host::{
         BufferCursor, DebugInterface, FrameBuffer, FrameBufferSource, Host, HostContext,
-        IoExtender, RomFormat, RomSet, Snapshot, Tape,
+        IoExtender, PixelFormat, RomFormat, RomSet, Snapshot, Tape,
     },
     poke,
     zx::{
-        keys::ZXKey,
+        keys::{KeyboardIssue, ZXKey},
         machine::ZXMachine,
         sound::ay::ZXAYMode,
         video::colors::{ZXBrightness, ZXColor},
+        Page,
     },
-    EmulationMode, EmulationStopReason, Emulator, RustzxSettings,
+    BorderSize, EmulationMode, EmulationStopReason, Emulator, Palette, RustzxSettings,
 };
 use rustzx_utils::{
     io::{DynamicAsset, GzipAsset},
@@ -30,12 +31,11 @@ const FRAME_HOST_DURATION_LIMIT: Duration = Duration::from_millis(100);
 const FRAME_EMULATED_DURATION: Duration = Duration::from_millis(20);
 const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(3);
 
-// TODO(#83): Add tests for gigascreen
-
 struct FrameContent {
     buffer: Vec<u8>,
     width: usize,
     height: usize,
+    gigascreen: bool,
 }
 
 impl FrameBuffer for FrameContent {
@@ -47,30 +47,63 @@ impl FrameBuffer for FrameContent {
         _source: FrameBufferSource,
         context: Self::Context,
     ) -> Self {
-        if context.use_gigascreen {
-            unimplemented!("Gigascreen tests are not yet implemented");
+        // Gigascreen buffer keeps both current and previous frame color
+        // indices of the pixel in a byte, plain buffer packs two pixels
+        let buffer_size = if context.use_gigascreen {
+            width * height
         } else {
-            let buffer_size = (width * height) / 2;
-            Self {
-                buffer: vec![0u8; buffer_size],
-                width,
-                height,
-            }
+            (width * height) / 2
+        };
+        Self {
+            buffer: vec![0u8; buffer_size],
+            width,
+            height,
+            gigascreen: context.use_gigascreen,
         }
     }
 
     fn set_color(&mut self, x: usize, y: usize, color: ZXColor, brightness: ZXBrightness) {
         let pixel_index = x + y * self.width;
+        let indexed_color = (color as u8) + (brightness as u8) * 8;
+        if self.gigascreen {
+            self.buffer[pixel_index] = indexed_color | (indexed_color << 4);
+            return;
+        }
         let buffer_index = pixel_index / 2;
         // 0xF0 mask for even pixels, 0x0F mask for odd pixels
         let mask = 0xF0 >> ((pixel_index % 2) * 4);
-        let indexed_color = (color as u8) + (brightness as u8) * 8;
         // 0x0A => 0xAA, 0x03  => 0x33, etc.
         let color_overlay_byte = indexed_color | (indexed_color << 4);
         // clear previous color nibble and set to new value
         self.buffer[buffer_index] =
             (self.buffer[buffer_index] & (!mask)) | (color_overlay_byte & mask)
     }
+
+    fn set_blended_color(
+        &mut self,
+        x: usize,
+        y: usize,
+        current: (ZXColor, ZXBrightness),
+        previous: (ZXColor, ZXBrightness),
+    ) {
+        if !self.gigascreen {
+            self.set_color(x, y, current.0, current.1);
+            return;
+        }
+        let current = (current.0 as u8) + (current.1 as u8) * 8;
+        let previous = (previous.0 as u8) + (previous.1 as u8) * 8;
+        // Mix of the same colors should not depend on the frames order
+        self.buffer[x + y * self.width] = (current.max(previous) << 4) | current.min(previous);
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(&self.buffer)
+    }
+
+    fn pixel_format(&self) -> Option<PixelFormat> {
+        // Gigascreen pixels are not in the standard format
+        (!self.gigascreen).then_some(PixelFormat::Indexed4)
+    }
 }
 
 impl FrameContent {
@@ -79,9 +112,14 @@ impl FrameContent {
 
         {
             let mut encoder = png::Encoder::new(&mut out, self.width as u32, self.height as u32);
-            encoder.set_depth(png::BitDepth::Four);
             encoder.set_color(png::ColorType::Indexed);
-            encoder.set_palette(make_png_palette());
+            if self.gigascreen {
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_palette(make_blended_png_palette());
+            } else {
+                encoder.set_depth(png::BitDepth::Four);
+                encoder.set_palette(make_png_palette());
+            }
             let mut writer = encoder.write_header().expect("Failed to write PNG header");
             writer
                 .write_image_data(&self.buffer)
@@ -97,13 +135,16 @@ struct TesterFrameBufferContext {
     use_gigascreen: bool,
 }
 
-#[derive(Default)]
-struct TesterContext;
+/// Gigascreen frame buffers are used when the tester is created with
+/// frames blending enabled
+struct TesterContext {
+    use_gigascreen: bool,
+}
 
 impl HostContext<TesterHost> for TesterContext {
     fn frame_buffer_context(&self) -> <FrameContent as FrameBuffer>::Context {
         TesterFrameBufferContext {
-            use_gigascreen: false,
+            use_gigascreen: self.use_gigascreen,
         }
     }
 }
@@ -161,6 +202,7 @@ impl DebugPort {
 struct TestDebugInterface {
     breakpoints: std::collections::HashSet<u16>,
     last_hit: Option<u16>,
+    scanlines: Option<Vec<(u16, ZXColor)>>,
 }
 
 impl TestDebugInterface {
@@ -186,6 +228,12 @@ impl DebugInterface for TestDebugInterface {
         }
         false
     }
+
+    fn on_scanline(&mut self, line: u16, border: ZXColor) {
+        if let Some(scanlines) = &mut self.scanlines {
+            scanlines.push((line, border));
+        }
+    }
 }
 
 struct TesterHost;
@@ -212,18 +260,41 @@ pub mod presets {
     pub fn settings_48k_nosound() -> RustzxSettings {
         RustzxSettings {
             machine: ZXMachine::Sinclair48K,
+            custom_specs: None,
             emulation_mode: EmulationMode::FrameCount(1),
+            cpu_speed_multiplier: 1.0,
+            deterministic: false,
             tape_fastload_enabled: true,
+            tape_fastload_border_frames: 0,
             kempston_enabled: false,
+            second_kempston_enabled: false,
+            fuller_enabled: false,
             mouse_enabled: false,
+            lightgun_enabled: false,
+            printer_enabled: false,
+            beta_disk_enabled: false,
+            border_size: BorderSize::Minimal,
+            initial_border_color: ZXColor::Black,
+            initial_screen_attribute: None,
+            palette: Palette::Modern,
+            keyboard_issue: KeyboardIssue::Issue3,
+            blend_frames: false,
+            flash_rate_frames: Some(16),
+            rewind_snapshots: 0,
+            rewind_interval_frames: 1,
             ay_mode: ZXAYMode::ABC,
             ay_enabled: false,
             beeper_enabled: false,
             sound_enabled: false,
             sound_volume: 100,
+            beeper_lowpass_cutoff: None,
+            mono_output: false,
             sound_sample_rate: DEFAULT_SOUND_BITRATE,
+            sound_oversampling: 1,
             load_default_rom: true,
             autoload_enabled: true,
+            timex_enabled: false,
+            next_enabled: false,
         }
     }
 
@@ -234,6 +305,15 @@ pub mod presets {
         }
     }
 
+    /// +3 ROM is not built-in, it should be loaded by the test
+    pub fn settings_plus3_nosound() -> RustzxSettings {
+        RustzxSettings {
+            machine: ZXMachine::SinclairPlus3,
+            load_default_rom: false,
+            ..settings_48k_nosound()
+        }
+    }
+
     pub fn settings_48k() -> RustzxSettings {
         RustzxSettings {
             sound_enabled: true,
@@ -253,15 +333,22 @@ pub mod presets {
 
 impl RustZXTester {
     pub fn new(test_name: &str, settings: RustzxSettings) -> Self {
-        let emulator = Emulator::new(settings, TesterContext::default())
-            .expect("Failed to initialize emulator");
+        Self::try_new(test_name, settings).expect("Failed to initialize emulator")
+    }
 
-        Self {
+    /// Same as [RustZXTester::new], but returns emulator construction error
+    pub fn try_new(test_name: &str, settings: RustzxSettings) -> rustzx_core::Result<Self> {
+        let context = TesterContext {
+            use_gigascreen: settings.blend_frames,
+        };
+        let emulator = Emulator::new(settings, context)?;
+
+        Ok(Self {
             emulator,
             test_name: test_name.to_owned(),
             sound_buffer: None,
             sync_timeout: DEFAULT_SYNC_TIMEOUT,
-        }
+        })
     }
 
     fn assets_folder(&self) -> PathBuf {
@@ -272,7 +359,8 @@ impl RustZXTester {
         Path::new("test_data/actual").join(&self.test_name)
     }
 
-    fn load_asset_data(&mut self, name: impl AsRef<Path>) -> Vec<u8> {
+    /// Reads test asset, gzip-compressed assets are unpacked
+    pub fn load_asset_data(&mut self, name: impl AsRef<Path>) -> Vec<u8> {
         let path = self.assets_folder().join(name);
         let content = std::fs::read(&path).expect("Failed to load asset");
 
@@ -300,6 +388,63 @@ impl RustZXTester {
             .expect("Failed to load test TAP");
     }
 
+    /// Swaps tape without resetting the machine, see `Emulator::insert_tape`
+    pub fn insert_tap(&mut self, name: impl AsRef<Path>) {
+        let asset = self.load_asset(name);
+        self.emulator
+            .insert_tape(Tape::Tap(asset))
+            .expect("Failed to insert test TAP");
+    }
+
+    /// Loads TAP asset as-is, without host-side decompression of `.gz` files
+    pub fn load_tap_raw(&mut self, name: impl AsRef<Path>) {
+        let content = std::fs::read(self.assets_folder().join(name)).expect("Failed to load asset");
+        self.emulator
+            .load_tape(Tape::Tap(BufferCursor::new(content).into()))
+            .expect("Failed to load test TAP");
+    }
+
+    /// Loads 48K snapshot with empty ram, which starts `program` at 0x8000
+    pub fn load_program(&mut self, program: &[u8]) {
+        const HEADER_SIZE: usize = 27;
+        const SP: u16 = 0xFF00;
+        let mut sna = vec![0; HEADER_SIZE + 0xC000];
+        sna[23..25].copy_from_slice(&SP.to_le_bytes());
+        // Interrupt mode 1
+        sna[25] = 1;
+        let ram = &mut sna[HEADER_SIZE..];
+        ram[0x4000..0x4000 + program.len()].copy_from_slice(program);
+        // PC is popped from the stack on snapshot load
+        let sp = SP as usize - 0x4000;
+        ram[sp..sp + 2].copy_from_slice(&0x8000u16.to_le_bytes());
+        let asset: DynamicAsset = BufferCursor::new(sna).into();
+        self.emulator
+            .load_snapshot(Snapshot::Sna(asset))
+            .expect("Failed to load test program")
+    }
+
+    /// Copies `program` to the beginning of the RAM `bank` and starts it by
+    /// setting PC to the address where the bank is currently paged in
+    pub fn load_program_to_bank(&mut self, bank: usize, program: &[u8]) {
+        self.emulator
+            .ram_bank_mut(bank)
+            .expect("RAM bank should exist on the tested machine")[..program.len()]
+            .copy_from_slice(program);
+        let block = (0..4)
+            .find(|&block| self.emulator.current_ram_bank_at(block) == Some(Page::Ram(bank as u8)))
+            .expect("RAM bank of the test program should be paged in");
+        self.emulator.cpu_mut().regs.set_pc(block as u16 * 0x4000);
+    }
+
+    /// Loads SNA asset as-is, without host-side decompression of `.gz` files
+    pub fn load_sna_raw(&mut self, name: impl AsRef<Path>) {
+        let content = std::fs::read(self.assets_folder().join(name)).expect("Failed to load asset");
+        let asset: DynamicAsset = BufferCursor::new(content).into();
+        self.emulator
+            .load_snapshot(Snapshot::Sna(asset))
+            .expect("Failed to load test SNA")
+    }
+
     pub fn load_sna(&mut self, name: impl AsRef<Path>) {
         let asset = self.load_asset(name);
         self.emulator
@@ -309,11 +454,16 @@ impl RustZXTester {
 
     pub fn load_single_page_rom(&mut self, name: impl AsRef<Path>) {
         let rom_data = self.load_asset_data(name);
-        struct DiagRomSet {
+        self.load_rom_pages(vec![rom_data, vec![0u8; 16 * 1024]]);
+    }
+
+    /// Loads ROM from the provided 16K pages
+    pub fn load_rom_pages(&mut self, pages: Vec<Vec<u8>>) {
+        struct MemoryRomSet {
             pages: VecDeque<Vec<u8>>,
         }
 
-        impl RomSet for DiagRomSet {
+        impl RomSet for MemoryRomSet {
             type Asset = BufferCursor<Vec<u8>>;
 
             fn format(&self) -> RomFormat {
@@ -321,12 +471,12 @@ impl RustZXTester {
             }
 
             fn next_asset(&mut self) -> Option<Self::Asset> {
-                Some(BufferCursor::new(self.pages.pop_front().unwrap()))
+                self.pages.pop_front().map(BufferCursor::new)
             }
         }
 
-        let rom_set = DiagRomSet {
-            pages: VecDeque::from(vec![rom_data, vec![0u8; 16 * 1024]]),
+        let rom_set = MemoryRomSet {
+            pages: VecDeque::from(pages),
         };
 
         self.emulator.load_rom(rom_set).unwrap();
@@ -539,17 +689,35 @@ impl RustZXTester {
 
     /// Disables the message and key press prompt after a few lines of scroll in BASIC
     pub fn disable_scroll_message(&mut self) {
-        self.emulator.execute_poke(poke::DisableScrollMessageRom48);
+        self.emulator
+            .execute_poke(poke::DisableScrollMessageRom48)
+            .expect("Failed to apply poke");
     }
 
-    pub fn add_breakpoint(&mut self, address: u16) {
-        if let Some(interface) = self.emulator.debug_interface() {
-            interface.add_breakpoint(address);
-        } else {
-            let mut interface = TestDebugInterface::default();
-            interface.add_breakpoint(address);
-            self.emulator.set_debug_interface(interface);
+    fn test_debug_interface(&mut self) -> &mut TestDebugInterface {
+        if self.emulator.debug_interface().is_none() {
+            self.emulator
+                .set_debug_interface(TestDebugInterface::default());
         }
+        self.emulator.debug_interface().unwrap()
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.test_debug_interface().add_breakpoint(address);
+    }
+
+    /// Starts recording of scanlines, reported to the debug interface
+    pub fn start_scanline_capture(&mut self) {
+        self.test_debug_interface().scanlines = Some(Vec::new());
+    }
+
+    /// Returns `(line, border color)` pairs, recorded since the last call
+    pub fn take_scanlines(&mut self) -> Vec<(u16, ZXColor)> {
+        self.test_debug_interface()
+            .scanlines
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
     }
 
     pub fn clear_breakpoints(&mut self) {
@@ -580,6 +748,18 @@ fn make_png_palette() -> Vec<u8> {
         })
 }
 
+/// Palette of the gigascreen frame buffer, color with index `first << 4 |
+/// second` is the average of both colors
+fn make_blended_png_palette() -> Vec<u8> {
+    let mut palette = Vec::with_capacity(3 * 256);
+    for first in DEFAULT_PALETTE {
+        for second in DEFAULT_PALETTE {
+            palette.extend((0..3).map(|n| ((first[n] as u16 + second[n] as u16) / 2) as u8));
+        }
+    }
+    palette
+}
+
 fn make_screen_filename(name: impl AsRef<Path>) -> PathBuf {
     name.as_ref().with_extension("screen.png")
 }
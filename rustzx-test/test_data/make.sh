@@ -101,6 +101,10 @@ cat "${BUILD_DIR}/loader_screen.tap" \
     "${BUILD_DIR}/simple_tape_loaderless.tap" \
     > "${BUILD_DIR}/simple_tape.tap"
 gzip --stdout "${BUILD_DIR}/simple_tape.tap" > "${OUT_DIR}/simple_tape.tap.gz"
+# Archive with unrelated file placed before the tape
+echo "simple_tape test archive" > "${BUILD_DIR}/README.txt"
+rm -f "${OUT_DIR}/simple_tape.zip"
+zip -j -X -q "${OUT_DIR}/simple_tape.zip" "${BUILD_DIR}/README.txt" "${BUILD_DIR}/simple_tape.tap"
 log_success "Done"
 log_unindent
 
@@ -0,0 +1,1870 @@
+use expect_test::expect;
+use rustzx_core::{
+    error::{Error, RomLoadError, StateLoadError},
+    poke::{parse_pok, Poke, PokeAction},
+    zx::{
+        constants::{CANVAS_BUFFER_WIDTH, CANVAS_HEIGHT},
+        events::EmulationEvents,
+        heatmap::MemStats,
+        joy::kempston::{KempstonJoyNum, KempstonKey},
+        machine::{ContentionModel, MachineSpec, ZXMachine},
+        video::colors::{ZXBrightness, ZXColor},
+        Page,
+    },
+    BorderSize, EmulationStopReason, IterableEnum, Palette, RustzxSettings, DETERMINISTIC_SEED,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+use rustzx_utils::palette::rgba;
+use std::time::Duration;
+
+#[test]
+fn peek_instruction() {
+    let mut t = RustZXTester::new("peek_instruction", presets::settings_48k_nosound());
+
+    // 48K ROM starts with `DI; XOR A; LD DE, 0xFFFF; JP 0x11CB`
+    assert_eq!(
+        t.emulator().peek_instruction(),
+        ([0xF3, 0xAF, 0x11, 0xFF], 1)
+    );
+
+    t.emulate_until_breakpoint(0x0002, Duration::from_millis(100));
+    assert_eq!(
+        t.emulator().peek_instruction(),
+        ([0x11, 0xFF, 0xFF, 0xC3], 3)
+    );
+}
+
+#[test]
+fn ram_banks_128k() {
+    let mut t = RustZXTester::new("ram_banks_128k", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+
+    // Bank 0 is mapped to 0xC000 after reset, bank 1 is not paged in
+    t.emulator().ram_bank_mut(1).unwrap()[0] = 0xAA;
+    t.emulator().ram_bank_mut(0).unwrap()[0] = 0x55;
+    assert_eq!(t.emulator().peek(0xC000), 0x55);
+    assert_eq!(t.emulator().ram_bank(1).unwrap()[0], 0xAA);
+    assert_eq!(t.emulator().ram_bank(7).unwrap().len(), 0x4000);
+    assert!(t.emulator().ram_bank(8).is_err());
+}
+
+#[test]
+fn ram_banks_48k() {
+    let mut t = RustZXTester::new("ram_banks_48k", presets::settings_48k_nosound());
+
+    t.emulator().ram_bank_mut(2).unwrap()[0x3FFF] = 0x42;
+    assert_eq!(t.emulator().peek(0xFFFF), 0x42);
+    assert!(t.emulator().ram_bank(3).is_err());
+    assert!(t.emulator().ram_bank_mut(5).is_err());
+}
+
+#[test]
+fn video_memory_edits() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+
+    let mut t = RustZXTester::new("video_memory_edits", presets::settings_48k_nosound());
+    t.load_program(PROGRAM);
+    t.emulator()
+        .with_ram_bank_mut(0, |screen| {
+            // White paper
+            screen[0x1800] = 0x38;
+        })
+        .unwrap();
+    t.emulate_frame();
+    t.expect_screen(
+        "white",
+        expect![[r#"zID+KF6jqOy5lm8iQ4H5lPGuak/1KvT9vY26E6NDmho="#]],
+    );
+}
+
+#[test]
+fn video_memory_pokes() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    struct RedPaper;
+    impl Poke for RedPaper {
+        fn actions(&self) -> &[PokeAction] {
+            const ACTIONS: &[PokeAction] = &[PokeAction::mem(0x5801, 0x10)];
+            ACTIONS
+        }
+    }
+
+    let mut t = RustZXTester::new("video_memory_pokes", presets::settings_48k_nosound());
+    t.load_program(PROGRAM);
+    t.emulate_frame();
+    // Program does not touch video memory, poked attribute should be shown
+    // on the next frame anyway
+    t.emulator().execute_poke(RedPaper).unwrap();
+    t.emulate_frame();
+    t.expect_screen(
+        "red",
+        expect![[r#"wmCnMPzgl62VTf0RE02qu13EO5YNbOyJkmH6q6FUeCY="#]],
+    );
+}
+
+#[test]
+fn video_memory_pokes_128k() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+
+    let mut t = RustZXTester::new("video_memory_pokes_128k", presets::settings_128k_nosound());
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator()
+        .with_ram_bank_mut(5, |screen| screen[..6912].fill(0))
+        .unwrap();
+    // Blue paper
+    let trainers = parse_pok("NBlue\nZ  5 22528   8   0\nY\n").unwrap();
+    t.emulator().execute_poke(&trainers[0]).unwrap();
+    t.emulate_frame();
+    t.expect_screen(
+        "blue",
+        expect![[r#"xij5hSI3C9DQRheL0bM4DPMtNFb8DDaNuwVkVLAyuR8="#]],
+    );
+}
+
+#[test]
+fn screen_bytes() {
+    let mut t = RustZXTester::new("screen_bytes_48k", presets::settings_48k_nosound());
+    assert_eq!(t.emulator().current_screen_bank(), 0);
+    t.emulator().ram_bank_mut(0).unwrap()[0x1800] = 0x38;
+    assert_eq!(t.emulator().screen_bytes().len(), 6912);
+    assert_eq!(t.emulator().screen_bytes()[0x1800], 0x38);
+
+    let mut t = RustZXTester::new("screen_bytes_128k", presets::settings_128k_nosound());
+    assert_eq!(t.emulator().current_screen_bank(), 5);
+    t.emulator().ram_bank_mut(5).unwrap()[0x1AFF] = 0x47;
+    assert_eq!(t.emulator().screen_bytes()[0x1AFF], 0x47);
+}
+
+#[test]
+fn export_screenshot() {
+    let mut t = RustZXTester::new("export_screenshot", presets::settings_48k_nosound());
+    t.emulator()
+        .with_ram_bank_mut(0, |screen| {
+            screen[..6912].fill(0);
+            screen[0x0000] = 0xF0;
+            // Bright, blue paper and red ink
+            screen[0x1800] = 0x4A;
+        })
+        .unwrap();
+
+    let scr = t.emulator().export_scr();
+    assert_eq!(scr.len(), 6912);
+    assert_eq!(scr[0x0000], 0xF0);
+    assert_eq!(scr[0x1800], 0x4A);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x02,             // LD A, 2
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+    t.emulate_frame();
+
+    // Minimal border is placed around the screen
+    let png = t.emulator().export_png().unwrap();
+    let (width, pixels) = decode_rgb_png(&png);
+    assert_eq!((width, pixels.len() / 3 / width), (320, 240));
+    let pixel = |x: usize, y: usize| pixels[(y * width + x) * 3..][..3].to_vec();
+    let rgb = |color, brightness| Palette::Modern.rgba(color, brightness)[..3].to_vec();
+    assert_eq!(pixel(0, 0), rgb(ZXColor::Red, ZXBrightness::Normal));
+    assert_eq!(pixel(32, 24), rgb(ZXColor::Red, ZXBrightness::Bright));
+    assert_eq!(pixel(36, 24), rgb(ZXColor::Blue, ZXBrightness::Bright));
+    assert_eq!(pixel(32, 25), rgb(ZXColor::Blue, ZXBrightness::Bright));
+    assert_eq!(pixel(40, 24), rgb(ZXColor::Black, ZXBrightness::Normal));
+}
+
+/// Decodes RGB PNG image, returns its width and pixels
+fn decode_rgb_png(png: &[u8]) -> (usize, Vec<u8>) {
+    let decoder = png::Decoder::new(png);
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!(info.color_type, png::ColorType::RGB);
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    (info.width as usize, pixels)
+}
+
+#[test]
+fn palettes() {
+    let colors = |palette: Palette| -> Vec<[u8; 4]> {
+        [ZXBrightness::Normal, ZXBrightness::Bright]
+            .into_iter()
+            .flat_map(|brightness| (0..8).map(move |bits| (ZXColor::from_bits(bits), brightness)))
+            .map(|(color, brightness)| palette.rgba(color, brightness))
+            .collect()
+    };
+    assert_eq!(colors(Palette::Modern), rgba::ORIGINAL);
+    for palette in Palette::iter() {
+        let colors = colors(palette);
+        // Bright black is still (almost) black and bright colors are lighter
+        assert!(colors[8][..3].iter().all(|&c| c < 0x10), "{:?}", palette);
+        for index in 1..8 {
+            let sum = |color: [u8; 4]| color[..3].iter().map(|&c| c as u32).sum::<u32>();
+            assert!(sum(colors[index + 8]) > sum(colors[index]), "{:?}", palette);
+        }
+    }
+    // Monochrome colors are ordered by luma
+    let green = colors(Palette::MonochromeGreen);
+    assert!(green[..8].windows(2).all(|pair| pair[0][1] < pair[1][1]));
+    assert_eq!(green[15], [0x33, 0xFF, 0x33, 0xFF]);
+
+    // Screenshots use palette from the settings
+    let settings = RustzxSettings {
+        palette: Palette::MonochromeAmber,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("palettes", settings);
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x07,             // LD A, 7
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+    t.emulate_frame();
+    let (_, pixels) = decode_rgb_png(&t.emulator().export_png().unwrap());
+    assert_eq!(pixels[..3], colors(Palette::MonochromeAmber)[7][..3]);
+}
+
+#[test]
+fn power_on_seed() {
+    let mut t = RustZXTester::new("power_on_seed", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+
+    t.emulator().power_on(42);
+    assert_eq!(t.emulator().current_screen_bank(), 5);
+    let first: Vec<Vec<u8>> = (0..8)
+        .map(|bank| t.emulator().ram_bank(bank).unwrap().to_vec())
+        .collect();
+    assert!(first[0].iter().any(|&b| b != 0));
+
+    t.emulate_for(Duration::from_millis(100));
+    t.emulator().power_on(42);
+    for (bank, expected) in first.iter().enumerate() {
+        assert_eq!(t.emulator().ram_bank(bank).unwrap(), expected.as_slice());
+    }
+
+    t.emulator().power_on(43);
+    assert_ne!(t.emulator().ram_bank(0).unwrap(), first[0].as_slice());
+}
+
+#[test]
+fn power_on_r_register() {
+    let mut t = RustZXTester::new("power_on_r_register", presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+    assert_ne!(t.emulator().cpu().regs.get_r(), 0);
+
+    t.emulator().power_on(42);
+    assert_eq!(t.emulator().cpu().regs.get_r(), 0);
+    assert_eq!(t.emulator().cpu().regs.get_pc(), 0x0000);
+
+    // DI; XOR A; LD DE, 0xFFFF; JP START-NEW, then LD B, A; LD A, 0x07;
+    // OUT (0xFE), A; LD A, 0x3F; LD I, A. Prefixed LD I, A refreshes R twice
+    t.emulator().step_instructions(9).unwrap();
+    assert_eq!(t.emulator().cpu().regs.get_pc(), 0x11D4);
+    assert_eq!(t.emulator().cpu().regs.get_r(), 10);
+}
+
+#[test]
+fn deterministic_mode() {
+    let settings = || RustzxSettings {
+        deterministic: true,
+        ..presets::settings_48k_nosound()
+    };
+    let mut first = RustZXTester::new("deterministic_mode", settings());
+    let mut second = RustZXTester::new("deterministic_mode", settings());
+
+    // RAM is filled with the seeded pattern instead of zeroes
+    let mut seeded = RustZXTester::new("deterministic_mode", presets::settings_48k_nosound());
+    let zeroed = seeded.emulator().ram_bank(0).unwrap().to_vec();
+    assert!(zeroed.iter().all(|&b| b == 0));
+    seeded.emulator().power_on(DETERMINISTIC_SEED);
+    for bank in 0..3 {
+        let expected = seeded.emulator().ram_bank(bank).unwrap().to_vec();
+        assert_eq!(
+            first.emulator().ram_bank(bank).unwrap(),
+            expected.as_slice()
+        );
+    }
+
+    // ROM trap would skip loader instructions without R register refresh
+    assert!(!first.emulator().fast_tape_load());
+    first.emulator().set_fast_tape_load(true);
+    assert!(!first.emulator().fast_tape_load());
+
+    for t in [&mut first, &mut second] {
+        t.emulate_for(Duration::from_millis(500));
+    }
+    assert_eq!(
+        first.emulator().cpu().regs.get_r(),
+        second.emulator().cpu().regs.get_r()
+    );
+    assert_eq!(
+        first.emulator().state_fingerprint(),
+        second.emulator().state_fingerprint()
+    );
+}
+
+#[test]
+fn reset_cpu_only() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0x7F,       // LD BC, 0x7FFD
+        0x3E, 0x3F,             // LD A, 0x3F
+        0xED, 0x79,             // OUT (C), A
+        0xED, 0x5E,             // IM 2
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("reset_cpu_only", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+    // Bank 2 is mapped at 0x8000
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().step_instructions(6).unwrap();
+    assert!(t.emulator().is_paging_locked());
+    assert_eq!(t.emulator().current_rom_bank(), 1);
+    assert_eq!(t.emulator().current_ram_bank_at(3), Some(Page::Ram(7)));
+    assert_eq!(t.emulator().current_screen_bank(), 7);
+
+    t.emulator().reset_cpu_only();
+    let regs = &t.emulator().cpu().regs;
+    assert_eq!(regs.get_pc(), 0);
+    assert_eq!(regs.get_i(), 0);
+    assert!(!regs.get_iff1() && !regs.get_iff2());
+    assert_eq!(format!("{:?}", t.emulator().cpu().get_im()), "Im0");
+    assert!(!t.emulator().is_paging_locked());
+    assert_eq!(t.emulator().current_rom_bank(), 0);
+    assert_eq!(t.emulator().current_ram_bank_at(3), Some(Page::Ram(0)));
+    assert_eq!(t.emulator().current_screen_bank(), 5);
+    // RAM is preserved
+    assert_eq!(&t.emulator().ram_bank(2).unwrap()[..PROGRAM.len()], PROGRAM);
+}
+
+#[test]
+fn interrupt_state() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xED, 0x57,             // LD A, I
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("interrupt_state", presets::settings_48k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().set_interrupt_state(false, true, 2).unwrap();
+    assert_eq!(t.emulator().interrupt_state(), (false, true, 2));
+    // Invalid mode is rejected without changing the cpu state
+    assert!(t.emulator().set_interrupt_state(true, false, 3).is_err());
+    assert_eq!(t.emulator().interrupt_state(), (false, true, 2));
+    assert!(!t.emulator().cpu().regs.get_iff1());
+    assert!(t.emulator().cpu().regs.get_iff2());
+    assert_eq!(format!("{:?}", t.emulator().cpu().get_im()), "Im2");
+
+    // P/V flag of `LD A, I` reflects IFF2
+    t.emulator().step_instructions(1).unwrap();
+    assert_ne!(t.emulator().cpu().regs.get_flags() & 0x04, 0);
+    t.emulator().step_instructions(1).unwrap();
+    assert_eq!(t.emulator().interrupt_state(), (false, false, 2));
+}
+
+#[test]
+fn next_mmu() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0x3B, 0x24,       // LD BC, 0x243B
+        0x3E, 0x53,             // LD A, 0x53
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0x25,             // LD B, 0x25
+        0x3E, 0x0E,             // LD A, 14
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x5A,             // LD A, 0x5A
+        0x32, 0x00, 0x60,       // LD (0x6000), A
+        0xED, 0x78,             // IN A, (C)
+        0x18, 0xFE,             // JR $
+    ];
+    // Next registers are not decoded unless enabled in settings
+    let mut t = RustZXTester::new("next_mmu_disabled", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().step_instructions(10).unwrap();
+    assert!(!t.emulator().is_next_mmu_active());
+    assert_eq!(t.emulator().ram_bank(7).unwrap()[0], 0x00);
+
+    let mut settings = presets::settings_128k_nosound();
+    settings.next_enabled = true;
+    let mut t = RustZXTester::new("next_mmu", settings);
+    t.emulate_for(Duration::from_millis(100));
+    assert!(!t.emulator().is_next_mmu_active());
+    assert_eq!(t.emulator().next_mmu_slot(0), Some(0xFF));
+    assert_eq!(t.emulator().next_mmu_slot(3), Some(11));
+    assert_eq!(t.emulator().next_mmu_slot(8), None);
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().step_instructions(10).unwrap();
+
+    // Lower half of bank 7 is paged to 0x6000 by MMU3
+    assert!(t.emulator().is_next_mmu_active());
+    assert_eq!(t.emulator().cpu().regs.get_acc(), 14);
+    assert_eq!(t.emulator().ram_bank(7).unwrap()[0], 0x5A);
+    assert_eq!(t.emulator().next_mmu_slot(2), Some(10));
+    assert_eq!(t.emulator().next_mmu_slot(3), Some(14));
+    assert_eq!(t.emulator().peek(0x6000), 0x5A);
+    // Block reports the bank of its lower half
+    assert_eq!(t.emulator().current_ram_bank_at(1), Some(Page::Ram(5)));
+
+    let state = t.emulator().serialize_state();
+    t.emulator().reset_cpu_only();
+    assert!(!t.emulator().is_next_mmu_active());
+    assert_eq!(t.emulator().next_mmu_slot(3), Some(11));
+    t.emulator().deserialize_state(&state).unwrap();
+    assert_eq!(t.emulator().next_mmu_slot(3), Some(14));
+    assert_eq!(t.emulator().peek(0x6000), 0x5A);
+
+    // Selected register is restored with the state, whole block 1 is then
+    // paged to bank 7 by MMU2 and MMU3
+    #[rustfmt::skip]
+    const REMAP_PROGRAM: &[u8] = &[
+        0x01, 0x3B, 0x25,       // LD BC, 0x253B
+        0xED, 0x78,             // IN A, (C)
+        0x06, 0x24,             // LD B, 0x24
+        0x3E, 0x52,             // LD A, 0x52
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0x25,             // LD B, 0x25
+        0x3E, 0x0E,             // LD A, 14
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0x24,             // LD B, 0x24
+        0x3E, 0x53,             // LD A, 0x53
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0x25,             // LD B, 0x25
+        0x3E, 0x0F,             // LD A, 15
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(2, REMAP_PROGRAM);
+    t.emulator().take_events();
+    t.emulator().take_paged_blocks();
+    t.emulator().step_instructions(2).unwrap();
+    assert_eq!(t.emulator().cpu().regs.get_acc(), 14);
+    t.emulator().step_instructions(6).unwrap();
+    // Lower half of the block is already paged, upper one is not changed yet
+    assert_eq!(t.emulator().current_ram_bank_at(1), Some(Page::Ram(7)));
+    assert!(t
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::MEMORY_PAGED));
+    assert_eq!(t.emulator().take_paged_blocks(), 0b0010);
+    t.emulator().step_instructions(6).unwrap();
+    assert_eq!(t.emulator().current_ram_bank_at(1), Some(Page::Ram(7)));
+    assert_eq!(t.emulator().take_paged_blocks(), 0b0010);
+    // Writing the same page again doesn't change memory map
+    t.emulator().cpu_mut().regs.set_pc(0x8000 + 0x17);
+    t.emulator().step_instructions(4).unwrap();
+    assert_eq!(t.emulator().take_paged_blocks(), 0);
+    assert_eq!(
+        t.emulator().peek(0x4000),
+        t.emulator().ram_bank(7).unwrap()[0]
+    );
+
+    // Reset returns to the 16K memory map
+    t.emulator().reset_cpu_only();
+    assert_eq!(t.emulator().current_ram_bank_at(1), Some(Page::Ram(5)));
+    assert_ne!(t.emulator().take_paged_blocks() & 0b0010, 0);
+}
+
+#[test]
+fn state_fingerprint() {
+    let mut a = RustZXTester::new("state_fingerprint_a", presets::settings_128k_nosound());
+    let mut b = RustZXTester::new("state_fingerprint_b", presets::settings_128k_nosound());
+    a.emulate_for(Duration::from_millis(200));
+    b.emulate_for(Duration::from_millis(200));
+    let fingerprint = a.emulator().state_fingerprint();
+    assert_eq!(fingerprint, b.emulator().state_fingerprint());
+
+    // Fingerprint is stable while state is not changed
+    assert_eq!(fingerprint, a.emulator().state_fingerprint());
+
+    // Diverge single register
+    let bc = b.emulator().cpu().regs.get_bc();
+    b.emulator().cpu_mut().regs.set_bc(bc ^ 0x0001);
+    assert_ne!(fingerprint, b.emulator().state_fingerprint());
+    b.emulator().cpu_mut().regs.set_bc(bc);
+    assert_eq!(fingerprint, b.emulator().state_fingerprint());
+
+    // Diverge single memory byte
+    b.emulator().ram_bank_mut(3).unwrap()[0x1234] ^= 0x80;
+    assert_ne!(fingerprint, b.emulator().state_fingerprint());
+    b.emulator().ram_bank_mut(3).unwrap()[0x1234] ^= 0x80;
+    assert_eq!(fingerprint, b.emulator().state_fingerprint());
+
+    // Pending NMI is the part of the state, but not of the CPU or memory
+    b.emulator().trigger_nmi();
+    assert_ne!(fingerprint, b.emulator().state_fingerprint());
+}
+
+#[test]
+fn headless_frames() {
+    let mut reference = RustZXTester::new("headless_frames", presets::settings_48k_nosound());
+    reference.emulate_for(Duration::from_secs(2));
+
+    let mut t = RustZXTester::new("headless_frames", presets::settings_48k_nosound());
+    let frame: *const _ = t.emulator().emulate_frame().unwrap();
+    assert!(core::ptr::eq(frame, t.emulator().screen_buffer()));
+    t.emulator().emulate_frame_count(99).unwrap();
+
+    assert_eq!(
+        t.emulator().state_fingerprint(),
+        reference.emulator().state_fingerprint()
+    );
+    t.expect_screen(
+        "boot",
+        expect![[r#"6bn8p/VotC0q0cvxEJdG9YLXajnfTyC4asKsvYl8xzI="#]],
+    );
+}
+
+#[test]
+fn pokes() {
+    let mut t = RustZXTester::new("pokes", presets::settings_128k_nosound());
+
+    t.emulator().apply_pokes(&[(0x0000, 0xAA), (0x8000, 0xBB)]);
+    assert_eq!(t.emulator().peek(0x0000), 0xAA);
+    assert_eq!(t.emulator().peek(0x8000), 0xBB);
+
+    // Bank 3 is not paged in, poke should not touch currently mapped bank 0
+    let trainers = parse_pok("NLives\nZ  3 49168  42   0\nY\n").unwrap();
+    t.emulator().execute_poke(&trainers[0]).unwrap();
+    assert_eq!(t.emulator().ram_bank(3).unwrap()[0x10], 42);
+    assert_eq!(t.emulator().ram_bank(0).unwrap()[0x10], 0);
+
+    // Bank which does not exist on the machine is reported
+    struct MissingBank;
+    impl Poke for MissingBank {
+        fn actions(&self) -> &[PokeAction] {
+            const ACTIONS: &[PokeAction] = &[PokeAction::ram_bank(8, 0xC010, 1)];
+            ACTIONS
+        }
+    }
+    assert!(t.emulator().execute_poke(MissingBank).is_err());
+}
+
+/// Returns count of loop iterations executed during single frame with given cpu speed
+fn cpu_speed_loop_count(speed: f64) -> u16 {
+    let mut settings = presets::settings_48k_nosound();
+    settings.cpu_speed_multiplier = speed;
+    let mut t = RustZXTester::new("cpu_speed", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0x00, 0x00,       // LD BC, 0
+        0x03,                   // loop: INC BC
+        0xED, 0x43, 0x00, 0x90, // LD (0x9000), BC
+        0x18, 0xF9,             // JR loop
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+
+    u16::from_le_bytes([t.emulator().peek(0x9000), t.emulator().peek(0x9001)])
+}
+
+#[test]
+fn cpu_speed() {
+    let normal = cpu_speed_loop_count(1.0);
+    let fast = cpu_speed_loop_count(2.0);
+    let slow = cpu_speed_loop_count(0.5);
+    // Single loop iteration takes 36 clocks (with possible memory contention)
+    assert!(normal > 1500 && normal < (69888 / 36) as u16, "{}", normal);
+    assert!(fast.abs_diff(normal * 2) < 10, "{} {}", fast, normal);
+    assert!(slow.abs_diff(normal / 2) < 10, "{} {}", slow, normal);
+    // Out of range values are clamped
+    assert_eq!(cpu_speed_loop_count(100.0), cpu_speed_loop_count(16.0));
+    // Non-finite values fall back to the normal speed
+    assert_eq!(cpu_speed_loop_count(f64::NAN), normal);
+    assert_eq!(cpu_speed_loop_count(f64::INFINITY), normal);
+}
+
+#[test]
+fn disassemble() {
+    let mut t = RustZXTester::new("disassemble", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(100));
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xDD, 0x36, 0xFE, 0x07, // LD (IX-2), 7
+        0xFD, 0xCB, 0x03, 0x16, // RL (IY+3)
+        0xDD, 0xCB, 0x01, 0xC7, // SET 0, (IX+1), A
+        0xED, 0x4B, 0x00, 0x90, // LD BC, (0x9000)
+        0x18, 0xEE,             // JR 0xC000
+    ];
+    // Bank 0 is mapped to 0xC000 after reset
+    t.load_program_to_bank(0, PROGRAM);
+
+    let text = t
+        .emulator()
+        .disassemble(0xC000, 5)
+        .iter()
+        .map(|i| {
+            let bytes: Vec<String> = i.raw_bytes().iter().map(|b| format!("{:02X}", b)).collect();
+            format!(
+                "{:04X}: {:<12} {}\n",
+                i.address,
+                bytes.join(" "),
+                i.mnemonic
+            )
+        })
+        .collect::<String>();
+    expect![[r#"
+        C000: DD 36 FE 07  LD (IX-0x02), 0x07
+        C004: FD CB 03 16  RL (IY+0x03)
+        C008: DD CB 01 C7  SET 0, (IX+0x01), A
+        C00C: ED 4B 00 90  LD BC, (0x9000)
+        C010: 18 EE        JR 0xC000
+    "#]]
+    .assert_eq(&text);
+}
+
+#[test]
+fn cpu_halted() {
+    let mut t = RustZXTester::new("cpu_halted", presets::settings_48k_nosound());
+    t.emulate_frame();
+    assert!(!t.emulator().is_halted());
+    t.emulator().take_events();
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x76,                   // HALT
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+
+    assert!(t.emulator().is_halted());
+    assert!(t
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::CPU_HALTED));
+    assert!(t.emulator().take_events().is_empty());
+}
+
+#[test]
+fn paging_plus3() {
+    let mut t = RustZXTester::new("paging_plus3", presets::settings_plus3_nosound());
+    // Each ROM page is filled with its own marker byte
+    t.load_rom_pages((0..4).map(|page| vec![0xA0 + page; 16 * 1024]).collect());
+    t.emulator().ram_bank_mut(0).unwrap()[0] = 0x55;
+    t.emulator().ram_bank_mut(1).unwrap()[0] = 0x66;
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3A, 0x00, 0x00,       // LD A, (0x0000)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x01, 0xFD, 0x1F,       // LD BC, 0x1FFD
+        0x3E, 0x04,             // LD A, 0x04 ; ROM high bit
+        0xED, 0x79,             // OUT (C), A
+        0x3A, 0x00, 0x00,       // LD A, (0x0000)
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0x06, 0x7F,             // LD B, 0x7F
+        0x3E, 0x10,             // LD A, 0x10 ; ROM low bit
+        0xED, 0x79,             // OUT (C), A
+        0x3A, 0x00, 0x00,       // LD A, (0x0000)
+        0x32, 0x02, 0x90,       // LD (0x9002), A
+        0x06, 0x1F,             // LD B, 0x1F
+        0x3E, 0x01,             // LD A, 0x01 ; Special paging, banks 0, 1, 2, 3
+        0xED, 0x79,             // OUT (C), A
+        0x3A, 0x00, 0x00,       // LD A, (0x0000)
+        0x32, 0x03, 0x90,       // LD (0x9003), A
+        0x18, 0xFE,             // JR $
+    ];
+    // Bank 2 is mapped to 0x8000 in both normal mode and the first special configuration
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulate_frame();
+
+    let results: Vec<u8> = (0x9000..0x9004).map(|a| t.emulator().peek(a)).collect();
+    assert_eq!(results, [0xA0, 0xA2, 0xA3, 0x55]);
+    assert_eq!(t.emulator().peek(0x4000), 0x66);
+}
+
+#[test]
+fn load_rom_data() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair128K,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("load_rom_data", settings);
+    let (page0, page1) = (vec![0x11; 16 * 1024], vec![0x22; 16 * 1024]);
+    t.emulator().load_rom_data(&[&page0, &page1]).unwrap();
+    assert_eq!(t.emulator().peek(0x0000), 0x11);
+
+    // Single image with all pages
+    let image = [page1.as_slice(), page0.as_slice()].concat();
+    t.emulator().load_rom_data(&[&image]).unwrap();
+    assert_eq!(t.emulator().peek(0x3FFF), 0x22);
+
+    // 48K ROM does not fit 128K machine, ROM is not changed
+    assert!(matches!(
+        t.emulator().load_rom_data(&[&page0]),
+        Err(Error::RomLoad(RomLoadError::InvalidRomSize))
+    ));
+    assert_eq!(t.emulator().peek(0x0000), 0x22);
+}
+
+#[test]
+fn missing_embedded_rom() {
+    let settings = RustzxSettings {
+        load_default_rom: true,
+        ..presets::settings_plus3_nosound()
+    };
+    assert!(matches!(
+        RustZXTester::try_new("missing_embedded_rom", settings),
+        Err(Error::RomLoad(RomLoadError::NoEmbeddedRom))
+    ));
+    assert!(
+        RustZXTester::try_new("missing_embedded_rom", presets::settings_plus3_nosound()).is_ok()
+    );
+}
+
+#[test]
+fn paging_state_128k() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair128K,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("paging_state_128k", settings);
+    assert!(!t.emulator().is_paging_locked());
+    assert_eq!(t.emulator().current_rom_bank(), 0);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0x7F,       // LD BC, 0x7FFD
+        0x3E, 0x13,             // LD A, 0x13 ; ROM 1, bank 3
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x34,             // LD A, 0x34 ; ROM 1, bank 4, lock
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x01,             // LD A, 0x01 ; Ignored
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulate_frame();
+
+    assert!(t.emulator().is_paging_locked());
+    assert_eq!(t.emulator().current_rom_bank(), 1);
+    let map: Vec<Page> = (0..4)
+        .filter_map(|block| t.emulator().current_ram_bank_at(block))
+        .collect();
+    assert_eq!(
+        map,
+        [Page::Rom(1), Page::Ram(5), Page::Ram(2), Page::Ram(4)]
+    );
+    assert_eq!(t.emulator().current_ram_bank_at(4), None);
+
+    // Paging is never locked on 48K
+    let mut t = RustZXTester::new("paging_state_48k", presets::settings_48k_nosound());
+    assert!(!t.emulator().is_paging_locked());
+    assert_eq!(t.emulator().current_ram_bank_at(3), Some(Page::Ram(2)));
+}
+
+#[test]
+fn memory_paged_event() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair128K,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("memory_paged_event", settings);
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0x7F,       // LD BC, 0x7FFD
+        0x3E, 0x10,             // LD A, 0x10 ; ROM 1
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x13,             // LD A, 0x13 ; ROM 1, bank 3
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x13,             // LD A, 0x13 ; Same memory map
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x1B,             // LD A, 0x1B ; Shadow screen only
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().take_events();
+    t.emulator().take_paged_blocks();
+
+    let step = |t: &mut RustZXTester, count| {
+        t.emulator().step_instructions(count).unwrap();
+        let paged = t
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::MEMORY_PAGED);
+        (paged, t.emulator().take_paged_blocks())
+    };
+    assert_eq!(step(&mut t, 4), (true, 0b0001));
+    assert_eq!(step(&mut t, 2), (true, 0b1000));
+    assert_eq!(step(&mut t, 2), (false, 0));
+    assert_eq!(step(&mut t, 2), (false, 0));
+}
+
+#[test]
+fn shadow_screen_switched_event() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair128K,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("shadow_screen_switched_event", settings);
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0x7F,       // LD BC, 0x7FFD
+        0x3E, 0x08,             // LD A, 0x08 ; Shadow screen
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x0B,             // LD A, 0x0B ; Shadow screen, bank 3
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x03,             // LD A, 0x03 ; Normal screen, bank 3
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().take_events();
+
+    let step = |t: &mut RustZXTester, count| {
+        t.emulator().step_instructions(count).unwrap();
+        let switched = t
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::SHADOW_SCREEN_SWITCHED);
+        (switched, t.emulator().current_screen_bank())
+    };
+    assert_eq!(step(&mut t, 4), (true, 7));
+    assert_eq!(step(&mut t, 2), (false, 7));
+    assert_eq!(step(&mut t, 2), (true, 5));
+}
+
+#[test]
+fn interrupt_length_and_raster_position() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0x18, 0xFE,             // JR $
+    ];
+    let run = |interrupt_length: Option<u32>| {
+        let mut t = RustZXTester::new("interrupt_length", presets::settings_48k_nosound());
+        assert_eq!(t.emulator().interrupt_length(), 32);
+        if let Some(clocks) = interrupt_length {
+            t.emulator().set_interrupt_length(clocks);
+        }
+        t.emulate_frame();
+        t.load_program_to_bank(1, PROGRAM);
+        t.emulate_frame();
+
+        // Frame ends after the instruction crossing its last clock, beam is
+        // back at the top
+        let (line, col) = t.emulator().raster_position();
+        assert_eq!(line, 0);
+        assert!(col < 23);
+        t.emulator().is_halted()
+    };
+    // Interrupt is accepted right after HALT at the start of the frame
+    assert!(!run(None));
+    // Zero-length interrupt is never seen by the cpu
+    assert!(run(Some(0)));
+}
+
+/// Returns count of interrupts accepted during the single INT signal, when
+/// the handler re-enables interrupts right away
+fn interrupt_reaccept_count(speed: f64) -> u8 {
+    let mut settings = presets::settings_48k_nosound();
+    settings.cpu_speed_multiplier = speed;
+    let mut t = RustZXTester::new("interrupt_cpu_speed", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xED, 0x56,             // IM 1
+        0x06, 0x00,             // LD B, 0
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0xF3,                   // DI
+        0x78,                   // LD A, B
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x18, 0xFE,             // JR $
+    ];
+    // INC B; EI; RET
+    t.emulator()
+        .apply_pokes(&[(0x0038, 0x04), (0x0039, 0xFB), (0x003A, 0xC9)]);
+    t.emulator().set_interrupt_length(100);
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+    t.emulate_frame();
+    t.emulator().peek(0x9000)
+}
+
+#[test]
+fn interrupt_length_cpu_speed() {
+    // Acceptance with the handler takes 31 clocks, so the 100 clocks long
+    // signal is accepted 3 times
+    let normal = interrupt_reaccept_count(1.0);
+    assert_eq!(normal, 3);
+    // Signal length is measured in cpu clocks
+    assert_eq!(interrupt_reaccept_count(4.0), normal);
+    assert_eq!(interrupt_reaccept_count(0.5), normal);
+}
+
+#[test]
+fn interrupt_window_per_machine() {
+    // Instructions after `EI`, which shift the interrupt check by all
+    // possible clock remainders: NOP, INC HL, LD A, n and LD A, I
+    const PROBES: [&[u8]; 4] = [&[0x00], &[0x23], &[0x3E, 0x00], &[0xED, 0x57]];
+    let machines = [
+        (ZXMachine::Sinclair48K, 69888, 224, 32, [1, 2]),
+        (ZXMachine::Sinclair128K, 70908, 228, 36, [2, 0]),
+        (ZXMachine::SinclairPlus3, 70908, 228, 32, [2, 0]),
+    ];
+    for (machine, clocks_frame, clocks_line, length, banks) in machines {
+        let targets = (clocks_frame - 24..clocks_frame).chain(length - 24..length + 4);
+        let mut checked = Vec::new();
+        for target in targets.step_by(4) {
+            for probe in PROBES {
+                let settings = RustzxSettings {
+                    machine,
+                    load_default_rom: machine.capabilities().has_embedded_rom,
+                    ..presets::settings_48k_nosound()
+                };
+                let mut t = RustZXTester::new("interrupt_window", settings);
+                // `DI` followed by NOPs at 0x8000-0xEFFF, probe at 0xF000
+                for bank in banks {
+                    t.emulator().ram_bank_mut(bank).unwrap().fill(0);
+                }
+                t.emulator().ram_bank_mut(banks[0]).unwrap()[0] = 0xF3;
+                let high = t.emulator().ram_bank_mut(banks[1]).unwrap();
+                high[0x3000] = 0xFB;
+                high[0x3001..0x3001 + probe.len()].copy_from_slice(probe);
+                t.emulator().cpu_mut().regs.set_pc(0x8000);
+                t.emulator().run_until_clocks(target).unwrap();
+                t.emulator().cpu_mut().regs.set_pc(0xF000);
+                // Interrupt is checked after the instruction following `EI`
+                t.emulator().step_instructions(2).unwrap();
+                let (line, col) = t.emulator().raster_position();
+                let clocks = line as u32 * clocks_line + col as u32;
+                t.emulator().step().unwrap();
+                let accepted = t.emulator().last_interrupt_latency();
+                assert_eq!(
+                    accepted.is_some(),
+                    clocks < length,
+                    "{:?} at {}",
+                    machine,
+                    clocks
+                );
+                if let Some(latency) = accepted {
+                    assert_eq!(latency, clocks);
+                }
+                checked.push(clocks);
+            }
+        }
+        // INT goes active at the first clock of the frame
+        for clocks in [clocks_frame - 1, 0, length - 1, length] {
+            assert!(checked.contains(&clocks), "{:?} {}", machine, clocks);
+        }
+    }
+}
+
+#[test]
+fn run_until_clocks() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("run_until_clocks", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    let clocks = |t: &mut RustZXTester| {
+        let (line, col) = t.emulator().raster_position();
+        line as usize * 224 + col as usize
+    };
+
+    // `DI` and 83 `JR $` in the uncontended memory
+    assert_eq!(t.emulator().run_until_clocks(1000).unwrap(), 84);
+    assert_eq!(clocks(&mut t), 1000);
+    // Target has already passed
+    assert_eq!(t.emulator().run_until_clocks(990).unwrap(), 0);
+    t.emulator().run_until_clocks(1005).unwrap();
+    assert_eq!(clocks(&mut t), 1012);
+    // Target in the next frame, `JR $` crossing the frame boundary ends at
+    // clock 4 of the next frame
+    assert_eq!(t.emulator().run_until_clocks(69888 + 100).unwrap(), 5748);
+    assert_eq!(clocks(&mut t), 100);
+}
+
+#[test]
+fn run_realtime() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("run_realtime", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    let frame = t.emulator().frame_duration();
+    let mut run = |dt: &[Duration]| {
+        t.emulator().reset_instruction_counter();
+        for &dt in dt {
+            t.emulator().run_realtime(dt).unwrap();
+        }
+        t.emulator().instructions_executed()
+    };
+
+    // 48K frame (69888 clocks at 3.5 MHz) takes 19.968 ms: `DI` and 5824
+    // `JR $`, the last one ends 4 clocks past the frame
+    assert_eq!(frame, Duration::from_micros(19968));
+    assert_eq!(run(&[frame]), 5825);
+    // Fractions of the clock and overrun are carried over between calls
+    assert_eq!(run(&[frame / 10; 10]), 5824);
+    // Lag is limited to 5 frames
+    assert_eq!(run(&[Duration::from_secs(10)]), 29120);
+
+    // Host clock is started by the first call
+    t.emulator().reset_instruction_counter();
+    let reason = t.emulator().run_realtime_elapsed().unwrap();
+    assert_eq!(reason, EmulationStopReason::Completed);
+    assert_eq!(t.emulator().instructions_executed(), 0);
+    // ...and restarted after resume
+    t.emulator().pause();
+    t.emulator().resume();
+    t.emulator().run_realtime_elapsed().unwrap();
+    assert_eq!(t.emulator().instructions_executed(), 0);
+}
+
+#[test]
+fn pause() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("pause", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().run_until_clocks(1000).unwrap();
+    let raster = t.emulator().raster_position();
+    t.emulator().reset_instruction_counter();
+
+    t.emulator().pause();
+    assert!(t.emulator().is_paused());
+    let info = t.emulator().emulate_frames(Duration::MAX).unwrap();
+    assert_eq!(info.stop_reason, EmulationStopReason::Paused);
+    assert_eq!(t.emulator().step().unwrap(), EmulationStopReason::Paused);
+    let reason = t.emulator().step_over(Duration::MAX).unwrap();
+    assert_eq!(reason, EmulationStopReason::Paused);
+    let reason = t.emulator().run_realtime(Duration::from_secs(1)).unwrap();
+    assert_eq!(reason, EmulationStopReason::Paused);
+    assert_eq!(t.emulator().run_until_clocks(5000).unwrap(), 0);
+    t.emulator().emulate_frame_count(3).unwrap();
+    assert_eq!(t.emulator().emulate_turbo_frames().unwrap(), 0);
+    assert_eq!(t.emulator().instructions_executed(), 0);
+    assert_eq!(t.emulator().raster_position(), raster);
+
+    // Emulation continues from the same frame position, `DI` and 83 `JR $`
+    // have ended exactly at 1000
+    t.emulator().resume();
+    assert!(!t.emulator().is_paused());
+    assert_eq!(t.emulator().run_until_clocks(1096).unwrap(), 8);
+}
+
+#[test]
+fn profile_until() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x06, 0x0A,             // LD B, 10
+        0x10, 0xFE,             // DJNZ $
+        0x00,                   // NOP
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("profile_until", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    let profile = |t: &mut RustZXTester, addr| {
+        let info = t.emulator().profile_until(addr, Duration::MAX).unwrap();
+        assert_eq!(info.stop_reason, EmulationStopReason::Completed);
+        info.clocks
+    };
+
+    // 4 + 7 + 9 * 13 + 8
+    assert_eq!(profile(&mut t, 0x8005), 136);
+    // Single loop iteration
+    t.emulator().step().unwrap();
+    assert_eq!(profile(&mut t, 0x8006), 12);
+
+    // Frame wrap in the middle of the routine is accounted
+    t.emulator().run_until_clocks(69888 - 50).unwrap();
+    t.emulator().cpu_mut().regs.set_pc(0x8001);
+    assert_eq!(profile(&mut t, 0x8005), 132);
+    assert_eq!(t.emulator().raster_position().0, 0);
+
+    // Cpu speed does not change measured clocks
+    t.emulator().set_cpu_speed(2.0);
+    t.emulator().cpu_mut().regs.set_pc(0x8001);
+    assert_eq!(profile(&mut t, 0x8005), 132);
+    t.emulator().set_cpu_speed(1.0);
+
+    let info = t.emulator().profile_until(0x9000, Duration::ZERO).unwrap();
+    assert_eq!(info.stop_reason, EmulationStopReason::Timeout);
+    assert!(info.clocks > 0);
+}
+
+#[test]
+fn memory_heatmap() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x21, 0x00, 0x90,       // LD HL, 0x9000
+        0x06, 0x04,             // LD B, 4
+        0x77,                   // LD (HL), A
+        0x23,                   // INC HL
+        0x10, 0xFC,             // DJNZ -4
+        0x3A, 0x00, 0xA0,       // LD A, (0xA000)
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("memory_heatmap", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().reset_memory_heatmap();
+    assert!(t
+        .emulator()
+        .memory_heatmap()
+        .iter()
+        .all(|s| *s == MemStats::default()));
+
+    t.emulator().step_instructions(16).unwrap();
+    let heatmap = t.emulator().memory_heatmap();
+    assert_eq!(heatmap.len(), 256);
+    // Opcode fetches are counted as reads
+    let code = MemStats {
+        reads: 25,
+        writes: 0,
+        executes: 16,
+    };
+    assert_eq!(heatmap[0x80], code);
+    assert_eq!(heatmap[0x90].writes, 4);
+    assert_eq!(heatmap[0xA0].reads, 1);
+    let total_reads: u64 = heatmap.iter().map(|s| s.reads).sum();
+    assert_eq!(total_reads, 26);
+}
+
+#[test]
+fn trigger_nmi() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("trigger_nmi", presets::settings_48k_nosound());
+    t.load_program_to_bank(1, PROGRAM);
+    // Interrupts are not checked right after `DI`
+    t.emulator().step_instructions(2).unwrap();
+    let sp = t.emulator().cpu().regs.get_sp();
+
+    t.emulator().trigger_nmi();
+    assert!(t.emulator().nmi_pending());
+    let state = t.emulator().serialize_state();
+    // NMI is accepted before the instruction, `PUSH AF` at 0x0066 is executed
+    t.emulator().step().unwrap();
+    assert!(!t.emulator().nmi_pending());
+    assert_eq!(t.emulator().cpu().regs.get_pc(), 0x0067);
+    assert_eq!(t.emulator().cpu().regs.get_sp(), sp.wrapping_sub(4));
+    let ret = u16::from_le_bytes([
+        t.emulator().peek(sp.wrapping_sub(2)),
+        t.emulator().peek(sp.wrapping_sub(1)),
+    ]);
+    assert_eq!(ret, 0x8001);
+    // NMI is not accepted again
+    t.emulator().step().unwrap();
+    assert_eq!(t.emulator().cpu().regs.get_pc(), 0x0068);
+    // Pending NMI is restored with the state
+    t.emulator().deserialize_state(&state).unwrap();
+    assert!(t.emulator().nmi_pending());
+}
+
+#[test]
+fn custom_machine_specs() {
+    // Pentagon timings: longer frame without contended memory
+    let spec = MachineSpec {
+        clocks_line: 224,
+        clocks_frame: 71680,
+        interrupt_length: 36,
+        contention_model: ContentionModel::None,
+        clocks_first_pixel: 17988,
+    };
+    assert!(MachineSpec::preset(ZXMachine::Sinclair48K)
+        .validate()
+        .is_ok());
+    assert!(spec.validate().is_ok());
+    let settings = RustzxSettings {
+        custom_specs: Some(spec),
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("custom_machine_specs", settings);
+    assert_eq!(t.emulator().interrupt_length(), 36);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    // Program is placed in the contended memory of the 48K machine
+    t.load_program_to_bank(0, PROGRAM);
+    t.emulator().step().unwrap();
+    let clocks = |t: &mut RustZXTester| {
+        let (line, col) = t.emulator().raster_position();
+        line as usize * 224 + col as usize
+    };
+    let start = clocks(&mut t);
+    // Each `JR $` takes exactly 12 clocks during the whole frame
+    t.emulator().step_instructions(6000).unwrap();
+    assert_eq!(clocks(&mut t), (start + 6000 * 12) % 71680);
+    // Frame rate is defined by the frame length
+    assert_eq!(t.emulator().frame_duration(), Duration::from_micros(20480));
+
+    // State of the machine with different timings is rejected
+    let mut reference = RustZXTester::new("custom_machine_specs", presets::settings_48k_nosound());
+    let state = reference.emulator().serialize_state();
+    assert!(matches!(
+        t.emulator().deserialize_state(&state),
+        Err(Error::StateLoad(StateLoadError::MachineMismatch))
+    ));
+}
+
+#[test]
+fn interrupt_accepted_event() {
+    let run = |program: &[u8]| {
+        let mut t = RustZXTester::new("interrupt_accepted", presets::settings_48k_nosound());
+        t.load_program_to_bank(1, program);
+        t.emulate_frame();
+        t.emulator().take_events();
+        t.emulate_frame();
+        let accepted = t
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::INTERRUPT_ACCEPTED);
+        (accepted, t.emulator().last_interrupt_latency())
+    };
+
+    #[rustfmt::skip]
+    const HALTED: &[u8] = &[
+        0xFB,                   // loop: EI
+        0x76,                   // HALT
+        0x18, 0xFC,             // JR loop
+    ];
+    let (accepted, latency) = run(HALTED);
+    assert!(accepted);
+    // Halted cpu accepts interrupt at the end of the current NOP
+    assert!(latency.unwrap() < 8, "{:?}", latency);
+
+    #[rustfmt::skip]
+    const DISABLED: &[u8] = &[
+        0xF3,                   // DI
+        0x18, 0xFE,             // JR $
+    ];
+    assert_eq!(run(DISABLED), (false, None));
+}
+
+#[test]
+fn im2_bus_value() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x90,             // LD A, 0x90
+        0xED, 0x47,             // LD I, A
+        0xED, 0x5E,             // IM 2
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0x18, 0xFE,             // JR $
+    ];
+    let run = |bus_value: Option<u8>| {
+        let mut t = RustZXTester::new("im2_bus_value", presets::settings_48k_nosound());
+        t.emulator().set_im2_bus_value(bus_value);
+        t.load_program_to_bank(1, PROGRAM);
+        let ram = t.emulator().ram_bank_mut(1).unwrap();
+        // Vector table entries at 0x9010 and 0x90FF
+        ram[0x1010..0x1012].copy_from_slice(&[0x00, 0xA0]);
+        ram[0x10FF..0x1101].copy_from_slice(&[0x00, 0xB0]);
+        // Handlers store their marker to 0x9300
+        for (offset, marker) in [(0x2000, 0x01), (0x3000, 0x02)] {
+            ram[offset..offset + 7].copy_from_slice(&[0x3E, marker, 0x32, 0x00, 0x93, 0x18, 0xFE]);
+        }
+        t.emulate_frame();
+        t.emulate_frame();
+        t.emulator().peek(0x9300)
+    };
+    // Floating bus value is used by default
+    assert_eq!(run(None), 0x02);
+    assert_eq!(run(Some(0xFF)), 0x02);
+    assert_eq!(run(Some(0x10)), 0x01);
+}
+
+#[test]
+fn second_kempston() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xDB, 0x1F,             // IN A, (0x1F)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0xDB, 0x3F,             // IN A, (0x3F)
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0x18, 0xFE,             // JR $
+    ];
+    let run = |second_kempston_enabled: bool| {
+        let settings = RustzxSettings {
+            kempston_enabled: true,
+            second_kempston_enabled,
+            ..presets::settings_48k_nosound()
+        };
+        let mut t = RustZXTester::new("second_kempston", settings);
+        t.emulator()
+            .send_joystick(KempstonJoyNum::First, KempstonKey::Fire, true);
+        t.emulator()
+            .send_joystick(KempstonJoyNum::Second, KempstonKey::Left, true);
+        t.emulator().send_kempston_key(KempstonKey::Up, true);
+        t.load_program_to_bank(1, PROGRAM);
+        t.emulate_frame();
+        (t.emulator().peek(0x9000), t.emulator().peek(0x9001))
+    };
+    assert_eq!(run(true), (0x18, 0x02));
+    // Without the second joystick port 0x3F is not decoded by the joystick
+    let (first, unused) = run(false);
+    assert_eq!(first, 0x18);
+    assert_ne!(unused, 0x02);
+}
+
+#[test]
+fn borrow_completed_frame() {
+    let mut t = RustZXTester::new("borrow_completed_frame", presets::settings_48k_nosound());
+    t.emulate_frame();
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x02,             // LD A, 0x02
+        0xD3, 0xFE,             // OUT (0xFE), A ; red border
+        0x18, 0xFE,             // JR $
+    ];
+    #[rustfmt::skip]
+    const MID_FRAME: &[u8] = &[
+        0x3E, 0x04,             // LD A, 0x04
+        0xD3, 0xFE,             // OUT (0xFE), A ; green border
+        0x01, 0x00, 0x08,       // LD BC, 0x0800
+        0x0B,                   // DEC BC
+        0x78,                   // LD A, B
+        0xB1,                   // OR C
+        0x20, 0xFB,             // JR NZ, -5
+        0x3E, 0x01,             // LD A, 0x01
+        0xD3, 0xFE,             // OUT (0xFE), A ; blue border
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().ram_bank_mut(1).unwrap()[0x100..0x100 + MID_FRAME.len()]
+        .copy_from_slice(MID_FRAME);
+    t.emulate_frame();
+
+    let screen = t.emulator().screen_frame().unwrap();
+    assert_eq!(
+        (screen.width, screen.height),
+        (CANVAS_BUFFER_WIDTH, CANVAS_HEIGHT)
+    );
+    // Test frame buffer packs two 4-bit pixels per byte
+    assert_eq!(screen.data.len(), CANVAS_BUFFER_WIDTH * CANVAS_HEIGHT / 2);
+
+    // Stop in the middle of the frame, border of the previous frame is intact
+    t.emulator().cpu_mut().regs.set_pc(0x8100);
+    t.emulate_until_breakpoint(0x8110, Duration::from_millis(100));
+    let border = t.emulator().border_frame().unwrap();
+    assert_eq!((border.width, border.height), (320, 240));
+    assert!(border.data.iter().all(|&b| b == 0x22));
+
+    t.clear_breakpoints();
+    t.emulate_frame();
+    let border = t.emulator().border_frame().unwrap();
+    assert_eq!(border.data[0], 0x44);
+    assert_eq!(border.data[border.data.len() - 1], 0x11);
+}
+
+#[test]
+fn initial_boot_screen() {
+    for machine in [ZXMachine::Sinclair48K, ZXMachine::Sinclair128K] {
+        let mut settings = presets::settings_48k_nosound();
+        settings.machine = machine;
+        settings.initial_border_color = ZXColor::Blue;
+        settings.initial_screen_attribute = Some(0x38);
+        let mut t = RustZXTester::new("initial_boot_screen", settings);
+        let check = |t: &mut RustZXTester| {
+            assert_eq!(u8::from(t.emulator().border_color()), 1);
+            let screen = t.emulator().screen_bytes();
+            assert!(screen[..0x1800].iter().all(|&b| b == 0));
+            assert!(screen[0x1800..].iter().all(|&b| b == 0x38));
+        };
+        check(&mut t);
+        // Power-on memory pattern does not override the screen
+        t.emulator().power_on(42);
+        check(&mut t);
+    }
+
+    // Screen memory is left as is by default
+    let mut t = RustZXTester::new("initial_boot_screen", presets::settings_48k_nosound());
+    t.emulator().power_on(42);
+    assert_eq!(u8::from(t.emulator().border_color()), 0);
+    assert!(t.emulator().screen_bytes().iter().any(|&b| b != 0));
+}
+
+#[test]
+fn border_size() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0x01, 0x00, 0x02,       // LD BC, 0x0200
+        0x0B,                   // DEC BC
+        0x78,                   // LD A, B
+        0xB1,                   // OR C
+        0x20, 0xFB,             // JR NZ, -5
+        0x3E, 0x02,             // LD A, 0x02
+        0xD3, 0xFE,             // OUT (0xFE), A ; red border
+        0x01, 0x00, 0x06,       // LD BC, 0x0600
+        0x0B,                   // DEC BC
+        0x78,                   // LD A, B
+        0xB1,                   // OR C
+        0x20, 0xFB,             // JR NZ, -5
+        0x3E, 0x01,             // LD A, 0x01
+        0xD3, 0xFE,             // OUT (0xFE), A ; blue border
+        0x18, 0xE5,             // JR -27
+    ];
+    let render = |size: BorderSize| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.border_size = size;
+        let mut t = RustZXTester::new("border_size", settings);
+        t.load_program_to_bank(1, PROGRAM);
+        for _ in 0..3 {
+            t.emulate_frame();
+        }
+        assert_eq!(t.emulator().border_size(), size);
+        let border = t.emulator().border_frame().unwrap();
+        assert_eq!(
+            (border.width, border.height),
+            (size.screen_width(), size.screen_height())
+        );
+        (border.width, border.data.to_vec())
+    };
+    // Returns region of the bigger frame, which is covered by the smaller one
+    let crop = |(width, data): &(usize, Vec<u8>), from: BorderSize, to: BorderSize| {
+        let x = (from.border_width() - to.border_width()) / 2;
+        let y = from.border_height() - to.border_height();
+        data.chunks(width / 2)
+            .skip(y)
+            .take(to.screen_height())
+            .flat_map(|row| row[x..x + to.screen_width() / 2].iter().copied())
+            .collect::<Vec<_>>()
+    };
+
+    let none = render(BorderSize::None);
+    let minimal = render(BorderSize::Minimal);
+    let full = render(BorderSize::Full);
+    assert_eq!((none.0, minimal.0, full.0), (256, 320, 352));
+    // Border changes color during the frame
+    assert!(minimal.1.contains(&0x22) && minimal.1.contains(&0x11));
+    // Beam position does not depend on the border size
+    assert_eq!(
+        crop(&full, BorderSize::Full, BorderSize::Minimal),
+        minimal.1
+    );
+    assert_eq!(
+        crop(&minimal, BorderSize::Minimal, BorderSize::None),
+        none.1
+    );
+}
+
+#[test]
+fn scanline_callback() {
+    let mut t = RustZXTester::new("scanline_callback", presets::settings_48k_nosound());
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0x3E, 0x02,             // LD A, 0x02
+        0xD3, 0xFE,             // OUT (0xFE), A ; red border
+        0x01, 0x00, 0x02,       // LD BC, 0x0200
+        0x0B,                   // DEC BC
+        0x78,                   // LD A, B
+        0xB1,                   // OR C
+        0x20, 0xFB,             // JR NZ, -5
+        0x3E, 0x01,             // LD A, 0x01
+        0xD3, 0xFE,             // OUT (0xFE), A ; blue border
+        0x18, 0xED,             // JR -19
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+
+    t.start_scanline_capture();
+    t.emulate_frame();
+    let scanlines = t
+        .take_scanlines()
+        .into_iter()
+        .map(|(line, border)| (line, u8::from(border)))
+        .collect::<Vec<_>>();
+    // Each line of the frame is reported once, in order
+    assert!(scanlines.iter().map(|&(line, _)| line).eq(0..312));
+    // Border turns red after the interrupt routine and blue after the delay loop
+    let red = scanlines
+        .iter()
+        .position(|&(_, border)| border == 2)
+        .unwrap();
+    let blue = red
+        + scanlines[red..]
+            .iter()
+            .position(|&(_, border)| border == 1)
+            .unwrap();
+    assert_eq!((red, blue), (5, 64));
+    assert!(scanlines[blue..].iter().all(|&(_, border)| border == 1));
+}
+
+#[test]
+fn instruction_counter() {
+    let mut t = RustZXTester::new("instruction_counter", presets::settings_48k_nosound());
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xDD, 0xDD, 0x00,       // NOP with redundant prefix
+        0x06, 0x03,             // LD B, 3
+        0x10, 0xFE,             // DJNZ $
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().reset_instruction_counter();
+    assert_eq!(t.emulator().instructions_executed(), 0);
+
+    let step = |t: &mut RustZXTester, count| {
+        let reason = t.emulator().step_instructions(count).unwrap();
+        assert_eq!(reason, EmulationStopReason::Completed);
+        t.emulator().cpu().regs.get_pc()
+    };
+    assert_eq!(step(&mut t, 1), 0x8001);
+    assert_eq!(step(&mut t, 1), 0x8004);
+    assert_eq!(step(&mut t, 2), 0x8006);
+    assert_eq!(step(&mut t, 2), 0x8008);
+    assert_eq!(t.emulator().instructions_executed(), 6);
+
+    // Counter keeps running during the frame emulation
+    t.emulate_frame();
+    assert!(t.emulator().instructions_executed() > 6);
+    t.emulator().reset_instruction_counter();
+    assert_eq!(t.emulator().instructions_executed(), 0);
+
+    // Breakpoint stops stepping
+    t.emulator().cpu_mut().regs.set_pc(0x8004);
+    t.add_breakpoint(0x8008);
+    let reason = t.emulator().step_instructions(100).unwrap();
+    assert_eq!(reason, EmulationStopReason::Breakpoint);
+    assert_eq!(t.emulator().instructions_executed(), 4);
+}
+
+#[test]
+fn step_over() {
+    let mut t = RustZXTester::new("step_over", presets::settings_48k_nosound());
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xCD, 0x00, 0x81,       // CALL 0x8100
+        0xAF,                   // XOR A
+        0xC4, 0x00, 0x81,       // CALL NZ, 0x8100
+        0xCD, 0x10, 0x81,       // CALL 0x8110
+    ];
+    #[rustfmt::skip]
+    const SUBROUTINE: &[u8] = &[
+        0x06, 0x05,             // LD B, 5
+        0x10, 0xFE,             // DJNZ $
+        0xC9,                   // RET
+    ];
+    #[rustfmt::skip]
+    const ENDLESS: &[u8] = &[
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    let bank = t.emulator().ram_bank_mut(1).unwrap();
+    bank[0x100..0x100 + SUBROUTINE.len()].copy_from_slice(SUBROUTINE);
+    bank[0x110..0x110 + ENDLESS.len()].copy_from_slice(ENDLESS);
+    let timeout = Duration::from_millis(100);
+
+    let step_over = |t: &mut RustZXTester| {
+        let reason = t.emulator().step_over(timeout).unwrap();
+        (reason, t.emulator().cpu().regs.get_pc())
+    };
+    // Not a call, single instruction
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Completed, 0x8001));
+    let sp = t.emulator().cpu().regs.get_sp();
+    let instructions = t.emulator().instructions_executed();
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Completed, 0x8004));
+    // CALL, LD, 5 x DJNZ and RET
+    assert_eq!(t.emulator().instructions_executed() - instructions, 8);
+    assert_eq!(t.emulator().cpu().regs.get_sp(), sp);
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Completed, 0x8005));
+    // Call is not taken
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Completed, 0x8008));
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Timeout, 0x8110));
+
+    // Step into the subroutine with `step`
+    t.emulator().cpu_mut().regs.set_pc(0x8001);
+    assert_eq!(t.emulator().step().unwrap(), EmulationStopReason::Completed);
+    assert_eq!(t.emulator().cpu().regs.get_pc(), 0x8100);
+    // Breakpoint in the subroutine stops stepping over
+    t.emulator().cpu_mut().regs.set_pc(0x8001);
+    t.add_breakpoint(0x8104);
+    assert_eq!(step_over(&mut t), (EmulationStopReason::Breakpoint, 0x8104));
+}
+
+#[test]
+fn screen_dirty_cells() {
+    let mut t = RustZXTester::new("screen_dirty_cells", presets::settings_48k_nosound());
+
+    #[rustfmt::skip]
+    const CLEAR_SCREEN: &[u8] = &[
+        0xF3,                   // DI
+        0x21, 0x00, 0x40,       // LD HL, 0x4000
+        0x11, 0x01, 0x40,       // LD DE, 0x4001
+        0x01, 0xFF, 0x1A,       // LD BC, 0x1AFF
+        0x36, 0x00,             // LD (HL), 0x00
+        0xED, 0xB0,             // LDIR
+        0x18, 0xFE,             // JR $
+    ];
+    #[rustfmt::skip]
+    const DRAW: &[u8] = &[
+        0x3E, 0xFF,             // LD A, 0xFF
+        0x32, 0x00, 0x40,       // LD (0x4000), A
+        0x3E, 0x38,             // LD A, 0x38
+        0x32, 0x21, 0x58,       // LD (0x5821), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, CLEAR_SCREEN);
+    t.emulator().ram_bank_mut(1).unwrap()[0x100..0x100 + DRAW.len()].copy_from_slice(DRAW);
+    // Clearing takes a bit more than two frames
+    t.emulator().emulate_frame_count(5).unwrap();
+    assert_eq!(t.emulator().dirty_cells().count(), 0);
+
+    t.emulator().cpu_mut().regs.set_pc(0x8100);
+    t.emulator().emulate_frame().unwrap();
+    assert_eq!(
+        t.emulator().dirty_cells().collect::<Vec<_>>(),
+        vec![(0, 0), (1, 1)]
+    );
+    // Changes are reported for one more frame, as they could have been made
+    // after the beam has passed the cell
+    t.emulator().emulate_frame().unwrap();
+    assert_eq!(t.emulator().dirty_cells().count(), 2);
+    t.emulator().emulate_frame().unwrap();
+    assert_eq!(t.emulator().dirty_cells().count(), 0);
+}
+
+#[test]
+fn flash_rate() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x87,             // LD A, 0x87 ; flash, black paper, white ink
+        0x32, 0x00, 0x58,       // LD (0x5800), A
+        0xAF,                   // XOR A
+        0x32, 0x00, 0x40,       // LD (0x4000), A
+        0x18, 0xFE,             // JR $
+    ];
+    // Returns color of the first two pixels for each of the `frames`
+    let first_pixels = |flash_rate: Option<u32>, frames: usize| {
+        let mut t = RustZXTester::new("flash_rate", presets::settings_48k_nosound());
+        t.emulator().set_flash_rate(flash_rate);
+        t.load_program_to_bank(1, PROGRAM);
+        t.emulator().emulate_frame().unwrap();
+        (0..frames)
+            .map(|_| {
+                t.emulator().emulate_frame().unwrap();
+                t.emulator().screen_frame().unwrap().data[0]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let pixels = first_pixels(Some(16), 32);
+    assert!(pixels[..16].iter().all(|&p| p == 0x77));
+    assert!(pixels[16..].iter().all(|&p| p == 0x00));
+
+    let pixels = first_pixels(Some(2), 4);
+    assert_eq!(pixels, [0x77, 0x77, 0x00, 0x00]);
+
+    assert!(first_pixels(None, 40).iter().all(|&p| p == 0x00));
+}
+
+#[test]
+fn serialize_state_roundtrip() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xFB,                   // EI
+        0x21, 0x00, 0x40,       // LD HL, 0x4000
+        0x3C,                   // INC A
+        0xE6, 0x07,             // AND 0x07
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0x77,                   // LD (HL), A
+        0x2C,                   // INC L
+        0x18, 0xF7,             // JR -9
+    ];
+    let mut t = RustZXTester::new("serialize_state", presets::settings_48k_nosound());
+    t.emulate_frame();
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().emulate_frame_count(3).unwrap();
+    // Stop in the middle of the frame
+    t.emulate_until_breakpoint(0x8004, Duration::from_millis(100));
+    t.clear_breakpoints();
+    assert_ne!(t.emulator().raster_position(), (0, 0));
+    t.emulator().set_interrupt_length(40);
+    t.emulator().set_cpu_speed(2.0);
+    t.emulator().set_im2_bus_value(Some(0x10));
+
+    let state = t.emulator().serialize_state();
+    let fingerprint = t.emulator().state_fingerprint();
+    let run_frames = |t: &mut RustZXTester| {
+        (0..4)
+            .map(|_| {
+                t.emulator().emulate_frame().unwrap();
+                let emulator = t.emulator();
+                (
+                    emulator.state_fingerprint(),
+                    emulator.screen_frame().unwrap().data.to_vec(),
+                    emulator.border_frame().unwrap().data.to_vec(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let expected = run_frames(&mut t);
+    assert_ne!(t.emulator().state_fingerprint(), fingerprint);
+
+    // Interrupt timings and cpu speed are the part of the state
+    t.emulator().set_interrupt_length(32);
+    t.emulator().set_cpu_speed(1.0);
+    t.emulator().set_im2_bus_value(None);
+    t.emulator().deserialize_state(&state).unwrap();
+    assert_eq!(t.emulator().state_fingerprint(), fingerprint);
+    assert_eq!(t.emulator().interrupt_length(), 40);
+    let actual = run_frames(&mut t);
+    let fingerprints =
+        |frames: &[(u64, Vec<u8>, Vec<u8>)]| frames.iter().map(|frame| frame.0).collect::<Vec<_>>();
+    assert_eq!(fingerprints(&actual), fingerprints(&expected));
+    // Part of the first frame, drawn before the state was saved, is not
+    // the part of the state
+    assert!(actual[1..] == expected[1..]);
+}
+
+#[test]
+fn deserialize_invalid_state() {
+    let mut t = RustZXTester::new("deserialize_invalid_state", presets::settings_48k_nosound());
+    t.emulate_frame();
+    let state = t.emulator().serialize_state();
+    t.emulate_frame();
+    let fingerprint = t.emulator().state_fingerprint();
+
+    assert!(matches!(
+        t.emulator().deserialize_state(&state[..state.len() - 1]),
+        Err(Error::StateLoad(StateLoadError::InvalidState))
+    ));
+    assert!(matches!(
+        t.emulator().deserialize_state(b"garbage"),
+        Err(Error::StateLoad(StateLoadError::InvalidState))
+    ));
+    let mut other_version = state.clone();
+    other_version[4] += 1;
+    assert!(matches!(
+        t.emulator().deserialize_state(&other_version),
+        Err(Error::StateLoad(StateLoadError::UnsupportedVersion))
+    ));
+    // Failed restore leaves emulator intact
+    assert_eq!(t.emulator().state_fingerprint(), fingerprint);
+
+    let mut t128 = RustZXTester::new(
+        "deserialize_invalid_state",
+        presets::settings_128k_nosound(),
+    );
+    assert!(matches!(
+        t128.emulator().deserialize_state(&state),
+        Err(Error::StateLoad(StateLoadError::MachineMismatch))
+    ));
+}
+
+#[test]
+fn rewind() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.rewind_snapshots = 10;
+    settings.rewind_interval_frames = 5;
+    let mut t = RustZXTester::new("rewind", settings);
+    assert_eq!(t.emulator().rewind(10).unwrap(), 0);
+
+    let mut fingerprints = vec![t.emulator().state_fingerprint()];
+    for _ in 0..60 {
+        t.emulator().emulate_frame().unwrap();
+        fingerprints.push(t.emulator().state_fingerprint());
+    }
+    // States of frames 11, 16, .., 56 are kept
+    assert_eq!(t.emulator().rewind_available(), 49);
+
+    assert_eq!(t.emulator().rewind(12).unwrap(), 14);
+    assert_eq!(t.emulator().state_fingerprint(), fingerprints[46]);
+    assert_eq!(t.emulator().rewind_available(), 35);
+
+    // Emulation continues exactly as before the rewind
+    for expected in &fingerprints[47..] {
+        t.emulator().emulate_frame().unwrap();
+        assert_eq!(t.emulator().state_fingerprint(), *expected);
+    }
+
+    // Rewind beyond the oldest state stops at it
+    assert_eq!(t.emulator().rewind(1000).unwrap(), 49);
+    assert_eq!(t.emulator().state_fingerprint(), fingerprints[11]);
+    assert_eq!(t.emulator().rewind_available(), 0);
+}
+
+#[test]
+fn machine_16k() {
+    let settings = RustzxSettings {
+        machine: ZXMachine::Sinclair16K,
+        ..presets::settings_48k_nosound()
+    };
+    let mut t = RustZXTester::new("machine_16k", settings);
+    // Wait for ROM to load
+    t.emulate_for(Duration::from_millis(2000));
+    // ROM RAM test finds the last RAM byte at 0x7FFF (P-RAMT system variable)
+    assert_eq!(t.emulator().peek(0x5CB4), 0xFF);
+    assert_eq!(t.emulator().peek(0x5CB5), 0x7F);
+    assert!(t.emulator().ram_bank(1).is_err());
+    assert_eq!(t.emulator().current_ram_bank_at(2), Some(Page::Unmapped));
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x55,             // LD A, 0x55
+        0x32, 0x00, 0x80,       // LD (0x8000), A
+        0x3A, 0x00, 0x80,       // LD A, (0x8000)
+        0x32, 0x00, 0x70,       // LD (0x7000), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(0, PROGRAM);
+    t.emulate_for(Duration::from_millis(20));
+    // Upper 32K is not populated, writes are ignored
+    assert_eq!(t.emulator().peek(0x7000), 0xFF);
+    assert_eq!(t.emulator().peek(0xC000), 0xFF);
+
+    let state = t.emulator().serialize_state();
+    t.emulator().deserialize_state(&state).unwrap();
+    assert_eq!(t.emulator().current_ram_bank_at(3), Some(Page::Unmapped));
+}
@@ -93,9 +93,12 @@ fn diag_rom_ula_128k() {
     load_diag_rom_menu_sna(&mut t, ZXMachine::Sinclair128K);
     t.send_keystrokes(&[&[ZXKey::N6], &[ZXKey::N1]], Duration::from_millis(100));
     t.emulate_for(Duration::from_secs(3));
+    // Reports "OK, ULA is 6Cxxx type": EAR input of the issue 3 machine
+    // follows bit 4 of the last 0xFE port write. Before keyboard issue
+    // emulation EAR input stayed low and the test reported +2A/+3 behavior
     t.expect_screen(
         "result",
-        expect![[r#"I2YQuImYmwfzNB9Y48M4ce2JVwDsQcEO2jamkLoBYxs="#]],
+        expect![[r#"qYsN78imAMbkXusiHcNjc2NNB0ek6Bqa1cDlfvfUlbY="#]],
     );
 }
 
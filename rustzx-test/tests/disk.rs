@@ -0,0 +1,185 @@
+use rustzx_core::host::{BufferCursor, Disk};
+use rustzx_test::framework::{presets, RustZXTester};
+
+#[test]
+fn trdos_read_sector() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.beta_disk_enabled = true;
+    let mut t = RustZXTester::new("trdos_read_sector", settings);
+
+    #[rustfmt::skip]
+    const TRDOS_ENTRY: &[u8] = &[
+        0x3E, 0x3C,             // LD A, 0x3C ; drive A:, side 0
+        0xD3, 0xFF,             // OUT (0xFF), A
+        0x3E, 0x01,             // LD A, 0x01
+        0xD3, 0x5F,             // OUT (0x5F), A ; sector 1
+        0x3E, 0x80,             // LD A, 0x80
+        0xD3, 0x1F,             // OUT (0x1F), A ; read sector
+        0x21, 0x00, 0x80,       // LD HL, 0x8000
+        0x01, 0x7F, 0x00,       // LD BC, 0x007F
+        0xED, 0xB2,             // INIR
+        0xDB, 0xFF,             // IN A, (0xFF)
+        0x32, 0x00, 0x81,       // LD (0x8100), A
+        0xDB, 0x1F,             // IN A, (0x1F)
+        0x32, 0x01, 0x81,       // LD (0x8101), A
+        0xC3, 0x00, 0x90,       // JP 0x9000
+    ];
+    let mut trdos_rom = vec![0u8; 16 * 1024];
+    trdos_rom[0x3D00..0x3D00 + TRDOS_ENTRY.len()].copy_from_slice(TRDOS_ENTRY);
+    t.emulator()
+        .load_trdos_rom(BufferCursor::new(trdos_rom))
+        .unwrap();
+
+    let mut trd = vec![0u8; 16 * 256];
+    trd[..256]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, b)| *b = i as u8);
+    t.emulator()
+        .load_disk(Disk::Trd(BufferCursor::new(trd)))
+        .unwrap();
+    assert!(t.emulator().has_disk());
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xC3, 0x00, 0x3D,       // JP 0x3D00
+    ];
+    #[rustfmt::skip]
+    const RETURN: &[u8] = &[
+        0x3A, 0x00, 0x3D,       // LD A, (0x3D00)
+        0x32, 0x02, 0x81,       // LD (0x8102), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulator().ram_bank_mut(1).unwrap()[0x1000..0x1000 + RETURN.len()].copy_from_slice(RETURN);
+    t.emulate_frame();
+
+    let sector: Vec<u8> = (0x8000..0x8100).map(|a| t.emulator().peek(a)).collect();
+    assert_eq!(sector, (0..=255).collect::<Vec<u8>>());
+    // INTRQ is set after the command, status is clear
+    assert_eq!(t.emulator().peek(0x8100), 0xBF);
+    assert_eq!(t.emulator().peek(0x8101), 0x00);
+    // TR-DOS ROM is paged out after jump to RAM, 48K ROM charset is visible again
+    assert_eq!(t.emulator().peek(0x8102), 0x00);
+    assert_eq!(t.emulator().peek(0x3D00), 0x00);
+}
+
+#[test]
+fn trdos_requires_beta_disk() {
+    let mut t = RustZXTester::new("trdos_requires_beta_disk", presets::settings_48k_nosound());
+    assert!(t
+        .emulator()
+        .load_trdos_rom(BufferCursor::new(vec![0u8; 16 * 1024]))
+        .is_err());
+    assert!(t
+        .emulator()
+        .load_disk(Disk::Trd(BufferCursor::new(vec![0u8; 16 * 256])))
+        .is_err());
+}
+
+#[test]
+fn trdos_state_mid_sector() {
+    #[rustfmt::skip]
+    const TRDOS_ENTRY: &[u8] = &[
+        0x3E, 0x3C,             // LD A, 0x3C ; drive A:, side 0
+        0xD3, 0xFF,             // OUT (0xFF), A
+        0x3E, 0x01,             // LD A, 0x01
+        0xD3, 0x5F,             // OUT (0x5F), A ; sector 1
+        0x3E, 0x80,             // LD A, 0x80
+        0xD3, 0x1F,             // OUT (0x1F), A ; read sector
+        0x21, 0x00, 0x80,       // LD HL, 0x8000
+        0x01, 0x7F, 0x10,       // LD BC, 0x107F
+        0xED, 0xB2,             // INIR ; first 16 bytes
+        0x18, 0xFE,             // JR $
+    ];
+    #[rustfmt::skip]
+    const TRDOS_CONTINUE: &[u8] = &[
+        0x01, 0x7F, 0x10,       // LD BC, 0x107F
+        0xED, 0xB2,             // INIR ; next 16 bytes
+        0xDB, 0x1F,             // IN A, (0x1F)
+        0x32, 0x00, 0x81,       // LD (0x8100), A
+        0x18, 0xFE,             // JR $
+    ];
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xC3, 0x00, 0x3D,       // JP 0x3D00
+    ];
+    let tester = || {
+        let mut settings = presets::settings_48k_nosound();
+        settings.beta_disk_enabled = true;
+        let mut t = RustZXTester::new("trdos_state_mid_sector", settings);
+        let mut trdos_rom = vec![0u8; 16 * 1024];
+        trdos_rom[0x3D00..0x3D00 + TRDOS_ENTRY.len()].copy_from_slice(TRDOS_ENTRY);
+        trdos_rom[0x3D40..0x3D40 + TRDOS_CONTINUE.len()].copy_from_slice(TRDOS_CONTINUE);
+        t.emulator()
+            .load_trdos_rom(BufferCursor::new(trdos_rom))
+            .unwrap();
+        let trd: Vec<u8> = (0..16 * 256).map(|i| i as u8).collect();
+        t.emulator()
+            .load_disk(Disk::Trd(BufferCursor::new(trd)))
+            .unwrap();
+        t
+    };
+
+    let mut t = tester();
+    t.load_program_to_bank(2, PROGRAM);
+    t.emulate_frame();
+    assert_eq!(t.emulator().cpu().regs.get_hl(), 0x8010);
+    let state = t.emulator().serialize_state();
+
+    // Sector read is continued by the emulator with the same disk inserted
+    let mut t = tester();
+    t.emulator().deserialize_state(&state).unwrap();
+    t.emulator().cpu_mut().regs.set_pc(0x3D40);
+    t.emulate_frame();
+    let sector: Vec<u8> = (0x8000..0x8020).map(|a| t.emulator().peek(a)).collect();
+    assert_eq!(sector, (0..32).collect::<Vec<u8>>());
+    // Read is still in progress, status is busy with data request
+    assert_eq!(t.emulator().peek(0x8100), 0x03);
+}
+
+#[test]
+fn plus3_fdc_state() {
+    #[rustfmt::skip]
+    const SEEK: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0x1F,       // LD BC, 0x1FFD
+        0x3E, 0x08,             // LD A, 0x08 ; motor on
+        0xED, 0x79,             // OUT (C), A
+        0x01, 0xFD, 0x3F,       // LD BC, 0x3FFD
+        0x3E, 0x0F,             // LD A, 0x0F ; seek
+        0xED, 0x79,             // OUT (C), A
+        0xAF,                   // XOR A ; drive A:
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x05,             // LD A, 0x05 ; cylinder 5
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    #[rustfmt::skip]
+    const SENSE_INTERRUPT: &[u8] = &[
+        0x01, 0xFD, 0x3F,       // LD BC, 0x3FFD
+        0x3E, 0x08,             // LD A, 0x08 ; sense interrupt status
+        0xED, 0x79,             // OUT (C), A
+        0xED, 0x78,             // IN A, (C) ; ST0
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0xED, 0x78,             // IN A, (C) ; cylinder
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("plus3_fdc_state", presets::settings_plus3_nosound());
+    t.load_program_to_bank(2, SEEK);
+    t.emulate_frame();
+    let state = t.emulator().serialize_state();
+
+    // Seek result is reported by the restored controller
+    let mut t = RustZXTester::new("plus3_fdc_state", presets::settings_plus3_nosound());
+    t.emulator().deserialize_state(&state).unwrap();
+    t.emulator().ram_bank_mut(2).unwrap()[0x100..0x100 + SENSE_INTERRUPT.len()]
+        .copy_from_slice(SENSE_INTERRUPT);
+    t.emulator().cpu_mut().regs.set_pc(0x8100);
+    t.emulate_frame();
+    assert_eq!(t.emulator().peek(0x9000), 0x20);
+    assert_eq!(t.emulator().peek(0x9001), 0x05);
+}
@@ -0,0 +1,336 @@
+use rustzx_core::RustzxSettings;
+use rustzx_test::framework::{presets, RustZXTester};
+
+const CLOCKS_LINE_48K: usize = 224;
+const CLOCKS_LINE_128K: usize = 228;
+
+/// Returns tester with distinct values in the first bitmap and attribute
+/// bytes of the screen, the rest of the screen is filled with zeros
+fn tester(name: &str, settings: RustzxSettings, screen_bank: usize) -> RustZXTester {
+    let mut t = RustZXTester::new(name, settings);
+    let screen = &mut t.emulator().ram_bank_mut(screen_bank).unwrap()[..6912];
+    screen.fill(0);
+    screen[0x0000] = 0x11;
+    screen[0x0001] = 0x12;
+    screen[0x001F] = 0x1F;
+    screen[0x0100] = 0x21;
+    screen[0x1800] = 0x81;
+    screen[0x1801] = 0x82;
+    screen[0x1805] = 0x85;
+    screen[0x181F] = 0x9F;
+    t
+}
+
+fn frame_clocks(t: &mut RustZXTester, clocks_line: usize) -> usize {
+    let (line, col) = t.emulator().raster_position();
+    line as usize * clocks_line + col as usize
+}
+
+/// Runs `NOP` sled from 0x8000 in `code_bank` until exactly `clocks` T-state
+/// of the frame, `tail` is placed right after the sled
+fn run_until(
+    t: &mut RustZXTester,
+    code_bank: usize,
+    clocks_line: usize,
+    clocks: usize,
+    tail: &[u8],
+) {
+    // `DI` is followed by `LD B, 0` (7 clocks) to align start clocks to the
+    // `NOP` (4 clocks) sled
+    let start = frame_clocks(t, clocks_line) + 4;
+    let delays = (0..4)
+        .find(|m| (clocks - start - m * 7).is_multiple_of(4))
+        .unwrap();
+    let nops = (clocks - start - delays * 7) / 4;
+    let mut program = vec![0xF3];
+    program.extend([0x06, 0x00].repeat(delays));
+    program.extend(vec![0x00; nops]);
+    program.extend(tail);
+    t.load_program_to_bank(code_bank, &program);
+
+    t.emulator()
+        .step_instructions(1 + delays as u64 + nops as u64)
+        .unwrap();
+    assert_eq!(frame_clocks(t, clocks_line), clocks);
+}
+
+/// Executes `IN A, (0xFF)` with `high` byte of the port, started exactly at
+/// `clocks` T-state of the frame. Returns read value and instruction duration
+fn read_port_ff(
+    name: &str,
+    settings: RustzxSettings,
+    clocks_line: usize,
+    clocks: usize,
+    high: u8,
+) -> (u8, usize) {
+    let (screen_bank, code_bank) = match settings.machine.capabilities().has_128k_paging {
+        true => (5, 2),
+        false => (0, 1),
+    };
+    let mut t = tester(name, settings, screen_bank);
+    run_until(
+        &mut t,
+        code_bank,
+        clocks_line,
+        clocks,
+        &[0xDB, 0xFF, 0x18, 0xFE],
+    );
+    t.emulator().cpu_mut().regs.set_acc(high);
+    t.emulator().step().unwrap();
+    let duration = frame_clocks(&mut t, clocks_line) - clocks;
+    (t.emulator().cpu().regs.get_acc(), duration)
+}
+
+fn read_48k(clocks: usize, high: u8) -> (u8, usize) {
+    let settings = presets::settings_48k_nosound();
+    read_port_ff("floating_bus_48k", settings, CLOCKS_LINE_48K, clocks, high)
+}
+
+fn read_128k(clocks: usize, high: u8) -> (u8, usize) {
+    let settings = presets::settings_128k_nosound();
+    read_port_ff(
+        "floating_bus_128k",
+        settings,
+        CLOCKS_LINE_128K,
+        clocks,
+        high,
+    )
+}
+
+#[test]
+fn floating_bus_48k() {
+    // Port is sampled at the 10th clock of `IN A, (n)`, ULA fetches the
+    // first bitmap and attribute bytes pair at 14338
+    let values: Vec<u8> = (14326..14337)
+        .map(|clocks| read_48k(clocks, 0x00).0)
+        .collect();
+    assert_eq!(
+        values,
+        [0xFF, 0xFF, 0x11, 0x81, 0x12, 0x82, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]
+    );
+    // Last pair of the line, followed by the right border
+    assert_eq!(read_48k(14450, 0x00).0, 0x1F);
+    assert_eq!(read_48k(14451, 0x00).0, 0x9F);
+    assert_eq!(read_48k(14452, 0x00).0, 0xFF);
+    // Second line of the screen
+    assert_eq!(read_48k(14328 + CLOCKS_LINE_48K, 0x00).0, 0x21);
+    // Border and bottom of the frame
+    assert_eq!(read_48k(1000, 0x00).0, 0xFF);
+    assert_eq!(read_48k(14328 + CLOCKS_LINE_48K * 192, 0x00).0, 0xFF);
+}
+
+#[test]
+fn floating_bus_128k() {
+    // ULA fetches the first bitmap and attribute bytes pair at 14364
+    let values: Vec<u8> = (14352..14358)
+        .map(|clocks| read_128k(clocks, 0x00).0)
+        .collect();
+    assert_eq!(values, [0xFF, 0xFF, 0x11, 0x81, 0x12, 0x82]);
+    assert_eq!(read_128k(14354 + CLOCKS_LINE_128K, 0x00).0, 0x21);
+}
+
+#[test]
+fn floating_bus_plus3() {
+    let settings = presets::settings_plus3_nosound();
+    // No floating bus and no I/O contention on the gate array machines
+    assert_eq!(
+        read_port_ff(
+            "floating_bus_plus3",
+            settings,
+            CLOCKS_LINE_128K,
+            14357,
+            0x40
+        ),
+        (0xFF, 11)
+    );
+}
+
+#[test]
+fn floating_bus_contention() {
+    // Unattached odd port with uncontended high byte is never delayed
+    assert_eq!(read_48k(14328, 0x00).1, 11);
+    assert_eq!(read_48k(14328, 0x80).1, 11);
+    // High byte in the contended memory range delays each clock of the
+    // I/O cycle (C:1, C:1, C:1, C:1). Value is sampled after the delays,
+    // when ULA has already finished the bitmap fetch of the group
+    assert_eq!(read_48k(1000, 0x40), (0xFF, 11));
+    assert_eq!(read_48k(14328, 0x40), (0xFF, 23));
+    assert_eq!(read_48k(14332, 0x40), (0xFF, 19));
+    assert_eq!(read_48k(14335, 0x40), (0x85, 23));
+    // 128K has contended memory at 0xC000 only with odd bank paged in
+    assert_eq!(read_128k(14354, 0xC0).1, 11);
+    assert_eq!(read_128k(14354, 0x40).1, 23);
+}
+
+/// Executes block I/O or `IN A, (C)` instruction from `addr` on 48K, started
+/// exactly at `clocks` T-state. Returns instruction duration
+fn io_instruction_48k(clocks: usize, addr: u16, code: [u8; 2], bc: u16, hl: u16) -> usize {
+    let mut t = tester("io_contention_48k", presets::settings_48k_nosound(), 0);
+    run_until(&mut t, 1, CLOCKS_LINE_48K, clocks, &[]);
+    let bank = (addr as usize - 0x4000) / 0x4000;
+    let offset = addr as usize % 0x4000;
+    t.emulator().ram_bank_mut(bank).unwrap()[offset..offset + 2].copy_from_slice(&code);
+    let regs = &mut t.emulator().cpu_mut().regs;
+    regs.set_pc(addr);
+    regs.set_bc(bc);
+    regs.set_hl(hl);
+    regs.set_i(0x3F);
+    t.emulator().step().unwrap();
+    frame_clocks(&mut t, CLOCKS_LINE_48K) - clocks
+}
+
+#[test]
+fn io_and_memory_contention() {
+    const IN_A_C: [u8; 2] = [0xED, 0x78];
+    const INI: [u8; 2] = [0xED, 0xA2];
+    const OUTI: [u8; 2] = [0xED, 0xA3];
+    // Each access is contended on its own, delays are never merged. Delay
+    // pattern starts at 14335 with 6 clocks and repeats every 8 clocks.
+    //
+    // `IN A, (C)` from the contended memory: pc:4, pc+1:4, then I/O cycle.
+    // Opcode fetches end at 14345 (6 + 4) and 14353 (4 + 4), then
+    // - ULA port, contended high byte: C:1 (4 + 1), C:3 (0 + 3)
+    assert_eq!(io_instruction_48k(14335, 0x6000, IN_A_C, 0x40FE, 0), 26);
+    // - ULA port, uncontended high byte: N:1, C:3 (3 + 3)
+    assert_eq!(io_instruction_48k(14335, 0x6000, IN_A_C, 0x00FE, 0), 25);
+    // - odd port, contended high byte: C:1 (4 + 1), C:1 (0 + 1), C:1 (6 + 1),
+    //   C:1 (0 + 1)
+    assert_eq!(io_instruction_48k(14335, 0x6000, IN_A_C, 0x40FF, 0), 32);
+    // Same instruction from the uncontended memory: I/O cycle starts at
+    // 14343, C:1 (6 + 1), C:3 (0 + 3)
+    assert_eq!(io_instruction_48k(14335, 0x8000, IN_A_C, 0x40FE, 0), 18);
+    // `INI`: pc:4, pc+1:4, IR:1, I/O with original B, then hl:3. I/O starts
+    // at 14344: C:1 (5 + 1), C:3 (0 + 3), memory write at 14353 is delayed
+    // by 4 clocks
+    assert_eq!(io_instruction_48k(14335, 0x8000, INI, 0x40FE, 0x4000), 25);
+    assert_eq!(io_instruction_48k(14335, 0x8000, INI, 0x40FE, 0x8000), 21);
+    // `OUTI`: pc:4, pc+1:4, IR:1, hl:3, then I/O with decremented B. Memory
+    // read at 14344 is delayed by 5 clocks, I/O starts at 14352: C:1 (5 + 1),
+    // C:3 (0 + 3)
+    assert_eq!(io_instruction_48k(14335, 0x8000, OUTI, 0x41FE, 0x4000), 26);
+    // Uncontended memory read, I/O starts at 14347: C:1 (2 + 1), C:3 (0 + 3)
+    assert_eq!(io_instruction_48k(14335, 0x8000, OUTI, 0x41FE, 0x8000), 18);
+}
+
+/// Executes instruction from 0x8000 with I = 0x40 and HL = 0x4000, started
+/// exactly at `clocks` T-state. Returns instruction duration
+fn memory_instruction(
+    name: &str,
+    settings: RustzxSettings,
+    clocks_line: usize,
+    clocks: usize,
+    code: &[u8],
+) -> usize {
+    let (screen_bank, code_bank) = match settings.machine.capabilities().has_128k_paging {
+        true => (5, 2),
+        false => (0, 1),
+    };
+    let mut t = tester(name, settings, screen_bank);
+    run_until(&mut t, code_bank, clocks_line, clocks, code);
+    let regs = &mut t.emulator().cpu_mut().regs;
+    regs.set_i(0x40);
+    regs.set_hl(0x4000);
+    t.emulator().step().unwrap();
+    frame_clocks(&mut t, clocks_line) - clocks
+}
+
+#[test]
+fn gate_array_contention() {
+    const INC_BC: &[u8] = &[0x03];
+    const LD_A_HL: &[u8] = &[0x7E];
+    let plus3_instruction = |clocks, code| {
+        let settings = presets::settings_plus3_nosound();
+        memory_instruction("contention_plus3", settings, CLOCKS_LINE_128K, clocks, code)
+    };
+    // `INC BC`: pc:4, IR:1 x 2. ULA contends IR cycles with the contended
+    // address: C:1 (2 + 1), C:1 (0 + 1)
+    let settings = presets::settings_48k_nosound();
+    assert_eq!(
+        memory_instruction("contention_48k", settings, CLOCKS_LINE_48K, 14335, INC_BC),
+        8
+    );
+    // Gate array contends only MREQ cycles
+    for clocks in 14361..14369 {
+        assert_eq!(plus3_instruction(clocks, INC_BC), 6, "{}", clocks);
+    }
+    // `LD A, (HL)`: pc:4, hl:3. Memory read at 14365..14372 is delayed by
+    // 1, 0, 7, 6, 5, 4, 3, 2 clocks
+    let durations: Vec<usize> = (14361..14369)
+        .map(|clocks| plus3_instruction(clocks, LD_A_HL))
+        .collect();
+    assert_eq!(durations, [8, 7, 14, 13, 12, 11, 10, 9]);
+    // Border area is not contended
+    assert_eq!(plus3_instruction(14361 + 128, LD_A_HL), 7);
+}
+
+#[test]
+fn floating_bus_at() {
+    let mut t = tester("floating_bus_at", presets::settings_48k_nosound(), 0);
+    // First bitmap and attribute bytes pairs of the line, then 4 idle clocks
+    let values: Vec<u8> = (14336..14347)
+        .map(|clocks| t.emulator().floating_bus_at(clocks))
+        .collect();
+    assert_eq!(
+        values,
+        [0xFF, 0xFF, 0x11, 0x81, 0x12, 0x82, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]
+    );
+    assert_eq!(t.emulator().floating_bus_at(14338 + 224), 0x21);
+    assert_eq!(t.emulator().floating_bus_at(14338 + 69888), 0x11);
+    // Matches value read by `IN A, (0xFF)`, which samples the bus at its
+    // 10th clock
+    for clocks in (14326..14460).step_by(3) {
+        assert_eq!(
+            read_48k(clocks, 0x00).0,
+            t.emulator().floating_bus_at(clocks as u32 + 10),
+            "{}",
+            clocks
+        );
+    }
+}
+
+#[test]
+fn floating_bus_polling_loop() {
+    // Poll the floating bus until ULA fetches the known attribute value. Loop
+    // takes 30 clocks, so the attribute of the 6th column (fetched at 14357
+    // on each of the 8 lines of the first row) is missed on the first line
+    // and caught on the second one
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xDB, 0xFF,             // loop: IN A, (0xFF)
+        0xFE, 0x85,             // CP 0x85
+        0x20, 0xFA,             // JR NZ, loop
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = tester("floating_bus_polling", presets::settings_48k_nosound(), 0);
+    run_until(&mut t, 1, CLOCKS_LINE_48K, 14271, PROGRAM);
+    t.emulator().cpu_mut().regs.set_acc(0x00);
+    let loop_start = t.emulator().cpu().regs.get_pc();
+    while t.emulator().cpu().regs.get_pc() != loop_start + 6 {
+        t.emulator().step().unwrap();
+    }
+    // Attribute is sampled at the 10th clock of `IN`, followed by `CP` and
+    // not taken `JR`
+    assert_eq!(
+        frame_clocks(&mut t, CLOCKS_LINE_48K),
+        14357 + CLOCKS_LINE_48K - 10 + 11 + 7 + 7
+    );
+
+    t.emulate_frame();
+    assert_eq!(t.emulator().last_frame_floating_bus_reads(), 11);
+
+    // Without the expected attribute the loop polls the bus all the frame long
+    t.emulator().ram_bank_mut(0).unwrap()[0x1805] = 0x00;
+    t.emulator().cpu_mut().regs.set_pc(loop_start);
+    t.emulate_frame();
+    t.emulate_frame();
+    let reads = t.emulator().last_frame_floating_bus_reads();
+    assert!((69888 / 30..=69888 / 30 + 1).contains(&reads), "{}", reads);
+
+    // Reads statistics are not carried over loaded state and power cycle
+    let state = t.emulator().serialize_state();
+    t.emulator().deserialize_state(&state).unwrap();
+    assert_eq!(t.emulator().last_frame_floating_bus_reads(), 0);
+    t.emulate_frame();
+    t.emulator().power_on(0);
+    assert_eq!(t.emulator().last_frame_floating_bus_reads(), 0);
+}
@@ -0,0 +1,58 @@
+use expect_test::expect;
+use rustzx_test::framework::{presets, RustZXTester};
+
+/// Flips paper of the top-left attribute cell between white and black on
+/// every frame, the rest of the screen stays black
+#[test]
+fn blended_frames() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xFB,                   // EI
+        0x76,                   // HALT
+        0x3A, 0x00, 0x58,       // LD A, (0x5800)
+        0xEE, 0x38,             // XOR 0x38
+        0x32, 0x00, 0x58,       // LD (0x5800), A
+        0x18, 0xF5,             // JR -11
+    ];
+
+    let mut settings = presets::settings_48k_nosound();
+    // Tester frame buffer keeps colors of both frames only when it is created
+    // with frames blending enabled
+    settings.blend_frames = true;
+    let mut t = RustZXTester::new("blended_frames", settings);
+    t.emulator().set_blend_frames(false);
+    t.load_program(PROGRAM);
+    t.emulate_frame();
+
+    t.emulate_frame();
+    t.expect_screen(
+        "black",
+        expect![[r#"ls8nw8ztD4YUosVxomEBOt/PzVr5AXXGkcY0uUch21w="#]],
+    );
+    t.emulate_frame();
+    t.expect_screen(
+        "white",
+        expect![[r#"IQOtM0O4UQKe8KSSFoK3FWaF/qWVbFyytR46YWD9OiA="#]],
+    );
+
+    t.emulator().set_blend_frames(true);
+    // The first blended frame is mixed with the initial black frame
+    t.emulate_frame();
+    t.emulate_frame();
+    t.expect_screen(
+        "blended",
+        expect![[r#"McJxp7GzkcBgUMl0DURgA9iTGFRErBHx5NfcD85t+LM="#]],
+    );
+    t.emulate_frame();
+    t.expect_screen(
+        "blended",
+        expect![[r#"McJxp7GzkcBgUMl0DURgA9iTGFRErBHx5NfcD85t+LM="#]],
+    );
+
+    t.emulator().set_blend_frames(false);
+    t.emulate_frame();
+    t.expect_screen(
+        "white",
+        expect![[r#"IQOtM0O4UQKe8KSSFoK3FWaF/qWVbFyytR46YWD9OiA="#]],
+    );
+}
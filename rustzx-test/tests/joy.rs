@@ -1,8 +1,11 @@
 use expect_test::expect;
 use rustzx_core::{
     zx::{
+        input::{InputButton, InputProfile, InputTarget},
         joy::{
-            kempston::KempstonKey,
+            cursor::CursorKey,
+            fuller::FullerKey,
+            kempston::{KempstonJoyNum, KempstonKey},
             sinclair::{SinclairJoyNum, SinclairKey},
         },
         keys::{CompoundKey, ZXKey},
@@ -47,6 +50,86 @@ fn kempston_joy() {
     expect![[r#"00,01,03,07,0F,1F,3F,7F,FF,FE,FC,F8,F0,E0,C0,80,00,"#]].assert_eq(&out);
 }
 
+#[test]
+fn kempston_port_mask() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0xAF,                   // XOR A
+        0xDB, 0x0F,             // IN A, (0x0F)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0xAF,                   // XOR A
+        0xDB, 0x1F,             // IN A, (0x1F)
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0x18, 0xFE,             // JR $
+    ];
+    let run = |mask: Option<(u16, u16)>| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.kempston_enabled = true;
+        let mut t = RustZXTester::new("kempston_port_mask", settings);
+        if let Some((mask, value)) = mask {
+            t.emulator().set_kempston_port_mask(mask, value);
+        }
+        t.emulator().send_kempston_key(KempstonKey::Fire, true);
+        t.load_program_to_bank(1, PROGRAM);
+        t.emulate_frame();
+        (t.emulator().peek(0x9000), t.emulator().peek(0x9001))
+    };
+    // Loose decode, port 0x0F is an alias of 0x1F
+    assert_eq!(run(None), (0x10, 0x10));
+    // Exact decode, 0x0F reads floating bus in the top border
+    assert_eq!(run(Some((0x00FF, 0x001F))), (0xFF, 0x10));
+}
+
+#[test]
+fn input_profile() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.kempston_enabled = true;
+    let mut t = RustZXTester::new("input_profile", settings);
+    t.enable_debug_port();
+    t.load_sna("kempston_joy.48k.sna.gz");
+
+    let mut out = String::new();
+    let mut read_joy = |t: &mut RustZXTester| {
+        t.sync_target();
+        t.emulate_frame();
+        out += &t.debug_port().take_text();
+    };
+
+    // Buttons are not bound by default
+    t.emulator().send_input(InputButton::Fire, true);
+    read_joy(&mut t);
+    t.emulator().send_input(InputButton::Fire, false);
+
+    let mut profile = InputProfile::kempston(KempstonJoyNum::First);
+    profile.set_binding(
+        InputButton::AltFire,
+        Some(InputTarget::Kempston(
+            KempstonJoyNum::First,
+            KempstonKey::Ext1,
+        )),
+    );
+    t.emulator().configure_input(profile);
+    t.emulator().send_input(InputButton::Fire, true);
+    read_joy(&mut t);
+    t.emulator().send_input(InputButton::AltFire, true);
+    read_joy(&mut t);
+    // Right and up, deflection below the dead zone is ignored
+    t.emulator().send_input_axes(1.0, -0.8);
+    read_joy(&mut t);
+    t.emulator().send_input_axes(0.3, -0.8);
+    read_joy(&mut t);
+
+    // Held inputs are released on profile change
+    let profile = InputProfile::keys([ZXKey::Q, ZXKey::A, ZXKey::O, ZXKey::P, ZXKey::Space]);
+    t.emulator().configure_input(profile);
+    read_joy(&mut t);
+    t.emulator().send_input(InputButton::Fire, true);
+    read_joy(&mut t);
+
+    expect![[r#"00,10,30,39,38,00,00,"#]].assert_eq(&out);
+}
+
 #[test]
 fn sinclair_joy() {
     let mut t = RustZXTester::new("sinclair_joy", presets::settings_48k_nosound());
@@ -116,3 +199,118 @@ fn sinclair_joy() {
         expect![[r#"F6bYEdfQ8M9gpyCivt2vuKMws83uDEmuB3Q7OQlXucU="#]],
     );
 }
+
+#[test]
+fn cursor_joy() {
+    let mut t = RustZXTester::new("cursor_joy", presets::settings_48k_nosound());
+    t.enable_debug_port();
+    // Cursor joystick emulates key presses too, same as sinclair joy
+    t.load_sna("keyboard.48k.sna.gz");
+
+    let mut out = String::new();
+
+    for button in CursorKey::iter() {
+        t.emulator().send_cursor_key(button, true);
+        t.sync_target();
+        t.emulate_frame();
+        out += &t.debug_port().take_text();
+        t.emulator().send_cursor_key(button, false);
+    }
+
+    out += "WITH_OTHER_KEYS\n";
+    t.emulator().send_cursor_key(CursorKey::Up, true);
+    t.emulator()
+        .send_sinclair_key(SinclairJoyNum::Fist, SinclairKey::Left, true); // same N6 key
+    t.emulator().send_key(ZXKey::N7, true); // overlaps with joy key
+    t.sync_target();
+    t.emulate_frame();
+    out += &t.debug_port().take_text();
+    // Check that joy key release does not clear other pressed keys
+    t.emulator().send_cursor_key(CursorKey::Up, false);
+    t.sync_target();
+    t.emulate_frame();
+    out += &t.debug_port().take_text();
+
+    t.expect_text(
+        "log",
+        out,
+        expect![[r#"PCUgi8oOmUI9QYYRzv5VIJY7EA0ApdWk9UlRZoXWifI="#]],
+    );
+}
+
+#[test]
+fn fuller_box() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.fuller_enabled = true;
+    let mut t = RustZXTester::new("fuller_box", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xDB, 0x7F,             // IN A, (0x7F)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x3E, 0x07,             // LD A, 7
+        0xD3, 0x3F,             // OUT (0x3F), A
+        0x3E, 0x38,             // LD A, 0x38
+        0xD3, 0x5F,             // OUT (0x5F), A
+        0xDB, 0x3F,             // IN A, (0x3F)
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+
+    t.emulator().send_fuller_key(FullerKey::Up, true);
+    t.emulator().send_fuller_key(FullerKey::Fire, true);
+    t.emulate_frame();
+
+    // Active-low: up (bit 0) and fire (bit 7) are pressed
+    assert_eq!(t.emulator().peek(0x9000), 0x7E);
+    // AY register 7 is accessible via Fuller Box ports
+    assert_eq!(t.emulator().peek(0x9001), 0x38);
+}
+
+/// Runs program which polls lightgun sensor from the beginning of the frame.
+/// Returns count of polls before light was detected and Kempston port value
+fn lightgun_poll(x: u16, y: u16, attr: u8) -> (u16, u8) {
+    let mut settings = presets::settings_48k_nosound();
+    settings.lightgun_enabled = true;
+    let mut t = RustZXTester::new("lightgun", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0x00, 0x00,       // LD BC, 0
+        0xDB, 0xFE,             // loop: IN A, (0xFE)
+        0x03,                   // INC BC
+        0xCB, 0x77,             // BIT 6, A
+        0x28, 0xF9,             // JR Z, loop
+        0xED, 0x43, 0x00, 0x90, // LD (0x9000), BC
+        0xDB, 0x1F,             // IN A, (0x1F)
+        0x32, 0x02, 0x90,       // LD (0x9002), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulator().ram_bank_mut(0).unwrap()[0x1800..0x1B00].fill(attr);
+
+    t.emulator().set_lightgun(x, y, true);
+    t.emulate_frame();
+
+    let count = u16::from_le_bytes([t.emulator().peek(0x9000), t.emulator().peek(0x9001)]);
+    (count, t.emulator().peek(0x9002))
+}
+
+#[test]
+fn lightgun() {
+    // White paper
+    let (top, kempston) = lightgun_poll(128, 50, 0x38);
+    let (bottom, _) = lightgun_poll(128, 150, 0x38);
+    assert_ne!(top, 0);
+    assert!(bottom > top);
+    // Sensor sees the whole character cell under the target point
+    assert_eq!(lightgun_poll(135, 55, 0x38).0, top);
+    // Trigger is reported as Kempston fire
+    assert_eq!(kempston, 0x10);
+
+    // Black screen never reflects light to the gun
+    let (count, _) = lightgun_poll(128, 50, 0x00);
+    assert_eq!(count, 0);
+}
@@ -1,9 +1,14 @@
 use expect_test::expect;
 use rustzx_core::{
-    zx::keys::{CompoundKey, ZXKey},
+    zx::{
+        events::EmulationEvents,
+        keys::{CompoundKey, KeyboardIssue, ZXKey},
+        machine::ZXMachine,
+    },
     IterableEnum,
 };
 use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
 
 #[test]
 fn standard_keys() {
@@ -115,3 +120,285 @@ fn compound_keys() {
         expect![[r#"v01HM6RHAtHfvFEnvCXae4dl1FrHEISrnDgljzvMcoE="#]],
     );
 }
+
+#[test]
+fn matrix_keys() {
+    let mut t = RustZXTester::new("matrix_keys", presets::settings_48k_nosound());
+    t.enable_debug_port();
+    t.load_sna("keyboard.48k.sna.gz");
+
+    let mut out = String::new();
+
+    t.sync_target();
+    t.emulate_frame();
+    out += &t.debug_port().take_text();
+
+    // Raw matrix positions follow `ZXKey` declaration order, so pressing them
+    // sequentially should produce exactly the same log as `standard_keys` test
+    for pressed in [true, false] {
+        for row in 0..8 {
+            for bit in 0..5 {
+                t.emulator().press_matrix(row, bit, pressed);
+                t.sync_target();
+                t.emulate_frame();
+                out += &t.debug_port().take_text();
+            }
+        }
+    }
+
+    t.expect_text(
+        "log",
+        out,
+        expect![[r#"lF44fsm0VLApiGX1LTQOYRgj40hPrX9L5SaA3nF/i6w="#]],
+    );
+}
+
+#[test]
+fn remapped_keys() {
+    let mut t = RustZXTester::new("remapped_keys", presets::settings_48k_nosound());
+    t.enable_debug_port();
+    t.load_sna("keyboard.48k.sna.gz");
+
+    let press = |t: &mut RustZXTester, key| {
+        t.emulator().send_key(key, true);
+        t.sync_target();
+        t.emulate_frame();
+        t.emulator().send_key(key, false);
+        t.debug_port().take_text()
+    };
+
+    let expected = press(&mut t, ZXKey::Q);
+
+    t.emulator().set_key_mapping(ZXKey::A, ZXKey::Q);
+    assert_eq!(press(&mut t, ZXKey::A), expected);
+
+    t.emulator().reset_key_mapping();
+    assert_ne!(press(&mut t, ZXKey::A), expected);
+}
+
+#[test]
+fn break_key() {
+    let mut t = RustZXTester::new("break_key", presets::settings_48k_nosound());
+    t.enable_debug_port();
+    t.load_sna("keyboard.48k.sna.gz");
+
+    // BREAK should be reported exactly as simultaneously pressed CAPS SHIFT and SPACE
+    t.emulator().send_key(ZXKey::Shift, true);
+    t.emulator().send_key(ZXKey::Space, true);
+    t.sync_target();
+    t.emulate_frame();
+    let expected = t.debug_port().take_text();
+    t.emulator().send_key(ZXKey::Shift, false);
+    t.emulator().send_key(ZXKey::Space, false);
+    t.sync_target();
+    t.emulate_frame();
+    t.debug_port().take_text();
+
+    t.emulator().send_break(true);
+    t.sync_target();
+    t.emulate_frame();
+    assert_eq!(t.debug_port().take_text(), expected);
+}
+
+#[test]
+fn break_key_event() {
+    let mut t = RustZXTester::new("break_key_event", presets::settings_48k_nosound());
+    // Wait for ROM to load
+    t.emulate_for(Duration::from_millis(2000));
+    // Enter and run `10 GOTO 10`
+    t.send_keystrokes(
+        &[
+            &[ZXKey::N1],
+            &[ZXKey::N0],
+            &[ZXKey::G],
+            &[ZXKey::N1],
+            &[ZXKey::N0],
+            &[ZXKey::Enter],
+            &[ZXKey::R],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    let break_reported = |t: &mut RustZXTester| {
+        t.emulator()
+            .take_events()
+            .contains(EmulationEvents::BREAK_KEY)
+    };
+
+    t.emulate_for(Duration::from_millis(500));
+    assert!(!break_reported(&mut t));
+
+    t.emulator().send_break(true);
+    t.emulate_for(Duration::from_millis(100));
+    t.emulator().send_break(false);
+    assert!(break_reported(&mut t));
+    // Event should be reported only once
+    t.emulate_for(Duration::from_millis(100));
+    assert!(!break_reported(&mut t));
+}
+
+#[test]
+fn keyboard_matrix() {
+    let mut t = RustZXTester::new("keyboard_matrix", presets::settings_48k_nosound());
+    assert_eq!(t.emulator().keyboard_matrix(), [0xFF; 8]);
+
+    t.emulator().send_key(ZXKey::A, true);
+    t.emulator().send_compound_key(CompoundKey::Break, true);
+    t.emulator().press_matrix(7, 4, true);
+    assert_eq!(
+        t.emulator().keyboard_matrix(),
+        [0xFE, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xEE]
+    );
+
+    // Positions out of the matrix are ignored
+    t.emulator().press_matrix(8, 0, true);
+    t.emulator().press_matrix(0, 5, true);
+    assert_eq!(
+        t.emulator().keyboard_matrix(),
+        [0xFE, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xEE]
+    );
+
+    // Keys stay released after clear, even without release events
+    t.emulator().clear_keyboard();
+    assert_eq!(t.emulator().keyboard_matrix(), [0xFF; 8]);
+    t.emulator().send_compound_key(CompoundKey::Break, false);
+    t.emulator().send_key(ZXKey::A, false);
+    assert_eq!(t.emulator().keyboard_matrix(), [0xFF; 8]);
+}
+
+#[test]
+fn key_debounce() {
+    let mut t = RustZXTester::new("key_debounce", presets::settings_48k_nosound());
+    let a_pressed = |t: &mut RustZXTester| t.emulator().keyboard_matrix()[1] == 0xFE;
+    t.emulator().set_key_debounce(3);
+
+    // Release right after the press is deferred
+    t.emulator().send_key(ZXKey::A, true);
+    t.emulator().send_key(ZXKey::A, false);
+    t.emulate_frame();
+    t.emulate_frame();
+    assert!(a_pressed(&mut t));
+    t.emulate_frame();
+    assert!(!a_pressed(&mut t));
+
+    // Host auto-repeat re-presses the key before the deferred release
+    t.emulator().send_key(ZXKey::A, true);
+    for _ in 0..5 {
+        t.emulate_frame();
+        t.emulator().send_key(ZXKey::A, false);
+        t.emulate_frame();
+        assert!(a_pressed(&mut t));
+        t.emulator().send_key(ZXKey::A, true);
+    }
+    // Release after the debounce time is applied immediately
+    t.emulate_frame();
+    t.emulate_frame();
+    t.emulate_frame();
+    t.emulator().send_key(ZXKey::A, false);
+    assert!(!a_pressed(&mut t));
+
+    // Disabling debounce applies pending releases
+    t.emulator().send_key(ZXKey::A, true);
+    t.emulator().send_key(ZXKey::A, false);
+    assert!(a_pressed(&mut t));
+    t.emulator().set_key_debounce(0);
+    assert!(!a_pressed(&mut t));
+}
+
+/// Writes MIC only, EAR only and zero to the ULA port, reading it back after each write
+fn ear_sense(issue: KeyboardIssue) -> [u8; 3] {
+    let mut settings = presets::settings_48k_nosound();
+    settings.keyboard_issue = issue;
+    let mut t = RustZXTester::new("ear_sense", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x08,             // LD A, 0x08
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x3E, 0x10,             // LD A, 0x10
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x01, 0x90,       // LD (0x9001), A
+        0xAF,                   // XOR A
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x02, 0x90,       // LD (0x9002), A
+        0x18, 0xFE,             // JR $
+    ];
+    t.load_program_to_bank(1, PROGRAM);
+    t.emulate_frame();
+
+    [0x9000, 0x9001, 0x9002].map(|addr| t.emulator().peek(addr))
+}
+
+/// Reads the ULA port after writing EAR only, MIC only and zero to it, with
+/// `Space` key pressed
+fn ula_port_reads(machine: ZXMachine, issue: KeyboardIssue) -> [u8; 3] {
+    let mut settings = presets::settings_48k_nosound();
+    settings.machine = machine;
+    settings.load_default_rom = machine.capabilities().has_embedded_rom;
+    settings.keyboard_issue = issue;
+    let mut t = RustZXTester::new("ula_port_reads", settings);
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x10,             // LD A, 0x10
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xAF,                   // XOR A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x00, 0x70,       // LD (0x7000), A
+        0x3E, 0x08,             // LD A, 0x08
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xAF,                   // XOR A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x01, 0x70,       // LD (0x7001), A
+        0xAF,                   // XOR A
+        0xD3, 0xFE,             // OUT (0xFE), A
+        0xDB, 0xFE,             // IN A, (0xFE)
+        0x32, 0x02, 0x70,       // LD (0x7002), A
+        0x18, 0xFE,             // JR $
+    ];
+    // 0x6000 is RAM on every machine, including 16K
+    for (offset, &byte) in PROGRAM.iter().enumerate() {
+        t.emulator().apply_poke(0x6000 + offset as u16, byte);
+    }
+    t.emulator().cpu_mut().regs.set_pc(0x6000);
+    t.emulator().send_key(ZXKey::Space, true);
+    t.emulate_frame();
+
+    [0x7000, 0x7001, 0x7002].map(|addr| t.emulator().peek(addr))
+}
+
+#[test]
+fn ula_port_unused_bits() {
+    for machine in [
+        ZXMachine::Sinclair16K,
+        ZXMachine::Sinclair48K,
+        ZXMachine::Sinclair128K,
+        ZXMachine::SinclairPlus3,
+    ] {
+        // Bits 5 and 7 are always set, bit 0 is reset by `Space`
+        assert_eq!(
+            ula_port_reads(machine, KeyboardIssue::Issue2),
+            [0xFE, 0xFE, 0xBE],
+            "{:?}",
+            machine
+        );
+        assert_eq!(
+            ula_port_reads(machine, KeyboardIssue::Issue3),
+            [0xFE, 0xBE, 0xBE],
+            "{:?}",
+            machine
+        );
+    }
+}
+
+#[test]
+fn keyboard_issue() {
+    assert_eq!(ear_sense(KeyboardIssue::Issue2), [0xFF, 0xFF, 0xBF]);
+    assert_eq!(ear_sense(KeyboardIssue::Issue3), [0xBF, 0xFF, 0xBF]);
+}
@@ -0,0 +1,47 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rustzx_test::framework::{presets, RustZXTester};
+use std::{sync::Mutex, time::Duration};
+
+/// Logger which keeps all messages from `rustzx_core` in memory
+struct CapturingLogger {
+    records: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("rustzx_core")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn paging_log() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Debug);
+
+    // 128K ROM pages memory during the boot
+    let mut t = RustZXTester::new("paging_log", presets::settings_128k_nosound());
+    t.emulate_for(Duration::from_millis(500));
+
+    let records = LOGGER.records.lock().unwrap();
+    assert!(
+        records
+            .iter()
+            .any(|(level, message)| *level == Level::Debug
+                && message.starts_with("Port 0x7FFD write"))
+    );
+}
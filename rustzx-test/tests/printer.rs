@@ -0,0 +1,59 @@
+use rustzx_core::zx::PRINTER_ROW_SIZE;
+use rustzx_test::framework::{presets, RustZXTester};
+use std::time::Duration;
+
+/// Returns offset of the bitmap line `y` in the screen bank
+fn bitmap_line_offset(y: usize) -> usize {
+    ((y & 0xC0) << 5) | ((y & 0x07) << 8) | ((y & 0x38) << 2)
+}
+
+#[test]
+fn printer_copy() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.printer_enabled = true;
+
+    let mut tester = RustZXTester::new("printer_copy", settings);
+    // Wait for ROM to load
+    tester.emulate_for(Duration::from_millis(2000));
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xCD, 0xAC, 0x0E,       // CALL 0x0EAC ; ROM COPY
+        0x18, 0xFE,             // JR $
+    ];
+    let screen = tester.emulator().ram_bank_mut(0).unwrap();
+    for (index, byte) in screen[..6144].iter_mut().enumerate() {
+        *byte = (index * 7 + index / 256) as u8;
+    }
+    tester.load_program_to_bank(1, PROGRAM);
+    tester.emulate_for(Duration::from_millis(2000));
+
+    // COPY prints the top 22 character lines of the screen
+    let output = tester.emulator().take_printer_output();
+    assert_eq!(output.len(), 176 * PRINTER_ROW_SIZE);
+    let screen = tester.emulator().ram_bank(0).unwrap();
+    for (y, row) in output.chunks(PRINTER_ROW_SIZE).enumerate() {
+        let offset = bitmap_line_offset(y);
+        assert_eq!(row, &screen[offset..offset + PRINTER_ROW_SIZE], "row {}", y);
+    }
+    assert!(tester.emulator().take_printer_output().is_empty());
+}
+
+#[test]
+fn printer_not_connected() {
+    let mut tester = RustZXTester::new("printer_not_connected", presets::settings_48k_nosound());
+    tester.emulate_for(Duration::from_millis(2000));
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xCD, 0xAC, 0x0E,       // CALL 0x0EAC ; ROM COPY
+        0x3E, 0x01,             // LD A, 1
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x18, 0xFE,             // JR $
+    ];
+    tester.load_program_to_bank(1, PROGRAM);
+    tester.emulate_for(Duration::from_millis(100));
+
+    // ROM returns immediately when printer is missing
+    assert_eq!(tester.emulator().peek(0x9000), 0x01);
+    assert!(tester.emulator().take_printer_output().is_empty());
+}
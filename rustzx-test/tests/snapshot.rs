@@ -0,0 +1,19 @@
+use rustzx_test::framework::{presets, RustZXTester};
+
+#[test]
+fn sna_compressed() {
+    // Loads packed snapshot directly, core should detect and unpack it
+    // transparently
+    let mut packed = RustZXTester::new("sna_compressed", presets::settings_48k_nosound());
+    packed.load_sna_raw("keyboard.48k.sna.gz");
+    let mut unpacked = RustZXTester::new("sna_uncompressed", presets::settings_48k_nosound());
+    unpacked.load_sna("keyboard.48k.sna.gz");
+
+    for _ in 0..2 {
+        for addr in 0x4000..=0xFFFF {
+            assert_eq!(packed.emulator().peek(addr), unpacked.emulator().peek(addr));
+        }
+        packed.emulate_frame();
+        unpacked.emulate_frame();
+    }
+}
@@ -1,4 +1,5 @@
 use expect_test::expect;
+use rustzx_core::zx::sound::sample::SoundSample;
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -10,7 +11,7 @@ fn sound_48k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"bEAIV7LVE7591l6VUrCE1ySbEyBSNeVwLjWhDm9Jlpc="#]],
+        expect![[r#"rdQpTOBOcZW+zvDnAwaAJw2j+WmttBE2wCZLdClqBVE="#]],
     );
 }
 
@@ -22,6 +23,264 @@ fn sound_128k() {
     tester.emulate_for(Duration::from_secs(2));
     tester.expect_sound(
         "beeper_plus_ay",
-        expect![[r#"u8WCHu89dFvnMInLGaDFV4ha6FatBtXLJ6szqiUg+ys="#]],
+        expect![[r#"TvEHx7y2kHG/A1/Dds6jV+VrPSlwOfOmngzLMVzadj4="#]],
     );
 }
+
+#[test]
+fn audio_latency() {
+    let mut tester = RustZXTester::new("audio_latency", presets::settings_48k());
+    tester.emulate_for(Duration::from_millis(200));
+    // Default latency target is one frame (44100 Hz * 19.968 ms, rounded up)
+    assert_eq!(tester.emulator().buffered_audio_samples(), 881);
+
+    tester.emulator().set_audio_latency_ms(100);
+    tester.emulate_for(Duration::from_millis(200));
+    assert_eq!(tester.emulator().buffered_audio_samples(), 4410);
+
+    while tester.emulator().next_audio_sample().is_some() {}
+    tester.emulate_for(Duration::from_millis(40));
+    assert_eq!(tester.emulator().buffered_audio_samples(), 1761);
+}
+
+#[test]
+fn audio_exact_samples() {
+    let mut tester = RustZXTester::new("audio_exact_samples", presets::settings_48k());
+    let mut samples = vec![SoundSample::new(f32::NAN, f32::NAN); 1001];
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    assert!(samples
+        .iter()
+        .all(|s| s.left.is_finite() && s.right.is_finite()));
+    // Two frames (880.6 samples each) were emulated to produce requested
+    // samples, leftovers are kept
+    assert_eq!(tester.emulator().buffered_audio_samples(), 1761 - 1001);
+
+    let mut samples = vec![SoundSample::new(f32::NAN, f32::NAN); 3];
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    assert!(samples.iter().all(|s| s.left.is_finite()));
+    assert_eq!(tester.emulator().buffered_audio_samples(), 1761 - 1004);
+}
+
+/// Returns max difference between adjacent beeper samples
+fn beeper_max_step(cutoff: Option<usize>) -> f32 {
+    let mut settings = presets::settings_48k();
+    settings.ay_enabled = false;
+    settings.beeper_lowpass_cutoff = cutoff;
+    let mut tester = RustZXTester::new("beeper_lowpass", settings);
+    tester.load_sna("sound.48k.sna.gz");
+    tester.emulate_for(Duration::from_millis(500));
+
+    let mut samples = vec![SoundSample::new(0.0, 0.0); 44100];
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    samples
+        .windows(2)
+        .map(|pair| (pair[1].left - pair[0].left).abs())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn beeper_lowpass() {
+    let raw = beeper_max_step(None);
+    let filtered = beeper_max_step(Some(4000));
+    assert!(raw > 0.0);
+    assert!(filtered > 0.0);
+    assert!(filtered < raw * 0.5);
+}
+
+#[test]
+fn audio_sample_rate_no_drift() {
+    for rate in [11025, 44099, 44100, 48000] {
+        let mut settings = presets::settings_48k();
+        settings.sound_sample_rate = rate;
+        let mut tester = RustZXTester::new("audio_sample_rate_no_drift", settings);
+
+        // About 10 seconds of emulated time
+        let mut samples = 0;
+        for _ in 0..500 {
+            tester.emulator().emulate_frame().unwrap();
+            while tester.emulator().next_audio_sample().is_some() {
+                samples += 1;
+            }
+        }
+        let duration = tester.emulator().frame_duration() * 500;
+        let expected = (rate as f64 * duration.as_secs_f64()) as usize;
+        assert!(
+            samples.max(expected) - samples.min(expected) <= 1,
+            "{} Hz: expected {} samples, got {}",
+            rate,
+            expected,
+            samples
+        );
+    }
+}
+
+#[test]
+fn audio_samples_per_frame() {
+    for rate in [22050, 44100, 48000] {
+        let mut settings = presets::settings_48k();
+        settings.sound_sample_rate = rate;
+        let mut tester = RustZXTester::new("audio_samples_per_frame", settings);
+        tester.load_sna("sound.48k.sna.gz");
+
+        let frame_samples = rate as f64 * tester.emulator().frame_duration().as_secs_f64();
+        for _ in 0..100 {
+            let expected = tester.emulator().samples_per_frame();
+            assert!((expected as f64 - frame_samples).abs() < 1.0);
+            tester.emulator().emulate_frame().unwrap();
+            let mut out = vec![f32::NAN; expected * 2];
+            let taken = tester.emulator().take_audio(&mut out);
+            assert_eq!(taken, expected, "{} Hz", rate);
+            assert!(out.iter().all(|value| value.is_finite()));
+            while tester.emulator().next_audio_sample().is_some() {}
+        }
+        // Buffer underrun is padded with the last sample
+        let mut out = vec![f32::NAN; 5];
+        assert_eq!(tester.emulator().take_audio(&mut out), 0);
+        assert!(out.iter().all(|value| value.is_finite()));
+    }
+}
+
+#[test]
+fn mono_output() {
+    let mut settings = presets::settings_128k();
+    settings.mono_output = true;
+    let mut tester = RustZXTester::new("mono_output", settings);
+    tester.load_sna("sound.128k.sna.gz");
+    tester.emulate_for(Duration::from_millis(500));
+
+    let mut samples = vec![SoundSample::new(0.0, 0.0); 44100];
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    assert!(samples.iter().any(|s| s.left != 0.0));
+    assert!(samples.iter().all(|s| s.left == s.right));
+}
+
+#[test]
+fn per_source_volume() {
+    let render = |beeper: f64, ay: f64| {
+        let mut tester = RustZXTester::new("per_source_volume", presets::settings_128k());
+        tester.emulator().set_beeper_volume(beeper);
+        tester.emulator().set_ay_volume(ay);
+        tester.load_sna("sound.128k.sna.gz");
+        tester.emulate_for(Duration::from_millis(500));
+        let mut samples = vec![SoundSample::new(0.0, 0.0); 22050];
+        tester.emulator().fill_audio_samples(&mut samples).unwrap();
+        samples
+    };
+    let reference = render(0.0, 1.0);
+    assert!(reference.iter().any(|s| s.left != 0.0));
+    // Gain is applied before the master volume
+    for (ay, gain) in [(0.5, 0.5), (5.0, 2.0), (-1.0, 0.0)] {
+        let scaled = render(0.0, ay);
+        for (actual, expected) in scaled.iter().zip(&reference) {
+            assert_eq!(actual.left, expected.left * gain as f32);
+            assert_eq!(actual.right, expected.right * gain as f32);
+        }
+    }
+}
+
+#[test]
+fn pause_fades_audio_out() {
+    let mut tester = RustZXTester::new("pause_fades_audio_out", presets::settings_128k());
+    tester.load_sna("sound.128k.sna.gz");
+    tester.emulator().emulate_frame_count(4).unwrap();
+    tester.emulator().pause();
+    let mut samples = vec![SoundSample::new(1.0, 1.0); 4410];
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    // Buffered frame is faded out, no frames are emulated while paused
+    let audible = samples.iter().rposition(|s| s.left != 0.0).unwrap();
+    assert!(audible < tester.emulator().samples_per_frame());
+    assert!(samples[audible..]
+        .windows(2)
+        .all(|w| w[1].left.abs() <= w[0].left.abs()));
+
+    tester.emulator().resume();
+    tester.emulator().fill_audio_samples(&mut samples).unwrap();
+    assert!(samples.iter().filter(|s| s.left != 0.0).count() > audible);
+}
+
+#[test]
+fn turbo_keeps_last_frame_audio() {
+    let drain = |tester: &mut RustZXTester| {
+        std::iter::from_fn(|| tester.emulator().next_audio_sample())
+            .map(|sample| (sample.left, sample.right))
+            .collect::<Vec<_>>()
+    };
+
+    let mut reference = RustZXTester::new("turbo_reference", presets::settings_128k());
+    reference.load_sna("sound.128k.sna.gz");
+    reference.emulator().set_audio_latency_ms(200);
+    reference.emulator().emulate_frame_count(4).unwrap();
+    drain(&mut reference);
+    reference.emulator().emulate_frame().unwrap();
+    let expected = drain(&mut reference);
+
+    let mut tester = RustZXTester::new("turbo", presets::settings_128k());
+    tester.load_sna("sound.128k.sna.gz");
+    tester.emulator().set_audio_latency_ms(200);
+    tester.emulator().set_turbo(0);
+    assert_eq!(tester.emulator().turbo(), 1);
+    tester.emulator().set_turbo(5);
+    assert_eq!(tester.emulator().emulate_turbo_frames().unwrap(), 5);
+    let actual = drain(&mut tester);
+    assert_eq!(actual.len(), 882);
+    assert_eq!(actual, expected);
+    assert_eq!(
+        tester.emulator().screen_frame().unwrap().data,
+        reference.emulator().screen_frame().unwrap().data
+    );
+}
+
+#[test]
+fn ay_write_log() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x01, 0xFD, 0xFF,       // LD BC, 0xFFFD
+        0x3E, 0x07,             // LD A, 7
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0xBF,             // LD B, 0xBF
+        0x3E, 0x38,             // LD A, 0x38
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+        // 0x8010
+        0x06, 0xFF,             // LD B, 0xFF
+        0x3E, 0x08,             // LD A, 8
+        0xED, 0x79,             // OUT (C), A
+        0x06, 0xBF,             // LD B, 0xBF
+        0x3E, 0x0F,             // LD A, 0x0F
+        0xED, 0x79,             // OUT (C), A
+        0x3E, 0x0E,             // LD A, 0x0E
+        0xED, 0x79,             // OUT (C), A
+        0x18, 0xFE,             // JR $
+    ];
+    let mut t = RustZXTester::new("ay_write_log", presets::settings_128k_nosound());
+    t.load_program_to_bank(2, PROGRAM);
+    // Not logged by default
+    t.emulate_frame();
+    assert!(t.emulator().take_ay_log().is_empty());
+
+    t.emulator().start_ay_log(2);
+    t.emulator().cpu_mut().regs.set_pc(0x8000);
+    t.emulate_frame();
+    let log = t.emulator().take_ay_log();
+    assert_eq!(log.len(), 1);
+    assert_eq!((log[0].frame, log[0].register, log[0].value), (0, 7, 0x38));
+    assert!(log[0].clocks < 100);
+    assert!(t.emulator().take_ay_log().is_empty());
+
+    // Writes above capacity are dropped
+    t.emulator().cpu_mut().regs.set_pc(0x8010);
+    t.emulate_frame();
+    t.emulator().cpu_mut().regs.set_pc(0x8000);
+    t.emulate_frame();
+    let log: Vec<_> = t
+        .emulator()
+        .stop_ay_log()
+        .iter()
+        .map(|write| (write.frame, write.register, write.value))
+        .collect();
+    assert_eq!(log, [(1, 8, 0x0F), (1, 8, 0x0E)]);
+    t.emulator().cpu_mut().regs.set_pc(0x8000);
+    t.emulate_frame();
+    assert!(t.emulator().take_ay_log().is_empty());
+}
@@ -1,5 +1,5 @@
 use expect_test::expect;
-use rustzx_core::zx::keys::ZXKey;
+use rustzx_core::zx::{events::EmulationEvents, keys::ZXKey, TapeFileKind, TapePhase};
 use rustzx_test::framework::{presets, RustZXTester};
 use std::time::Duration;
 
@@ -161,6 +161,40 @@ fn tape_stop() {
     );
 }
 
+#[test]
+fn tape_autostop() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_autostop", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulator().set_tape_autostop(true);
+    tester.emulator().play_tape();
+
+    for block_index in 0..2 {
+        let mut frames = 0;
+        while !tester
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::TAPE_BLOCK_FINISHED)
+        {
+            tester.emulate_frame();
+            frames += 1;
+            assert!(frames < 500, "Block {} was not finished", block_index);
+        }
+        assert!(tester.emulator().is_tape_paused());
+        let position = tester.emulator().tape_position();
+        assert_eq!(position.block_index, block_index);
+        assert_eq!(position.block_progress, 1.0);
+
+        // Paused tape does not advance to the next block
+        tester.emulate_for(Duration::from_millis(2000));
+        assert_eq!(tester.emulator().tape_position(), position);
+        tester.emulator().tape_resume();
+    }
+}
+
 #[test]
 fn tape_rewind() {
     let mut settings = presets::settings_48k_nosound();
@@ -213,6 +247,67 @@ fn fastload() {
     );
 }
 
+#[test]
+fn fastload_runtime_switch() {
+    let mut tester = RustZXTester::new("fastload_switch", presets::settings_48k_nosound());
+    tester.emulator().set_fast_tape_load(false);
+    assert!(!tester.emulator().fast_tape_load());
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    // ROM loader waits for the tape signal, trap is not triggered
+    assert!(!tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+
+    tester.emulator().set_fast_tape_load(true);
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn tape_trap_address() {
+    let mut tester = RustZXTester::new("tape_trap_disabled", presets::settings_48k_nosound());
+    assert_eq!(tester.emulator().tape_trap_address(), Some(0x056B));
+    tester.emulator().set_tape_trap_address(None);
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(!tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+
+    // Trap is checked before the instruction execution, `RET NZ` preceding
+    // LD-START is not taken in the loader and could be used as a trap
+    let mut settings = presets::settings_48k_nosound();
+    settings.autoload_enabled = false;
+    let mut tester = RustZXTester::new("tape_trap_moved", settings);
+    tester.emulator().set_tape_trap_address(Some(0x056A));
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
 #[test]
 fn fastload_128k() {
     let mut tester = RustZXTester::new("fastload_128k", presets::settings_128k_nosound());
@@ -227,3 +322,389 @@ fn fastload_128k() {
         expect![[r#"tmGY7e4h+XA3px6BcqnCXF83NEdBqVw8PW9sQtpMAvM="#]],
     );
 }
+
+#[test]
+fn fastload_compressed() {
+    // Loads packed tape directly, core should detect and unpack it transparently
+    let mut tester = RustZXTester::new("fastload_compressed", presets::settings_48k_nosound());
+    tester.load_tap_raw("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(45));
+    tester.emulate_for(Duration::from_millis(10));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn fastload_zip() {
+    // Archive contains unrelated file first, core should pick the tape
+    let mut tester = RustZXTester::new("fastload_zip", presets::settings_48k_nosound());
+    tester.load_tap_raw("simple_tape.zip");
+    tester.emulate_for(Duration::from_millis(45));
+    tester.emulate_for(Duration::from_millis(10));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn fastload_uncompressed() {
+    // Unpacked tape without gzip header should be loaded as-is
+    let mut tester = RustZXTester::new("fastload_uncompressed", presets::settings_48k_nosound());
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(45));
+    tester.emulate_for(Duration::from_millis(10));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
+
+#[test]
+fn save_record() {
+    let mut tester = RustZXTester::new("save_record", presets::settings_48k_nosound());
+    // Wait for ROM to load
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.emulator().start_tape_record();
+    // Emulate SAVE "a"
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::S],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::A],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+            // "Start tape, then press any key"
+            &[ZXKey::Space],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(10000));
+    let tap = tester.emulator().stop_tape_record();
+
+    // Header block for the empty BASIC program named "a"
+    assert_eq!(&tap[0..2], &[19, 0]);
+    assert_eq!(&tap[2..14], b"\x00\x00a         ");
+    let data_start = 2 + 19;
+    let data_size = u16::from_le_bytes([tap[data_start], tap[data_start + 1]]) as usize;
+    assert_eq!(tap.len(), data_start + 2 + data_size);
+    assert_eq!(tap[data_start + 2], 0xFF);
+
+    // Check blocks checksums
+    assert_eq!(tap[2..data_start].iter().fold(0, |acc, b| acc ^ b), 0);
+    assert_eq!(tap[data_start + 2..].iter().fold(0, |acc, b| acc ^ b), 0);
+}
+
+#[test]
+fn tape_position_seek() {
+    let mut tester = RustZXTester::new("tape_position_seek", presets::settings_48k_nosound());
+    tester.load_tap("simple_tape.tap.gz");
+
+    // Header + data for the loader and for the screen
+    let position = tester.emulator().tape_position();
+    assert_eq!(position.block_index, 0);
+    assert_eq!(position.blocks_count, 4);
+    assert_eq!(position.block_progress, 0.0);
+
+    tester.emulate_for(Duration::from_millis(55));
+    let position = tester.emulator().tape_position();
+    assert_eq!(position.block_index, 3);
+    assert_eq!(position.block_progress, 1.0);
+
+    tester.emulator().tape_seek_block(2).unwrap();
+    let position = tester.emulator().tape_position();
+    assert_eq!(position.block_index, 2);
+    assert_eq!(position.block_progress, 0.0);
+
+    assert!(tester.emulator().tape_seek_block(4).is_err());
+}
+
+#[test]
+fn tape_block_info() {
+    let mut tester = RustZXTester::new("tape_block_info", presets::settings_48k_nosound());
+    tester.load_tap("simple_tape.tap.gz");
+
+    let info = tester.emulator().tape_block_info(0).unwrap();
+    assert_eq!(info.size, 19);
+    assert_eq!(info.flag, Some(0x00));
+    let header = info.header.unwrap();
+    assert_eq!(header.kind, TapeFileKind::Program);
+    assert_eq!(&header.name, b"screen    ");
+    assert_eq!(header.length, 38);
+    assert_eq!(header.autostart_line(), Some(10));
+    assert_eq!(header.start_address(), None);
+
+    let info = tester.emulator().tape_block_info(1).unwrap();
+    assert_eq!((info.size, info.flag, info.header), (40, Some(0xFF), None));
+
+    let header = tester
+        .emulator()
+        .tape_block_info(2)
+        .unwrap()
+        .header
+        .unwrap();
+    assert_eq!(header.kind, TapeFileKind::Code);
+    assert_eq!(header.length, 6912);
+    assert_eq!(header.start_address(), Some(0x4000));
+    assert_eq!(header.autostart_line(), None);
+
+    assert_eq!(tester.emulator().tape_block_info(3).unwrap().size, 6914);
+    assert!(tester.emulator().tape_block_info(4).is_err());
+    // Tape position is not affected
+    assert_eq!(tester.emulator().tape_position().block_index, 0);
+}
+
+#[test]
+fn tape_hotswap() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("tape_hotswap", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    // Wait for ROM to load
+    tester.emulate_for(Duration::from_millis(2000));
+    tester.emulator().ram_bank_mut(1).unwrap()[0x1000] = 0xAA;
+
+    tester.emulator().eject_tape();
+    assert_eq!(tester.emulator().tape_position().blocks_count, 0);
+
+    // Swap tape in the middle of the real time playback
+    tester.insert_tap("simple_tape.tap.gz");
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(2500));
+    assert!(tester.emulator().tape_position().block_progress > 0.0);
+    tester.insert_tap("simple_tape.tap.gz");
+    let position = tester.emulator().tape_position();
+    assert_eq!((position.block_index, position.blocks_count), (0, 4));
+    assert_eq!(position.block_progress, 0.0);
+    tester.emulate_for(Duration::from_millis(100));
+    assert_eq!(tester.emulator().tape_position().block_progress, 0.0);
+
+    // Machine state is preserved and new tape is ready for loading
+    assert_eq!(tester.emulator().peek(0x9000), 0xAA);
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+    assert_eq!(tester.emulator().tape_position().block_index, 3);
+}
+
+#[test]
+fn tape_load_requested() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    settings.autoload_enabled = false;
+    let mut tester = RustZXTester::new("tape_load_requested", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    let load_requested = |tester: &mut RustZXTester| {
+        tester
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::TAPE_LOAD_REQUESTED)
+    };
+
+    // Wait for ROM to load
+    tester.emulate_for(Duration::from_millis(2000));
+    assert!(!load_requested(&mut tester));
+    // Emulate LOAD ""
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(!load_requested(&mut tester));
+    tester.send_keystrokes(&[&[ZXKey::Enter]], Duration::from_millis(100));
+    tester.emulate_for(Duration::from_millis(100));
+    assert!(load_requested(&mut tester));
+    // Tape playback is up to the frontend
+    assert_eq!(tester.emulator().tape_phase(), TapePhase::Stopped);
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(100));
+    assert_eq!(tester.emulator().tape_phase(), TapePhase::Pilot);
+}
+
+#[test]
+fn loading_border_pattern() {
+    // Order of the colors depends on the last loader border write
+    let colors = |tester: &mut RustZXTester| {
+        tester.emulator().loading_border_pattern().map(|pattern| {
+            let mut colors = pattern.map(u8::from);
+            colors.sort_unstable();
+            colors
+        })
+    };
+
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_enabled = false;
+    let mut tester = RustZXTester::new("loading_border_pattern", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    assert_eq!(colors(&mut tester), None);
+    // Header pilot tone, ROM loader is started by the autoload snapshot
+    tester.emulator().play_tape();
+    tester.emulate_for(Duration::from_millis(2000));
+    assert_eq!(tester.emulator().tape_phase(), TapePhase::Pilot);
+    // Red and cyan, written by the ROM loader
+    assert_eq!(colors(&mut tester), Some([2, 5]));
+    assert_eq!(
+        tester.emulator().border_color() as u8,
+        tester.emulator().loading_border_pattern().unwrap()[1] as u8
+    );
+
+    let mut settings = presets::settings_48k_nosound();
+    settings.tape_fastload_border_frames = 10;
+    let mut tester = RustZXTester::new("loading_border_pattern_fastload", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    while !tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED)
+    {
+        assert_eq!(colors(&mut tester), None);
+        tester.emulate_frame();
+    }
+    // Blue and yellow, which ROM loader would have written for data
+    for _ in 0..9 {
+        assert_eq!(colors(&mut tester), Some([1, 6]));
+        tester.emulate_frame();
+    }
+    tester.emulate_frame();
+    assert_eq!(colors(&mut tester), None);
+}
+
+#[test]
+fn tape_load_error() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xDD, 0x21, 0x00, 0x90, // LD IX, 0x9000
+        0x11, 0x0A, 0x00,       // LD DE, 10
+        0xAF,                   // XOR A
+        0x37,                   // SCF
+        0xCD, 0x02, 0x08,       // CALL 0x0802 ; ROM LD-BLOCK
+        0x18, 0xFE,             // JR $
+    ];
+    let run_loader = |tester: &mut RustZXTester| {
+        // Wait for ROM to load
+        tester.emulate_for(Duration::from_millis(2000));
+        tester.emulator().take_events();
+        tester.load_program_to_bank(1, PROGRAM);
+    };
+    let load_error = |tester: &mut RustZXTester| {
+        tester
+            .emulator()
+            .take_events()
+            .contains(EmulationEvents::TAPE_LOAD_ERROR)
+    };
+
+    // Loader waits for the signal without tape
+    let mut tester = RustZXTester::new("tape_load_error", presets::settings_48k_nosound());
+    run_loader(&mut tester);
+    tester.emulate_for(Duration::from_millis(500));
+    assert!(!load_error(&mut tester));
+    tester.emulate_for(Duration::from_millis(1000));
+    assert!(load_error(&mut tester));
+    // Error is reported once per stall
+    tester.emulate_for(Duration::from_millis(2000));
+    assert!(!load_error(&mut tester));
+
+    // Header block is longer than requested, ROM reports the checksum error
+    let mut settings = presets::settings_48k_nosound();
+    settings.autoload_enabled = false;
+    let mut tester = RustZXTester::new("tape_load_error", settings);
+    tester.load_tap("simple_tape.tap.gz");
+    run_loader(&mut tester);
+    tester.emulate_for(Duration::from_millis(20));
+    assert!(load_error(&mut tester));
+}
+
+/// Renders TAP blocks to the audio signal with the standard ROM timings
+fn tap_to_audio(tap: &[u8], sample_rate: usize) -> Vec<f32> {
+    const CPU_CLOCKS_PER_SECOND: f64 = 69888.0 * 50.0;
+    let mut pulses = vec![];
+    let mut rest = tap;
+    while rest.len() >= 2 {
+        let len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        let block = &rest[2..2 + len];
+        rest = &rest[2 + len..];
+        let pilot_pulses = if block[0] < 0x80 { 8063 } else { 3223 };
+        pulses.extend(std::iter::repeat_n(2168, pilot_pulses));
+        pulses.extend([667, 735]);
+        for byte in block {
+            for bit in (0..8).rev() {
+                let length = if byte & (1 << bit) != 0 { 1710 } else { 855 };
+                pulses.extend([length, length]);
+            }
+        }
+        // One second pause
+        pulses.push(CPU_CLOCKS_PER_SECOND as usize);
+    }
+
+    let mut samples = vec![];
+    let mut level = 1.0;
+    let mut time = 0.0;
+    for pulse in pulses {
+        time += pulse as f64 / CPU_CLOCKS_PER_SECOND;
+        while (samples.len() as f64) < time * sample_rate as f64 {
+            samples.push(level);
+        }
+        level = -level;
+    }
+    samples
+}
+
+#[test]
+fn external_ear_audio() {
+    let mut settings = presets::settings_48k_nosound();
+    settings.autoload_enabled = false;
+
+    let mut tester = RustZXTester::new("external_ear_audio", settings);
+    let tap = tester.load_asset_data("simple_tape.tap.gz");
+    // Inserted tape is ignored while external audio is played
+    tester.load_tap("simple_tape.tap.gz");
+    tester.emulate_for(Duration::from_millis(2000));
+    // Audio starts with the long pilot tone, so it could be fed before LOAD ""
+    tester
+        .emulator()
+        .feed_ear_audio(&tap_to_audio(&tap, 44100), 44100);
+    let queued = tester.emulator().queued_ear_samples();
+    tester.send_keystrokes(
+        &[
+            &[ZXKey::J],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::SymShift, ZXKey::P],
+            &[ZXKey::Enter],
+        ],
+        Duration::from_millis(100),
+    );
+    tester.emulate_for(Duration::from_millis(600));
+    assert!(!tester
+        .emulator()
+        .take_events()
+        .contains(EmulationEvents::TAPE_FAST_LOAD_TRIGGER_DETECTED));
+    // One second of audio was played
+    assert!(queued - tester.emulator().queued_ear_samples() > 44000);
+
+    while tester.emulator().queued_ear_samples() != 0 {
+        tester.emulate_for(Duration::from_millis(1000));
+    }
+    tester.emulate_for(Duration::from_millis(100));
+    tester.expect_screen(
+        "finished",
+        expect![[r#"zDQzdQr19uTYaZouk7ex+pkylk2TRFAuenooMVFjkyQ="#]],
+    );
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "timex")]
+
+use expect_test::expect;
+use rustzx_core::zx::{
+    constants::{CANVAS_BUFFER_WIDTH, TIMEX_CANVAS_BUFFER_WIDTH},
+    video::TimexVideoMode,
+};
+use rustzx_test::framework::{presets, RustZXTester};
+
+#[test]
+fn timex_port() {
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xF3,                   // DI
+        0x3E, 0x06,             // LD A, 0x06 ; Hi-res mode
+        0xD3, 0xFF,             // OUT (0xFF), A
+        0xDB, 0xFF,             // IN A, (0xFF)
+        0x32, 0x00, 0x90,       // LD (0x9000), A
+        0x18, 0xFE,             // JR $
+    ];
+    let run = |timex_enabled| {
+        let mut settings = presets::settings_48k_nosound();
+        settings.timex_enabled = timex_enabled;
+        let mut t = RustZXTester::new("timex_port", settings);
+        t.load_program(PROGRAM);
+        t.emulate_frame();
+        t
+    };
+
+    // Sinclair machine does not decode port 0xFF even with `timex` feature
+    let mut t = run(false);
+    assert_eq!(t.emulator().canvas_buffer_width(), CANVAS_BUFFER_WIDTH);
+    assert_eq!(t.emulator().timex_video_mode(), TimexVideoMode::Standard);
+
+    let mut t = run(true);
+    assert_eq!(
+        t.emulator().canvas_buffer_width(),
+        TIMEX_CANVAS_BUFFER_WIDTH
+    );
+    assert_eq!(t.emulator().timex_video_mode(), TimexVideoMode::HiRes);
+    assert_eq!(t.emulator().peek(0x9000), 0x06);
+}
+
+/// Fills bitmap with vertical stripes and the second screen area with 0x55,
+/// which is rendered as attributes of every line in hi-colour mode and as
+/// every second column of pixels in hi-res mode
+#[test]
+fn timex_video_modes() {
+    let run = |name: &str, mode: u8| {
+        #[rustfmt::skip]
+        let program: &[u8] = &[
+            0xF3,                   // DI
+            0x21, 0x00, 0x40,       // LD HL, 0x4000
+            0x11, 0x01, 0x40,       // LD DE, 0x4001
+            0x01, 0xFF, 0x17,       // LD BC, 0x17FF
+            0x36, 0xF0,             // LD (HL), 0xF0
+            0xED, 0xB0,             // LDIR
+            0x21, 0x00, 0x60,       // LD HL, 0x6000
+            0x11, 0x01, 0x60,       // LD DE, 0x6001
+            0x01, 0xFF, 0x17,       // LD BC, 0x17FF
+            0x36, 0x55,             // LD (HL), 0x55
+            0xED, 0xB0,             // LDIR
+            0x3E, mode,             // LD A, mode
+            0xD3, 0xFF,             // OUT (0xFF), A
+            0x18, 0xFE,             // JR $
+        ];
+        let mut settings = presets::settings_48k_nosound();
+        settings.timex_enabled = true;
+        let mut t = RustZXTester::new(name, settings);
+        t.load_program(program);
+        // Screen filling takes about 4 frames, the next one is completely
+        // rendered in the selected mode
+        for _ in 0..6 {
+            t.emulate_frame();
+        }
+        t
+    };
+
+    let t = run("timex_hi_colour", 0x02);
+    t.expect_screen(
+        "hi_colour",
+        expect![[r#"j+MaMzXK0NfgaemRypX3AQtA4fsiwnzqvDgYIenjmGY="#]],
+    );
+
+    // Green ink on magenta paper
+    let mut t = run("timex_hi_res", 0x26);
+    t.expect_screen(
+        "hi_res",
+        expect![[r#"NWe0tgm5iGUvQLdue4znOu4/9JRR9rtLg24wdg6sjz0="#]],
+    );
+    // Exported frame keeps the hi-res canvas, border pixels are doubled
+    let png = t.emulator().export_png().unwrap();
+    let (info, _) = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    assert_eq!((info.width, info.height), (640, 240));
+}
@@ -59,6 +59,12 @@ pub trait Z80Bus {
     fn reti(&mut self);
     /// Method, invoked by Z80 in case of HALT line change
     fn halt(&mut self, halted: bool);
+    /// Method, invoked by Z80 when maskable interrupt is accepted, before the
+    /// acknowledge cycle. Default implementation is empty
+    fn interrupt_accepted(&mut self) {}
+    /// Method, invoked by Z80 when non-maskable interrupt is accepted, before
+    /// the jump to 0x0066. Default implementation is empty
+    fn nmi_accepted(&mut self) {}
     /// Checks int signal
     fn int_active(&self) -> bool;
     /// Checks nmi signal
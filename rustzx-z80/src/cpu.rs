@@ -5,6 +5,7 @@ use crate::{
         execute_bits, execute_extended, execute_normal, execute_pop_16, execute_push_16, Opcode,
         Prefix,
     },
+    registers::REGS_STATE_SIZE,
     RegName16, Regs, Z80Bus,
 };
 
@@ -26,6 +27,9 @@ impl From<IntMode> for u8 {
     }
 }
 
+/// Size of the serialized cpu state, see [Z80::save_state]
+pub const Z80_STATE_SIZE: usize = REGS_STATE_SIZE + 4;
+
 /// Z80 Processor struct
 pub struct Z80 {
     /// Contains Z80 registers data
@@ -37,6 +41,8 @@ pub struct Z80 {
     /// type of interrupt
     pub(crate) int_mode: IntMode,
     active_prefix: Prefix,
+    /// count of executed instructions
+    instructions: u64,
 }
 
 impl Default for Z80 {
@@ -47,6 +53,7 @@ impl Default for Z80 {
             skip_interrupt: false,
             int_mode: IntMode::Im0,
             active_prefix: Prefix::None,
+            instructions: 0,
         }
     }
 }
@@ -77,6 +84,33 @@ impl Z80 {
         self.halted
     }
 
+    /// Returns count of instructions executed since the cpu construction or
+    /// the last [Z80::reset_instruction_counter] call. Instructions executed
+    /// while cpu is halted are counted as NOPs, redundant index prefixes are
+    /// not counted
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Resets counter of executed instructions
+    pub fn reset_instruction_counter(&mut self) {
+        self.instructions = 0;
+    }
+
+    /// Emulates RESET signal: PC, I and R are cleared, interrupts are disabled
+    /// and IM 0 is selected. Other registers keep their values
+    pub fn reset(&mut self) {
+        self.regs.set_pc(0);
+        self.regs.set_i(0);
+        self.regs.set_r(0);
+        self.regs.set_iff1(false);
+        self.regs.set_iff2(false);
+        self.int_mode = IntMode::Im0;
+        self.halted = false;
+        self.skip_interrupt = false;
+        self.active_prefix = Prefix::None;
+    }
+
     /// Returns current interrupt mode
     pub fn get_im(&self) -> IntMode {
         self.int_mode
@@ -93,6 +127,42 @@ impl Z80 {
         }
     }
 
+    /// Returns complete cpu state: registers, including hidden MEMPTR and Q,
+    /// and internal state (halt, interrupt mode, pending prefix, interrupt
+    /// blocking after `EI`). Used for emulator save states
+    pub fn save_state(&self) -> [u8; Z80_STATE_SIZE] {
+        let mut state = [0u8; Z80_STATE_SIZE];
+        let (regs, internal) = state.split_at_mut(REGS_STATE_SIZE);
+        self.regs
+            .save_state(regs.try_into().expect("Slice has registers state size"));
+        internal.copy_from_slice(&[
+            self.halted as u8,
+            self.skip_interrupt as u8,
+            self.int_mode.into(),
+            self.active_prefix.to_byte().unwrap_or(0),
+        ]);
+        state
+    }
+
+    /// Restores cpu state, saved with [Z80::save_state]. Returns `false` and
+    /// leaves cpu intact if state is invalid
+    pub fn load_state(&mut self, state: &[u8; Z80_STATE_SIZE]) -> bool {
+        let (regs, internal) = state.split_at(REGS_STATE_SIZE);
+        let int_mode = match internal[2] {
+            0 => IntMode::Im0,
+            1 => IntMode::Im1,
+            2 => IntMode::Im2,
+            _ => return false,
+        };
+        self.regs
+            .load_state(regs.try_into().expect("Slice has registers state size"));
+        self.halted = internal[0] != 0;
+        self.skip_interrupt = internal[1] != 0;
+        self.int_mode = int_mode;
+        self.active_prefix = Prefix::from_byte(internal[3]);
+        true
+    }
+
     /// Pops program counter to the stack. Exposed as a public crate interface to support
     /// 48K SNA loading in `rustzx-core` and fast tape loaders (Perform RET)
     pub fn pop_pc_from_stack(&mut self, bus: &mut impl Z80Bus) {
@@ -107,6 +177,7 @@ impl Z80 {
 
     fn handle_interrupt(&mut self, bus: &mut impl Z80Bus) {
         if bus.nmi_active() {
+            bus.nmi_accepted();
             // q resets during interrupt
             self.regs.clear_q();
             // Release halt line on the bus
@@ -128,6 +199,7 @@ impl Z80 {
             self.regs.inc_r();
             // 5 + 3 + 3 = 11 clocks
         } else if bus.int_active() && self.regs.get_iff1() {
+            bus.interrupt_accepted();
             // q resets during interrupt
             self.regs.clear_q();
             // Release halt line on the bus
@@ -180,6 +252,7 @@ impl Z80 {
             // Save Q register value from previous emulation step, which is later used to
             // properly calculate flags in some instructions
             cpu.regs.step_q();
+            cpu.instructions += 1;
         };
 
         let byte1 = if self.active_prefix != Prefix::None {
@@ -0,0 +1,404 @@
+//! Z80 disassembler
+//!
+//! Opcode decoding follows the same x/y/z/p/q split as the execution code,
+//! see [document](http://www.z80.info/decoding.htm) by Cristian Dinu
+use crate::{
+    opcode::{instruction_length, uses_indirect_hl, Opcode, Prefix, MAX_INSTRUCTION_LENGTH},
+    smallnum::{U1, U2, U3},
+    Z80Bus,
+};
+use alloc::{format, string::String, vec::Vec};
+
+const REGS_8: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+const REGS_16_SP: [&str; 4] = ["BC", "DE", "HL", "SP"];
+const REGS_16_AF: [&str; 4] = ["BC", "DE", "HL", "AF"];
+const CONDITIONS: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+const ALU_OPS: [&str; 8] = [
+    "ADD A, ", "ADC A, ", "SUB ", "SBC A, ", "AND ", "XOR ", "OR ", "CP ",
+];
+const ROT_OPS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SRL"];
+const ACC_OPS: [&str; 8] = ["RLCA", "RRCA", "RLA", "RRA", "DAA", "CPL", "SCF", "CCF"];
+const INT_MODES: [u8; 8] = [0, 0, 1, 2, 0, 0, 1, 2];
+const ASSORTED_OPS: [&str; 8] = [
+    "LD I, A", "LD R, A", "LD A, I", "LD A, R", "RRD", "RLD", "NOP", "NOP",
+];
+const BLOCK_OPS: [[&str; 4]; 4] = [
+    ["LDI", "CPI", "INI", "OUTI"],
+    ["LDD", "CPD", "IND", "OUTD"],
+    ["LDIR", "CPIR", "INIR", "OTIR"],
+    ["LDDR", "CPDR", "INDR", "OTDR"],
+];
+
+/// Single disassembled instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// Address of the first instruction byte
+    pub address: u16,
+    /// Instruction bytes, only first `length` bytes are used
+    pub bytes: [u8; MAX_INSTRUCTION_LENGTH],
+    /// Length of the instruction in bytes
+    pub length: u8,
+    /// Instruction text, e.g. `LD A, (IX+0x05)`
+    pub mnemonic: String,
+}
+
+impl Instruction {
+    /// Decodes instruction encoded at the beginning of `bytes`, which are located
+    /// at `address`. Missing trailing bytes are treated as zeros.
+    pub fn decode(address: u16, bytes: &[u8]) -> Self {
+        let mut raw = [0u8; MAX_INSTRUCTION_LENGTH];
+        raw.iter_mut().zip(bytes).for_each(|(dst, src)| *dst = *src);
+        let length = instruction_length(&raw);
+        let mnemonic = Decoder {
+            bytes: raw,
+            next_addr: address.wrapping_add(length as u16),
+            index: None,
+            operands: 1,
+        }
+        .mnemonic();
+        raw[length as usize..].fill(0);
+
+        Self {
+            address,
+            bytes: raw,
+            length,
+            mnemonic,
+        }
+    }
+
+    /// Returns instruction bytes
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+}
+
+/// Disassembles `count` instructions starting from `addr`. Memory is read
+/// via [Z80Bus::read_internal], so result reflects current memory paging
+pub fn disassemble(bus: &mut impl Z80Bus, addr: u16, count: usize) -> Vec<Instruction> {
+    disassemble_with(addr, count, |addr| bus.read_internal(addr))
+}
+
+/// Disassembles `count` instructions starting from `addr`, reading memory
+/// with provided `read` function
+pub fn disassemble_with(
+    addr: u16,
+    count: usize,
+    mut read: impl FnMut(u16) -> u8,
+) -> Vec<Instruction> {
+    let mut addr = addr;
+    (0..count)
+        .map(|_| {
+            let mut bytes = [0u8; MAX_INSTRUCTION_LENGTH];
+            for (offset, byte) in bytes.iter_mut().enumerate() {
+                *byte = read(addr.wrapping_add(offset as u16));
+            }
+            let instruction = Instruction::decode(addr, &bytes);
+            addr = addr.wrapping_add(instruction.length as u16);
+            instruction
+        })
+        .collect()
+}
+
+struct Decoder {
+    bytes: [u8; MAX_INSTRUCTION_LENGTH],
+    /// Address of the next instruction, used for relative jumps
+    next_addr: u16,
+    /// Index register name for `DD`/`FD` prefixed instructions
+    index: Option<&'static str>,
+    /// Position of the first operand byte, including (IX+d) displacement
+    operands: usize,
+}
+
+impl Decoder {
+    fn mnemonic(mut self) -> String {
+        match Prefix::from_byte(self.bytes[0]) {
+            Prefix::None => self.normal(Opcode::from_byte(self.bytes[0])),
+            Prefix::CB => self.bits(Opcode::from_byte(self.bytes[1])),
+            Prefix::ED => {
+                self.operands = 2;
+                self.extended(Opcode::from_byte(self.bytes[1]))
+            }
+            prefix @ (Prefix::DD | Prefix::FD) => {
+                self.index = Some(if prefix == Prefix::DD { "IX" } else { "IY" });
+                match Prefix::from_byte(self.bytes[1]) {
+                    Prefix::None => {
+                        self.operands = 2;
+                        self.normal(Opcode::from_byte(self.bytes[1]))
+                    }
+                    // DD CB d op
+                    Prefix::CB => self.indexed_bits(Opcode::from_byte(self.bytes[3])),
+                    // Prefix is ignored by the CPU when followed by another prefix
+                    _ => format!("DB 0x{:02X}", self.bytes[0]),
+                }
+            }
+        }
+    }
+
+    fn n(&self) -> String {
+        format!("0x{:02X}", self.bytes[self.operands])
+    }
+
+    fn nn(&self) -> String {
+        let value = u16::from_le_bytes([self.bytes[self.operands], self.bytes[self.operands + 1]]);
+        format!("0x{:04X}", value)
+    }
+
+    /// Target address of the relative jump
+    fn relative(&self) -> String {
+        let offset = self.bytes[self.operands] as i8;
+        format!("0x{:04X}", self.next_addr.wrapping_add(offset as u16))
+    }
+
+    /// (IX+d) operand with displacement located at `pos`
+    fn indexed(&self, pos: usize) -> String {
+        let index = self.index.unwrap_or("HL");
+        let displacement = self.bytes[pos] as i8;
+        if displacement < 0 {
+            format!("({}-0x{:02X})", index, displacement.unsigned_abs())
+        } else {
+            format!("({}+0x{:02X})", index, displacement)
+        }
+    }
+
+    /// 8-bit register operand. When `halves` is set, H and L are replaced with
+    /// index register halves for `DD`/`FD` prefixed instructions
+    fn reg_8(&self, code: U3, halves: bool) -> String {
+        match (self.index, code) {
+            (Some(_), U3::N6) => self.indexed(self.operands - 1),
+            (Some(index), U3::N4) if halves => format!("{}H", index),
+            (Some(index), U3::N5) if halves => format!("{}L", index),
+            _ => REGS_8[code.as_byte() as usize].into(),
+        }
+    }
+
+    fn hl(&self) -> &'static str {
+        self.index.unwrap_or("HL")
+    }
+
+    fn reg_16_sp(&self, code: U2) -> &'static str {
+        match code {
+            U2::N2 => self.hl(),
+            _ => REGS_16_SP[code.as_byte() as usize],
+        }
+    }
+
+    fn reg_16_af(&self, code: U2) -> &'static str {
+        match code {
+            U2::N2 => self.hl(),
+            _ => REGS_16_AF[code.as_byte() as usize],
+        }
+    }
+
+    /// Non-prefixed instructions, also modified with `DD`/`FD` prefixes
+    fn normal(&mut self, opcode: Opcode) -> String {
+        if self.index.is_some() && uses_indirect_hl(opcode) {
+            // Skip displacement byte, (IX+d) operand is built from previous byte
+            self.operands += 1;
+        }
+        let y = opcode.y.as_byte() as usize;
+        match opcode.x {
+            U2::N0 => match opcode.z {
+                U3::N0 => match opcode.y {
+                    U3::N0 => "NOP".into(),
+                    U3::N1 => "EX AF, AF'".into(),
+                    U3::N2 => format!("DJNZ {}", self.relative()),
+                    U3::N3 => format!("JR {}", self.relative()),
+                    _ => format!("JR {}, {}", CONDITIONS[y - 4], self.relative()),
+                },
+                U3::N1 => match opcode.q {
+                    U1::N0 => format!("LD {}, {}", self.reg_16_sp(opcode.p), self.nn()),
+                    U1::N1 => format!("ADD {}, {}", self.hl(), self.reg_16_sp(opcode.p)),
+                },
+                U3::N2 => match (opcode.q, opcode.p) {
+                    (U1::N0, U2::N0) => "LD (BC), A".into(),
+                    (U1::N0, U2::N1) => "LD (DE), A".into(),
+                    (U1::N0, U2::N2) => format!("LD ({}), {}", self.nn(), self.hl()),
+                    (U1::N0, U2::N3) => format!("LD ({}), A", self.nn()),
+                    (U1::N1, U2::N0) => "LD A, (BC)".into(),
+                    (U1::N1, U2::N1) => "LD A, (DE)".into(),
+                    (U1::N1, U2::N2) => format!("LD {}, ({})", self.hl(), self.nn()),
+                    (U1::N1, U2::N3) => format!("LD A, ({})", self.nn()),
+                },
+                U3::N3 => match opcode.q {
+                    U1::N0 => format!("INC {}", self.reg_16_sp(opcode.p)),
+                    U1::N1 => format!("DEC {}", self.reg_16_sp(opcode.p)),
+                },
+                U3::N4 => format!("INC {}", self.reg_8(opcode.y, true)),
+                U3::N5 => format!("DEC {}", self.reg_8(opcode.y, true)),
+                U3::N6 => format!("LD {}, {}", self.reg_8(opcode.y, true), self.n()),
+                U3::N7 => ACC_OPS[y].into(),
+            },
+            U2::N1 if opcode.byte == 0x76 => "HALT".into(),
+            U2::N1 => {
+                // LD H, (IX+d) and LD (IX+d), L use plain H and L registers
+                let halves = opcode.y != U3::N6 && opcode.z != U3::N6;
+                format!(
+                    "LD {}, {}",
+                    self.reg_8(opcode.y, halves),
+                    self.reg_8(opcode.z, halves)
+                )
+            }
+            U2::N2 => format!("{}{}", ALU_OPS[y], self.reg_8(opcode.z, true)),
+            U2::N3 => match opcode.z {
+                U3::N0 => format!("RET {}", CONDITIONS[y]),
+                U3::N1 => match (opcode.q, opcode.p) {
+                    (U1::N0, _) => format!("POP {}", self.reg_16_af(opcode.p)),
+                    (U1::N1, U2::N0) => "RET".into(),
+                    (U1::N1, U2::N1) => "EXX".into(),
+                    (U1::N1, U2::N2) => format!("JP ({})", self.hl()),
+                    (U1::N1, U2::N3) => format!("LD SP, {}", self.hl()),
+                },
+                U3::N2 => format!("JP {}, {}", CONDITIONS[y], self.nn()),
+                U3::N3 => match opcode.y {
+                    U3::N0 => format!("JP {}", self.nn()),
+                    U3::N2 => format!("OUT ({}), A", self.n()),
+                    U3::N3 => format!("IN A, ({})", self.n()),
+                    U3::N4 => format!("EX (SP), {}", self.hl()),
+                    U3::N5 => "EX DE, HL".into(),
+                    U3::N6 => "DI".into(),
+                    U3::N7 => "EI".into(),
+                    // CB prefix is handled before
+                    U3::N1 => unreachable!(),
+                },
+                U3::N4 => format!("CALL {}, {}", CONDITIONS[y], self.nn()),
+                U3::N5 => match opcode.q {
+                    U1::N0 => format!("PUSH {}", self.reg_16_af(opcode.p)),
+                    // DD, ED, FD prefixes are handled before
+                    U1::N1 => format!("CALL {}", self.nn()),
+                },
+                U3::N6 => format!("{}{}", ALU_OPS[y], self.n()),
+                U3::N7 => format!("RST 0x{:02X}", y * 8),
+            },
+        }
+    }
+
+    /// `CB` prefixed instructions
+    fn bits(&self, opcode: Opcode) -> String {
+        let operand = REGS_8[opcode.z.as_byte() as usize];
+        let y = opcode.y.as_byte();
+        match opcode.x {
+            U2::N0 => format!("{} {}", ROT_OPS[y as usize], operand),
+            U2::N1 => format!("BIT {}, {}", y, operand),
+            U2::N2 => format!("RES {}, {}", y, operand),
+            U2::N3 => format!("SET {}, {}", y, operand),
+        }
+    }
+
+    /// `DDCB`/`FDCB` prefixed instructions. Undocumented forms with z != 6 also
+    /// store result to the register r[z], which is shown as the last operand
+    fn indexed_bits(&self, opcode: Opcode) -> String {
+        let operand = self.indexed(2);
+        let y = opcode.y.as_byte();
+        let text = match opcode.x {
+            U2::N0 => format!("{} {}", ROT_OPS[y as usize], operand),
+            U2::N1 => return format!("BIT {}, {}", y, operand),
+            U2::N2 => format!("RES {}, {}", y, operand),
+            U2::N3 => format!("SET {}, {}", y, operand),
+        };
+        match opcode.z {
+            U3::N6 => text,
+            z => format!("{}, {}", text, REGS_8[z.as_byte() as usize]),
+        }
+    }
+
+    /// `ED` prefixed instructions
+    fn extended(&self, opcode: Opcode) -> String {
+        let y = opcode.y.as_byte() as usize;
+        match opcode.x {
+            U2::N1 => match opcode.z {
+                U3::N0 if opcode.y == U3::N6 => "IN (C)".into(),
+                U3::N0 => format!("IN {}, (C)", REGS_8[y]),
+                U3::N1 if opcode.y == U3::N6 => "OUT (C), 0".into(),
+                U3::N1 => format!("OUT (C), {}", REGS_8[y]),
+                U3::N2 => match opcode.q {
+                    U1::N0 => format!("SBC HL, {}", self.reg_16_sp(opcode.p)),
+                    U1::N1 => format!("ADC HL, {}", self.reg_16_sp(opcode.p)),
+                },
+                U3::N3 => match opcode.q {
+                    U1::N0 => format!("LD ({}), {}", self.nn(), self.reg_16_sp(opcode.p)),
+                    U1::N1 => format!("LD {}, ({})", self.reg_16_sp(opcode.p), self.nn()),
+                },
+                U3::N4 => "NEG".into(),
+                U3::N5 if opcode.y == U3::N1 => "RETI".into(),
+                U3::N5 => "RETN".into(),
+                U3::N6 => format!("IM {}", INT_MODES[y]),
+                U3::N7 => ASSORTED_OPS[y].into(),
+            },
+            U2::N2 if y >= 4 && opcode.z.as_byte() < 4 => {
+                BLOCK_OPS[y - 4][opcode.z.as_byte() as usize].into()
+            }
+            // Invalid instructions are executed as NOP
+            _ => "NOP".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: &[u8]) -> String {
+        let instruction = Instruction::decode(0x8000, bytes);
+        assert_eq!(instruction.raw_bytes(), bytes);
+        instruction.mnemonic
+    }
+
+    #[test]
+    fn nonprefixed() {
+        assert_eq!(text(&[0x00]), "NOP");
+        assert_eq!(text(&[0x08]), "EX AF, AF'");
+        assert_eq!(text(&[0x10, 0xFE]), "DJNZ 0x8000");
+        assert_eq!(text(&[0x38, 0x10]), "JR C, 0x8012");
+        assert_eq!(text(&[0x21, 0x00, 0x40]), "LD HL, 0x4000");
+        assert_eq!(text(&[0x22, 0x34, 0x12]), "LD (0x1234), HL");
+        assert_eq!(text(&[0x3A, 0x34, 0x12]), "LD A, (0x1234)");
+        assert_eq!(text(&[0x36, 0x07]), "LD (HL), 0x07");
+        assert_eq!(text(&[0x7E]), "LD A, (HL)");
+        assert_eq!(text(&[0x76]), "HALT");
+        assert_eq!(text(&[0x96]), "SUB (HL)");
+        assert_eq!(text(&[0x8F]), "ADC A, A");
+        assert_eq!(text(&[0xC0]), "RET NZ");
+        assert_eq!(text(&[0xF1]), "POP AF");
+        assert_eq!(text(&[0xE9]), "JP (HL)");
+        assert_eq!(text(&[0xD3, 0xFE]), "OUT (0xFE), A");
+        assert_eq!(text(&[0xEB]), "EX DE, HL");
+        assert_eq!(text(&[0xFC, 0x00, 0x80]), "CALL M, 0x8000");
+        assert_eq!(text(&[0xCD, 0x00, 0x80]), "CALL 0x8000");
+        assert_eq!(text(&[0xFE, 0x10]), "CP 0x10");
+        assert_eq!(text(&[0xFF]), "RST 0x38");
+    }
+
+    #[test]
+    fn prefixed() {
+        assert_eq!(text(&[0xCB, 0x7E]), "BIT 7, (HL)");
+        assert_eq!(text(&[0xCB, 0x31]), "SLL C");
+        assert_eq!(text(&[0xED, 0xB0]), "LDIR");
+        assert_eq!(text(&[0xED, 0x43, 0x00, 0x40]), "LD (0x4000), BC");
+        assert_eq!(text(&[0xED, 0x70]), "IN (C)");
+        assert_eq!(text(&[0xED, 0x5E]), "IM 2");
+        assert_eq!(text(&[0xED, 0x00]), "NOP");
+        assert_eq!(text(&[0xDD, 0x21, 0x00, 0x40]), "LD IX, 0x4000");
+        assert_eq!(text(&[0xDD, 0x7E, 0x05]), "LD A, (IX+0x05)");
+        assert_eq!(text(&[0xFD, 0x36, 0xFB, 0xFF]), "LD (IY-0x05), 0xFF");
+        assert_eq!(text(&[0xDD, 0x66, 0x05]), "LD H, (IX+0x05)");
+        assert_eq!(text(&[0xDD, 0x65]), "LD IXH, IXL");
+        assert_eq!(text(&[0xFD, 0x84]), "ADD A, IYH");
+        assert_eq!(text(&[0xDD, 0x29]), "ADD IX, IX");
+        assert_eq!(text(&[0xDD, 0xE3]), "EX (SP), IX");
+        assert_eq!(text(&[0xDD, 0xEB]), "EX DE, HL");
+        assert_eq!(text(&[0xDD, 0x18, 0x00]), "JR 0x8003");
+        let prefix_chain = Instruction::decode(0x8000, &[0xDD, 0xFD, 0x21]);
+        assert_eq!(prefix_chain.raw_bytes(), &[0xDD]);
+        assert_eq!(prefix_chain.mnemonic, "DB 0xDD");
+    }
+
+    #[test]
+    fn indexed_bits() {
+        assert_eq!(text(&[0xDD, 0xCB, 0x05, 0x46]), "BIT 0, (IX+0x05)");
+        assert_eq!(text(&[0xDD, 0xCB, 0x05, 0x40]), "BIT 0, (IX+0x05)");
+        assert_eq!(text(&[0xFD, 0xCB, 0x80, 0x06]), "RLC (IY-0x80)");
+        // Undocumented forms, which also store result to register
+        assert_eq!(text(&[0xFD, 0xCB, 0x01, 0x00]), "RLC (IY+0x01), B");
+        assert_eq!(text(&[0xDD, 0xCB, 0x01, 0xFF]), "SET 7, (IX+0x01), A");
+    }
+}
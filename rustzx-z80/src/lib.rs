@@ -2,9 +2,12 @@
 
 //! Module which contains all CPU-specific structures, functions, constants
 
+extern crate alloc;
+
 mod bus;
 mod codegen;
 mod cpu;
+mod disasm;
 mod opcode;
 mod registers;
 mod smallnum;
@@ -12,8 +15,9 @@ mod tables;
 
 pub use bus::Z80Bus;
 pub use codegen::{CodeGenerator, CodegenMemorySpace};
-pub use cpu::{IntMode, Z80};
-pub use opcode::{Opcode, Prefix};
+pub use cpu::{IntMode, Z80, Z80_STATE_SIZE};
+pub use disasm::{disassemble, disassemble_with, Instruction};
+pub use opcode::{instruction_length, Opcode, Prefix, MAX_INSTRUCTION_LENGTH};
 pub use registers::{
     flag_pos, RegName16, RegName8, Regs, FLAG_CARRY, FLAG_F3, FLAG_F5, FLAG_HALF_CARRY, FLAG_PV,
     FLAG_SIGN, FLAG_SUB, FLAG_ZERO,
@@ -0,0 +1,126 @@
+use crate::{
+    opcode::{Opcode, Prefix},
+    smallnum::{U1, U2, U3},
+};
+
+/// Maximal length of the Z80 instruction in bytes
+pub const MAX_INSTRUCTION_LENGTH: usize = 4;
+
+/// Returns length in bytes of the instruction, encoded at the beginning of `bytes`.
+/// Missing trailing bytes are treated as zeros.
+///
+/// Prefix `DD`/`FD` followed by another prefix (`DD`, `ED`, `FD`) is treated as
+/// a separate 1-byte instruction, the same way as the CPU executes it.
+pub fn instruction_length(bytes: &[u8]) -> u8 {
+    let byte_at = |idx: usize| bytes.get(idx).copied().unwrap_or(0);
+
+    match Prefix::from_byte(byte_at(0)) {
+        Prefix::None => nonprefixed_length(Opcode::from_byte(byte_at(0))),
+        Prefix::CB => 2,
+        Prefix::ED => 1 + extended_length(Opcode::from_byte(byte_at(1))),
+        Prefix::DD | Prefix::FD => match Prefix::from_byte(byte_at(1)) {
+            Prefix::DD | Prefix::ED | Prefix::FD => 1,
+            // DD CB d op
+            Prefix::CB => 4,
+            Prefix::None => {
+                let opcode = Opcode::from_byte(byte_at(1));
+                let displacement = if uses_indirect_hl(opcode) { 1 } else { 0 };
+                1 + nonprefixed_length(opcode) + displacement
+            }
+        },
+    }
+}
+
+/// Length of the non-prefixed instruction including opcode byte
+fn nonprefixed_length(opcode: Opcode) -> u8 {
+    match opcode.x {
+        U2::N0 => match opcode.z {
+            // NOP, EX AF, AF'
+            U3::N0 if opcode.y.as_byte() < 2 => 1,
+            // DJNZ d, JR d, JR cc, d
+            U3::N0 => 2,
+            // LD rp, nn
+            U3::N1 if opcode.q == U1::N0 => 3,
+            // LD (nn), HL; LD HL, (nn); LD (nn), A; LD A, (nn)
+            U3::N2 if opcode.p.as_byte() >= 2 => 3,
+            // LD r, n
+            U3::N6 => 2,
+            _ => 1,
+        },
+        U2::N1 | U2::N2 => 1,
+        U2::N3 => match opcode.z {
+            // JP cc, nn; CALL cc, nn
+            U3::N2 | U3::N4 => 3,
+            U3::N3 => match opcode.y {
+                // JP nn
+                U3::N0 => 3,
+                // OUT (n), A; IN A, (n)
+                U3::N2 | U3::N3 => 2,
+                _ => 1,
+            },
+            // CALL nn
+            U3::N5 if opcode.byte == 0xCD => 3,
+            // ALU n
+            U3::N6 => 2,
+            _ => 1,
+        },
+    }
+}
+
+/// Length of the `ED`-prefixed instruction without prefix byte
+fn extended_length(opcode: Opcode) -> u8 {
+    match (opcode.x, opcode.z) {
+        // LD (nn), rp; LD rp, (nn)
+        (U2::N1, U3::N3) => 3,
+        _ => 1,
+    }
+}
+
+/// Returns true if `DD`/`FD` prefixed instruction uses (IX+d)/(IY+d) operand
+pub(crate) fn uses_indirect_hl(opcode: Opcode) -> bool {
+    match opcode.x {
+        // INC (HL), DEC (HL), LD (HL), n
+        U2::N0 => matches!(opcode.byte, 0x34..=0x36),
+        // LD r, (HL); LD (HL), r; excluding HALT
+        U2::N1 => (opcode.y == U3::N6 || opcode.z == U3::N6) && opcode.byte != 0x76,
+        // ALU (HL)
+        U2::N2 => opcode.z == U3::N6,
+        U2::N3 => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonprefixed() {
+        assert_eq!(instruction_length(&[0x00]), 1); // NOP
+        assert_eq!(instruction_length(&[0x10, 0xFE]), 2); // DJNZ d
+        assert_eq!(instruction_length(&[0x21, 0x00, 0x40]), 3); // LD HL, nn
+        assert_eq!(instruction_length(&[0x22, 0x00, 0x40]), 3); // LD (nn), HL
+        assert_eq!(instruction_length(&[0x0A]), 1); // LD A, (BC)
+        assert_eq!(instruction_length(&[0x3E, 0x07]), 2); // LD A, n
+        assert_eq!(instruction_length(&[0x76]), 1); // HALT
+        assert_eq!(instruction_length(&[0xC3, 0x00, 0x00]), 3); // JP nn
+        assert_eq!(instruction_length(&[0xD3, 0xFE]), 2); // OUT (n), A
+        assert_eq!(instruction_length(&[0xCD, 0x00, 0x00]), 3); // CALL nn
+        assert_eq!(instruction_length(&[0xC5]), 1); // PUSH BC
+        assert_eq!(instruction_length(&[0xFE, 0x10]), 2); // CP n
+    }
+
+    #[test]
+    fn prefixed() {
+        assert_eq!(instruction_length(&[0xCB, 0x7E]), 2); // BIT 7, (HL)
+        assert_eq!(instruction_length(&[0xED, 0xB0]), 2); // LDIR
+        assert_eq!(instruction_length(&[0xED, 0x43, 0x00, 0x40]), 4); // LD (nn), BC
+        assert_eq!(instruction_length(&[0xDD, 0x21, 0x00, 0x40]), 4); // LD IX, nn
+        assert_eq!(instruction_length(&[0xDD, 0x7E, 0x05]), 3); // LD A, (IX+d)
+        assert_eq!(instruction_length(&[0xFD, 0x36, 0x05, 0xFF]), 4); // LD (IY+d), n
+        assert_eq!(instruction_length(&[0xDD, 0x66, 0x05]), 3); // LD H, (IX+d)
+        assert_eq!(instruction_length(&[0xDD, 0x76]), 2); // HALT
+        assert_eq!(instruction_length(&[0xDD, 0xE9]), 2); // JP (IX)
+        assert_eq!(instruction_length(&[0xDD, 0xCB, 0x05, 0x46]), 4); // BIT 0, (IX+d)
+        assert_eq!(instruction_length(&[0xDD, 0xFD, 0x21]), 1); // Prefix chain
+    }
+}
@@ -5,9 +5,10 @@ mod internal_alu;
 mod internal_block;
 mod internal_rot;
 mod internal_stack;
+mod length;
 mod types;
 
 pub use self::{
     group_bits::*, group_extended::*, group_nonprefixed::*, internal_alu::*, internal_block::*,
-    internal_rot::*, internal_stack::*, types::*,
+    internal_rot::*, internal_stack::*, length::*, types::*,
 };
@@ -529,11 +529,13 @@ impl Regs {
         core::mem::swap(&mut self.l, &mut self.l_alt);
     }
 
-    /// Sets F3 and F5 flags to 11th and and 15th bit of PC respectively. This obscurity is used by
+    /// Sets F3 and F5 flags to 11th and 13th bit of PC respectively. This obscurity is used by
     /// block memory operations when performing repeated execution cycle
     pub fn update_flags_block_mem_cycle(&mut self) {
         self.f &= !(FLAG_F3 | FLAG_F5);
         self.f |= (self.pc >> 8) as u8 & (FLAG_F3 | FLAG_F5);
+        // Instruction changes F, therefore update Q
+        self.q = self.f;
     }
 
     /// Obscure logic for changing flags after block io opcode iteration,
@@ -596,8 +598,59 @@ impl Regs {
         // Instruction changes F, therefore update Q
         self.q = self.f;
     }
+
+    /// Writes all registers, including hidden MEMPTR and Q, to `out`
+    pub(crate) fn save_state(&self, out: &mut [u8; REGS_STATE_SIZE]) {
+        let (words, bytes) = out.split_at_mut(6);
+        words[0..2].copy_from_slice(&self.pc.to_le_bytes());
+        words[2..4].copy_from_slice(&self.sp.to_le_bytes());
+        words[4..6].copy_from_slice(&self.mem_ptr.to_le_bytes());
+        for (dst, src) in bytes.iter_mut().zip(self.state_bytes()) {
+            *dst = *src;
+        }
+        bytes[24] = self.iff1 as u8;
+        bytes[25] = self.iff2 as u8;
+    }
+
+    /// Restores registers, saved with [Regs::save_state]
+    pub(crate) fn load_state(&mut self, state: &[u8; REGS_STATE_SIZE]) {
+        let (words, bytes) = state.split_at(6);
+        self.pc = u16::from_le_bytes([words[0], words[1]]);
+        self.sp = u16::from_le_bytes([words[2], words[3]]);
+        self.mem_ptr = u16::from_le_bytes([words[4], words[5]]);
+        for (dst, src) in self.state_bytes_mut().into_iter().zip(bytes) {
+            *dst = *src;
+        }
+        self.iff1 = bytes[24] != 0;
+        self.iff2 = bytes[25] != 0;
+    }
+
+    #[rustfmt::skip]
+    fn state_bytes(&self) -> [&u8; 24] {
+        [
+            &self.q, &self.last_q, &self.ixh, &self.ixl, &self.iyh, &self.iyl, &self.r, &self.i,
+            &self.a, &self.f, &self.b, &self.c, &self.d, &self.e, &self.h, &self.l,
+            &self.a_alt, &self.f_alt, &self.b_alt, &self.c_alt,
+            &self.d_alt, &self.e_alt, &self.h_alt, &self.l_alt,
+        ]
+    }
+
+    #[rustfmt::skip]
+    fn state_bytes_mut(&mut self) -> [&mut u8; 24] {
+        [
+            &mut self.q, &mut self.last_q, &mut self.ixh, &mut self.ixl,
+            &mut self.iyh, &mut self.iyl, &mut self.r, &mut self.i,
+            &mut self.a, &mut self.f, &mut self.b, &mut self.c,
+            &mut self.d, &mut self.e, &mut self.h, &mut self.l,
+            &mut self.a_alt, &mut self.f_alt, &mut self.b_alt, &mut self.c_alt,
+            &mut self.d_alt, &mut self.e_alt, &mut self.h_alt, &mut self.l_alt,
+        ]
+    }
 }
 
+/// Size of the registers state, see [Regs::save_state]
+pub(crate) const REGS_STATE_SIZE: usize = 32;
+
 pub enum BlockIoOpcode {
     Inir,
     Indr,
@@ -0,0 +1,180 @@
+//! Flags of the ED-prefixed block instructions, including undocumented F3/F5
+//! and the flags of the interrupted (repeating) iterations. Expected values
+//! are derived from "The Undocumented Z80 Documented" and the block
+//! instruction flags research by MrKWatkins and David Banks
+use crate::TestingBus;
+use rustzx_z80::{RegName8, Z80};
+
+const MEMORY_SIZE: usize = 64 * 1024;
+const DEFAULT_ADDRESS: u16 = 0x0100;
+
+/// Registers and data for the single block instruction iteration
+struct BlockOp {
+    address: u16,
+    opcode: u8,
+    a: u8,
+    f: u8,
+    bc: u16,
+    hl: u16,
+    // value at (HL) for memory reads and on the data bus for IO reads
+    data: u8,
+}
+
+impl Default for BlockOp {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS,
+            opcode: 0x00,
+            a: 0x00,
+            f: 0x00,
+            bc: 0x0001,
+            hl: 0x4000,
+            data: 0x00,
+        }
+    }
+}
+
+impl BlockOp {
+    /// Executes single iteration and returns cpu state after it
+    fn execute(self) -> Z80 {
+        let mut cpu = Z80::default();
+        cpu.regs.set_pc(self.address);
+        cpu.regs.set_acc(self.a);
+        cpu.regs.set_flags(self.f);
+        cpu.regs.set_bc(self.bc);
+        cpu.regs.set_hl(self.hl);
+        cpu.regs.set_de(0x8000);
+
+        let mut bus = TestingBus::new(MEMORY_SIZE);
+        bus.load_to_memory(&[0xED, self.opcode], self.address);
+        bus.patch_memory(self.hl, self.data);
+        bus.set_io_value(self.data);
+        cpu.emulate(&mut bus);
+        cpu
+    }
+
+    fn flags(self) -> u8 {
+        self.execute().regs.get_flags()
+    }
+}
+
+#[test]
+fn ldi_ldd_flags() {
+    // LDI: F3 is bit 3 and F5 is bit 1 of A + (HL), PV is set while BC != 0
+    #[rustfmt::skip]
+    let ldi = BlockOp { opcode: 0xA0, a: 0x10, bc: 2, data: 0x0A, ..Default::default() };
+    assert_eq!(ldi.flags(), 0x2C);
+    // S, Z and C are preserved, H and N are reset
+    #[rustfmt::skip]
+    let ldi = BlockOp { opcode: 0xA0, a: 0x01, f: 0xFF, data: 0x01, ..Default::default() };
+    assert_eq!(ldi.flags(), 0xE1);
+    #[rustfmt::skip]
+    let ldi = BlockOp { opcode: 0xA0, data: 0x08, ..Default::default() };
+    assert_eq!(ldi.flags(), 0x08);
+    // LDD
+    #[rustfmt::skip]
+    let ldd = BlockOp { opcode: 0xA8, a: 0x20, bc: 3, data: 0x02, ..Default::default() };
+    let cpu = ldd.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x24);
+    assert_eq!((cpu.regs.get_hl(), cpu.regs.get_de()), (0x3FFF, 0x7FFF));
+}
+
+#[test]
+fn ldir_lddr_flags() {
+    // Repeating iteration takes F3 and F5 from bits 11 and 13 of PC
+    #[rustfmt::skip]
+    let ldir = BlockOp { address: 0x2800, opcode: 0xB0, bc: 2, ..Default::default() };
+    let cpu = ldir.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x2C);
+    assert_eq!(cpu.regs.get_pc(), 0x2800);
+    // Last iteration is the same as LDI
+    #[rustfmt::skip]
+    let ldir = BlockOp { address: 0x2800, opcode: 0xB0, ..Default::default() };
+    let cpu = ldir.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x00);
+    assert_eq!(cpu.regs.get_pc(), 0x2802);
+    // F3 from A + (HL) is replaced with PC bits
+    #[rustfmt::skip]
+    let lddr = BlockOp { opcode: 0xB8, a: 0x08, bc: 2, ..Default::default() };
+    assert_eq!(lddr.flags(), 0x04);
+}
+
+#[test]
+fn cpi_cpd_flags() {
+    // CPI: F3 is bit 3 and F5 is bit 1 of A - (HL) - H
+    #[rustfmt::skip]
+    let cpi = BlockOp { opcode: 0xA1, a: 0x10, bc: 2, data: 0x01, ..Default::default() };
+    assert_eq!(cpi.flags(), 0x3E);
+    // CPD: match found, carry is preserved
+    #[rustfmt::skip]
+    let cpd = BlockOp { opcode: 0xA9, a: 0x42, f: 0x01, data: 0x42, ..Default::default() };
+    assert_eq!(cpd.flags(), 0x43);
+}
+
+#[test]
+fn cpir_cpdr_flags() {
+    // Repeating iteration, F3 and F5 of A - (HL) - H are replaced with PC bits
+    #[rustfmt::skip]
+    let cpir = BlockOp { opcode: 0xB1, bc: 2, data: 0x01, ..Default::default() };
+    let cpu = cpir.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x96);
+    assert_eq!(cpu.regs.get_pc(), DEFAULT_ADDRESS);
+    // Match found, instruction is not repeated
+    #[rustfmt::skip]
+    let cpdr = BlockOp { opcode: 0xB9, a: 0x05, bc: 3, data: 0x05, ..Default::default() };
+    let cpu = cpdr.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x46);
+    assert_eq!(cpu.regs.get_pc(), DEFAULT_ADDRESS + 2);
+}
+
+#[test]
+fn ini_ind_outi_outd_flags() {
+    // INI: S, Z, F3 and F5 from decremented B, N is bit 7 of the value,
+    // H and C are set when value + (C + 1) overflows, PV is parity of
+    // ((value + (C + 1)) & 7) ^ B
+    #[rustfmt::skip]
+    let ini = BlockOp { opcode: 0xA2, bc: 0x01FE, data: 0x80, ..Default::default() };
+    assert_eq!(ini.flags(), 0x53);
+    // IND uses C - 1
+    #[rustfmt::skip]
+    let ind = BlockOp { opcode: 0xAA, bc: 0x2910, data: 0x05, ..Default::default() };
+    assert_eq!(ind.flags(), 0x28);
+    #[rustfmt::skip]
+    let ind = BlockOp { opcode: 0xAA, bc: 0x2910, data: 0x06, ..Default::default() };
+    assert_eq!(ind.flags(), 0x2C);
+    // OUTI uses L after HL increment instead of C
+    #[rustfmt::skip]
+    let outi = BlockOp { opcode: 0xA3, bc: 0x8100, hl: 0x40FF, data: 0x01, ..Default::default() };
+    assert_eq!(outi.flags(), 0x84);
+    #[rustfmt::skip]
+    let outd = BlockOp { opcode: 0xAB, bc: 0x0200, hl: 0x4010, data: 0xF8, ..Default::default() };
+    assert_eq!(outd.flags(), 0x17);
+}
+
+#[test]
+fn inir_indr_otir_otdr_flags() {
+    // N and C set: H is set when low nibble of B wraps down
+    #[rustfmt::skip]
+    let inir = BlockOp { opcode: 0xB2, bc: 0x02FE, data: 0x80, ..Default::default() };
+    let cpu = inir.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x07);
+    assert_eq!(cpu.regs.get_reg_8(RegName8::B), 0x01);
+    assert_eq!(cpu.regs.get_pc(), DEFAULT_ADDRESS);
+    #[rustfmt::skip]
+    let inir = BlockOp { address: 0x2800, opcode: 0xB2, bc: 0x11FE, data: 0x80, ..Default::default() };
+    assert_eq!(inir.flags(), 0x3B);
+    // C reset: H is reset, PV is parity of ((value + L) & 7) ^ B ^ (B & 7)
+    #[rustfmt::skip]
+    let otir = BlockOp { opcode: 0xB3, bc: 0x0300, data: 0x10, ..Default::default() };
+    assert_eq!(otir.flags(), 0x00);
+    // N reset and C set: H is set when low nibble of B wraps up
+    #[rustfmt::skip]
+    let otdr = BlockOp { opcode: 0xBB, bc: 0x1000, hl: 0x40F0, data: 0x20, ..Default::default() };
+    assert_eq!(otdr.flags(), 0x11);
+    // INDR last iteration is the same as IND
+    #[rustfmt::skip]
+    let indr = BlockOp { opcode: 0xBA, bc: 0x0110, data: 0x05, ..Default::default() };
+    let cpu = indr.execute();
+    assert_eq!(cpu.regs.get_flags(), 0x40);
+    assert_eq!(cpu.regs.get_pc(), DEFAULT_ADDRESS + 2);
+}
@@ -0,0 +1,71 @@
+use crate::TestingBus;
+use rustzx_z80::Z80;
+
+const MEMORY_SIZE: usize = 64 * 1024;
+const PROGRAM_BASE_ADDRESS: u16 = 0x0100;
+const STACK_ADDRESS: u16 = 0x8000;
+const IM1_HANDLER_ADDRESS: u16 = 0x0038;
+
+const EI_OPCODE: u8 = 0xFB;
+const HALT_OPCODE: u8 = 0x76;
+const NOP_OPCODE: u8 = 0x00;
+
+/// Creates CPU in IM1 with disabled interrupts and active INT line
+fn make_tester(program: &[u8]) -> (Z80, TestingBus) {
+    let mut cpu = Z80::default();
+    cpu.set_im(1);
+    cpu.regs.set_pc(PROGRAM_BASE_ADDRESS);
+    cpu.regs.set_sp(STACK_ADDRESS);
+
+    let mut bus = TestingBus::new(MEMORY_SIZE);
+    bus.load_to_memory(program, PROGRAM_BASE_ADDRESS);
+    bus.set_int(true);
+
+    (cpu, bus)
+}
+
+fn interrupt_return_address(bus: &mut TestingBus) -> u16 {
+    u16::from_le_bytes([
+        bus.read_memory(STACK_ADDRESS - 2),
+        bus.read_memory(STACK_ADDRESS - 1),
+    ])
+}
+
+#[test]
+fn interrupt_deferred_after_ei() {
+    let (mut cpu, mut bus) = make_tester(&[EI_OPCODE, NOP_OPCODE, NOP_OPCODE]);
+
+    // EI
+    cpu.emulate(&mut bus);
+    assert!(cpu.regs.get_iff1());
+    assert_eq!(cpu.regs.get_pc(), PROGRAM_BASE_ADDRESS + 1);
+
+    // Interrupt is still blocked, NOP after EI is executed
+    cpu.emulate(&mut bus);
+    assert!(cpu.regs.get_iff1());
+    assert_eq!(cpu.regs.get_pc(), PROGRAM_BASE_ADDRESS + 2);
+
+    // Interrupt is accepted before the second NOP, handler's NOP is executed
+    cpu.emulate(&mut bus);
+    assert!(!cpu.regs.get_iff1());
+    assert_eq!(cpu.regs.get_pc(), IM1_HANDLER_ADDRESS + 1);
+    assert_eq!(cpu.regs.get_sp(), STACK_ADDRESS - 2);
+    assert_eq!(interrupt_return_address(&mut bus), PROGRAM_BASE_ADDRESS + 2);
+}
+
+#[test]
+fn interrupt_after_ei_halt() {
+    let (mut cpu, mut bus) = make_tester(&[EI_OPCODE, HALT_OPCODE]);
+
+    // EI; HALT
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert!(cpu.is_halted());
+    assert!(cpu.regs.get_iff1());
+
+    // Interrupt releases HALT and returns to the instruction after it
+    cpu.emulate(&mut bus);
+    assert!(!cpu.is_halted());
+    assert_eq!(cpu.regs.get_pc(), IM1_HANDLER_ADDRESS + 1);
+    assert_eq!(interrupt_return_address(&mut bus), PROGRAM_BASE_ADDRESS + 2);
+}
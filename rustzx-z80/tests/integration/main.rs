@@ -1,3 +1,7 @@
+mod block_flags;
+mod interrupts;
+mod memptr;
+mod refresh;
 mod zexall;
 
 use rustzx_z80::Z80Bus;
@@ -7,6 +11,8 @@ pub struct TestingBus {
     memory: Vec<u8>,
     breakpoints: HashSet<u16>,
     last_breakpoint: Option<u16>,
+    int_active: bool,
+    io_value: u8,
 }
 
 impl TestingBus {
@@ -15,6 +21,8 @@ impl TestingBus {
             memory: vec![0; memory_size as usize],
             breakpoints: Default::default(),
             last_breakpoint: None,
+            int_active: false,
+            io_value: 0,
         }
     }
 
@@ -39,6 +47,15 @@ impl TestingBus {
     pub fn last_breakpoint(&mut self) -> Option<u16> {
         self.last_breakpoint.take()
     }
+
+    pub fn set_int(&mut self, active: bool) {
+        self.int_active = active;
+    }
+
+    /// Sets value returned by all IO port reads
+    pub fn set_io_value(&mut self, value: u8) {
+        self.io_value = value;
+    }
 }
 
 impl Z80Bus for TestingBus {
@@ -57,7 +74,7 @@ impl Z80Bus for TestingBus {
     }
 
     fn read_io(&mut self, _port: u16) -> u8 {
-        0
+        self.io_value
     }
 
     fn write_io(&mut self, _port: u16, _data: u8) {}
@@ -77,7 +94,7 @@ impl Z80Bus for TestingBus {
     fn halt(&mut self, _halted: bool) {}
 
     fn int_active(&self) -> bool {
-        false
+        self.int_active
     }
 
     fn nmi_active(&self) -> bool {
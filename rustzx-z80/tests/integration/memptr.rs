@@ -0,0 +1,117 @@
+use crate::TestingBus;
+use rustzx_z80::{FLAG_F3, FLAG_F5, Z80};
+
+const MEMORY_SIZE: usize = 64 * 1024;
+const PROGRAM_BASE_ADDRESS: u16 = 0x0100;
+const STACK_ADDRESS: u16 = 0x8000;
+
+fn make_tester(program: &[u8]) -> (Z80, TestingBus) {
+    let mut cpu = Z80::default();
+    cpu.regs.set_pc(PROGRAM_BASE_ADDRESS);
+    cpu.regs.set_sp(STACK_ADDRESS);
+
+    let mut bus = TestingBus::new(MEMORY_SIZE);
+    bus.load_to_memory(program, PROGRAM_BASE_ADDRESS);
+
+    (cpu, bus)
+}
+
+/// Executes `steps` emulation steps and returns resulting MEMPTR value
+fn memptr_after(program: &[u8], steps: usize) -> u16 {
+    let (mut cpu, mut bus) = make_tester(program);
+    for _ in 0..steps {
+        cpu.emulate(&mut bus);
+    }
+    cpu.regs.get_mem_ptr()
+}
+
+#[test]
+fn memptr_loads_and_stores() {
+    // LD A, (0x1234)
+    assert_eq!(memptr_after(&[0x3A, 0x34, 0x12], 1), 0x1235);
+    // LD A, 0xAB; LD (0x12FF), A ; low byte wraps, high byte is A
+    assert_eq!(memptr_after(&[0x3E, 0xAB, 0x32, 0xFF, 0x12], 2), 0xAB00);
+    // LD BC, 0x4000; LD A, (BC)
+    assert_eq!(memptr_after(&[0x01, 0x00, 0x40, 0x0A], 2), 0x4001);
+    // LD HL, (0x2000)
+    assert_eq!(memptr_after(&[0x2A, 0x00, 0x20], 1), 0x2001);
+    // LD (0x2000), BC
+    assert_eq!(memptr_after(&[0xED, 0x43, 0x00, 0x20], 1), 0x2001);
+    // LD HL, 0x1000; ADD HL, HL ; HL + 1 before addition
+    assert_eq!(memptr_after(&[0x21, 0x00, 0x10, 0x29], 2), 0x1001);
+    // LD HL, 0x3000; RLD
+    assert_eq!(memptr_after(&[0x21, 0x00, 0x30, 0xED, 0x6F], 2), 0x3001);
+    // LD IX, 0x1000; LD A, (IX-2)
+    assert_eq!(
+        memptr_after(&[0xDD, 0x21, 0x00, 0x10, 0xDD, 0x7E, 0xFE], 2),
+        0x0FFE
+    );
+}
+
+#[test]
+fn memptr_jumps() {
+    // JP 0x1234
+    assert_eq!(memptr_after(&[0xC3, 0x34, 0x12], 1), 0x1234);
+    // JP NZ, 0x1234 ; not taken after XOR A, still set to the address
+    assert_eq!(memptr_after(&[0xAF, 0xC2, 0x34, 0x12], 2), 0x1234);
+    // CALL 0x1234
+    assert_eq!(memptr_after(&[0xCD, 0x34, 0x12], 1), 0x1234);
+    // CALL 0x0200; ...; 0x0200: RET
+    let mut program = vec![0u8; 0x101];
+    program[..3].copy_from_slice(&[0xCD, 0x00, 0x02]);
+    program[0x100] = 0xC9;
+    assert_eq!(memptr_after(&program, 2), 0x0103);
+    // JR 0x10
+    assert_eq!(memptr_after(&[0x18, 0x10], 1), 0x0112);
+}
+
+#[test]
+fn memptr_io() {
+    // LD A, 0x12; IN A, (0x34)
+    assert_eq!(memptr_after(&[0x3E, 0x12, 0xDB, 0x34], 2), 0x1235);
+    // LD A, 0x12; OUT (0xFF), A
+    assert_eq!(memptr_after(&[0x3E, 0x12, 0xD3, 0xFF], 2), 0x1200);
+    // LD BC, 0x7FFE; IN A, (C)
+    assert_eq!(memptr_after(&[0x01, 0xFE, 0x7F, 0xED, 0x78], 2), 0x7FFF);
+}
+
+#[test]
+fn memptr_block_instructions() {
+    // LD BC, 2; LDIR ; repetition sets MEMPTR to PC + 1
+    let program = [0x01, 0x02, 0x00, 0xED, 0xB0];
+    assert_eq!(memptr_after(&program, 2), 0x0104);
+    // LDI does not change MEMPTR
+    assert_eq!(memptr_after(&[0x01, 0x02, 0x00, 0xED, 0xA0], 2), 0x0000);
+    // LD HL, 0x2000; CPI ; MEMPTR + 1
+    assert_eq!(memptr_after(&[0x21, 0x00, 0x20, 0xED, 0xA1], 2), 0x0001);
+    // LD HL, 0x2000; CPD ; MEMPTR - 1
+    assert_eq!(memptr_after(&[0x21, 0x00, 0x20, 0xED, 0xA9], 2), 0xFFFF);
+}
+
+#[test]
+fn memptr_bit_hl_flags() {
+    #[rustfmt::skip]
+    let program = [
+        0x3A, 0x00, 0x28,       // LD A, (0x2800) ; MEMPTR = 0x2801
+        0x21, 0x00, 0x40,       // LD HL, 0x4000
+        0xCB, 0x46,             // BIT 0, (HL)
+        0x3A, 0x00, 0x20,       // LD A, (0x2000) ; MEMPTR = 0x2001
+        0xCB, 0x46,             // BIT 0, (HL)
+        0xDD, 0x21, 0x00, 0x08, // LD IX, 0x0800
+        0xDD, 0xCB, 0x00, 0x46, // BIT 0, (IX+0) ; MEMPTR = IX + d
+    ];
+    let (mut cpu, mut bus) = make_tester(&program);
+    let f3f5 = |cpu: &Z80| cpu.regs.get_flags() & (FLAG_F3 | FLAG_F5);
+
+    for _ in 0..3 {
+        cpu.emulate(&mut bus);
+    }
+    // F5 and F3 are copied from MEMPTR bits 13 and 11
+    assert_eq!(f3f5(&cpu), FLAG_F5 | FLAG_F3);
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert_eq!(f3f5(&cpu), FLAG_F5);
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert_eq!(f3f5(&cpu), FLAG_F3);
+}
@@ -0,0 +1,101 @@
+use crate::TestingBus;
+use rustzx_z80::Z80;
+
+const MEMORY_SIZE: usize = 64 * 1024;
+const PROGRAM_BASE_ADDRESS: u16 = 0x0100;
+const STACK_ADDRESS: u16 = 0x8000;
+
+fn make_tester(program: &[u8]) -> (Z80, TestingBus) {
+    let mut cpu = Z80::default();
+    cpu.set_im(1);
+    cpu.regs.set_pc(PROGRAM_BASE_ADDRESS);
+    cpu.regs.set_sp(STACK_ADDRESS);
+    cpu.regs.set_r(0);
+
+    let mut bus = TestingBus::new(MEMORY_SIZE);
+    bus.load_to_memory(program, PROGRAM_BASE_ADDRESS);
+
+    (cpu, bus)
+}
+
+/// Executes `steps` emulation steps and returns resulting R register value
+fn r_after(program: &[u8], steps: usize) -> u8 {
+    let (mut cpu, mut bus) = make_tester(program);
+    for _ in 0..steps {
+        cpu.emulate(&mut bus);
+    }
+    cpu.regs.get_r()
+}
+
+#[test]
+fn refresh_per_opcode_fetch() {
+    // NOP
+    assert_eq!(r_after(&[0x00], 1), 1);
+    // LD BC, 0x1234 ; operand reads are not M1 cycles
+    assert_eq!(r_after(&[0x01, 0x34, 0x12], 1), 1);
+    // RLC B
+    assert_eq!(r_after(&[0xCB, 0x00], 1), 2);
+    // NEG
+    assert_eq!(r_after(&[0xED, 0x44], 1), 2);
+    // LD IX, 0x1234
+    assert_eq!(r_after(&[0xDD, 0x21, 0x34, 0x12], 1), 2);
+    // LD A, (IY+1)
+    assert_eq!(r_after(&[0xFD, 0x7E, 0x01], 1), 2);
+    // RLC (IX+1) ; displacement and opcode after DDCB are not M1 cycles
+    assert_eq!(r_after(&[0xDD, 0xCB, 0x01, 0x06], 1), 2);
+    // DD DD NOP ; each redundant prefix is a separate M1 cycle
+    assert_eq!(r_after(&[0xDD, 0xDD, 0x00], 2), 3);
+    // DD ED NEG ; ED cancels index prefix
+    assert_eq!(r_after(&[0xDD, 0xED, 0x44], 2), 3);
+}
+
+#[test]
+fn refresh_block_and_halt() {
+    // LD BC, 3; LDIR ; every repetition refetches both opcode bytes
+    let program = [0x01, 0x03, 0x00, 0xED, 0xB0];
+    assert_eq!(r_after(&program, 4), 1 + 3 * 2);
+
+    // HALT ; halted cpu keeps executing NOPs
+    assert_eq!(r_after(&[0x76], 5), 5);
+}
+
+#[test]
+fn refresh_bit7_preserved() {
+    #[rustfmt::skip]
+    let program = [
+        0x3E, 0xFE,             // LD A, 0xFE
+        0xED, 0x4F,             // LD R, A
+        0x00,                   // NOP
+        0x00,                   // NOP
+        0xED, 0x5F,             // LD A, R
+    ];
+    let (mut cpu, mut bus) = make_tester(&program);
+
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    // Value is written after opcode fetches
+    assert_eq!(cpu.regs.get_r(), 0xFE);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_r(), 0xFF);
+    // Only lower 7 bits are incremented
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_r(), 0x80);
+    // LD A, R observes both of its own fetches
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_acc(), 0x82);
+}
+
+#[test]
+fn refresh_on_interrupt() {
+    // EI; NOP
+    let (mut cpu, mut bus) = make_tester(&[0xFB, 0x00, 0x00]);
+    cpu.emulate(&mut bus);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_r(), 2);
+
+    // Interrupt acknowledge cycle increments R, then handler NOP is fetched
+    bus.set_int(true);
+    cpu.emulate(&mut bus);
+    assert_eq!(cpu.regs.get_pc(), 0x0039);
+    assert_eq!(cpu.regs.get_r(), 4);
+}
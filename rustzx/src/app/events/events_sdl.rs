@@ -4,16 +4,18 @@ use crate::{app::settings::Settings, backends::SDL_CONTEXT};
 use rustzx_core::{
     zx::{
         joy::{
-            kempston::KempstonKey,
+            cursor::CursorKey,
+            fuller::FullerKey,
+            kempston::{KempstonJoyNum, KempstonKey},
             sinclair::{SinclairJoyNum, SinclairKey},
         },
         keys::{CompoundKey, ZXKey},
         mouse::kempston::{KempstonMouseButton, KempstonMouseWheelDirection},
     },
-    EmulationMode,
+    BorderSize, EmulationMode,
 };
 use sdl2::{
-    event::Event as SdlEvent,
+    event::{Event as SdlEvent, WindowEvent},
     keyboard::Scancode,
     mouse::{MouseButton, MouseUtil},
     EventPump,
@@ -24,12 +26,21 @@ pub struct EventsSdl {
     event_pump: EventPump,
     mouse: MouseUtil,
     kempston_enabled: bool,
+    second_kempston_enabled: bool,
+    cursor_joy_enabled: bool,
+    fuller_enabled: bool,
     mouse_enabled: bool,
     mouse_locked: bool,
     mouse_sensitivity: usize,
     enable_joy_keyaboard_layer: bool,
     mouse_x_counter: i32,
     mouse_y_counter: i32,
+    lightgun_enabled: bool,
+    scale: usize,
+    border_size: BorderSize,
+    lightgun_x: u16,
+    lightgun_y: u16,
+    lightgun_trigger: bool,
 }
 
 impl EventsSdl {
@@ -54,10 +65,37 @@ impl EventsSdl {
             mouse_enabled: settings.enable_mouse,
             mouse_locked: false,
             kempston_enabled: !settings.disable_kempston,
+            second_kempston_enabled: settings.enable_second_kempston,
+            cursor_joy_enabled: settings.enable_cursor_joy,
+            fuller_enabled: settings.enable_fuller,
             enable_joy_keyaboard_layer: false,
             mouse_sensitivity: settings.mouse_sensitivity,
             mouse_x_counter: 0,
             mouse_y_counter: 0,
+            lightgun_enabled: settings.enable_lightgun,
+            scale: settings.scale,
+            border_size: settings.border_size,
+            lightgun_x: u16::MAX,
+            lightgun_y: u16::MAX,
+            lightgun_trigger: false,
+        }
+    }
+
+    /// Converts window coordinates to the canvas coordinates. Points outside of the
+    /// canvas are mapped out of the range, so lightgun never detects light there
+    fn set_lightgun_pos(&mut self, x: i32, y: i32) {
+        let to_canvas = |pos: i32, canvas_pos: usize| {
+            u16::try_from(pos / self.scale as i32 - canvas_pos as i32).unwrap_or(u16::MAX)
+        };
+        self.lightgun_x = to_canvas(x, self.border_size.border_width());
+        self.lightgun_y = to_canvas(y, self.border_size.border_height());
+    }
+
+    fn lightgun_event(&self) -> Event {
+        Event::Lightgun {
+            x: self.lightgun_x,
+            y: self.lightgun_y,
+            trigger: self.lightgun_trigger,
         }
     }
 
@@ -172,7 +210,65 @@ impl EventsSdl {
             _ => None,
         };
 
-        kempston_event.map(|k| Event::Kempston(k, pressed))
+        kempston_event.map(|k| Event::Kempston(KempstonJoyNum::First, k, pressed))
+    }
+
+    /// returns second kempston joy key form scancode of None if not found
+    fn scancode_to_second_kempston_event(
+        &self,
+        scancode: Option<Scancode>,
+        pressed: bool,
+    ) -> Option<Event> {
+        if !(self.second_kempston_enabled && self.enable_joy_keyaboard_layer) {
+            return None;
+        }
+
+        let kempston_event = match scancode? {
+            Scancode::Return => Some(KempstonKey::Fire),
+            Scancode::I => Some(KempstonKey::Up),
+            Scancode::K => Some(KempstonKey::Down),
+            Scancode::J => Some(KempstonKey::Left),
+            Scancode::L => Some(KempstonKey::Right),
+            _ => None,
+        };
+
+        kempston_event.map(|k| Event::Kempston(KempstonJoyNum::Second, k, pressed))
+    }
+
+    /// returns cursor joy key form scancode of None if not found
+    fn scancode_to_cursor_event(&self, scancode: Option<Scancode>, pressed: bool) -> Option<Event> {
+        if !(self.cursor_joy_enabled && self.enable_joy_keyaboard_layer) {
+            return None;
+        }
+
+        let cursor_event = match scancode? {
+            Scancode::LAlt | Scancode::RAlt => Some(CursorKey::Fire),
+            Scancode::Up => Some(CursorKey::Up),
+            Scancode::Down => Some(CursorKey::Down),
+            Scancode::Left => Some(CursorKey::Left),
+            Scancode::Right => Some(CursorKey::Right),
+            _ => None,
+        };
+
+        cursor_event.map(|k| Event::Cursor(k, pressed))
+    }
+
+    /// returns fuller joy key form scancode of None if not found
+    fn scancode_to_fuller_event(&self, scancode: Option<Scancode>, pressed: bool) -> Option<Event> {
+        if !(self.fuller_enabled && self.enable_joy_keyaboard_layer) {
+            return None;
+        }
+
+        let fuller_event = match scancode? {
+            Scancode::LAlt | Scancode::RAlt => Some(FullerKey::Fire),
+            Scancode::Up => Some(FullerKey::Up),
+            Scancode::Down => Some(FullerKey::Down),
+            Scancode::Left => Some(FullerKey::Left),
+            Scancode::Right => Some(FullerKey::Right),
+            _ => None,
+        };
+
+        fuller_event.map(|k| Event::Fuller(k, pressed))
     }
 
     fn scancode_to_sinclair_event(
@@ -208,6 +304,10 @@ impl EventsSdl {
         scancode: Option<Scancode>,
         pressed: bool,
     ) -> Option<Event> {
+        // Fast-forward is active while the key is held
+        if scancode == Some(Scancode::F10) {
+            return Some(Event::FastForward(pressed));
+        }
         if let (Some(code), true) = (scancode, pressed) {
             match code {
                 Scancode::F1 => Some(Event::QuickSave),
@@ -216,12 +316,15 @@ impl EventsSdl {
                 Scancode::F4 => Some(Event::ChangeSpeed(EmulationMode::FrameCount(2))),
                 Scancode::F5 => Some(Event::ChangeSpeed(EmulationMode::Max)),
                 Scancode::F6 => Some(Event::SwitchFrameTrace),
+                Scancode::F7 => Some(Event::Rewind),
+                Scancode::F8 => Some(Event::SwitchFastTapeLoad),
                 Scancode::F9 => {
                     self.enable_joy_keyaboard_layer = !self.enable_joy_keyaboard_layer;
                     Some(Event::ChangeJoyKeyboardLayer(
                         self.enable_joy_keyaboard_layer,
                     ))
                 }
+                Scancode::F11 => Some(Event::Reset),
                 Scancode::Insert => Some(Event::InsertTape),
                 Scancode::Delete => Some(Event::StopTape),
                 Scancode::Escape => {
@@ -255,11 +358,32 @@ impl EventDevice for EventsSdl {
 
                     // Form highest priority event to lowest
                     self.scancode_to_emulator_event(scancode, pressed)
+                        .or_else(|| self.scancode_to_cursor_event(scancode, pressed))
+                        .or_else(|| self.scancode_to_fuller_event(scancode, pressed))
                         .or_else(|| self.scancode_to_kempston_event(scancode, pressed))
+                        .or_else(|| self.scancode_to_second_kempston_event(scancode, pressed))
                         .or_else(|| self.scancode_to_sinclair_event(scancode, pressed))
                         .or_else(|| self.scancode_to_zxkey_event(scancode, pressed))
                         .or_else(|| self.scancode_to_compound_key_event(scancode, pressed))
                 }
+                SdlEvent::MouseMotion { x, y, .. } if self.lightgun_enabled => {
+                    self.set_lightgun_pos(x, y);
+                    Some(self.lightgun_event())
+                }
+                SdlEvent::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } if self.lightgun_enabled => {
+                    self.lightgun_trigger = true;
+                    Some(self.lightgun_event())
+                }
+                SdlEvent::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } if self.lightgun_enabled => {
+                    self.lightgun_trigger = false;
+                    Some(self.lightgun_event())
+                }
                 SdlEvent::MouseMotion { xrel, yrel, .. } => {
                     // Change of direction  requires counter reset to eliminate lag
                     if self.mouse_x_counter.signum() != xrel.signum() {
@@ -325,6 +449,11 @@ impl EventDevice for EventsSdl {
                     }
                 }
                 SdlEvent::DropFile { filename, .. } => Some(Event::OpenFile(filename.into())),
+                // Key release events are not delivered to the unfocused window
+                SdlEvent::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => Some(Event::ReleaseKeys),
                 _ => None,
             }
         } else {
@@ -5,7 +5,9 @@ mod events_sdl;
 use rustzx_core::{
     zx::{
         joy::{
-            kempston::KempstonKey,
+            cursor::CursorKey,
+            fuller::FullerKey,
+            kempston::{KempstonJoyNum, KempstonKey},
             sinclair::{SinclairJoyNum, SinclairKey},
         },
         keys::{CompoundKey, ZXKey},
@@ -21,19 +23,27 @@ pub use events_sdl::EventsSdl;
 pub enum Event {
     ZXKey(ZXKey, bool),
     CompoundKey(CompoundKey, bool),
-    Kempston(KempstonKey, bool),
+    Kempston(KempstonJoyNum, KempstonKey, bool),
     Sinclair(SinclairJoyNum, SinclairKey, bool),
+    Cursor(CursorKey, bool),
+    Fuller(FullerKey, bool),
     MouseMove { x: i8, y: i8 },
     MouseButton(KempstonMouseButton, bool),
     MouseWheel(KempstonMouseWheelDirection),
+    Lightgun { x: u16, y: u16, trigger: bool },
     SwitchFrameTrace,
     ChangeJoyKeyboardLayer(bool),
     ChangeSpeed(EmulationMode),
+    FastForward(bool),
     InsertTape,
     StopTape,
     QuickSave,
     QuickLoad,
+    Rewind,
+    SwitchFastTapeLoad,
     OpenFile(PathBuf),
+    ReleaseKeys,
+    Reset,
     Exit,
 }
 
@@ -14,8 +14,10 @@ use crate::{
 use anyhow::{anyhow, Context};
 use rustzx_core::{
     host::SnapshotRecorder,
-    zx::constants::{
-        CANVAS_HEIGHT, CANVAS_WIDTH, CANVAS_X, CANVAS_Y, FPS, SCREEN_HEIGHT, SCREEN_WIDTH,
+    zx::{
+        constants::{CANVAS_HEIGHT, CANVAS_WIDTH},
+        events::EmulationEvents,
+        PRINTER_ROW_DOTS, PRINTER_ROW_SIZE,
     },
     Emulator,
 };
@@ -29,12 +31,12 @@ use std::{
 
 /// max 100 ms interval in `max frames` speed mode
 const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+/// count of frames rewound by a single key press (one second)
+const REWIND_FRAMES: usize = 50;
+/// count of frames emulated per host frame while fast-forward key is held
+const TURBO_FRAMES: u32 = 8;
 
 /// returns frame length from given `fps`
-fn frame_length(fps: usize) -> Duration {
-    Duration::from_millis((1000_f64 / fps as f64) as u64)
-}
-
 /// Application instance type
 pub struct RustzxApp {
     /// main emulator object
@@ -65,8 +67,10 @@ impl RustzxApp {
             None
         };
         let mut video = Box::new(VideoSdl::new(&settings));
-        let tex_border = video.gen_texture(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
-        let tex_canvas = video.gen_texture(CANVAS_WIDTH as u32, CANVAS_HEIGHT as u32);
+        let tex_border = video.gen_texture(
+            settings.border_size.screen_width() as u32,
+            settings.border_size.screen_height() as u32,
+        );
         let scale = settings.scale as u32;
         let events = Box::new(EventsSdl::new(&settings));
         let sample_rate = snd
@@ -74,14 +78,31 @@ impl RustzxApp {
             .map(|s| s.sample_rate())
             .unwrap_or(DEFAULT_SAMPLE_RATE);
 
-        let mut emulator = Emulator::new(settings.to_rustzx_settings(sample_rate), AppHostContext)
+        let context = AppHostContext {
+            palette: settings.palette,
+        };
+        if settings.rom.is_none() && !settings.machine.capabilities().has_embedded_rom {
+            return Err(anyhow!(
+                "ROM of the selected machine is not built-in, please provide it with `--rom`"
+            ));
+        }
+        let mut emulator = Emulator::new(settings.to_rustzx_settings(sample_rate), context)
             .map_err(|e| anyhow!("Failed to construct emulator: {}", e))?;
+        emulator.set_tape_autostop(settings.tape_autostop);
+        emulator.set_key_debounce(settings.key_debounce);
+        let tex_canvas =
+            video.gen_texture(emulator.canvas_buffer_width() as u32, CANVAS_HEIGHT as u32);
 
         if let Some(rom) = settings.rom.as_ref() {
             emulator
                 .load_rom(host::load_rom(rom, settings.machine)?)
                 .map_err(|e| anyhow!("Emulator failed to load rom: {}", e))?;
         }
+        if let Some(rom) = settings.trdos_rom.as_ref() {
+            emulator
+                .load_trdos_rom(host::load_trdos_rom(rom)?)
+                .map_err(|e| anyhow!("Emulator failed to load TR-DOS rom: {}", e))?;
+        }
         if let Some(snapshot) = settings.snap.as_ref() {
             emulator
                 .load_snapshot(host::load_snapshot(snapshot)?)
@@ -97,6 +118,11 @@ impl RustzxApp {
                 .load_screen(host::load_screen(screen)?)
                 .map_err(|e| anyhow!("Emulator failed to load screen: {}", e))?;
         }
+        if let Some(disk) = settings.disk.as_ref() {
+            emulator
+                .load_disk(host::load_disk(disk)?)
+                .map_err(|e| anyhow!("Emulator failed to load disk: {}", e))?;
+        }
 
         let file_autodetect = settings.file_autodetect.clone();
 
@@ -133,21 +159,36 @@ impl RustzxApp {
             title.push_str(" [FRAME_TRACE]");
         }
 
+        if !self.emulator.fast_tape_load() {
+            title.push_str(" [REAL_TAPE]");
+        }
+
         self.video.set_title(&title);
     }
 
     pub fn start(&mut self) -> anyhow::Result<()> {
         let scale = self.scale;
         'emulator: loop {
-            let frame_target_dt = frame_length(FPS);
+            let frame_target_dt = self.emulator.frame_duration();
             // absolute start time
             let frame_start = Instant::now();
             // Emulate all requested frames
-            let emulator_dt = self
-                .emulator
-                .emulate_frames(MAX_FRAME_TIME)
-                .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?
-                .duration;
+            let emulator_dt = if self.emulator.turbo() > 1 {
+                self.emulator
+                    .emulate_turbo_frames()
+                    .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?;
+                frame_start.elapsed()
+            } else {
+                self.emulator
+                    .emulate_frames(MAX_FRAME_TIME)
+                    .map_err(|e| anyhow!("Emulation step failed: {:#?}", e))?
+                    .duration
+            };
+            let events = self.emulator.take_events();
+            if self.settings.tape_autoplay && events.contains(EmulationEvents::TAPE_LOAD_REQUESTED)
+            {
+                self.emulator.play_tape();
+            }
             // if sound enabled sound ganeration allowed then move samples to sound thread
             if let Some(ref mut snd) = self.snd {
                 // if can be turned off even on speed change, so check it everytime
@@ -158,26 +199,29 @@ impl RustzxApp {
                 }
             }
 
-            self.video
-                .update_texture(self.tex_border, self.emulator.border_buffer().rgba_data());
-            self.video
-                .update_texture(self.tex_canvas, self.emulator.screen_buffer().rgba_data());
+            if let Some(border) = self.emulator.border_frame() {
+                self.video.update_texture(self.tex_border, border.data);
+            }
+            if let Some(screen) = self.emulator.screen_frame() {
+                self.video.update_texture(self.tex_canvas, screen.data);
+            }
 
+            let border_size = self.emulator.border_size();
             self.video.begin();
             self.video.draw_texture_2d(
                 self.tex_border,
                 Some(Rect::new(
                     0,
                     0,
-                    SCREEN_WIDTH as u32 * scale,
-                    SCREEN_HEIGHT as u32 * scale,
+                    border_size.screen_width() as u32 * scale,
+                    border_size.screen_height() as u32 * scale,
                 )),
             );
             self.video.draw_texture_2d(
                 self.tex_canvas,
                 Some(Rect::new(
-                    CANVAS_X as i32 * scale as i32,
-                    CANVAS_Y as i32 * scale as i32,
+                    border_size.border_width() as i32 * scale as i32,
+                    border_size.border_height() as i32 * scale as i32,
                     CANVAS_WIDTH as u32 * scale,
                     CANVAS_HEIGHT as u32 * scale,
                 )),
@@ -203,12 +247,22 @@ impl RustzxApp {
                     Event::ChangeSpeed(speed) => {
                         self.emulator.set_speed(speed);
                     }
-                    Event::Kempston(key, state) => {
-                        self.emulator.send_kempston_key(key, state);
+                    Event::FastForward(active) => {
+                        self.emulator
+                            .set_turbo(if active { TURBO_FRAMES } else { 1 });
+                    }
+                    Event::Kempston(num, key, state) => {
+                        self.emulator.send_joystick(num, key, state);
                     }
                     Event::Sinclair(num, key, state) => {
                         self.emulator.send_sinclair_key(num, key, state);
                     }
+                    Event::Cursor(key, state) => {
+                        self.emulator.send_cursor_key(key, state);
+                    }
+                    Event::Fuller(key, state) => {
+                        self.emulator.send_fuller_key(key, state);
+                    }
                     Event::CompoundKey(key, state) => {
                         self.emulator.send_compound_key(key, state);
                     }
@@ -221,11 +275,29 @@ impl RustzxApp {
                     Event::MouseWheel(direction) => {
                         self.emulator.send_mouse_wheel(direction);
                     }
+                    Event::Lightgun { x, y, trigger } => {
+                        self.emulator.set_lightgun(x, y, trigger);
+                    }
+                    Event::InsertTape if self.emulator.is_tape_paused() => {
+                        self.emulator.tape_resume()
+                    }
                     Event::InsertTape => self.emulator.play_tape(),
                     Event::StopTape => self.emulator.stop_tape(),
                     Event::OpenFile(path) => self.load_file_autodetect(&path)?,
+                    Event::ReleaseKeys => self.emulator.clear_keyboard(),
+                    Event::Reset => self.emulator.reset_cpu_only(),
                     Event::QuickSave => self.quick_save()?,
                     Event::QuickLoad => self.quick_load()?,
+                    Event::Rewind => {
+                        self.emulator
+                            .rewind(REWIND_FRAMES)
+                            .map_err(|e| anyhow!("Emulator failed to rewind: {}", e))?;
+                    }
+                    Event::SwitchFastTapeLoad => {
+                        let enabled = !self.emulator.fast_tape_load();
+                        self.emulator.set_fast_tape_load(enabled);
+                        self.update_window_title();
+                    }
                 }
             }
             // how long emulation iteration was
@@ -246,7 +318,24 @@ impl RustzxApp {
                 );
             }
         }
-        Ok(())
+        self.save_printer_output()
+    }
+
+    /// Saves paper printed by ZX Printer as binary PBM image
+    fn save_printer_output(&mut self) -> anyhow::Result<()> {
+        let path = match self.settings.printer_output.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let rows = self.emulator.take_printer_output();
+        let mut image = format!(
+            "P4\n{} {}\n",
+            PRINTER_ROW_DOTS,
+            rows.len() / PRINTER_ROW_SIZE
+        )
+        .into_bytes();
+        image.extend_from_slice(&rows);
+        fs::write(path, image).with_context(|| "Failed to save printer output")
     }
 
     fn load_file_autodetect(&mut self, path: &Path) -> anyhow::Result<()> {
@@ -258,15 +347,20 @@ impl RustzxApp {
                         anyhow!("Emulator failed to load auto-detected snapshot: {}", e)
                     })?;
             }
+            // Tape is swapped without reset, so multi-tape games could continue
             DetectedFileKind::Tape => {
                 self.emulator
-                    .load_tape(host::load_tape(path)?)
-                    .map_err(|e| anyhow!("Emulator failed to load auto-detected tape: {}", e))?;
+                    .insert_tape(host::load_tape(path)?)
+                    .map_err(|e| anyhow!("Emulator failed to insert auto-detected tape: {}", e))?;
             }
             DetectedFileKind::Screen => self
                 .emulator
                 .load_screen(host::load_screen(path)?)
                 .map_err(|e| anyhow!("Emulator failed load screen via auto-detect: {}", e))?,
+            DetectedFileKind::Disk => self
+                .emulator
+                .load_disk(host::load_disk(path)?)
+                .map_err(|e| anyhow!("Emulator failed load disk via auto-detect: {}", e))?,
         }
         Ok(())
     }
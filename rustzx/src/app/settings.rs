@@ -1,6 +1,6 @@
 use rustzx_core::{
-    zx::{machine::ZXMachine, sound::ay::ZXAYMode},
-    EmulationMode, RustzxSettings,
+    zx::{keys::KeyboardIssue, machine::ZXMachine, sound::ay::ZXAYMode, video::colors::ZXColor},
+    BorderSize, EmulationMode, Palette, RustzxSettings,
 };
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -26,21 +26,48 @@ pub enum SoundBackend {
 #[structopt(name = "RustZX")]
 pub struct Settings {
     /// Specify machine type for launch. Possible values:
+    ///   [`16k`, `16`] - Sinclair ZX Spectrum 16K
     ///   [`48k`, `48`] - Sinclair ZX Spectrum 48K
     ///   [`128k`, `128`] - Sinclair ZX Spectrum 128K
+    ///   [`plus3`, `+3`] - Amstrad ZX Spectrum +2A/+3 (requires `--rom`)
     #[structopt(verbatim_doc_comment, short, long, default_value = "48k", parse(try_from_str = machine_from_str))]
     pub machine: ZXMachine,
     /// Set emulation speed at emualtor start-up. Can be specified as deciamal non-zero
     /// value or as a special value `MAX` to run emulator as fast as possible
     #[structopt(long, default_value = "1", parse(try_from_str = emulation_speed_from_str))]
     pub speed: EmulationMode,
+    /// Set cpu speed multiplier relative to the display, e.g. `2` runs twice more
+    /// cpu instructions per frame. Can be set in range [0.1..=16]. Defaults to 1
+    #[structopt(long, default_value = "1")]
+    pub cpu_speed: f64,
     /// Disable fast tape loading
     #[structopt(long = "nofastload")]
     pub disable_fastload: bool,
+    /// Pause tape at the end of each block (for multi-load tapes without fast loading).
+    /// Press `Insert` to continue playback
+    #[structopt(long)]
+    pub tape_autostop: bool,
+    /// Start tape playback when ROM starts loading (e.g. on `LOAD ""`)
+    #[structopt(long)]
+    pub tape_autoplay: bool,
     /// Disable automatic tape loading via placing emulator to tape load state immediately
     /// after launch
     #[structopt(long = "noautoload")]
     pub disable_autoload: bool,
+    /// Set size of the rendered border. Can be set to `none` (256x192), `minimal`
+    /// (320x240) or `full` (352x296 overscan). Defaults to `minimal`
+    #[structopt(long, default_value = "minimal", parse(try_from_str = border_size_from_str))]
+    pub border_size: BorderSize,
+    /// Set border color on power-on as color index in range [0..=7]. Defaults to 0 (black)
+    #[structopt(long, default_value = "0", parse(try_from_str = border_color_from_str))]
+    pub initial_border: ZXColor,
+    /// Clear the screen on power-on and fill attributes with the given value
+    #[structopt(long)]
+    pub initial_attribute: Option<u8>,
+    /// Set color palette. Can be set to `modern`, `pal-tv`, `green`, `amber` or
+    /// `white`. Defaults to `modern`
+    #[structopt(long, default_value = "modern", parse(try_from_str = palette_from_str))]
+    pub palette: Palette,
     /// Set windows scale for emulator. Can be set as decimal non-zero value. Defaults to 2
     #[structopt(short, long, default_value = "2", parse(try_from_str = scale_from_str))]
     pub scale: usize,
@@ -48,9 +75,60 @@ pub struct Settings {
     /// to the kempston joy
     #[structopt(long = "nokempston")]
     pub disable_kempston: bool,
+    /// Enables second kempston joy on port 0x3F. If enabled, `IJKL` and `Enter` keys are
+    /// bound to the second kempston joy instead of Sinclair joy 2
+    #[structopt(long = "second-kempston")]
+    pub enable_second_kempston: bool,
+    /// Enables cursor (Protek/AGF) joy support. If enabled, arrow and `Alt` keys are bound
+    /// to the cursor joy instead of kempston
+    #[structopt(long = "cursor-joy")]
+    pub enable_cursor_joy: bool,
+    /// Enables Fuller Box (joy and AY-3-8910 chip). If enabled, arrow and `Alt` keys are
+    /// bound to the Fuller joy instead of kempston
+    #[structopt(long = "fuller")]
+    pub enable_fuller: bool,
     /// Enables kempston mouse support. If enabled, locks mouse in application
     #[structopt(long = "mouse")]
     pub enable_mouse: bool,
+    /// Enable Timex SCLD hi-colour and hi-res video modes on port 0xFF
+    #[cfg(feature = "timex")]
+    #[structopt(long)]
+    pub timex: bool,
+    /// Enables Magnum Light Phaser lightgun. If enabled, gun is aimed with the mouse
+    /// pointer and fired with the left mouse button
+    #[structopt(long = "lightgun")]
+    pub enable_lightgun: bool,
+    /// Enables ZX Printer. Printed paper is saved to the given path as PBM image on exit
+    #[structopt(long)]
+    pub printer_output: Option<PathBuf>,
+    /// Emulate Issue 2 keyboard of the 48K machine. Some old games read EAR input
+    /// state and work correctly only on Issue 2 machines
+    #[structopt(long = "issue2")]
+    pub issue2: bool,
+    /// Ignore key releases within given count of frames after the key press, to
+    /// smooth out host keyboard auto-repeat. 0 disables debouncing
+    #[structopt(long, default_value = "0")]
+    pub key_debounce: u32,
+    /// Blend adjacent frames to display gigascreen demos without flicker
+    #[structopt(long = "blend-frames")]
+    pub blend_frames: bool,
+    /// Reproducible emulation: fixed power-on RAM pattern, tape fast loading
+    /// is disabled
+    #[structopt(long)]
+    pub deterministic: bool,
+    /// Sets count of frames between flashing attributes swaps. Defaults to 16
+    #[structopt(long = "flash-rate", default_value = "16")]
+    pub flash_rate: u32,
+    /// Disables flashing attributes
+    #[structopt(long = "no-flash")]
+    pub disable_flash: bool,
+    /// Sets count of emulator states kept for rewinding with `F7`. Defaults to
+    /// 0 (rewind is disabled)
+    #[structopt(long = "rewind-snapshots", default_value = "0")]
+    pub rewind_snapshots: usize,
+    /// Sets count of frames between rewind states. Defaults to 10
+    #[structopt(long = "rewind-interval", default_value = "10")]
+    pub rewind_interval: usize,
     /// Sets mouse sensitivity [1..=100]. Defaults to 20
     #[structopt(long = "mouse-sensitivity", default_value = "20")]
     pub mouse_sensitivity: usize,
@@ -68,6 +146,14 @@ pub struct Settings {
     /// Disable beeper
     #[structopt(long = "nobeeper")]
     pub disable_beeper: bool,
+    /// Set beeper low-pass filter cutoff frequency in Hz, `0` disables filtering.
+    /// Defaults to 8000
+    #[structopt(long, default_value = "8000")]
+    pub beeper_lowpass: usize,
+    /// Set sound oversampling factor (1-4). Higher values reduce aliasing of
+    /// high-pitched tones at the cost of CPU time. Defaults to 1
+    #[structopt(long, default_value = "1")]
+    pub sound_oversampling: usize,
     /// Disable sound
     #[structopt(long = "nosound")]
     pub disable_sound: bool,
@@ -84,10 +170,13 @@ pub struct Settings {
         possible_values = &SoundBackend::VARIANTS
     )]
     pub sound_backend: SoundBackend,
-    /// Set path to custom rom file. in case of multipart ROMs for 128k, the first part file,
-    /// extension of which should end with `.0`
+    /// Set path to custom rom file. in case of multipart ROMs for 128k and +3, the first part
+    /// file, extension of which should end with `.0`
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub rom: Option<PathBuf>,
+    /// Set path to TR-DOS ROM file. Enables Beta Disk 128 interface if provided
+    #[structopt(long)]
+    pub trdos_rom: Option<PathBuf>,
     /// Set tape file path. Only `.tap` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub tape: Option<PathBuf>,
@@ -97,6 +186,10 @@ pub struct Settings {
     /// Set screen file to load. Only `.scr` files are supported currently
     #[structopt(long, conflicts_with = "file-autodetect")]
     pub screen: Option<PathBuf>,
+    /// Set disk image to insert. `.dsk` images are supported on +3, `.trd` and `.scl`
+    /// images require Beta Disk interface enabled with `--trdos-rom`
+    #[structopt(long, conflicts_with = "file-autodetect")]
+    pub disk: Option<PathBuf>,
 
     /// Load provided file to emulator. Emulator will perform autodetect of format if possible
     pub file_autodetect: Option<PathBuf>,
@@ -104,8 +197,10 @@ pub struct Settings {
 
 fn machine_from_str(s: &str) -> Result<ZXMachine, anyhow::Error> {
     match s.to_lowercase().as_str() {
+        "16k" | "16" => Ok(ZXMachine::Sinclair16K),
         "48k" | "48" => Ok(ZXMachine::Sinclair48K),
         "128k" | "128" => Ok(ZXMachine::Sinclair128K),
+        "plus3" | "+3" => Ok(ZXMachine::SinclairPlus3),
         s => Err(anyhow::anyhow!("Invalid machine type `{}`", s)),
     }
 }
@@ -131,6 +226,33 @@ fn scale_from_str(s: &str) -> Result<usize, anyhow::Error> {
     Ok(scale.into())
 }
 
+fn border_size_from_str(s: &str) -> Result<BorderSize, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "none" => Ok(BorderSize::None),
+        "minimal" => Ok(BorderSize::Minimal),
+        "full" => Ok(BorderSize::Full),
+        s => Err(anyhow::anyhow!("Invalid border size `{}`", s)),
+    }
+}
+
+fn border_color_from_str(s: &str) -> Result<ZXColor, anyhow::Error> {
+    match s.parse::<u8>() {
+        Ok(color @ 0..=7) => Ok(ZXColor::from_bits(color)),
+        _ => Err(anyhow::anyhow!("Invalid border color `{}`", s)),
+    }
+}
+
+fn palette_from_str(s: &str) -> Result<Palette, anyhow::Error> {
+    match s.to_lowercase().as_str() {
+        "modern" => Ok(Palette::Modern),
+        "pal-tv" => Ok(Palette::PalTv),
+        "green" => Ok(Palette::MonochromeGreen),
+        "amber" => Ok(Palette::MonochromeAmber),
+        "white" => Ok(Palette::MonochromeWhite),
+        s => Err(anyhow::anyhow!("Invalid palette `{}`", s)),
+    }
+}
+
 fn ay_mode_from_str(s: &str) -> Result<ZXAYMode, anyhow::Error> {
     match s.to_lowercase().as_str() {
         "mono" => Ok(ZXAYMode::Mono),
@@ -173,23 +295,54 @@ fn sound_sample_rate_from_str(s: &str) -> Result<usize, anyhow::Error> {
 
 impl Settings {
     pub fn to_rustzx_settings(&self, sound_sample_rate: usize) -> RustzxSettings {
-        let ay_enabled = (matches!(self.machine, ZXMachine::Sinclair128K) || self.force_enable_ay)
-            && (!self.force_disable_ay);
+        let ay_enabled =
+            (self.machine.capabilities().has_ay || self.force_enable_ay || self.enable_fuller)
+                && (!self.force_disable_ay);
 
         RustzxSettings {
             machine: self.machine,
+            custom_specs: None,
             emulation_mode: self.speed,
+            cpu_speed_multiplier: self.cpu_speed,
+            deterministic: self.deterministic,
             tape_fastload_enabled: !self.disable_fastload,
+            tape_fastload_border_frames: 0,
             kempston_enabled: !self.disable_kempston,
+            second_kempston_enabled: self.enable_second_kempston,
+            fuller_enabled: self.enable_fuller,
             mouse_enabled: self.enable_mouse,
+            lightgun_enabled: self.enable_lightgun,
+            printer_enabled: self.printer_output.is_some(),
+            beta_disk_enabled: self.trdos_rom.is_some(),
+            border_size: self.border_size,
+            initial_border_color: self.initial_border,
+            initial_screen_attribute: self.initial_attribute,
+            palette: self.palette,
+            keyboard_issue: if self.issue2 {
+                KeyboardIssue::Issue2
+            } else {
+                KeyboardIssue::Issue3
+            },
+            blend_frames: self.blend_frames,
+            flash_rate_frames: (!self.disable_flash).then_some(self.flash_rate),
+            rewind_snapshots: self.rewind_snapshots,
+            rewind_interval_frames: self.rewind_interval,
             ay_mode: self.ay_mode,
             ay_enabled,
             beeper_enabled: !self.disable_beeper,
             sound_enabled: !self.disable_sound,
             sound_volume: 100,
+            beeper_lowpass_cutoff: Some(self.beeper_lowpass).filter(|&cutoff| cutoff != 0),
+            mono_output: false,
             load_default_rom: self.rom.is_none(),
             sound_sample_rate,
+            sound_oversampling: self.sound_oversampling,
             autoload_enabled: !self.disable_autoload,
+            #[cfg(feature = "timex")]
+            timex_enabled: self.timex,
+            #[cfg(not(feature = "timex"))]
+            timex_enabled: false,
+            next_enabled: false,
         }
     }
 }
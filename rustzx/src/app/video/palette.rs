@@ -1,5 +1,4 @@
 use rustzx_core::zx::video::colors::{ZXBrightness, ZXColor};
-use rustzx_utils::palette::rgba::ORIGINAL as DEFAULT_PALETTE;
 
 type ColorRgba = [u8; 4];
 
@@ -9,15 +8,21 @@ pub struct Palette {
     colors: [ColorRgba; MAX_COLORS],
 }
 
-impl Default for Palette {
-    fn default() -> Self {
-        Palette {
-            colors: DEFAULT_PALETTE,
+impl Palette {
+    /// Builds color lookup table for the selected palette
+    pub fn new(palette: rustzx_core::Palette) -> Self {
+        let mut colors = [[0; 4]; MAX_COLORS];
+        for (index, color) in colors.iter_mut().enumerate() {
+            let brightness = if index < 8 {
+                ZXBrightness::Normal
+            } else {
+                ZXBrightness::Bright
+            };
+            *color = palette.rgba(ZXColor::from_bits(index as u8 & 0x07), brightness);
         }
+        Palette { colors }
     }
-}
 
-impl Palette {
     pub fn get_rgba(&self, color: ZXColor, brightness: ZXBrightness) -> ColorRgba {
         let index = ((color as u8) + (brightness as u8) * 8) as usize;
         assert!(index < MAX_COLORS);
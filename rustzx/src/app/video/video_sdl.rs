@@ -1,6 +1,5 @@
 use super::{Rect, TextureInfo, VideoDevice};
 use crate::{app::settings::Settings, backends::SDL_CONTEXT};
-use rustzx_core::zx::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use sdl2::{
     pixels::PixelFormatEnum as PixelFormat,
     rect::Rect as SdlRect,
@@ -28,8 +27,8 @@ impl VideoSdl {
         if let Some(video) = video_subsystem {
             // construct window and renderer form it
             let (width, height) = (
-                SCREEN_WIDTH * settings.scale,
-                SCREEN_HEIGHT * settings.scale,
+                settings.border_size.screen_width() * settings.scale,
+                settings.border_size.screen_height() * settings.scale,
             );
             let window = video
                 .window("RustZX", width as u32, height as u32)
@@ -1,13 +1,15 @@
 use crate::app::video::Palette;
 use rustzx_core::{
-    host::{FrameBuffer, FrameBufferSource},
+    host::{FrameBuffer, FrameBufferSource, PixelFormat},
     zx::video::colors::{ZXBrightness, ZXColor},
 };
 
 const RGBA_PIXEL_SIZE: usize = 4;
 
 #[derive(Clone)]
-pub struct FrameBufferContext;
+pub struct FrameBufferContext {
+    pub palette: rustzx_core::Palette,
+}
 
 pub struct RgbaFrameBuffer {
     buffer: Vec<u8>,
@@ -22,11 +24,11 @@ impl FrameBuffer for RgbaFrameBuffer {
         width: usize,
         height: usize,
         _source: FrameBufferSource,
-        _context: Self::Context,
+        context: Self::Context,
     ) -> Self {
         Self {
             buffer: vec![0u8; width * height * RGBA_PIXEL_SIZE],
-            palette: Palette::default(),
+            palette: Palette::new(context.palette),
             buffer_row_size: width * RGBA_PIXEL_SIZE,
         }
     }
@@ -41,10 +43,31 @@ impl FrameBuffer for RgbaFrameBuffer {
             .zip(&mut self.buffer[buffer_pos..buffer_pos + RGBA_PIXEL_SIZE])
             .for_each(|(source, dest)| *dest = source);
     }
-}
 
-impl RgbaFrameBuffer {
-    pub fn rgba_data(&self) -> &[u8] {
-        &self.buffer
+    fn set_blended_color(
+        &mut self,
+        x: usize,
+        y: usize,
+        current: (ZXColor, ZXBrightness),
+        previous: (ZXColor, ZXBrightness),
+    ) {
+        let buffer_pos = y * self.buffer_row_size + x * RGBA_PIXEL_SIZE;
+        let current = self.palette.get_rgba(current.0, current.1);
+        let previous = self.palette.get_rgba(previous.0, previous.1);
+
+        current
+            .iter()
+            .zip(previous.iter())
+            .map(|(&a, &b)| ((a as u16 + b as u16) / 2) as u8)
+            .zip(&mut self.buffer[buffer_pos..buffer_pos + RGBA_PIXEL_SIZE])
+            .for_each(|(source, dest)| *dest = source);
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        Some(&self.buffer)
+    }
+
+    fn pixel_format(&self) -> Option<PixelFormat> {
+        Some(PixelFormat::Rgba8888)
     }
 }
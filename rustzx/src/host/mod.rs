@@ -4,10 +4,11 @@ use anyhow::{anyhow, bail, Context};
 use frame_buffer::{FrameBufferContext, RgbaFrameBuffer};
 use rustzx_core::{
     host::{
-        FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen, Snapshot, StubDebugInterface,
-        StubIoExtender, Tape,
+        Disk, FrameBuffer, Host, HostContext, RomFormat, RomSet, Screen, Snapshot,
+        StubDebugInterface, StubIoExtender, Tape,
     },
     zx::machine::ZXMachine,
+    Palette,
 };
 use rustzx_utils::{
     io::{DynamicAsset, FileAsset, GzipAsset},
@@ -18,6 +19,9 @@ use std::{collections::VecDeque, fs::File, path::Path};
 const SUPPORTED_SNAPSHOT_FORMATS: [&str; 1] = ["sna"];
 const SUPPORTED_TAPE_FORMATS: [&str; 1] = ["tap"];
 const SUPPORTED_SCREEN_FORMATS: [&str; 1] = ["scr"];
+const SUPPORTED_DISK_FORMATS: [&str; 3] = ["dsk", "trd", "scl"];
+// Formats which are unpacked from zip archives by the emulator core
+const SUPPORTED_ARCHIVED_FORMATS: [&str; 2] = ["tap", "sna"];
 
 pub struct AppHost;
 
@@ -30,11 +34,15 @@ impl Host for AppHost {
     type TapeAsset = DynamicAsset;
 }
 
-pub struct AppHostContext;
+pub struct AppHostContext {
+    pub palette: Palette,
+}
 
 impl HostContext<AppHost> for AppHostContext {
     fn frame_buffer_context(&self) -> <<AppHost as Host>::FrameBuffer as FrameBuffer>::Context {
-        FrameBufferContext
+        FrameBufferContext {
+            palette: self.palette,
+        }
     }
 }
 
@@ -58,11 +66,13 @@ pub enum DetectedFileKind {
     Tape,
     Snapshot,
     Screen,
+    Disk,
 }
 
 pub enum DetectedContainerKind {
     None,
     Gzip,
+    Zip,
 }
 
 pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
@@ -71,7 +81,10 @@ pub fn load_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
     let file = File::open(path).with_context(|| "Failed to open tape file")?;
 
     match container_kind {
-        DetectedContainerKind::None => Ok(FileAsset::from(file).into()),
+        // Zip archives are unpacked by the emulator core
+        DetectedContainerKind::None | DetectedContainerKind::Zip => {
+            Ok(FileAsset::from(file).into())
+        }
         DetectedContainerKind::Gzip => {
             let gzip = GzipAsset::new(file)?;
             Ok(gzip.into())
@@ -121,13 +134,39 @@ pub fn load_screen(path: &Path) -> anyhow::Result<Screen<DynamicAsset>> {
         .with_context(|| "Failed to load screen file")
 }
 
+pub fn load_disk(path: &Path) -> anyhow::Result<Disk<DynamicAsset>> {
+    if !file_extension_matches_one_of(path, &SUPPORTED_DISK_FORMATS) {
+        bail!("Invalid disk format");
+    }
+
+    if !path.exists() {
+        bail!("Provided disk file does not exist");
+    }
+
+    let asset = load_asset(path).with_context(|| "Failed to load disk file")?;
+    if file_extension_matches(path, "trd") {
+        Ok(Disk::Trd(asset))
+    } else if file_extension_matches(path, "scl") {
+        Ok(Disk::Scl(asset))
+    } else {
+        Ok(Disk::Dsk(asset))
+    }
+}
+
+pub fn load_trdos_rom(path: &Path) -> anyhow::Result<DynamicAsset> {
+    if !path.exists() {
+        bail!("Provided TR-DOS ROM file does not exist");
+    }
+    load_rom_asset(path).with_context(|| "TR-DOS ROM load failed")
+}
+
 fn load_rom_asset(path: &Path) -> anyhow::Result<DynamicAsset> {
     load_asset(path).with_context(|| "Failed to load rom asset")
 }
 
 pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
     match machine {
-        ZXMachine::Sinclair48K => {
+        ZXMachine::Sinclair16K | ZXMachine::Sinclair48K => {
             if !path.exists() {
                 bail!("Provided 48K ROM file does not exist")
             }
@@ -138,35 +177,40 @@ pub fn load_rom(path: &Path, machine: ZXMachine) -> anyhow::Result<FileRomSet> {
                 ]),
             })
         }
-        ZXMachine::Sinclair128K => {
-            let rom0_path = path;
-            if !file_extension_matches(rom0_path, "0") {
-                bail!("128K ROM filename should end with '.0' extension");
-            }
-            if !rom0_path.exists() {
-                bail!("Provided 128K ROM0 file does not exist");
-            }
-            let rom1_path = if is_container(rom0_path) {
-                let container_ext = rom0_path.extension().unwrap().to_string_lossy();
-                let mut new_path = rom0_path.to_owned();
-                new_path.set_extension(""); // removes just container extension
-                new_path.with_extension(format!("1.{}", container_ext))
-            } else {
-                rom0_path.to_owned().with_extension("1")
-            };
-
-            if !rom1_path.exists() {
-                bail!("Provided 128K ROM1 file does not exist");
-            }
+        ZXMachine::Sinclair128K => load_multipart_rom(path, 2, "128K"),
+        ZXMachine::SinclairPlus3 => load_multipart_rom(path, 4, "+3"),
+    }
+}
 
-            Ok(FileRomSet {
-                pages: VecDeque::from(vec![
-                    load_rom_asset(rom0_path).with_context(|| "128K ROM0 load failed")?,
-                    load_rom_asset(&rom1_path).with_context(|| "128K ROM1 load failed")?,
-                ]),
-            })
+/// Loads ROM, split into files with `.0`, `.1`, ... extensions
+fn load_multipart_rom(path: &Path, count: usize, name: &str) -> anyhow::Result<FileRomSet> {
+    if !file_extension_matches(path, "0") {
+        bail!("{} ROM filename should end with '.0' extension", name);
+    }
+
+    let mut pages = VecDeque::new();
+    for index in 0..count {
+        let page_path = if index == 0 {
+            path.to_owned()
+        } else if is_container(path) {
+            let container_ext = path.extension().unwrap().to_string_lossy();
+            let mut new_path = path.to_owned();
+            new_path.set_extension(""); // removes just container extension
+            new_path.with_extension(format!("{}.{}", index, container_ext))
+        } else {
+            path.with_extension(index.to_string())
+        };
+
+        if !page_path.exists() {
+            bail!("Provided {} ROM{} file does not exist", name, index);
         }
+        pages.push_back(
+            load_rom_asset(&page_path)
+                .with_context(|| format!("{} ROM{} load failed", name, index))?,
+        );
     }
+
+    Ok(FileRomSet { pages })
 }
 
 pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
@@ -178,6 +222,9 @@ pub fn detect_file_type(path: &Path) -> anyhow::Result<DetectedFileKind> {
         Ok(DetectedFileKind::Snapshot)
     } else if file_extension_matches_one_of(path, &SUPPORTED_SCREEN_FORMATS) {
         Ok(DetectedFileKind::Screen)
+    } else if file_extension_matches_one_of(path, &SUPPORTED_DISK_FORMATS) {
+        load_disk(path)?;
+        Ok(DetectedFileKind::Disk)
     } else {
         Err(anyhow!("Not supported file format"))
     }
@@ -197,11 +244,25 @@ fn detect_container(path: &Path) -> DetectedContainerKind {
 
     match ext.as_str() {
         "gz" => DetectedContainerKind::Gzip,
+        "zip" => DetectedContainerKind::Zip,
         _ => DetectedContainerKind::None,
     }
 }
 
+/// Returns format of the file which will be picked from the zip archive
+fn zip_archived_format(path: &Path) -> Option<&'static str> {
+    let mut asset = FileAsset::from(File::open(path).ok()?);
+    rustzx_core::zip_archived_format(&mut asset, &SUPPORTED_ARCHIVED_FORMATS)
+        .ok()
+        .flatten()
+}
+
 fn file_extension_matches(path: &Path, expected: &str) -> bool {
+    // Zip archive name says nothing about its content
+    if matches!(detect_container(path), DetectedContainerKind::Zip) {
+        return zip_archived_format(path) == Some(expected);
+    }
+
     let mut path = path.to_owned();
     // Ignore outer container extension during comparison
     if is_container(&path) {